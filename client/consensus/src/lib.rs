@@ -1,13 +1,14 @@
 use std::sync::Arc;
 use std::collections::HashMap;
 use std::marker::PhantomData;
-use fp_consensus::{ensure_log, FindLogError};
+use fp_consensus::{ensure_log, find_log, FindLogError, Hashes, Log};
 use fp_rpc::EthereumRuntimeRPCApi;
-use sc_client_api::{BlockOf, backend::AuxStore};
+use codec::Decode;
+use sc_client_api::{BlockOf, backend::{self, AuxStore, StateBackendFor}};
 use sp_blockchain::{HeaderBackend, ProvideCache, well_known_cache_keys::Id as CacheKeyId};
-use sp_block_builder::BlockBuilder as BlockBuilderApi;
-use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
-use sp_api::ProvideRuntimeApi;
+use sp_core::hashing::twox_128;
+use sp_runtime::{generic::{BlockId, Digest}, traits::{Block as BlockT, Header as HeaderT}};
+use sp_api::{ProvideRuntimeApi, ApiExt, Core};
 use sp_consensus::{
 	BlockImportParams, Error as ConsensusError, BlockImport,
 	BlockCheckParams, ImportResult,
@@ -22,6 +23,14 @@ pub enum Error {
 	NoRuntimeLog,
 	#[display(fmt = "Cannot access the runtime at genesis, rejecting!")]
 	RuntimeApiCallFailed,
+	#[display(fmt = "Block execution failed: {}, rejecting!", _0)]
+	BlockExecutionFailed(String),
+	#[display(fmt = "Ethereum block not built by the runtime, rejecting!")]
+	NoRuntimeBlock,
+	#[display(fmt = "Ethereum hashes in the digest differ from the runtime block, rejecting!")]
+	HashesMismatch,
+	#[display(fmt = "Ethereum block in the storage changes cannot be decoded, rejecting!")]
+	InvalidRuntimeBlock,
 }
 
 impl From<Error> for String {
@@ -45,53 +54,147 @@ impl From<Error> for ConsensusError {
 	}
 }
 
-pub struct ShadowsBlockImport<B: BlockT, I, C> {
+/// Compare the Ethereum hashes of a block digest with the Ethereum block built by the runtime. A
+/// pre-runtime log carries the block the author started from, so only its transactions have to
+/// match.
+pub fn check_log<Hash>(
+	digest: &Digest<Hash>,
+	runtime_block: ethereum::Block,
+) -> Result<(), Error> {
+	let log = find_log(digest)?;
+	let is_pre_log = matches!(log, Log::Pre(_));
+	let claimed = log.into_hashes();
+	let built = Hashes::from_block(runtime_block);
+
+	let matches = if is_pre_log {
+		claimed.transaction_hashes == built.transaction_hashes
+	} else {
+		claimed == built
+	};
+
+	if matches {
+		Ok(())
+	} else {
+		Err(Error::HashesMismatch)
+	}
+}
+
+/// Ethereum block stored by the runtime in the given storage changes, if it was written.
+fn changed_runtime_block(
+	main_storage_changes: &[(Vec<u8>, Option<Vec<u8>>)],
+) -> Result<Option<ethereum::Block>, Error> {
+	let key = [twox_128(b"Ethereum"), twox_128(b"CurrentBlock")].concat();
+
+	match main_storage_changes.iter().find(|(changed, _)| *changed == key) {
+		Some((_, Some(value))) => ethereum::Block::decode(&mut &value[..])
+			.map(Some)
+			.map_err(|_| Error::InvalidRuntimeBlock),
+		_ => Ok(None),
+	}
+}
+
+pub struct ShadowsBlockImport<B: BlockT, I, C, BE> {
 	inner: I,
 	client: Arc<C>,
+	substrate_backend: Arc<BE>,
 	backend: Arc<fc_db::Backend<B>>,
+	verify_digests: bool,
 	_marker: PhantomData<B>,
 }
 
-impl<Block: BlockT, I: Clone + BlockImport<Block>, C> Clone for ShadowsBlockImport<Block, I, C> {
+impl<Block: BlockT, I: Clone + BlockImport<Block>, C, BE> Clone for ShadowsBlockImport<Block, I, C, BE> {
 	fn clone(&self) -> Self {
 		ShadowsBlockImport {
 			inner: self.inner.clone(),
 			client: self.client.clone(),
+			substrate_backend: self.substrate_backend.clone(),
 			backend: self.backend.clone(),
+			verify_digests: self.verify_digests,
 			_marker: PhantomData,
 		}
 	}
 }
 
-impl<B, I, C> ShadowsBlockImport<B, I, C> where
+impl<B, I, C, BE> ShadowsBlockImport<B, I, C, BE> where
 	B: BlockT,
+	BE: backend::Backend<B>,
 	I: BlockImport<B, Transaction = sp_api::TransactionFor<C, B>> + Send + Sync,
 	I::Error: Into<ConsensusError>,
 	C: ProvideRuntimeApi<B> + Send + Sync + HeaderBackend<B> + AuxStore + ProvideCache<B> + BlockOf,
 	C::Api: EthereumRuntimeRPCApi<B>,
-	C::Api: BlockBuilderApi<B>,
+	C::Api: ApiExt<B, StateBackend = StateBackendFor<BE, B>>,
 {
+	/// With `verify_digests`, the Ethereum digest of every imported block is checked against the
+	/// block built by the runtime.
 	pub fn new(
 		inner: I,
 		client: Arc<C>,
+		substrate_backend: Arc<BE>,
 		backend: Arc<fc_db::Backend::<B>>,
+		verify_digests: bool,
 	) -> Self {
 		Self {
 			inner,
 			client,
+			substrate_backend,
 			backend,
+			verify_digests,
 			_marker: PhantomData,
 		}
 	}
+
+	/// Blocks built locally come with the storage changes of their execution, which hold the
+	/// Ethereum block. Other blocks are executed here, and their storage changes handed over to
+	/// the import so that they are not executed a second time.
+	fn verify_digest(
+		&self,
+		block: &mut BlockImportParams<B, sp_api::TransactionFor<C, B>>,
+	) -> Result<(), Error> {
+		if let Some(changes) = &block.storage_changes {
+			let runtime_block = changed_runtime_block(&changes.main_storage_changes)?
+				.ok_or(Error::NoRuntimeBlock)?;
+			return check_log(block.header.digest(), runtime_block)
+		}
+
+		// Nothing to execute when only the header is imported.
+		let body = match &block.body {
+			Some(body) => body.clone(),
+			None => return Ok(()),
+		};
+
+		let parent_hash = *block.header.parent_hash();
+		let parent_id = BlockId::Hash(parent_hash);
+		let api = self.client.runtime_api();
+		api.execute_block(&parent_id, B::new(block.header.clone(), body))
+			.map_err(|e| Error::BlockExecutionFailed(format!("{:?}", e)))?;
+		let runtime_block = api.current_block(&parent_id)
+			.map_err(|e| Error::BlockExecutionFailed(format!("{:?}", e)))?
+			.ok_or(Error::NoRuntimeBlock)?;
+		check_log(block.header.digest(), runtime_block)?;
+
+		let state = self.substrate_backend.state_at(parent_id)
+			.map_err(|e| Error::BlockExecutionFailed(format!("{:?}", e)))?;
+		let changes_trie_state = backend::changes_tries_state_at_block(
+			&parent_id,
+			self.substrate_backend.changes_trie_storage(),
+		).map_err(|e| Error::BlockExecutionFailed(format!("{:?}", e)))?;
+		block.storage_changes = Some(
+			api.into_storage_changes(&state, changes_trie_state.as_ref(), parent_hash)
+				.map_err(|e| Error::BlockExecutionFailed(e))?
+		);
+
+		Ok(())
+	}
 }
 
-impl<B, I, C> BlockImport<B> for ShadowsBlockImport<B, I, C> where
+impl<B, I, C, BE> BlockImport<B> for ShadowsBlockImport<B, I, C, BE> where
 	B: BlockT,
+	BE: backend::Backend<B>,
 	I: BlockImport<B, Transaction = sp_api::TransactionFor<C, B>> + Send + Sync,
 	I::Error: Into<ConsensusError>,
 	C: ProvideRuntimeApi<B> + Send + Sync + HeaderBackend<B> + AuxStore + ProvideCache<B> + BlockOf,
 	C::Api: EthereumRuntimeRPCApi<B>,
-	C::Api: BlockBuilderApi<B>,
+	C::Api: ApiExt<B, StateBackend = StateBackendFor<BE, B>>,
 {
 	type Error = ConsensusError;
 	type Transaction = sp_api::TransactionFor<C, B>;
@@ -105,13 +208,314 @@ impl<B, I, C> BlockImport<B> for ShadowsBlockImport<B, I, C> where
 
 	fn import_block(
 		&mut self,
-		block: BlockImportParams<B, Self::Transaction>,
+		mut block: BlockImportParams<B, Self::Transaction>,
 		new_cache: HashMap<CacheKeyId, Vec<u8>>,
 	) -> Result<ImportResult, Self::Error> {
 		// actions are needed and mapping syncing is delegated to a separate
 		// worker.
 		ensure_log(&block.header.digest()).map_err(|e| Error::from(e))?;
 
+		if self.verify_digests {
+			self.verify_digest(&mut block)?;
+		}
+
 		self.inner.import_block(block, new_cache).map_err(Into::into)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use ethereum_types::{H64, H160, H256, U256, Bloom};
+	use std::sync::Mutex;
+	use fp_consensus::{PostLog, PreLog, SHADOWS_ENGINE_ID};
+	use fp_rpc::{EIP658Receipt, TransactionStatus};
+	use sp_api::ApiRef;
+	use sp_blockchain::{BlockStatus, Info};
+	use sp_consensus::BlockOrigin;
+	use sp_runtime::{
+		generic::DigestItem,
+		testing::{Block as RawBlock, ExtrinsicWrapper, Header},
+	};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	fn runtime_block() -> ethereum::Block {
+		let partial_header = ethereum::PartialHeader {
+			parent_hash: H256::repeat_byte(1),
+			beneficiary: H160::default(),
+			state_root: H256::default(),
+			receipts_root: H256::default(),
+			logs_bloom: Bloom::default(),
+			difficulty: U256::zero(),
+			number: U256::one(),
+			gas_limit: U256::from(4_000_000),
+			gas_used: U256::zero(),
+			timestamp: 42,
+			extra_data: Vec::new(),
+			mix_hash: H256::default(),
+			nonce: H64::default(),
+		};
+		ethereum::Block::new(partial_header, Vec::new(), Vec::new())
+	}
+
+	fn post_log_digest(hashes: Hashes) -> Digest<H256> {
+		Digest {
+			logs: vec![DigestItem::Consensus(SHADOWS_ENGINE_ID, PostLog::Hashes(hashes).encode())],
+		}
+	}
+
+	#[test]
+	fn matching_digest_is_accepted() {
+		let digest = post_log_digest(Hashes::from_block(runtime_block()));
+
+		assert!(check_log(&digest, runtime_block()).is_ok());
+	}
+
+	#[test]
+	fn tampered_block_hash_is_rejected() {
+		let mut hashes = Hashes::from_block(runtime_block());
+		hashes.block_hash = H256::repeat_byte(0xff);
+		let digest = post_log_digest(hashes);
+
+		assert!(matches!(check_log(&digest, runtime_block()), Err(Error::HashesMismatch)));
+	}
+
+	#[test]
+	fn tampered_transaction_hashes_are_rejected() {
+		let mut hashes = Hashes::from_block(runtime_block());
+		hashes.transaction_hashes.push(H256::repeat_byte(0xff));
+		let digest = post_log_digest(hashes);
+
+		assert!(matches!(check_log(&digest, runtime_block()), Err(Error::HashesMismatch)));
+	}
+
+	#[test]
+	fn pre_log_only_checks_transactions() {
+		let mut pre_block = runtime_block();
+		pre_block.header.timestamp = 43;
+		let digest = Digest {
+			logs: vec![DigestItem::PreRuntime(SHADOWS_ENGINE_ID, PreLog::Block(pre_block).encode())],
+		};
+
+		assert!(check_log(&digest, runtime_block()).is_ok());
+	}
+
+	#[test]
+	fn missing_digest_is_rejected() {
+		let digest = Digest::<H256> { logs: Vec::new() };
+
+		assert!(matches!(check_log(&digest, runtime_block()), Err(Error::NoRuntimeLog)));
+	}
+
+	/// A client without blocks, imported blocks are checked against their storage changes.
+	#[derive(Clone)]
+	struct TestClient;
+
+	impl HeaderBackend<Block> for TestClient {
+		fn header(&self, _id: BlockId<Block>) -> sp_blockchain::Result<Option<Header>> {
+			Ok(None)
+		}
+
+		fn info(&self) -> Info<Block> {
+			Info {
+				best_hash: H256::default(),
+				best_number: 0,
+				genesis_hash: H256::default(),
+				finalized_hash: H256::default(),
+				finalized_number: 0,
+				number_leaves: 0,
+			}
+		}
+
+		fn status(&self, _id: BlockId<Block>) -> sp_blockchain::Result<BlockStatus> {
+			Ok(BlockStatus::Unknown)
+		}
+
+		fn number(&self, _hash: H256) -> sp_blockchain::Result<Option<u64>> {
+			Ok(None)
+		}
+
+		fn hash(&self, _number: u64) -> sp_blockchain::Result<Option<H256>> {
+			Ok(None)
+		}
+	}
+
+	impl AuxStore for TestClient {
+		fn insert_aux<
+			'a,
+			'b: 'a,
+			'c: 'a,
+			I: IntoIterator<Item=&'a(&'c [u8], &'c [u8])>,
+			D: IntoIterator<Item=&'a &'b [u8]>,
+		>(&self, _insert: I, _delete: D) -> sp_blockchain::Result<()> {
+			Ok(())
+		}
+
+		fn get_aux(&self, _key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+			Ok(None)
+		}
+	}
+
+	impl ProvideCache<Block> for TestClient {
+		fn cache(&self) -> Option<Arc<dyn sp_blockchain::Cache<Block>>> {
+			None
+		}
+	}
+
+	impl BlockOf for TestClient {
+		type Type = Block;
+	}
+
+	struct RuntimeApi;
+
+	impl ProvideRuntimeApi<Block> for TestClient {
+		type Api = RuntimeApi;
+
+		fn runtime_api<'a>(&'a self) -> ApiRef<'a, Self::Api> {
+			RuntimeApi.into()
+		}
+	}
+
+	sp_api::mock_impl_runtime_apis! {
+		impl EthereumRuntimeRPCApi<Block> for RuntimeApi {
+			fn chain_id() -> u64 { 42 }
+
+			fn account_basic(_address: H160) -> fp_evm::Account {
+				fp_evm::Account { balance: U256::zero(), nonce: U256::zero() }
+			}
+
+			fn gas_price() -> U256 { U256::zero() }
+
+			fn account_code_at(_address: H160) -> Vec<u8> { Vec::new() }
+
+			fn author() -> H160 { H160::default() }
+
+			fn storage_at(_address: H160, _index: U256) -> H256 { H256::default() }
+
+			fn call(
+				_from: H160,
+				_to: H160,
+				_data: Vec<u8>,
+				_value: U256,
+				_gas_limit: U256,
+				_gas_price: Option<U256>,
+				_nonce: Option<U256>,
+				_estimate: bool,
+			) -> Result<fp_evm::CallInfo, sp_runtime::DispatchError> {
+				Err(sp_runtime::DispatchError::Other("unsupported"))
+			}
+
+			fn create(
+				_from: H160,
+				_data: Vec<u8>,
+				_value: U256,
+				_gas_limit: U256,
+				_gas_price: Option<U256>,
+				_nonce: Option<U256>,
+				_estimate: bool,
+			) -> Result<fp_evm::CreateInfo, sp_runtime::DispatchError> {
+				Err(sp_runtime::DispatchError::Other("unsupported"))
+			}
+
+			fn current_block() -> Option<ethereum::Block> { None }
+
+			fn current_receipts() -> Option<Vec<EIP658Receipt>> { None }
+
+			fn current_transaction_statuses() -> Option<Vec<TransactionStatus>> { None }
+
+			fn current_all() -> (
+				Option<ethereum::Block>,
+				Option<Vec<EIP658Receipt>>,
+				Option<Vec<TransactionStatus>>
+			) {
+				(None, None, None)
+			}
+
+			fn pending_block(
+				_xts: Vec<ExtrinsicWrapper<u64>>,
+			) -> (Option<ethereum::Block>, Option<Vec<TransactionStatus>>) {
+				(None, None)
+			}
+		}
+	}
+
+	/// Records the blocks passed on by `ShadowsBlockImport`.
+	#[derive(Clone, Default)]
+	struct TestImport {
+		imported: Arc<Mutex<Vec<H256>>>,
+	}
+
+	impl BlockImport<Block> for TestImport {
+		type Error = ConsensusError;
+		type Transaction = sp_api::TransactionFor<TestClient, Block>;
+
+		fn check_block(&mut self, _block: BlockCheckParams<Block>) -> Result<ImportResult, Self::Error> {
+			Ok(ImportResult::imported(false))
+		}
+
+		fn import_block(
+			&mut self,
+			block: BlockImportParams<Block, Self::Transaction>,
+			_new_cache: HashMap<CacheKeyId, Vec<u8>>,
+		) -> Result<ImportResult, Self::Error> {
+			self.imported.lock().unwrap().push(block.header.hash());
+			Ok(ImportResult::imported(false))
+		}
+	}
+
+	/// Import a block claiming `hashes` in its digest, and built by the runtime as
+	/// `runtime_block()`. Returns the import result and the blocks that reached the inner import.
+	fn import_block(hashes: Hashes) -> (Result<ImportResult, ConsensusError>, Vec<H256>) {
+		let header = Header::new(
+			1,
+			H256::default(),
+			H256::default(),
+			H256::repeat_byte(1),
+			post_log_digest(hashes),
+		);
+		let mut changes = sp_api::StorageChanges::default();
+		changes.main_storage_changes.push((
+			[twox_128(b"Ethereum"), twox_128(b"CurrentBlock")].concat(),
+			Some(runtime_block().encode()),
+		));
+		let mut block = BlockImportParams::new(BlockOrigin::NetworkBroadcast, header);
+		block.body = Some(Vec::new());
+		block.storage_changes = Some(changes);
+
+		let inner = TestImport::default();
+		let backend = fc_db::Backend::<Block>::new(&fc_db::DatabaseSettings {
+			source: fc_db::DatabaseSettingsSrc::InMemory,
+		}).unwrap();
+		let mut block_import = ShadowsBlockImport::new(
+			inner.clone(),
+			Arc::new(TestClient),
+			Arc::new(sc_client_api::in_mem::Backend::<Block>::new()),
+			Arc::new(backend),
+			true,
+		);
+
+		let result = block_import.import_block(block, HashMap::new());
+		let imported = inner.imported.lock().unwrap().clone();
+		(result, imported)
+	}
+
+	#[test]
+	fn block_import_accepts_matching_digest() {
+		let (result, imported) = import_block(Hashes::from_block(runtime_block()));
+
+		assert!(result.is_ok());
+		assert_eq!(imported.len(), 1);
+	}
+
+	#[test]
+	fn block_import_rejects_tampered_digest() {
+		let mut hashes = Hashes::from_block(runtime_block());
+		hashes.transaction_hashes.push(H256::repeat_byte(0xff));
+		let (result, imported) = import_block(hashes);
+
+		assert!(result.is_err());
+		assert!(imported.is_empty());
+	}
+}
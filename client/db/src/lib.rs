@@ -1,13 +1,17 @@
 
 mod utils;
+mod parity_db_adapter;
+mod upgrade;
 
 pub use sp_database::Database;
+pub use upgrade::CURRENT_VERSION;
 
-use std::{sync::Arc, path::{Path, PathBuf}, marker::PhantomData};
+use std::{sync::Arc, path::{Path, PathBuf}, marker::PhantomData, collections::{HashMap, hash_map::Entry}};
 use sp_core::H256;
 use sp_runtime::traits::Block as BlockT;
 use parking_lot::Mutex;
 use codec::{Encode, Decode};
+use fp_rpc::{EIP658Receipt, TransactionStatus};
 
 const DB_HASH_LEN: usize = 32;
 /// Hash type that this backend uses for the database.
@@ -29,6 +33,13 @@ pub enum DatabaseSettingsSrc {
 		/// Cache size in MiB.
 		cache_size: usize,
 	},
+	/// Load a ParityDb database from a given path.
+	ParityDb {
+		/// Path to the database.
+		path: PathBuf,
+	},
+	/// Keep the database in memory, discarding it when the backend is dropped. Used in tests.
+	InMemory,
 }
 
 impl DatabaseSettingsSrc {
@@ -36,21 +47,32 @@ impl DatabaseSettingsSrc {
 	pub fn path(&self) -> Option<&Path> {
 		match self {
 			DatabaseSettingsSrc::RocksDb { path, .. } => Some(path.as_path()),
+			DatabaseSettingsSrc::ParityDb { path } => Some(path.as_path()),
+			DatabaseSettingsSrc::InMemory => None,
 		}
 	}
 }
 
 pub(crate) mod columns {
-	pub const NUM_COLUMNS: u32 = 4;
+	pub const NUM_COLUMNS: u32 = 10;
 
 	pub const META: u32 = 0;
 	pub const BLOCK_MAPPING: u32 = 1;
 	pub const TRANSACTION_MAPPING: u32 = 2;
 	pub const SYNCED_MAPPING: u32 = 3;
+	pub const ETHEREUM_BLOCK: u32 = 4;
+	pub const ETHEREUM_RECEIPTS: u32 = 5;
+	pub const ETHEREUM_STATUSES: u32 = 6;
+	pub const SUBSTRATE_MAPPING: u32 = 7;
+	pub const CANONICAL_NUMBER: u32 = 8;
+	pub const CANONICAL_BLOCK: u32 = 9;
 }
 
 pub(crate) mod static_keys {
-	pub const CURRENT_SYNCING_TIPS: &[u8] = b"CURRENT_SYNCING_TIPS";
+	pub const CANONICAL_HEAD: &[u8] = b"CANONICAL_HEAD";
+	pub const VERSION: &[u8] = b"VERSION";
+	pub const MIGRATION_PROGRESS: &[u8] = b"MIGRATION_PROGRESS";
+	pub const FILTER_POOL: &[u8] = b"FILTER_POOL";
 }
 
 pub struct Backend<Block: BlockT> {
@@ -60,7 +82,7 @@ pub struct Backend<Block: BlockT> {
 
 impl<Block: BlockT> Backend<Block> {
 	pub fn new(config: &DatabaseSettings) -> Result<Self, String> {
-		let db = utils::open_database(config)?;
+		let db = utils::open_database::<Block>(config)?;
 
 		Ok(Self {
 			mapping: Arc::new(MappingDb {
@@ -90,20 +112,27 @@ pub struct MetaDb<Block: BlockT> {
 }
 
 impl<Block: BlockT> MetaDb<Block> {
-	pub fn current_syncing_tips(&self) -> Result<Vec<Block::Hash>, String> {
-		match self.db.get(crate::columns::META, &crate::static_keys::CURRENT_SYNCING_TIPS) {
-			Some(raw) => Ok(Vec::<Block::Hash>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
-			None => Ok(Vec::new()),
+	/// Number and hash of the best block in the canonical index. Every canonical block up to it
+	/// has been synced.
+	pub fn canonical_head(&self) -> Result<Option<(u64, Block::Hash)>, String> {
+		match self.db.get(crate::columns::META, &crate::static_keys::CANONICAL_HEAD) {
+			Some(raw) => Ok(Some(Decode::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+			None => Ok(None),
 		}
 	}
 
-	pub fn write_current_syncing_tips(&self, tips: Vec<Block::Hash>) -> Result<(), String> {
+	/// Filter pool of the RPC layer, as last written with `write_filter_pool`.
+	pub fn filter_pool(&self) -> Result<Option<Vec<u8>>, String> {
+		Ok(self.db.get(crate::columns::META, crate::static_keys::FILTER_POOL))
+	}
+
+	pub fn write_filter_pool(&self, pool: &[u8]) -> Result<(), String> {
 		let mut transaction = sp_database::Transaction::new();
 
 		transaction.set(
 			crate::columns::META,
-			crate::static_keys::CURRENT_SYNCING_TIPS,
-			&tips.encode(),
+			crate::static_keys::FILTER_POOL,
+			pool,
 		);
 
 		self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
@@ -112,10 +141,21 @@ impl<Block: BlockT> MetaDb<Block> {
 	}
 }
 
+/// Full Ethereum data of a block, kept so that history can still be served once the runtime
+/// state of the block has been pruned.
+#[derive(Clone, Encode, Decode)]
+pub struct EthereumBlockData {
+	pub block: ethereum::Block,
+	pub receipts: Vec<EIP658Receipt>,
+	pub statuses: Vec<TransactionStatus>,
+}
+
 pub struct MappingCommitment<Block: BlockT> {
 	pub block_hash: Block::Hash,
 	pub ethereum_block_hash: H256,
 	pub ethereum_transaction_hashes: Vec<H256>,
+	/// Stored alongside the mapping when the block state was available while syncing.
+	pub ethereum_block_data: Option<EthereumBlockData>,
 }
 
 #[derive(Clone, Encode, Decode)]
@@ -123,6 +163,26 @@ pub struct TransactionMetadata<Block: BlockT> {
 	pub block_hash: Block::Hash,
 	pub ethereum_block_hash: H256,
 	pub ethereum_index: u32,
+	/// Whether `block_hash` is part of the canonical chain.
+	pub canonical: bool,
+}
+
+/// Ethereum hashes found in the digest of a Substrate block.
+#[derive(Clone, Encode, Decode)]
+pub struct SubstrateMapping {
+	pub ethereum_block_hash: H256,
+	pub ethereum_transaction_hashes: Vec<H256>,
+}
+
+/// Changes to the canonical chain, as `(number, hash)` pairs of Substrate blocks. Every block must
+/// have been written with `write_hashes` first.
+pub struct CanonicalCommitment<Block: BlockT> {
+	/// Blocks leaving the canonical chain.
+	pub retracted: Vec<(u64, Block::Hash)>,
+	/// Blocks joining the canonical chain.
+	pub enacted: Vec<(u64, Block::Hash)>,
+	/// The new best block.
+	pub head: (u64, Block::Hash),
 }
 
 pub struct MappingDb<Block: BlockT> {
@@ -152,6 +212,79 @@ impl<Block: BlockT> MappingDb<Block> {
 		}
 	}
 
+	/// Canonical Substrate block of the given Ethereum block hash.
+	pub fn canonical_block_hash(
+		&self,
+		ethereum_block_hash: &H256,
+	) -> Result<Option<Block::Hash>, String> {
+		match self.db.get(crate::columns::CANONICAL_BLOCK, &ethereum_block_hash.encode()) {
+			Some(raw) => Ok(Some(Block::Hash::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+			None => Ok(None),
+		}
+	}
+
+	/// Canonical Substrate block at the given height. Ethereum and Substrate block numbers are
+	/// the same.
+	pub fn canonical_hash(
+		&self,
+		number: u64,
+	) -> Result<Option<Block::Hash>, String> {
+		match self.db.get(crate::columns::CANONICAL_NUMBER, &number.encode()) {
+			Some(raw) => Ok(Some(Block::Hash::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+			None => Ok(None),
+		}
+	}
+
+	pub fn substrate_mapping(
+		&self,
+		block_hash: &Block::Hash,
+	) -> Result<Option<SubstrateMapping>, String> {
+		match self.db.get(crate::columns::SUBSTRATE_MAPPING, &block_hash.encode()) {
+			Some(raw) => Ok(Some(SubstrateMapping::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+			None => Ok(None),
+		}
+	}
+
+	/// Whether the Ethereum data of the block is stored, see `MappingCommitment::ethereum_block_data`.
+	pub fn has_ethereum_block_data(&self, block_hash: &Block::Hash) -> bool {
+		self.db.contains(crate::columns::ETHEREUM_BLOCK, &block_hash.encode())
+	}
+
+	pub fn ethereum_block(
+		&self,
+		block_hash: &Block::Hash,
+	) -> Result<Option<ethereum::Block>, String> {
+		self.read_compressed(crate::columns::ETHEREUM_BLOCK, block_hash)
+	}
+
+	pub fn ethereum_receipts(
+		&self,
+		block_hash: &Block::Hash,
+	) -> Result<Option<Vec<EIP658Receipt>>, String> {
+		self.read_compressed(crate::columns::ETHEREUM_RECEIPTS, block_hash)
+	}
+
+	pub fn ethereum_transaction_statuses(
+		&self,
+		block_hash: &Block::Hash,
+	) -> Result<Option<Vec<TransactionStatus>>, String> {
+		self.read_compressed(crate::columns::ETHEREUM_STATUSES, block_hash)
+	}
+
+	fn read_compressed<T: Decode>(
+		&self,
+		column: u32,
+		block_hash: &Block::Hash,
+	) -> Result<Option<T>, String> {
+		match self.db.get(column, &block_hash.encode()) {
+			Some(raw) => {
+				let raw = utils::decompress(&raw)?;
+				Ok(Some(T::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?))
+			},
+			None => Ok(None),
+		}
+	}
+
 	pub fn transaction_metadata(
 		&self,
 		ethereum_transaction_hash: &H256,
@@ -168,38 +301,519 @@ impl<Block: BlockT> MappingDb<Block> {
 	) -> Result<(), String> {
 		let _lock = self.write_lock.lock();
 
-		let mut transaction = sp_database::Transaction::new();
+		let mut batch = WriteBatch::new(self);
+		batch.write_mapping(commitment)?;
+		batch.commit()
+	}
 
-		let mut block_hashes = self.block_hashes(&commitment.ethereum_block_hash)?;
-		block_hashes.push(commitment.block_hash);
-		transaction.set(
-			crate::columns::BLOCK_MAPPING,
-			&commitment.ethereum_block_hash.encode(),
-			&block_hashes.encode()
+	/// Move the canonical chain: retracted blocks are removed from the canonical index and their
+	/// transaction mappings marked as non-canonical, then enacted blocks are added.
+	pub fn write_canonical(
+		&self,
+		commitment: CanonicalCommitment<Block>,
+	) -> Result<(), String> {
+		let _lock = self.write_lock.lock();
+
+		let mut batch = WriteBatch::new(self);
+		batch.write_canonical(commitment)?;
+		batch.commit()
+	}
+
+	/// Write the mappings of a range of blocks and move the canonical chain over it, in a single
+	/// database commit.
+	pub fn write_sync_batch(
+		&self,
+		commitments: Vec<MappingCommitment<Block>>,
+		canonical: CanonicalCommitment<Block>,
+	) -> Result<(), String> {
+		let _lock = self.write_lock.lock();
+
+		let mut batch = WriteBatch::new(self);
+		for commitment in commitments {
+			batch.write_mapping(commitment)?;
+		}
+		batch.write_canonical(canonical)?;
+		batch.commit()
+	}
+
+	/// Remove every mapping of the given blocks, as `(number, hash)` pairs, e.g. after they were
+	/// reverted from the Substrate database.
+	pub fn remove_blocks(
+		&self,
+		blocks: &[(u64, Block::Hash)],
+	) -> Result<(), String> {
+		let _lock = self.write_lock.lock();
+
+		let mut batch = WriteBatch::new(self);
+		let canonical_head = match self.db.get(crate::columns::META, crate::static_keys::CANONICAL_HEAD) {
+			Some(raw) => Some(<(u64, Block::Hash)>::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?),
+			None => None,
+		};
+
+		for (number, block_hash) in blocks {
+			let key = block_hash.encode();
+
+			if let Some(mapping) = self.substrate_mapping(block_hash)? {
+				batch.block_hashes_mut(mapping.ethereum_block_hash)?
+					.retain(|hash| hash != block_hash);
+
+				if self.canonical_block_hash(&mapping.ethereum_block_hash)?.as_ref() == Some(block_hash) {
+					batch.transaction.remove(crate::columns::CANONICAL_BLOCK, &mapping.ethereum_block_hash.encode());
+				}
+
+				for ethereum_transaction_hash in mapping.ethereum_transaction_hashes {
+					batch.metadata_mut(ethereum_transaction_hash)?
+						.retain(|entry| &entry.block_hash != block_hash);
+				}
+			}
+
+			if self.canonical_hash(*number)?.as_ref() == Some(block_hash) {
+				batch.transaction.remove(crate::columns::CANONICAL_NUMBER, &number.encode());
+			}
+
+			for column in &[
+				crate::columns::SYNCED_MAPPING,
+				crate::columns::SUBSTRATE_MAPPING,
+				crate::columns::ETHEREUM_BLOCK,
+				crate::columns::ETHEREUM_RECEIPTS,
+				crate::columns::ETHEREUM_STATUSES,
+			] {
+				batch.transaction.remove(*column, &key);
+			}
+		}
+
+		// Move the head back below the removed blocks.
+		if let Some((_, head_hash)) = canonical_head {
+			if blocks.iter().any(|(_, block_hash)| *block_hash == head_hash) {
+				let lowest = blocks.iter().map(|(number, _)| *number).min().unwrap_or_default();
+				let parent = match lowest.checked_sub(1) {
+					Some(parent) => self.canonical_hash(parent)?.map(|hash| (parent, hash)),
+					None => None,
+				};
+				match parent {
+					Some(parent) => batch.transaction.set(
+						crate::columns::META,
+						crate::static_keys::CANONICAL_HEAD,
+						&parent.encode(),
+					),
+					None => batch.transaction.remove(crate::columns::META, crate::static_keys::CANONICAL_HEAD),
+				}
+			}
+		}
+
+		batch.commit()
+	}
+}
+
+/// Writes to the mapping columns, committed at once. Lists read from the database are kept and
+/// updated in memory, so that several blocks sharing Ethereum hashes can be written together.
+struct WriteBatch<'a, Block: BlockT> {
+	mapping: &'a MappingDb<Block>,
+	transaction: sp_database::Transaction<DbHash>,
+	block_hashes: HashMap<H256, Vec<Block::Hash>>,
+	metadata: HashMap<H256, Vec<TransactionMetadata<Block>>>,
+	substrate_mappings: HashMap<Block::Hash, SubstrateMapping>,
+}
+
+impl<'a, Block: BlockT> WriteBatch<'a, Block> {
+	fn new(mapping: &'a MappingDb<Block>) -> Self {
+		Self {
+			mapping,
+			transaction: sp_database::Transaction::new(),
+			block_hashes: HashMap::new(),
+			metadata: HashMap::new(),
+			substrate_mappings: HashMap::new(),
+		}
+	}
+
+	fn block_hashes_mut(&mut self, ethereum_block_hash: H256) -> Result<&mut Vec<Block::Hash>, String> {
+		Ok(match self.block_hashes.entry(ethereum_block_hash) {
+			Entry::Occupied(occupied) => occupied.into_mut(),
+			Entry::Vacant(vacant) => vacant.insert(self.mapping.block_hashes(&ethereum_block_hash)?),
+		})
+	}
+
+	fn metadata_mut(
+		&mut self,
+		ethereum_transaction_hash: H256,
+	) -> Result<&mut Vec<TransactionMetadata<Block>>, String> {
+		Ok(match self.metadata.entry(ethereum_transaction_hash) {
+			Entry::Occupied(occupied) => occupied.into_mut(),
+			Entry::Vacant(vacant) => vacant.insert(self.mapping.transaction_metadata(&ethereum_transaction_hash)?),
+		})
+	}
+
+	fn substrate_mapping(&self, block_hash: &Block::Hash) -> Result<Option<SubstrateMapping>, String> {
+		match self.substrate_mappings.get(block_hash) {
+			Some(mapping) => Ok(Some(mapping.clone())),
+			None => self.mapping.substrate_mapping(block_hash),
+		}
+	}
+
+	fn write_mapping(&mut self, commitment: MappingCommitment<Block>) -> Result<(), String> {
+		// Writing a block again replaces its previous mappings.
+		let block_hashes = self.block_hashes_mut(commitment.ethereum_block_hash)?;
+		if !block_hashes.contains(&commitment.block_hash) {
+			block_hashes.push(commitment.block_hash);
+		}
+
+		let substrate_mapping = SubstrateMapping {
+			ethereum_block_hash: commitment.ethereum_block_hash,
+			ethereum_transaction_hashes: commitment.ethereum_transaction_hashes.clone(),
+		};
+		self.transaction.set(
+			crate::columns::SUBSTRATE_MAPPING,
+			&commitment.block_hash.encode(),
+			&substrate_mapping.encode(),
 		);
+		self.substrate_mappings.insert(commitment.block_hash, substrate_mapping);
 
 		for (i, ethereum_transaction_hash) in commitment.ethereum_transaction_hashes.into_iter().enumerate() {
-			let mut metadata = self.transaction_metadata(&ethereum_transaction_hash)?;
+			let metadata = self.metadata_mut(ethereum_transaction_hash)?;
+			let canonical = metadata.iter()
+				.any(|entry| entry.block_hash == commitment.block_hash && entry.canonical);
+			metadata.retain(|entry| entry.block_hash != commitment.block_hash);
 			metadata.push(TransactionMetadata::<Block> {
 				block_hash: commitment.block_hash,
 				ethereum_block_hash: commitment.ethereum_block_hash,
 				ethereum_index: i as u32,
+				canonical,
 			});
-			transaction.set(
-				crate::columns::TRANSACTION_MAPPING,
-				&ethereum_transaction_hash.encode(),
-				&metadata.encode(),
+		}
+
+		if let Some(data) = commitment.ethereum_block_data {
+			let key = commitment.block_hash.encode();
+			self.transaction.set_from_vec(
+				crate::columns::ETHEREUM_BLOCK,
+				&key,
+				utils::compress(&data.block.encode())?,
+			);
+			self.transaction.set_from_vec(
+				crate::columns::ETHEREUM_RECEIPTS,
+				&key,
+				utils::compress(&data.receipts.encode())?,
+			);
+			self.transaction.set_from_vec(
+				crate::columns::ETHEREUM_STATUSES,
+				&key,
+				utils::compress(&data.statuses.encode())?,
 			);
 		}
 
-		transaction.set(
+		self.transaction.set(
 			crate::columns::SYNCED_MAPPING,
 			&commitment.block_hash.encode(),
 			&true.encode(),
 		);
 
-		self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+		Ok(())
+	}
+
+	fn write_canonical(&mut self, commitment: CanonicalCommitment<Block>) -> Result<(), String> {
+		for (number, block_hash) in commitment.retracted {
+			if self.mapping.canonical_hash(number)? == Some(block_hash) {
+				self.transaction.remove(crate::columns::CANONICAL_NUMBER, &number.encode());
+			}
+			self.mark(block_hash, false)?;
+		}
+
+		for (number, block_hash) in commitment.enacted {
+			self.transaction.set(crate::columns::CANONICAL_NUMBER, &number.encode(), &block_hash.encode());
+			self.mark(block_hash, true)?;
+		}
+
+		self.transaction.set(
+			crate::columns::META,
+			crate::static_keys::CANONICAL_HEAD,
+			&commitment.head.encode(),
+		);
 
 		Ok(())
 	}
+
+	/// Add or remove a block from the canonical index of Ethereum hashes.
+	fn mark(&mut self, block_hash: Block::Hash, canonical: bool) -> Result<(), String> {
+		let mapping = match self.substrate_mapping(&block_hash)? {
+			Some(mapping) => mapping,
+			None => return Ok(()),
+		};
+
+		if canonical {
+			self.transaction.set(
+				crate::columns::CANONICAL_BLOCK,
+				&mapping.ethereum_block_hash.encode(),
+				&block_hash.encode(),
+			);
+		} else if self.mapping.canonical_block_hash(&mapping.ethereum_block_hash)? == Some(block_hash) {
+			self.transaction.remove(
+				crate::columns::CANONICAL_BLOCK,
+				&mapping.ethereum_block_hash.encode(),
+			);
+		}
+
+		for ethereum_transaction_hash in mapping.ethereum_transaction_hashes {
+			for entry in self.metadata_mut(ethereum_transaction_hash)?
+				.iter_mut()
+				.filter(|entry| entry.block_hash == block_hash)
+			{
+				entry.canonical = canonical;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn commit(mut self) -> Result<(), String> {
+		for (ethereum_block_hash, hashes) in self.block_hashes {
+			if hashes.is_empty() {
+				self.transaction.remove(crate::columns::BLOCK_MAPPING, &ethereum_block_hash.encode());
+			} else {
+				self.transaction.set(crate::columns::BLOCK_MAPPING, &ethereum_block_hash.encode(), &hashes.encode());
+			}
+		}
+
+		for (ethereum_transaction_hash, entries) in self.metadata {
+			if entries.is_empty() {
+				self.transaction.remove(crate::columns::TRANSACTION_MAPPING, &ethereum_transaction_hash.encode());
+			} else {
+				self.transaction.set(
+					crate::columns::TRANSACTION_MAPPING,
+					&ethereum_transaction_hash.encode(),
+					&entries.encode(),
+				);
+			}
+		}
+
+		self.mapping.db.commit(self.transaction).map_err(|e| format!("{:?}", e))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	/// Run `scenario` against a fresh database of every kind.
+	fn with_each_backend(scenario: impl Fn(&Backend<Block>)) {
+		let tmp = tempfile::tempdir().unwrap();
+		let sources = vec![
+			DatabaseSettingsSrc::InMemory,
+			DatabaseSettingsSrc::RocksDb { path: tmp.path().join("rocksdb"), cache_size: 0 },
+			DatabaseSettingsSrc::ParityDb { path: tmp.path().join("paritydb") },
+		];
+
+		for source in sources {
+			let backend = Backend::<Block>::new(&DatabaseSettings { source }).unwrap();
+			scenario(&backend);
+		}
+	}
+
+	fn write_block(backend: &Backend<Block>, seed: u8, transactions: Vec<H256>) -> H256 {
+		let block_hash = H256::repeat_byte(seed);
+		backend.mapping().write_hashes(MappingCommitment {
+			block_hash,
+			ethereum_block_hash: H256::from_low_u64_be(seed as u64),
+			ethereum_transaction_hashes: transactions,
+			ethereum_block_data: None,
+		}).unwrap();
+		block_hash
+	}
+
+	fn canonical_blocks(backend: &Backend<Block>, transaction_hash: &H256) -> Vec<H256> {
+		backend.mapping().transaction_metadata(transaction_hash).unwrap()
+			.into_iter()
+			.filter(|metadata| metadata.canonical)
+			.map(|metadata| metadata.block_hash)
+			.collect()
+	}
+
+	#[test]
+	fn mappings_and_meta_round_trip() {
+		with_each_backend(|backend| {
+			let transaction_hash = H256::repeat_byte(0xaa);
+			let genesis = write_block(backend, 0, vec![]);
+			let block = write_block(backend, 1, vec![transaction_hash]);
+			assert!(backend.mapping().is_synced(&block).unwrap());
+			assert!(!backend.mapping().is_synced(&H256::repeat_byte(3)).unwrap());
+			assert_eq!(backend.mapping().block_hashes(&H256::from_low_u64_be(1)).unwrap(), vec![block]);
+
+			backend.mapping().write_canonical(CanonicalCommitment {
+				retracted: vec![],
+				enacted: vec![(0, genesis), (1, block)],
+				head: (1, block),
+			}).unwrap();
+			assert_eq!(backend.mapping().canonical_hash(1).unwrap(), Some(block));
+			assert_eq!(canonical_blocks(backend, &transaction_hash), vec![block]);
+			assert_eq!(backend.meta().canonical_head().unwrap(), Some((1, block)));
+		});
+	}
+
+	#[test]
+	fn filter_pool_is_stored() {
+		with_each_backend(|backend| {
+			assert_eq!(backend.meta().filter_pool().unwrap(), None);
+
+			backend.meta().write_filter_pool(b"[1]").unwrap();
+			backend.meta().write_filter_pool(b"[1,2]").unwrap();
+
+			assert_eq!(backend.meta().filter_pool().unwrap(), Some(b"[1,2]".to_vec()));
+		});
+	}
+
+	#[test]
+	fn fork_switch_updates_canonical_index() {
+		with_each_backend(|backend| {
+			let shared = H256::repeat_byte(0xaa);
+			let only_a = H256::repeat_byte(0xbb);
+
+			let a1 = write_block(backend, 1, vec![only_a]);
+			let a2 = write_block(backend, 2, vec![shared]);
+			let b1 = write_block(backend, 11, vec![]);
+			let b2 = write_block(backend, 12, vec![shared]);
+			let b3 = write_block(backend, 13, vec![]);
+
+			backend.mapping().write_canonical(CanonicalCommitment {
+				retracted: vec![],
+				enacted: vec![(1, a1), (2, a2)],
+				head: (2, a2),
+			}).unwrap();
+
+			assert_eq!(backend.mapping().canonical_hash(2).unwrap(), Some(a2));
+			assert_eq!(backend.mapping().canonical_block_hash(&H256::from_low_u64_be(1)).unwrap(), Some(a1));
+			assert_eq!(canonical_blocks(backend, &shared), vec![a2]);
+			assert_eq!(canonical_blocks(backend, &only_a), vec![a1]);
+
+			backend.mapping().write_canonical(CanonicalCommitment {
+				retracted: vec![(1, a1), (2, a2)],
+				enacted: vec![(1, b1), (2, b2), (3, b3)],
+				head: (3, b3),
+			}).unwrap();
+
+			assert_eq!(backend.mapping().canonical_hash(1).unwrap(), Some(b1));
+			assert_eq!(backend.mapping().canonical_hash(2).unwrap(), Some(b2));
+			assert_eq!(backend.mapping().canonical_hash(3).unwrap(), Some(b3));
+			assert_eq!(backend.mapping().canonical_block_hash(&H256::from_low_u64_be(1)).unwrap(), None);
+			assert_eq!(backend.mapping().canonical_block_hash(&H256::from_low_u64_be(12)).unwrap(), Some(b2));
+			assert_eq!(canonical_blocks(backend, &shared), vec![b2]);
+			assert!(canonical_blocks(backend, &only_a).is_empty());
+			assert_eq!(backend.meta().canonical_head().unwrap(), Some((3, b3)));
+		});
+	}
+
+	#[test]
+	fn switching_to_shorter_fork_removes_higher_numbers() {
+		with_each_backend(|backend| {
+			let a1 = write_block(backend, 1, vec![]);
+			let a2 = write_block(backend, 2, vec![]);
+			let b1 = write_block(backend, 11, vec![]);
+
+			backend.mapping().write_canonical(CanonicalCommitment {
+				retracted: vec![],
+				enacted: vec![(1, a1), (2, a2)],
+				head: (2, a2),
+			}).unwrap();
+			backend.mapping().write_canonical(CanonicalCommitment {
+				retracted: vec![(2, a2), (1, a1)],
+				enacted: vec![(1, b1)],
+				head: (1, b1),
+			}).unwrap();
+
+			assert_eq!(backend.mapping().canonical_hash(1).unwrap(), Some(b1));
+			assert_eq!(backend.mapping().canonical_hash(2).unwrap(), None);
+			assert_eq!(backend.mapping().canonical_block_hash(&H256::from_low_u64_be(2)).unwrap(), None);
+			assert_eq!(backend.meta().canonical_head().unwrap(), Some((1, b1)));
+		});
+	}
+
+	#[test]
+	fn removed_blocks_are_unmapped() {
+		with_each_backend(|backend| {
+			let shared = H256::repeat_byte(0xaa);
+
+			let a1 = write_block(backend, 1, vec![shared]);
+			let a2 = write_block(backend, 2, vec![]);
+			let b1 = write_block(backend, 11, vec![shared]);
+
+			backend.mapping().write_canonical(CanonicalCommitment {
+				retracted: vec![],
+				enacted: vec![(1, a1), (2, a2)],
+				head: (2, a2),
+			}).unwrap();
+			backend.mapping().remove_blocks(&[(2, a2), (1, a1)]).unwrap();
+
+			assert!(!backend.mapping().is_synced(&a1).unwrap());
+			assert!(backend.mapping().substrate_mapping(&a2).unwrap().is_none());
+			assert!(backend.mapping().block_hashes(&H256::from_low_u64_be(1)).unwrap().is_empty());
+			assert_eq!(backend.mapping().canonical_hash(1).unwrap(), None);
+			assert_eq!(backend.mapping().canonical_block_hash(&H256::from_low_u64_be(2)).unwrap(), None);
+			assert_eq!(backend.meta().canonical_head().unwrap(), None);
+
+			let metadata = backend.mapping().transaction_metadata(&shared).unwrap();
+			assert_eq!(metadata.len(), 1);
+			assert_eq!(metadata[0].block_hash, b1);
+		});
+	}
+
+	#[test]
+	fn writing_a_block_again_replaces_its_mappings() {
+		with_each_backend(|backend| {
+			let transaction_hash = H256::repeat_byte(0xaa);
+			let block = write_block(backend, 1, vec![transaction_hash]);
+			backend.mapping().write_canonical(CanonicalCommitment {
+				retracted: vec![],
+				enacted: vec![(1, block)],
+				head: (1, block),
+			}).unwrap();
+			write_block(backend, 1, vec![transaction_hash]);
+
+			assert_eq!(backend.mapping().block_hashes(&H256::from_low_u64_be(1)).unwrap(), vec![block]);
+			assert_eq!(canonical_blocks(backend, &transaction_hash), vec![block]);
+			assert_eq!(backend.mapping().transaction_metadata(&transaction_hash).unwrap().len(), 1);
+		});
+	}
+
+	#[test]
+	fn removing_the_head_moves_it_back() {
+		with_each_backend(|backend| {
+			let genesis = write_block(backend, 0, vec![]);
+			let a1 = write_block(backend, 1, vec![]);
+			let a2 = write_block(backend, 2, vec![]);
+			backend.mapping().write_canonical(CanonicalCommitment {
+				retracted: vec![],
+				enacted: vec![(0, genesis), (1, a1), (2, a2)],
+				head: (2, a2),
+			}).unwrap();
+
+			backend.mapping().remove_blocks(&[(2, a2)]).unwrap();
+
+			assert_eq!(backend.meta().canonical_head().unwrap(), Some((1, a1)));
+			assert_eq!(backend.mapping().canonical_hash(2).unwrap(), None);
+		});
+	}
+
+	#[test]
+	fn sync_batch_is_written_at_once() {
+		with_each_backend(|backend| {
+			let shared = H256::repeat_byte(0xaa);
+			let commitments = (1..=3u8).map(|seed| MappingCommitment {
+				block_hash: H256::repeat_byte(seed),
+				ethereum_block_hash: H256::from_low_u64_be(seed as u64),
+				ethereum_transaction_hashes: vec![shared],
+				ethereum_block_data: None,
+			}).collect();
+
+			backend.mapping().write_sync_batch(commitments, CanonicalCommitment {
+				retracted: vec![],
+				enacted: vec![(1, H256::repeat_byte(1)), (2, H256::repeat_byte(2))],
+				head: (2, H256::repeat_byte(2)),
+			}).unwrap();
+
+			assert!(backend.mapping().is_synced(&H256::repeat_byte(3)).unwrap());
+			assert_eq!(backend.mapping().transaction_metadata(&shared).unwrap().len(), 3);
+			assert_eq!(canonical_blocks(backend, &shared), vec![H256::repeat_byte(1), H256::repeat_byte(2)]);
+			assert_eq!(backend.meta().canonical_head().unwrap(), Some((2, H256::repeat_byte(2))));
+		});
+	}
 }
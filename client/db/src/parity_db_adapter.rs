@@ -0,0 +1,39 @@
+use sp_database::{error::DatabaseError, Change, ColumnId, Database, Transaction};
+
+fn database_error(message: String) -> DatabaseError {
+	DatabaseError(Box::new(std::io::Error::new(std::io::ErrorKind::Other, message)))
+}
+
+/// Wraps ParityDb to implement `sp_database::Database`.
+pub struct DbAdapter(pub parity_db::Db);
+
+impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
+	fn commit(&self, transaction: Transaction<H>) -> Result<(), DatabaseError> {
+		let mut changes = Vec::with_capacity(transaction.0.len());
+		for change in transaction.0 {
+			match change {
+				Change::Set(col, key, value) => changes.push((col as u8, key, Some(value))),
+				Change::Remove(col, key) => changes.push((col as u8, key, None)),
+				// None of the columns is content-addressed, so nothing is ever stored by hash.
+				Change::Store(..) | Change::Release(..) =>
+					return Err(database_error("Storing values by hash is not supported".to_string())),
+			}
+		}
+
+		self.0.commit(changes).map_err(|e| database_error(format!("{:?}", e)))
+	}
+
+	fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
+		match self.0.get(col as u8, key) {
+			Ok(value) => value,
+			Err(e) => {
+				log::error!(target: "fc-db", "Failed to read column {}: {:?}", col, e);
+				None
+			},
+		}
+	}
+
+	fn lookup(&self, _hash: &H) -> Option<Vec<u8>> {
+		None
+	}
+}
@@ -0,0 +1,449 @@
+//! Versioning of the database layout, and migrations between versions.
+
+use std::collections::{HashMap, hash_map::Entry};
+use codec::{Encode, Decode};
+use kvdb::KeyValueDB;
+use log::info;
+use sp_core::H256;
+use sp_runtime::traits::Block as BlockT;
+use crate::{Database, DbHash, TransactionMetadata, SubstrateMapping};
+
+/// Version of the database layout written by this code.
+pub const CURRENT_VERSION: u32 = 2;
+/// Version of the databases created before the version was stored.
+const UNVERSIONED: u32 = 1;
+/// Number of entries migrated per database commit.
+const BATCH_SIZE: usize = 10_000;
+
+/// Upgrade from the previous version to `version`.
+struct Migration {
+	version: u32,
+	description: &'static str,
+	run: fn(&dyn KeyValueDB) -> Result<(), String>,
+}
+
+/// Every migration, in order.
+fn migrations<Block: BlockT>() -> Vec<Migration> {
+	vec![
+		Migration {
+			version: 2,
+			description: "canonical transaction mappings and Substrate block index",
+			run: migrate_1_to_2::<Block>,
+		},
+	]
+}
+
+fn read_version(db: &dyn KeyValueDB) -> Result<Option<u32>, String> {
+	match db.get(crate::columns::META, crate::static_keys::VERSION).map_err(|e| format!("{:?}", e))? {
+		Some(raw) => Ok(Some(u32::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+		None => Ok(None),
+	}
+}
+
+fn write_version(db: &dyn KeyValueDB, version: u32) -> Result<(), String> {
+	let mut transaction = db.transaction();
+	transaction.put(crate::columns::META, crate::static_keys::VERSION, &version.encode());
+	transaction.delete(crate::columns::META, crate::static_keys::MIGRATION_PROGRESS);
+	db.write(transaction).map_err(|e| format!("{:?}", e))
+}
+
+/// Step of the running migration, and the last key that step migrated, if it started.
+type Progress = (u32, Option<Vec<u8>>);
+
+fn read_progress(db: &dyn KeyValueDB) -> Result<Option<Progress>, String> {
+	match db.get(crate::columns::META, crate::static_keys::MIGRATION_PROGRESS).map_err(|e| format!("{:?}", e))? {
+		Some(raw) => Ok(Some(Progress::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+		None => Ok(None),
+	}
+}
+
+/// Bring a key-value database to the current version, running every migration it is missing.
+pub fn upgrade_database<Block: BlockT>(db: &dyn KeyValueDB) -> Result<(), String> {
+	let version = match read_version(db)? {
+		Some(version) => version,
+		None if db.iter(crate::columns::META).next().is_none()
+			&& db.iter(crate::columns::SYNCED_MAPPING).next().is_none() =>
+		{
+			return write_version(db, CURRENT_VERSION)
+		},
+		None => UNVERSIONED,
+	};
+
+	if version > CURRENT_VERSION {
+		return Err(format!(
+			"Unknown Frontier database version {}, the highest supported version is {}",
+			version, CURRENT_VERSION,
+		))
+	}
+
+	for migration in migrations::<Block>().into_iter().filter(|migration| migration.version > version) {
+		info!(
+			target: "fc-db",
+			"Upgrading Frontier database to version {}: {}",
+			migration.version,
+			migration.description,
+		);
+		(migration.run)(db)?;
+		write_version(db, migration.version)?;
+	}
+
+	Ok(())
+}
+
+/// Check the version of a database that cannot be migrated. Such databases never had an older
+/// layout, so a missing version means the database is new.
+pub fn check_version(db: &dyn Database<DbHash>) -> Result<(), String> {
+	let version = match db.get(crate::columns::META, crate::static_keys::VERSION) {
+		Some(raw) => u32::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?,
+		None => {
+			let mut transaction = sp_database::Transaction::new();
+			transaction.set(crate::columns::META, crate::static_keys::VERSION, &CURRENT_VERSION.encode());
+			return db.commit(transaction).map_err(|e| format!("{:?}", e))
+		},
+	};
+
+	if version != CURRENT_VERSION {
+		return Err(format!(
+			"Frontier database version {} cannot be opened, the supported version is {}",
+			version, CURRENT_VERSION,
+		))
+	}
+
+	Ok(())
+}
+
+/// Counts migrated entries and commits them every `BATCH_SIZE` entries, together with the
+/// progress of the migration. Migration steps rewrite entries in place, so an interrupted step
+/// resumes after the last committed key instead of migrating entries twice.
+struct Batch<'a> {
+	db: &'a dyn KeyValueDB,
+	transaction: kvdb::DBTransaction,
+	step: u32,
+	/// Entries up to this key were migrated by a previous run.
+	resume_after: Option<Vec<u8>>,
+	last_key: Option<Vec<u8>>,
+	pending: usize,
+	done: usize,
+	what: &'static str,
+}
+
+impl<'a> Batch<'a> {
+	/// Start or resume migration step `step`, returns `None` if a previous run completed it.
+	fn new(db: &'a dyn KeyValueDB, step: u32, what: &'static str) -> Result<Option<Self>, String> {
+		let resume_after = match read_progress(db)? {
+			Some((done, _)) if done > step => return Ok(None),
+			Some((done, last_key)) if done == step => last_key,
+			_ => None,
+		};
+
+		Ok(Some(Self {
+			db,
+			transaction: db.transaction(),
+			step,
+			last_key: resume_after.clone(),
+			resume_after,
+			pending: 0,
+			done: 0,
+			what,
+		}))
+	}
+
+	/// Whether the entry at `key` was migrated by a previous run.
+	fn is_migrated(&self, key: &[u8]) -> bool {
+		self.resume_after.as_ref().map_or(false, |last_key| key <= &last_key[..])
+	}
+
+	/// Count the entry at `key`, returns whether the batch is full. Keys must be counted in
+	/// iteration order.
+	fn count(&mut self, key: &[u8]) -> bool {
+		self.last_key = Some(key.to_vec());
+		self.pending += 1;
+		self.pending >= BATCH_SIZE
+	}
+
+	fn commit(&mut self) -> Result<(), String> {
+		let mut transaction = std::mem::replace(&mut self.transaction, self.db.transaction());
+		transaction.put(
+			crate::columns::META,
+			crate::static_keys::MIGRATION_PROGRESS,
+			&(self.step, self.last_key.clone()).encode(),
+		);
+		self.db.write(transaction).map_err(|e| format!("{:?}", e))?;
+		self.done += self.pending;
+		self.pending = 0;
+		info!(target: "fc-db", "Migrated {} {}", self.done, self.what);
+		Ok(())
+	}
+
+	/// Commit the remaining entries and mark the step as completed.
+	fn finish(mut self) -> Result<(), String> {
+		self.step += 1;
+		self.last_key = None;
+		self.commit()
+	}
+}
+
+/// Version 2 marks transaction mappings as canonical or not, and indexes the Ethereum hashes of
+/// every Substrate block. Transaction mappings start as non-canonical: the canonical index is
+/// empty after this migration and mapping sync rebuilds it from the genesis block, storing the
+/// Ethereum data of the blocks it walks along the way.
+fn migrate_1_to_2<Block: BlockT>(db: &dyn KeyValueDB) -> Result<(), String> {
+	if let Some(mut batch) = Batch::new(db, 0, "Substrate block mappings")? {
+		migrate_block_mappings::<Block>(db, &mut batch)?;
+		batch.finish()?;
+	}
+	if let Some(mut batch) = Batch::new(db, 1, "transaction mappings")? {
+		migrate_transaction_mappings::<Block>(db, &mut batch)?;
+		batch.finish()?;
+	}
+
+	Ok(())
+}
+
+fn migrate_block_mappings<Block: BlockT>(db: &dyn KeyValueDB, batch: &mut Batch) -> Result<(), String> {
+	for (key, value) in db.iter(crate::columns::BLOCK_MAPPING) {
+		if batch.is_migrated(&key) {
+			continue
+		}
+		let ethereum_block_hash = H256::decode(&mut &key[..]).map_err(|e| format!("{:?}", e))?;
+		let block_hashes = Vec::<Block::Hash>::decode(&mut &value[..]).map_err(|e| format!("{:?}", e))?;
+
+		for block_hash in block_hashes {
+			batch.transaction.put(
+				crate::columns::SUBSTRATE_MAPPING,
+				&block_hash.encode(),
+				&SubstrateMapping {
+					ethereum_block_hash,
+					ethereum_transaction_hashes: Vec::new(),
+				}.encode(),
+			);
+		}
+
+		if batch.count(&key) {
+			batch.commit()?;
+		}
+	}
+
+	Ok(())
+}
+
+fn migrate_transaction_mappings<Block: BlockT>(db: &dyn KeyValueDB, batch: &mut Batch) -> Result<(), String> {
+	#[derive(Decode)]
+	struct TransactionMetadataV1<Block: BlockT> {
+		block_hash: Block::Hash,
+		ethereum_block_hash: H256,
+		ethereum_index: u32,
+	}
+
+	// Block mappings updated in the current batch, not readable from the database yet.
+	let mut mappings = HashMap::<Block::Hash, SubstrateMapping>::new();
+	for (key, value) in db.iter(crate::columns::TRANSACTION_MAPPING) {
+		if batch.is_migrated(&key) {
+			continue
+		}
+		let ethereum_transaction_hash = H256::decode(&mut &key[..]).map_err(|e| format!("{:?}", e))?;
+		let legacy = Vec::<TransactionMetadataV1<Block>>::decode(&mut &value[..])
+			.map_err(|e| format!("{:?}", e))?;
+
+		let mut metadata = Vec::with_capacity(legacy.len());
+		for entry in legacy {
+			let mapping = match mappings.entry(entry.block_hash) {
+				Entry::Occupied(occupied) => occupied.into_mut(),
+				Entry::Vacant(vacant) => {
+					let existing = db.get(crate::columns::SUBSTRATE_MAPPING, &entry.block_hash.encode())
+						.map_err(|e| format!("{:?}", e))?;
+					vacant.insert(match existing {
+						Some(raw) => SubstrateMapping::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?,
+						None => SubstrateMapping {
+							ethereum_block_hash: entry.ethereum_block_hash,
+							ethereum_transaction_hashes: Vec::new(),
+						},
+					})
+				},
+			};
+			let index = entry.ethereum_index as usize;
+			if mapping.ethereum_transaction_hashes.len() <= index {
+				mapping.ethereum_transaction_hashes.resize(index + 1, H256::default());
+			}
+			mapping.ethereum_transaction_hashes[index] = ethereum_transaction_hash;
+
+			metadata.push(TransactionMetadata::<Block> {
+				block_hash: entry.block_hash,
+				ethereum_block_hash: entry.ethereum_block_hash,
+				ethereum_index: entry.ethereum_index,
+				canonical: false,
+			});
+		}
+
+		batch.transaction.put(crate::columns::TRANSACTION_MAPPING, &key, &metadata.encode());
+
+		if batch.count(&key) {
+			for (block_hash, mapping) in mappings.drain() {
+				batch.transaction.put(crate::columns::SUBSTRATE_MAPPING, &block_hash.encode(), &mapping.encode());
+			}
+			batch.commit()?;
+		}
+	}
+	for (block_hash, mapping) in mappings.drain() {
+		batch.transaction.put(crate::columns::SUBSTRATE_MAPPING, &block_hash.encode(), &mapping.encode());
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+	use crate::{Backend, DatabaseSettings, DatabaseSettingsSrc};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	const V1_NUM_COLUMNS: u32 = 4;
+
+	fn open_kvdb(path: &std::path::Path, columns: u32) -> kvdb_rocksdb::Database {
+		let config = kvdb_rocksdb::DatabaseConfig::with_columns(columns);
+		kvdb_rocksdb::Database::open(&config, path.to_str().unwrap()).unwrap()
+	}
+
+	fn open_backend(path: &std::path::Path) -> Result<Backend<Block>, String> {
+		Backend::<Block>::new(&DatabaseSettings {
+			source: DatabaseSettingsSrc::RocksDb { path: path.to_path_buf(), cache_size: 0 },
+		})
+	}
+
+	/// A database written with the layout from before versioning: block `0x01..` holds
+	/// transactions `0xaa..` and `0xbb..`, block `0x02..` (a fork of it) holds `0xbb..` only, and
+	/// block `0x03..` is empty.
+	fn write_v1_fixture(db: &kvdb_rocksdb::Database) {
+		let block_1 = H256::repeat_byte(1);
+		let block_2 = H256::repeat_byte(2);
+		let block_3 = H256::repeat_byte(3);
+		let transaction_a = H256::repeat_byte(0xaa);
+		let transaction_b = H256::repeat_byte(0xbb);
+
+		let mut transaction = db.transaction();
+		transaction.put(
+			crate::columns::BLOCK_MAPPING,
+			&H256::from_low_u64_be(1).encode(),
+			&vec![block_1].encode(),
+		);
+		transaction.put(
+			crate::columns::BLOCK_MAPPING,
+			&H256::from_low_u64_be(2).encode(),
+			&vec![block_2].encode(),
+		);
+		transaction.put(
+			crate::columns::BLOCK_MAPPING,
+			&H256::from_low_u64_be(3).encode(),
+			&vec![block_3].encode(),
+		);
+		transaction.put(
+			crate::columns::TRANSACTION_MAPPING,
+			&transaction_a.encode(),
+			&vec![(block_1, H256::from_low_u64_be(1), 0u32)].encode(),
+		);
+		transaction.put(
+			crate::columns::TRANSACTION_MAPPING,
+			&transaction_b.encode(),
+			&vec![
+				(block_1, H256::from_low_u64_be(1), 1u32),
+				(block_2, H256::from_low_u64_be(2), 0u32),
+			].encode(),
+		);
+		for block in &[block_1, block_2, block_3] {
+			transaction.put(crate::columns::SYNCED_MAPPING, &block.encode(), &true.encode());
+		}
+		db.write(transaction).unwrap();
+	}
+
+	#[test]
+	fn new_database_gets_current_version() {
+		let tmp = tempfile::tempdir().unwrap();
+		drop(open_backend(tmp.path()).unwrap());
+
+		let db = open_kvdb(tmp.path(), crate::columns::NUM_COLUMNS);
+		assert_eq!(read_version(&db).unwrap(), Some(CURRENT_VERSION));
+	}
+
+	#[test]
+	fn unknown_version_is_refused() {
+		let tmp = tempfile::tempdir().unwrap();
+		{
+			let db = open_kvdb(tmp.path(), crate::columns::NUM_COLUMNS);
+			write_version(&db, CURRENT_VERSION + 1).unwrap();
+		}
+
+		assert!(open_backend(tmp.path()).is_err());
+	}
+
+	#[test]
+	fn unversioned_database_is_migrated() {
+		let tmp = tempfile::tempdir().unwrap();
+		{
+			let db = open_kvdb(tmp.path(), V1_NUM_COLUMNS);
+			write_v1_fixture(&db);
+		}
+
+		let backend = open_backend(tmp.path()).unwrap();
+		let mapping = backend.mapping();
+
+		let metadata = mapping.transaction_metadata(&H256::repeat_byte(0xbb)).unwrap();
+		assert_eq!(metadata.len(), 2);
+		assert_eq!(metadata[0].block_hash, H256::repeat_byte(1));
+		assert_eq!(metadata[0].ethereum_index, 1);
+		assert_eq!(metadata[1].block_hash, H256::repeat_byte(2));
+		assert!(metadata.iter().all(|entry| !entry.canonical));
+
+		let block_1 = mapping.substrate_mapping(&H256::repeat_byte(1)).unwrap().unwrap();
+		assert_eq!(block_1.ethereum_block_hash, H256::from_low_u64_be(1));
+		assert_eq!(
+			block_1.ethereum_transaction_hashes,
+			vec![H256::repeat_byte(0xaa), H256::repeat_byte(0xbb)],
+		);
+		let block_3 = mapping.substrate_mapping(&H256::repeat_byte(3)).unwrap().unwrap();
+		assert!(block_3.ethereum_transaction_hashes.is_empty());
+
+		assert!(mapping.is_synced(&H256::repeat_byte(3)).unwrap());
+		assert_eq!(backend.meta().canonical_head().unwrap(), None);
+		drop(backend);
+
+		let db = open_kvdb(tmp.path(), crate::columns::NUM_COLUMNS);
+		assert_eq!(read_version(&db).unwrap(), Some(CURRENT_VERSION));
+	}
+
+	#[test]
+	fn interrupted_migration_resumes_after_the_last_commit() {
+		let tmp = tempfile::tempdir().unwrap();
+		{
+			let db = open_kvdb(tmp.path(), crate::columns::NUM_COLUMNS);
+			write_v1_fixture(&db);
+			migrate_1_to_2::<Block>(&db).unwrap();
+
+			// Stopped after committing the last transaction mapping, before completing the step.
+			let mut transaction = db.transaction();
+			transaction.put(
+				crate::columns::META,
+				crate::static_keys::MIGRATION_PROGRESS,
+				&(1u32, Some(H256::repeat_byte(0xbb).encode())).encode(),
+			);
+			db.write(transaction).unwrap();
+		}
+
+		let backend = open_backend(tmp.path()).unwrap();
+		let mapping = backend.mapping();
+
+		let metadata = mapping.transaction_metadata(&H256::repeat_byte(0xbb)).unwrap();
+		assert_eq!(metadata.len(), 2);
+		assert_eq!(metadata[1].block_hash, H256::repeat_byte(2));
+		assert_eq!(metadata[1].ethereum_index, 0);
+		assert_eq!(
+			mapping.substrate_mapping(&H256::repeat_byte(1)).unwrap().unwrap().ethereum_transaction_hashes,
+			vec![H256::repeat_byte(0xaa), H256::repeat_byte(0xbb)],
+		);
+		drop(backend);
+
+		let db = open_kvdb(tmp.path(), crate::columns::NUM_COLUMNS);
+		assert_eq!(read_progress(&db).unwrap(), None);
+	}
+}
@@ -1,8 +1,9 @@
 
 use std::sync::Arc;
+use sp_runtime::traits::Block as BlockT;
 use crate::{Database, DbHash, DatabaseSettings, DatabaseSettingsSrc};
 
-pub fn open_database(
+pub fn open_database<Block: BlockT>(
 	config: &DatabaseSettings,
 ) -> Result<Arc<dyn Database<DbHash>>, String> {
 	let db: Arc<dyn Database<DbHash>> = match &config.source {
@@ -13,9 +14,28 @@ pub fn open_database(
 
 			let db = kvdb_rocksdb::Database::open(&db_config, &path)
 				.map_err(|err| format!("{}", err))?;
-			sp_database::as_database(db)
-		}
+			crate::upgrade::upgrade_database::<Block>(&db)?;
+			return Ok(sp_database::as_database(db))
+		},
+		DatabaseSettingsSrc::ParityDb { path } => {
+			let config = parity_db::Options::with_columns(&path, crate::columns::NUM_COLUMNS as u8);
+			let db = parity_db::Db::open(&config)
+				.map_err(|err| format!("{:?}", err))?;
+			Arc::new(crate::parity_db_adapter::DbAdapter(db))
+		},
+		DatabaseSettingsSrc::InMemory => {
+			Arc::new(sp_database::MemDb::default())
+		},
 	};
+	crate::upgrade::check_version(db.as_ref())?;
 
 	Ok(db)
 }
+
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, String> {
+	snap::raw::Encoder::new().compress_vec(data).map_err(|e| format!("{:?}", e))
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+	snap::raw::Decoder::new().decompress_vec(data).map_err(|e| format!("{:?}", e))
+}
@@ -0,0 +1,16 @@
+//! EIP-1767 GraphQL server for the Ethereum data of a Substrate node.
+//!
+//! Queries are resolved through an `EthApi`, so they give the same answers as the JSON-RPC
+//! methods, while fetching a block with its transactions, receipts and logs in one round trip.
+
+mod limits;
+mod scalars;
+mod schema;
+mod server;
+mod source;
+
+pub use limits::{GraphQLLimits, DEFAULT_MAX_BODY_SIZE, DEFAULT_MAX_DEPTH, DEFAULT_MAX_COMPLEXITY};
+pub use scalars::{Address, BigInt, Bytes, Bytes32, Long};
+pub use schema::{Context, Schema, Query, Mutation, schema};
+pub use server::start_server;
+pub use source::EthDataSource;
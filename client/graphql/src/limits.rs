@@ -0,0 +1,414 @@
+//! Bounds on the size and shape of GraphQL requests, checked before they are executed.
+
+use std::collections::HashMap;
+
+/// Size of a request body by default, in bytes.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+/// Nesting depth of the fields of a query by default.
+pub const DEFAULT_MAX_DEPTH: usize = 10;
+/// Number of fields selected by a query by default, counting fragments at every use.
+pub const DEFAULT_MAX_COMPLEXITY: usize = 500;
+
+/// Resources a single GraphQL request may use.
+#[derive(Debug, Clone, Copy)]
+pub struct GraphQLLimits {
+	/// Size of a request body, in bytes.
+	pub max_body_size: usize,
+	/// Nesting depth of the fields of a query.
+	pub max_depth: usize,
+	/// Number of fields selected by a query, counting fragments at every use.
+	pub max_complexity: usize,
+}
+
+impl Default for GraphQLLimits {
+	fn default() -> Self {
+		Self {
+			max_body_size: DEFAULT_MAX_BODY_SIZE,
+			max_depth: DEFAULT_MAX_DEPTH,
+			max_complexity: DEFAULT_MAX_COMPLEXITY,
+		}
+	}
+}
+
+impl GraphQLLimits {
+	/// Check the depth and complexity of every operation of the `query` document.
+	pub fn check_query(&self, query: &str) -> Result<(), String> {
+		let document = Parser { tokens: tokenize(query)?, position: 0 }.document()?;
+		let mut shapes = HashMap::new();
+
+		for operation in &document.operations {
+			let shape = document.shape(operation, &mut shapes, &mut Vec::new())?;
+			if shape.depth > self.max_depth {
+				return Err(format!("query depth of {} exceeds the limit of {}", shape.depth, self.max_depth))
+			}
+			if shape.complexity > self.max_complexity {
+				return Err(format!(
+					"query complexity of {} exceeds the limit of {}",
+					shape.complexity,
+					self.max_complexity,
+				))
+			}
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+	Name(&'a str),
+	Punctuator(char),
+	Spread,
+	/// Strings and numbers, which only appear in arguments and default values.
+	Value,
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token<'_>>, String> {
+	let bytes = query.as_bytes();
+	let mut tokens = Vec::new();
+	let mut i = 0;
+
+	while i < bytes.len() {
+		match bytes[i] {
+			b' ' | b'\t' | b'\n' | b'\r' | b',' => i += 1,
+			// Byte order mark.
+			0xEF if bytes[i..].starts_with(&[0xEF, 0xBB, 0xBF]) => i += 3,
+			b'#' => {
+				while i < bytes.len() && bytes[i] != b'\n' && bytes[i] != b'\r' {
+					i += 1;
+				}
+			},
+			b'"' if bytes[i..].starts_with(b"\"\"\"") => {
+				i += 3;
+				loop {
+					if i >= bytes.len() {
+						return Err("unterminated block string".into())
+					}
+					if bytes[i..].starts_with(b"\\\"\"\"") {
+						i += 4;
+					} else if bytes[i..].starts_with(b"\"\"\"") {
+						i += 3;
+						break
+					} else {
+						i += 1;
+					}
+				}
+				tokens.push(Token::Value);
+			},
+			b'"' => {
+				i += 1;
+				loop {
+					match bytes.get(i) {
+						None | Some(b'\n') | Some(b'\r') => return Err("unterminated string".into()),
+						Some(b'\\') => i += 2,
+						Some(b'"') => {
+							i += 1;
+							break
+						},
+						Some(_) => i += 1,
+					}
+				}
+				tokens.push(Token::Value);
+			},
+			b'.' if bytes[i..].starts_with(b"...") => {
+				i += 3;
+				tokens.push(Token::Spread);
+			},
+			b'-' | b'0'..=b'9' => {
+				while i < bytes.len()
+					&& matches!(bytes[i], b'-' | b'+' | b'.' | b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'_')
+				{
+					i += 1;
+				}
+				tokens.push(Token::Value);
+			},
+			b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+				let start = i;
+				while i < bytes.len() && matches!(bytes[i], b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') {
+					i += 1;
+				}
+				tokens.push(Token::Name(&query[start..i]));
+			},
+			b'{' | b'}' | b'(' | b')' | b'[' | b']' | b':' | b'@' | b'$' | b'=' | b'!' | b'|' | b'&' => {
+				tokens.push(Token::Punctuator(bytes[i] as char));
+				i += 1;
+			},
+			other => return Err(format!("unexpected character {:?} in query", other as char)),
+		}
+	}
+	Ok(tokens)
+}
+
+enum Selection<'a> {
+	Field(Option<Vec<Selection<'a>>>),
+	FragmentSpread(&'a str),
+	InlineFragment(Vec<Selection<'a>>),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Shape {
+	depth: usize,
+	complexity: usize,
+}
+
+struct Document<'a> {
+	operations: Vec<Vec<Selection<'a>>>,
+	fragments: HashMap<&'a str, Vec<Selection<'a>>>,
+}
+
+impl<'a> Document<'a> {
+	/// Shape of a selection set, the shapes of the fragments being computed once. `visiting`
+	/// holds the fragments being spread, to reject cycles.
+	fn shape(
+		&self,
+		selections: &[Selection<'a>],
+		fragments: &mut HashMap<&'a str, Shape>,
+		visiting: &mut Vec<&'a str>,
+	) -> Result<Shape, String> {
+		let mut shape = Shape::default();
+		for selection in selections {
+			let (depth, complexity) = match selection {
+				Selection::Field(selections) => {
+					let inner = match selections {
+						Some(selections) => self.shape(selections, fragments, visiting)?,
+						None => Shape::default(),
+					};
+					(inner.depth + 1, inner.complexity.saturating_add(1))
+				},
+				Selection::InlineFragment(selections) => {
+					let inner = self.shape(selections, fragments, visiting)?;
+					(inner.depth, inner.complexity)
+				},
+				Selection::FragmentSpread(name) => {
+					let inner = match fragments.get(name) {
+						Some(inner) => *inner,
+						None => {
+							if visiting.contains(name) {
+								return Err(format!("fragment {} spreads itself", name))
+							}
+							let selections = self.fragments.get(name)
+								.ok_or_else(|| format!("unknown fragment {}", name))?;
+							visiting.push(name);
+							let inner = self.shape(selections, fragments, visiting)?;
+							visiting.pop();
+							fragments.insert(name, inner);
+							inner
+						},
+					};
+					(inner.depth, inner.complexity)
+				},
+			};
+			shape.depth = shape.depth.max(depth);
+			shape.complexity = shape.complexity.saturating_add(complexity);
+		}
+		Ok(shape)
+	}
+}
+
+/// Reads the selection sets of the operations and fragments of a document, skipping arguments,
+/// variables and directives.
+struct Parser<'a> {
+	tokens: Vec<Token<'a>>,
+	position: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn peek(&self) -> Option<Token<'a>> {
+		self.tokens.get(self.position).copied()
+	}
+
+	fn next(&mut self) -> Result<Token<'a>, String> {
+		let token = self.peek().ok_or_else(|| "unexpected end of query".to_string())?;
+		self.position += 1;
+		Ok(token)
+	}
+
+	fn name(&mut self) -> Result<&'a str, String> {
+		match self.next()? {
+			Token::Name(name) => Ok(name),
+			token => Err(format!("expected a name, found {:?}", token)),
+		}
+	}
+
+	fn expect(&mut self, punctuator: char) -> Result<(), String> {
+		match self.next()? {
+			Token::Punctuator(found) if found == punctuator => Ok(()),
+			token => Err(format!("expected {:?}, found {:?}", punctuator, token)),
+		}
+	}
+
+	/// Skip a group of tokens enclosed in `open` and `close`, nested groups included.
+	fn skip_group(&mut self, open: char, close: char) -> Result<(), String> {
+		self.expect(open)?;
+		let mut depth = 1;
+		while depth > 0 {
+			match self.next()? {
+				Token::Punctuator(found) if found == open => depth += 1,
+				Token::Punctuator(found) if found == close => depth -= 1,
+				_ => (),
+			}
+		}
+		Ok(())
+	}
+
+	fn skip_directives(&mut self) -> Result<(), String> {
+		while self.peek() == Some(Token::Punctuator('@')) {
+			self.position += 1;
+			self.name()?;
+			if self.peek() == Some(Token::Punctuator('(')) {
+				self.skip_group('(', ')')?;
+			}
+		}
+		Ok(())
+	}
+
+	fn document(mut self) -> Result<Document<'a>, String> {
+		let mut document = Document { operations: Vec::new(), fragments: HashMap::new() };
+
+		while let Some(token) = self.peek() {
+			match token {
+				Token::Punctuator('{') => {
+					let selections = self.selection_set()?;
+					document.operations.push(selections);
+				},
+				Token::Name("query") | Token::Name("mutation") | Token::Name("subscription") => {
+					self.position += 1;
+					if let Some(Token::Name(_)) = self.peek() {
+						self.position += 1;
+					}
+					if self.peek() == Some(Token::Punctuator('(')) {
+						self.skip_group('(', ')')?;
+					}
+					self.skip_directives()?;
+					let selections = self.selection_set()?;
+					document.operations.push(selections);
+				},
+				Token::Name("fragment") => {
+					self.position += 1;
+					let name = self.name()?;
+					if self.name()? != "on" {
+						return Err(format!("expected the type condition of fragment {}", name))
+					}
+					self.name()?;
+					self.skip_directives()?;
+					let selections = self.selection_set()?;
+					document.fragments.insert(name, selections);
+				},
+				token => return Err(format!("expected a definition, found {:?}", token)),
+			}
+		}
+		Ok(document)
+	}
+
+	fn selection_set(&mut self) -> Result<Vec<Selection<'a>>, String> {
+		self.expect('{')?;
+		let mut selections = Vec::new();
+
+		loop {
+			match self.next()? {
+				Token::Punctuator('}') => return Ok(selections),
+				Token::Spread => match self.peek() {
+					Some(Token::Name("on")) => {
+						self.position += 1;
+						self.name()?;
+						self.skip_directives()?;
+						selections.push(Selection::InlineFragment(self.selection_set()?));
+					},
+					Some(Token::Name(name)) => {
+						self.position += 1;
+						self.skip_directives()?;
+						selections.push(Selection::FragmentSpread(name));
+					},
+					_ => {
+						self.skip_directives()?;
+						selections.push(Selection::InlineFragment(self.selection_set()?));
+					},
+				},
+				Token::Name(_) => {
+					// Alias.
+					if self.peek() == Some(Token::Punctuator(':')) {
+						self.position += 1;
+						self.name()?;
+					}
+					if self.peek() == Some(Token::Punctuator('(')) {
+						self.skip_group('(', ')')?;
+					}
+					self.skip_directives()?;
+					let inner = match self.peek() {
+						Some(Token::Punctuator('{')) => Some(self.selection_set()?),
+						_ => None,
+					};
+					selections.push(Selection::Field(inner));
+				},
+				token => return Err(format!("expected a selection, found {:?}", token)),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn limits(max_depth: usize, max_complexity: usize) -> GraphQLLimits {
+		GraphQLLimits { max_depth, max_complexity, ..Default::default() }
+	}
+
+	fn shape(query: &str) -> Shape {
+		let document = Parser { tokens: tokenize(query).unwrap(), position: 0 }.document().unwrap();
+		let mut fragments = HashMap::new();
+		document.shape(&document.operations[0], &mut fragments, &mut Vec::new()).unwrap()
+	}
+
+	#[test]
+	fn measures_fields_arguments_and_aliases() {
+		let query = r#"
+			# Latest block with its transactions.
+			query Latest($number: Long = 10) @cached {
+				head: block(number: $number) {
+					hash
+					transactions { hash logs(filter: { topics: [["0x01"]] }) { data } }
+					miner(block: 1) { balance @include(if: true) }
+				}
+				gasPrice
+				syncing { currentBlock }
+				call(data: { data: """ { "}" """ }) { data }
+			}
+		"#;
+		assert_eq!(shape(query), Shape { depth: 4, complexity: 13 });
+	}
+
+	#[test]
+	fn counts_fragments_at_every_use() {
+		let query = "
+			{ block { ...Fields transactions { ...Fields ... on Transaction { index } } } }
+			fragment Fields on Block { hash parent { hash } }
+		";
+		assert_eq!(shape(query), Shape { depth: 4, complexity: 9 });
+	}
+
+	#[test]
+	fn rejects_deep_and_complex_queries() {
+		let query = "{ block { parent { parent { hash } } } }";
+		assert!(limits(4, 10).check_query(query).is_ok());
+		assert!(limits(3, 10).check_query(query).is_err());
+		assert!(limits(4, 3).check_query(query).is_err());
+
+		// Every fragment doubling the fields of the previous one.
+		let query = "
+			{ ...F3 }
+			fragment F3 on Query { a: block { ...F2 } b: block { ...F2 } }
+			fragment F2 on Block { a: parent { ...F1 } b: parent { ...F1 } }
+			fragment F1 on Block { a: hash b: hash }
+		";
+		assert!(limits(10, 13).check_query(query).is_err());
+		assert!(limits(10, 14).check_query(query).is_ok());
+	}
+
+	#[test]
+	fn rejects_cycles_and_malformed_queries() {
+		let cycle = "{ ...A } fragment A on Query { block { ...B } } fragment B on Block { parent { ...A } }";
+		assert!(GraphQLLimits::default().check_query(cycle).is_err());
+		assert!(GraphQLLimits::default().check_query("{ block { hash }").is_err());
+		assert!(GraphQLLimits::default().check_query("{ block(hash: \"0x01) { hash } }").is_err());
+	}
+}
@@ -0,0 +1,125 @@
+//! Scalars of the EIP-1767 schema. They are all written as `0x` prefixed hex strings, and `BigInt`
+//! and `Long` also accept decimal input.
+
+use ethereum_types::{H160, H256, U256};
+use juniper::{ParseScalarResult, ParseScalarValue, ScalarToken, ScalarValue, Value};
+use rustc_hex::{FromHex, ToHex};
+
+fn strip_hex_prefix(value: &str) -> Option<&str> {
+	value.strip_prefix("0x").or_else(|| value.strip_prefix("0X"))
+}
+
+/// A 32 byte hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bytes32(pub H256);
+
+#[juniper::graphql_scalar(description = "Bytes32 is a 32 byte binary string, represented as 0x-prefixed hexadecimal.")]
+impl<S> GraphQLScalar for Bytes32 where S: ScalarValue {
+	fn resolve(&self) -> Value {
+		Value::scalar(format!("{:?}", self.0))
+	}
+
+	fn from_input_value(value: &juniper::InputValue) -> Option<Bytes32> {
+		strip_hex_prefix(value.as_string_value()?)?.parse().ok().map(Bytes32)
+	}
+
+	fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+		<String as ParseScalarValue<S>>::from_str(value)
+	}
+}
+
+/// A 20 byte account address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address(pub H160);
+
+#[juniper::graphql_scalar(description = "Address is a 20 byte Ethereum address, represented as 0x-prefixed hexadecimal.")]
+impl<S> GraphQLScalar for Address where S: ScalarValue {
+	fn resolve(&self) -> Value {
+		Value::scalar(format!("{:?}", self.0))
+	}
+
+	fn from_input_value(value: &juniper::InputValue) -> Option<Address> {
+		strip_hex_prefix(value.as_string_value()?)?.parse().ok().map(Address)
+	}
+
+	fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+		<String as ParseScalarValue<S>>::from_str(value)
+	}
+}
+
+/// An arbitrary length binary string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+#[juniper::graphql_scalar(description = "Bytes is an arbitrary length binary string, represented as 0x-prefixed hexadecimal.")]
+impl<S> GraphQLScalar for Bytes where S: ScalarValue {
+	fn resolve(&self) -> Value {
+		Value::scalar(format!("0x{}", self.0.to_hex::<String>()))
+	}
+
+	fn from_input_value(value: &juniper::InputValue) -> Option<Bytes> {
+		strip_hex_prefix(value.as_string_value()?)?.from_hex().ok().map(Bytes)
+	}
+
+	fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+		<String as ParseScalarValue<S>>::from_str(value)
+	}
+}
+
+/// A 256 bit unsigned integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BigInt(pub U256);
+
+#[juniper::graphql_scalar(description = "BigInt is a large integer, represented as 0x-prefixed hexadecimal. Decimal strings are accepted as input.")]
+impl<S> GraphQLScalar for BigInt where S: ScalarValue {
+	fn resolve(&self) -> Value {
+		Value::scalar(format!("0x{:x}", self.0))
+	}
+
+	fn from_input_value(value: &juniper::InputValue) -> Option<BigInt> {
+		let value = value.as_string_value()?;
+		match strip_hex_prefix(value) {
+			Some(hex) => U256::from_str_radix(hex, 16).ok(),
+			None => U256::from_dec_str(value).ok(),
+		}.map(BigInt)
+	}
+
+	fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+		<String as ParseScalarValue<S>>::from_str(value)
+	}
+}
+
+/// A 64 bit unsigned integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Long(pub u64);
+
+#[juniper::graphql_scalar(description = "Long is a 64 bit unsigned integer, represented as 0x-prefixed hexadecimal. Integers and decimal strings are accepted as input.")]
+impl<S> GraphQLScalar for Long where S: ScalarValue {
+	fn resolve(&self) -> Value {
+		Value::scalar(format!("0x{:x}", self.0))
+	}
+
+	fn from_input_value(value: &juniper::InputValue) -> Option<Long> {
+		if let Some(value) = value.as_int_value() {
+			return if value >= 0 { Some(Long(value as u64)) } else { None };
+		}
+		let value = value.as_string_value()?;
+		match strip_hex_prefix(value) {
+			Some(hex) => u64::from_str_radix(hex, 16).ok(),
+			None => value.parse().ok(),
+		}.map(Long)
+	}
+
+	fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+		match value {
+			ScalarToken::Int(_) => <i32 as ParseScalarValue<S>>::from_str(value),
+			_ => <String as ParseScalarValue<S>>::from_str(value),
+		}
+	}
+}
+
+impl From<U256> for Long {
+	fn from(value: U256) -> Self {
+		Long(value.low_u64())
+	}
+}
@@ -0,0 +1,806 @@
+//! Objects of the EIP-1767 schema, resolved through an [`EthDataSource`].
+
+use std::sync::Arc;
+use ethereum_types::{H160, H256, U256};
+use juniper::{
+	EmptySubscription, FieldError, FieldResult, GraphQLInputObject, GraphQLObject, RootNode,
+};
+use rustc_hex::FromHex;
+use fc_rpc_core::types::{self as rpc, BlockNumber, VariadicValue};
+use crate::EthDataSource;
+use crate::scalars::{Address, BigInt, Bytes, Bytes32, Long};
+
+/// Maximum number of blocks returned by a single `blocks` query.
+const MAX_BLOCKS_RANGE: u64 = 1000;
+
+/// Context of every resolver.
+pub struct Context {
+	eth: Arc<dyn EthDataSource>,
+}
+
+impl Context {
+	pub fn new(eth: Arc<dyn EthDataSource>) -> Self {
+		Self { eth }
+	}
+}
+
+impl juniper::Context for Context {}
+
+fn field_err(err: jsonrpc_core::Error) -> FieldError {
+	FieldError::new(err.message, juniper::Value::null())
+}
+
+fn at_hash(hash: H256) -> BlockNumber {
+	BlockNumber::Hash { hash, require_canonical: false }
+}
+
+/// The block an account is read at: the given one, or `default` when there is none.
+fn account_block(block: Option<Long>, default: BlockNumber) -> BlockNumber {
+	block.map(|number| BlockNumber::Num(number.0)).unwrap_or(default)
+}
+
+fn filter_addresses(addresses: Option<Vec<Address>>) -> Option<rpc::FilterAddress> {
+	addresses.map(|addresses| VariadicValue::Multiple(
+		addresses.into_iter().map(|address| address.0).collect()
+	))
+}
+
+/// Topics of a filter, where an empty list at a position matches any topic.
+fn filter_topics(topics: Option<Vec<Vec<Bytes32>>>) -> Option<rpc::Topic> {
+	topics.map(|topics| VariadicValue::Multiple(
+		topics.into_iter().map(|position| if position.is_empty() {
+			None
+		} else {
+			Some(VariadicValue::Multiple(position.into_iter().map(|topic| Some(topic.0)).collect()))
+		}).collect()
+	))
+}
+
+/// Input of `call` and `estimateGas`.
+#[derive(GraphQLInputObject)]
+pub struct CallData {
+	/// Sender of the call, defaults to the zero address.
+	from: Option<Address>,
+	/// Recipient of the call, a contract is created when there is none.
+	to: Option<Address>,
+	gas: Option<Long>,
+	gas_price: Option<BigInt>,
+	value: Option<BigInt>,
+	data: Option<Bytes>,
+}
+
+impl From<CallData> for rpc::CallRequest {
+	fn from(data: CallData) -> Self {
+		rpc::CallRequest {
+			from: data.from.map(|address| address.0),
+			to: data.to.map(|address| address.0),
+			gas_price: data.gas_price.map(|price| price.0),
+			gas: data.gas.map(|gas| gas.0.into()),
+			value: data.value.map(|value| value.0),
+			data: data.data.map(|data| rpc::Bytes(data.0)),
+			nonce: None,
+		}
+	}
+}
+
+/// Outcome of `call`.
+#[derive(GraphQLObject)]
+pub struct CallResult {
+	/// Data returned by the call, or the revert data when it failed.
+	data: Bytes,
+	/// Gas used by the call as estimated by `eth_estimateGas`, zero when it failed.
+	gas_used: Long,
+	/// 1 when the call succeeded, 0 when it failed.
+	status: Long,
+}
+
+fn call(context: &Context, data: CallData, number: BlockNumber) -> FieldResult<Option<CallResult>> {
+	let request: rpc::CallRequest = data.into();
+	match context.eth.call(request.clone(), number.clone()) {
+		Ok(data) => {
+			let gas_used = context.eth.estimate_gas(request, number).map_err(field_err)?;
+			Ok(Some(CallResult { data: Bytes(data.0), gas_used: gas_used.into(), status: Long(1) }))
+		},
+		// Failed executions are the only errors carrying the returned data.
+		Err(jsonrpc_core::Error { data: Some(serde_json::Value::String(data)), .. }) => {
+			let data = data.trim_start_matches("0x").from_hex().unwrap_or_default();
+			Ok(Some(CallResult { data: Bytes(data), gas_used: Long(0), status: Long(0) }))
+		},
+		Err(err) => Err(field_err(err)),
+	}
+}
+
+fn estimate_gas(context: &Context, data: CallData, number: BlockNumber) -> FieldResult<Long> {
+	context.eth.estimate_gas(data.into(), number).map(Long::from).map_err(field_err)
+}
+
+fn block_transactions(context: &Context, block: &rpc::Block) -> FieldResult<Vec<Transaction>> {
+	match &block.transactions {
+		rpc::BlockTransactions::Full(transactions) => Ok(
+			transactions.iter().cloned().map(Transaction::new).collect()
+		),
+		rpc::BlockTransactions::Hashes(hashes) => hashes.iter()
+			.filter_map(|hash| context.eth.transaction_by_hash(*hash).map_err(field_err).transpose())
+			.map(|transaction| transaction.map(Transaction::new))
+			.collect(),
+	}
+}
+
+/// Filter of the `logs` field of a block.
+#[derive(GraphQLInputObject)]
+pub struct BlockFilterCriteria {
+	/// Accounts the logs must come from, any account when there is none.
+	addresses: Option<Vec<Address>>,
+	/// Topics the logs must match, by position.
+	topics: Option<Vec<Vec<Bytes32>>>,
+}
+
+/// Filter of the `logs` query.
+#[derive(GraphQLInputObject)]
+pub struct FilterCriteria {
+	/// First block searched, defaults to the latest block.
+	from_block: Option<Long>,
+	/// Last block searched, defaults to the latest block.
+	to_block: Option<Long>,
+	/// Accounts the logs must come from, any account when there is none.
+	addresses: Option<Vec<Address>>,
+	/// Topics the logs must match, by position.
+	topics: Option<Vec<Vec<Bytes32>>>,
+}
+
+/// An Ethereum account at a particular block.
+pub struct Account {
+	address: H160,
+	number: BlockNumber,
+}
+
+#[juniper::graphql_object(context = Context)]
+impl Account {
+	fn address(&self) -> Address {
+		Address(self.address)
+	}
+
+	fn balance(&self, context: &Context) -> FieldResult<BigInt> {
+		context.eth.balance(self.address, self.number.clone()).map(BigInt).map_err(field_err)
+	}
+
+	fn transaction_count(&self, context: &Context) -> FieldResult<Long> {
+		context.eth.transaction_count(self.address, self.number.clone()).map(Long::from).map_err(field_err)
+	}
+
+	fn code(&self, context: &Context) -> FieldResult<Bytes> {
+		context.eth.code_at(self.address, self.number.clone()).map(|code| Bytes(code.0)).map_err(field_err)
+	}
+
+	fn storage(&self, context: &Context, slot: Bytes32) -> FieldResult<Bytes32> {
+		context.eth.storage_at(self.address, U256::from_big_endian(slot.0.as_bytes()), self.number.clone())
+			.map(Bytes32)
+			.map_err(field_err)
+	}
+}
+
+/// An Ethereum log.
+pub struct Log {
+	inner: rpc::Log,
+}
+
+#[juniper::graphql_object(context = Context)]
+impl Log {
+	/// Index of the log in its block.
+	fn index(&self) -> i32 {
+		self.inner.log_index.unwrap_or_default().low_u32() as i32
+	}
+
+	/// Account that emitted the log, at the given block or the latest one.
+	fn account(&self, block: Option<Long>) -> Account {
+		Account { address: self.inner.address, number: account_block(block, BlockNumber::Latest) }
+	}
+
+	fn topics(&self) -> Vec<Bytes32> {
+		self.inner.topics.iter().cloned().map(Bytes32).collect()
+	}
+
+	fn data(&self) -> Bytes {
+		Bytes(self.inner.data.0.clone())
+	}
+
+	fn transaction(&self, context: &Context) -> FieldResult<Transaction> {
+		let hash = self.inner.transaction_hash.unwrap_or_default();
+		context.eth.transaction_by_hash(hash)
+			.map_err(field_err)?
+			.map(Transaction::new)
+			.ok_or_else(|| FieldError::new("transaction not found", juniper::Value::null()))
+	}
+}
+
+/// An Ethereum transaction.
+pub struct Transaction {
+	inner: rpc::Transaction,
+}
+
+impl Transaction {
+	fn new(inner: rpc::Transaction) -> Self {
+		Self { inner }
+	}
+
+	fn receipt(&self, context: &Context) -> FieldResult<Option<rpc::Receipt>> {
+		context.eth.transaction_receipt(self.inner.hash).map_err(field_err)
+	}
+}
+
+#[juniper::graphql_object(context = Context)]
+impl Transaction {
+	fn hash(&self) -> Bytes32 {
+		Bytes32(self.inner.hash)
+	}
+
+	fn nonce(&self) -> Long {
+		self.inner.nonce.into()
+	}
+
+	/// Index of the transaction in its block, null while it is pending.
+	fn index(&self) -> Option<i32> {
+		self.inner.transaction_index.map(|index| index.low_u32() as i32)
+	}
+
+	/// Sender, at the given block or the latest one.
+	fn from(&self, block: Option<Long>) -> Account {
+		Account { address: self.inner.from, number: account_block(block, BlockNumber::Latest) }
+	}
+
+	/// Recipient, at the given block or the latest one. Null for contract creations.
+	fn to(&self, block: Option<Long>) -> Option<Account> {
+		self.inner.to.map(|address| Account { address, number: account_block(block, BlockNumber::Latest) })
+	}
+
+	fn value(&self) -> BigInt {
+		BigInt(self.inner.value)
+	}
+
+	fn gas_price(&self) -> BigInt {
+		BigInt(self.inner.gas_price)
+	}
+
+	fn gas(&self) -> Long {
+		self.inner.gas.into()
+	}
+
+	fn input_data(&self) -> Bytes {
+		Bytes(self.inner.input.0.clone())
+	}
+
+	/// Block the transaction was included in, null while it is pending.
+	fn block(&self, context: &Context) -> FieldResult<Option<Block>> {
+		match self.inner.block_hash {
+			Some(hash) => Ok(context.eth.block_by_hash(hash).map_err(field_err)?.map(Block::new)),
+			None => Ok(None),
+		}
+	}
+
+	/// 1 when the transaction succeeded, 0 when it failed, null while it is pending.
+	fn status(&self, context: &Context) -> FieldResult<Option<Long>> {
+		Ok(self.receipt(context)?.and_then(|receipt| match receipt.outcome {
+			rpc::ReceiptOutcome::StatusCode(status) => Some(Long(status.as_u64())),
+			rpc::ReceiptOutcome::StateRoot(_) => None,
+		}))
+	}
+
+	fn gas_used(&self, context: &Context) -> FieldResult<Option<Long>> {
+		Ok(self.receipt(context)?.and_then(|receipt| receipt.gas_used).map(Long::from))
+	}
+
+	fn cumulative_gas_used(&self, context: &Context) -> FieldResult<Option<Long>> {
+		Ok(self.receipt(context)?.map(|receipt| receipt.cumulative_gas_used.into()))
+	}
+
+	/// Contract created by the transaction, at the given block or the latest one.
+	fn created_contract(&self, context: &Context, block: Option<Long>) -> FieldResult<Option<Account>> {
+		Ok(self.receipt(context)?
+			.and_then(|receipt| receipt.contract_address)
+			.map(|address| Account { address, number: account_block(block, BlockNumber::Latest) }))
+	}
+
+	fn logs(&self, context: &Context) -> FieldResult<Option<Vec<Log>>> {
+		Ok(self.receipt(context)?
+			.map(|receipt| receipt.logs.into_iter().map(|inner| Log { inner }).collect()))
+	}
+
+	fn r(&self) -> BigInt {
+		BigInt(self.inner.r)
+	}
+
+	fn s(&self) -> BigInt {
+		BigInt(self.inner.s)
+	}
+
+	fn v(&self) -> BigInt {
+		BigInt(self.inner.v)
+	}
+}
+
+/// An Ethereum block.
+pub struct Block {
+	inner: rpc::Block,
+}
+
+impl Block {
+	fn new(block: rpc::RichBlock) -> Self {
+		Self { inner: block.inner }
+	}
+
+	/// The state of this block.
+	fn at(&self) -> BlockNumber {
+		at_hash(self.inner.hash.unwrap_or_default())
+	}
+}
+
+#[juniper::graphql_object(context = Context)]
+impl Block {
+	fn number(&self) -> Long {
+		self.inner.number.unwrap_or_default().into()
+	}
+
+	fn hash(&self) -> Bytes32 {
+		Bytes32(self.inner.hash.unwrap_or_default())
+	}
+
+	fn parent(&self, context: &Context) -> FieldResult<Option<Block>> {
+		if self.inner.number.unwrap_or_default().is_zero() {
+			return Ok(None);
+		}
+		Ok(context.eth.block_by_hash(self.inner.parent_hash).map_err(field_err)?.map(Block::new))
+	}
+
+	fn nonce(&self) -> Bytes {
+		Bytes(vec![0; 8])
+	}
+
+	fn transactions_root(&self) -> Bytes32 {
+		Bytes32(self.inner.transactions_root)
+	}
+
+	fn state_root(&self) -> Bytes32 {
+		Bytes32(self.inner.state_root)
+	}
+
+	fn receipts_root(&self) -> Bytes32 {
+		Bytes32(self.inner.receipts_root)
+	}
+
+	/// Author of the block, at the given block or this one.
+	fn miner(&self, block: Option<Long>) -> Account {
+		Account { address: self.inner.author, number: account_block(block, self.at()) }
+	}
+
+	fn extra_data(&self) -> Bytes {
+		Bytes(self.inner.extra_data.0.clone())
+	}
+
+	fn gas_limit(&self) -> Long {
+		self.inner.gas_limit.into()
+	}
+
+	fn gas_used(&self) -> Long {
+		self.inner.gas_used.into()
+	}
+
+	fn timestamp(&self) -> Long {
+		self.inner.timestamp.into()
+	}
+
+	fn logs_bloom(&self) -> Bytes {
+		Bytes(self.inner.logs_bloom.unwrap_or_default().as_bytes().to_vec())
+	}
+
+	fn mix_hash(&self) -> Bytes32 {
+		Bytes32(H256::zero())
+	}
+
+	fn difficulty(&self) -> BigInt {
+		BigInt(self.inner.difficulty)
+	}
+
+	fn total_difficulty(&self) -> BigInt {
+		BigInt(self.inner.total_difficulty)
+	}
+
+	fn ommer_count(&self) -> Option<i32> {
+		Some(0)
+	}
+
+	fn ommers(&self) -> Option<Vec<Option<Block>>> {
+		Some(vec![])
+	}
+
+	fn ommer_at(&self, _index: i32) -> Option<Block> {
+		None
+	}
+
+	fn ommer_hash(&self) -> Bytes32 {
+		Bytes32(self.inner.uncles_hash)
+	}
+
+	fn transaction_count(&self) -> Option<i32> {
+		Some(match &self.inner.transactions {
+			rpc::BlockTransactions::Full(transactions) => transactions.len() as i32,
+			rpc::BlockTransactions::Hashes(hashes) => hashes.len() as i32,
+		})
+	}
+
+	fn transactions(&self, context: &Context) -> FieldResult<Option<Vec<Transaction>>> {
+		block_transactions(context, &self.inner).map(Some)
+	}
+
+	fn transaction_at(&self, context: &Context, index: i32) -> FieldResult<Option<Transaction>> {
+		if index < 0 {
+			return Ok(None);
+		}
+		Ok(block_transactions(context, &self.inner)?.into_iter().nth(index as usize))
+	}
+
+	fn logs(&self, context: &Context, filter: BlockFilterCriteria) -> FieldResult<Vec<Log>> {
+		let filter = rpc::Filter {
+			from_block: None,
+			to_block: None,
+			block_hash: self.inner.hash,
+			address: filter_addresses(filter.addresses),
+			topics: filter_topics(filter.topics),
+		};
+		Ok(context.eth.logs(filter).map_err(field_err)?.into_iter().map(|inner| Log { inner }).collect())
+	}
+
+	fn account(&self, address: Address) -> Account {
+		Account { address: address.0, number: self.at() }
+	}
+
+	fn call(&self, context: &Context, data: CallData) -> FieldResult<Option<CallResult>> {
+		call(context, data, self.at())
+	}
+
+	fn estimate_gas(&self, context: &Context, data: CallData) -> FieldResult<Long> {
+		estimate_gas(context, data, self.at())
+	}
+}
+
+/// The pending state.
+pub struct Pending;
+
+impl Pending {
+	fn block(context: &Context) -> FieldResult<Option<rpc::Block>> {
+		Ok(context.eth.block_by_number(BlockNumber::Pending).map_err(field_err)?.map(|block| block.inner))
+	}
+}
+
+#[juniper::graphql_object(context = Context)]
+impl Pending {
+	fn transaction_count(&self, context: &Context) -> FieldResult<i32> {
+		Ok(match Self::block(context)? {
+			Some(block) => block_transactions(context, &block)?.len() as i32,
+			None => 0,
+		})
+	}
+
+	fn transactions(&self, context: &Context) -> FieldResult<Option<Vec<Transaction>>> {
+		match Self::block(context)? {
+			Some(block) => block_transactions(context, &block).map(Some),
+			None => Ok(Some(vec![])),
+		}
+	}
+
+	fn account(&self, address: Address) -> Account {
+		Account { address: address.0, number: BlockNumber::Pending }
+	}
+
+	fn call(&self, context: &Context, data: CallData) -> FieldResult<Option<CallResult>> {
+		call(context, data, BlockNumber::Pending)
+	}
+
+	fn estimate_gas(&self, context: &Context, data: CallData) -> FieldResult<Long> {
+		estimate_gas(context, data, BlockNumber::Pending)
+	}
+}
+
+/// Progress of the major sync.
+#[derive(GraphQLObject)]
+pub struct SyncState {
+	starting_block: Long,
+	current_block: Long,
+	highest_block: Long,
+}
+
+pub struct Query;
+
+#[juniper::graphql_object(context = Context)]
+impl Query {
+	/// Block by number or hash, the latest block when none is given.
+	fn block(&self, context: &Context, number: Option<Long>, hash: Option<Bytes32>) -> FieldResult<Option<Block>> {
+		let block = match (number, hash) {
+			(Some(_), Some(_)) => return Err(FieldError::new(
+				"only one of number or hash must be specified",
+				juniper::Value::null(),
+			)),
+			(None, Some(hash)) => context.eth.block_by_hash(hash.0),
+			(Some(number), None) => context.eth.block_by_number(BlockNumber::Num(number.0)),
+			(None, None) => context.eth.block_by_number(BlockNumber::Latest),
+		};
+		Ok(block.map_err(field_err)?.map(Block::new))
+	}
+
+	/// Blocks in the inclusive range, up to the latest block when `to` is not given.
+	fn blocks(&self, context: &Context, from: Long, to: Option<Long>) -> FieldResult<Vec<Block>> {
+		let to = match to {
+			Some(to) => to.0,
+			None => context.eth.block_number().map_err(field_err)?.low_u64(),
+		};
+		if to < from.0 {
+			return Ok(vec![]);
+		}
+		if to - from.0 >= MAX_BLOCKS_RANGE {
+			return Err(FieldError::new(
+				format!("block range is limited to {} blocks", MAX_BLOCKS_RANGE),
+				juniper::Value::null(),
+			));
+		}
+
+		let mut blocks = Vec::new();
+		for number in from.0..=to {
+			match context.eth.block_by_number(BlockNumber::Num(number)).map_err(field_err)? {
+				Some(block) => blocks.push(Block::new(block)),
+				None => break,
+			}
+		}
+		Ok(blocks)
+	}
+
+	fn pending(&self) -> Pending {
+		Pending
+	}
+
+	fn transaction(&self, context: &Context, hash: Bytes32) -> FieldResult<Option<Transaction>> {
+		Ok(context.eth.transaction_by_hash(hash.0).map_err(field_err)?.map(Transaction::new))
+	}
+
+	fn logs(&self, context: &Context, filter: FilterCriteria) -> FieldResult<Vec<Log>> {
+		let filter = rpc::Filter {
+			from_block: filter.from_block.map(|number| BlockNumber::Num(number.0)),
+			to_block: filter.to_block.map(|number| BlockNumber::Num(number.0)),
+			block_hash: None,
+			address: filter_addresses(filter.addresses),
+			topics: filter_topics(filter.topics),
+		};
+		Ok(context.eth.logs(filter).map_err(field_err)?.into_iter().map(|inner| Log { inner }).collect())
+	}
+
+	fn gas_price(&self, context: &Context) -> FieldResult<BigInt> {
+		context.eth.gas_price().map(BigInt).map_err(field_err)
+	}
+
+	fn protocol_version(&self, context: &Context) -> FieldResult<i32> {
+		context.eth.protocol_version().map(|version| version as i32).map_err(field_err)
+	}
+
+	/// Sync progress, null when the node is not syncing.
+	fn syncing(&self, context: &Context) -> FieldResult<Option<SyncState>> {
+		Ok(match context.eth.syncing().map_err(field_err)? {
+			rpc::SyncStatus::Info(info) => Some(SyncState {
+				starting_block: info.starting_block.into(),
+				current_block: info.current_block.into(),
+				highest_block: info.highest_block.into(),
+			}),
+			rpc::SyncStatus::None => None,
+		})
+	}
+
+	#[graphql(name = "chainID")]
+	fn chain_id(&self, context: &Context) -> FieldResult<BigInt> {
+		let chain_id = context.eth.chain_id().map_err(field_err)?.unwrap_or_default();
+		Ok(BigInt(chain_id.as_u64().into()))
+	}
+}
+
+pub struct Mutation;
+
+#[juniper::graphql_object(context = Context)]
+impl Mutation {
+	/// Submits a signed RLP encoded transaction, returning its hash.
+	fn send_raw_transaction(&self, context: &Context, data: Bytes) -> FieldResult<Bytes32> {
+		context.eth.send_raw_transaction(rpc::Bytes(data.0)).map(Bytes32).map_err(field_err)
+	}
+}
+
+pub type Schema = RootNode<'static, Query, Mutation, EmptySubscription<Context>>;
+
+pub fn schema() -> Schema {
+	Schema::new(Query, Mutation, EmptySubscription::new())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::BTreeMap;
+	use ethereum_types::U64;
+	use jsonrpc_core::Result;
+	use juniper::{DefaultScalarValue, Variables};
+
+	struct MockSource;
+
+	fn not_mocked<T>() -> Result<T> {
+		Err(jsonrpc_core::Error::internal_error())
+	}
+
+	fn transaction() -> rpc::Transaction {
+		rpc::Transaction {
+			hash: H256::repeat_byte(2),
+			block_hash: Some(H256::repeat_byte(1)),
+			block_number: Some(1.into()),
+			transaction_index: Some(0.into()),
+			..Default::default()
+		}
+	}
+
+	impl EthDataSource for MockSource {
+		fn block_number(&self) -> Result<U256> {
+			Ok(1.into())
+		}
+
+		fn block_by_hash(&self, _: H256) -> Result<Option<rpc::RichBlock>> {
+			not_mocked()
+		}
+
+		fn block_by_number(&self, number: BlockNumber) -> Result<Option<rpc::RichBlock>> {
+			if number != BlockNumber::Num(1) {
+				return Ok(None);
+			}
+			Ok(Some(rpc::Rich {
+				inner: rpc::Block {
+					hash: Some(H256::repeat_byte(1)),
+					parent_hash: H256::zero(),
+					uncles_hash: H256::zero(),
+					author: H160::zero(),
+					miner: H160::zero(),
+					state_root: H256::zero(),
+					transactions_root: H256::zero(),
+					receipts_root: H256::zero(),
+					number: Some(1.into()),
+					gas_used: 21000.into(),
+					gas_limit: 100000.into(),
+					extra_data: rpc::Bytes(vec![]),
+					logs_bloom: None,
+					timestamp: 6.into(),
+					difficulty: 0.into(),
+					total_difficulty: 0.into(),
+					seal_fields: vec![],
+					uncles: vec![],
+					transactions: rpc::BlockTransactions::Full(vec![transaction()]),
+					size: None,
+				},
+				extra_info: BTreeMap::new(),
+			}))
+		}
+
+		fn transaction_by_hash(&self, _: H256) -> Result<Option<rpc::Transaction>> {
+			Ok(Some(transaction()))
+		}
+
+		fn transaction_receipt(&self, _: H256) -> Result<Option<rpc::Receipt>> {
+			Ok(Some(rpc::Receipt {
+				transaction_hash: Some(H256::repeat_byte(2)),
+				transaction_index: Some(0.into()),
+				block_hash: Some(H256::repeat_byte(1)),
+				from: Some(H160::zero()),
+				to: None,
+				block_number: Some(1.into()),
+				cumulative_gas_used: 21000.into(),
+				gas_used: Some(21000.into()),
+				contract_address: None,
+				logs: vec![rpc::Log {
+					address: H160::repeat_byte(3),
+					topics: vec![],
+					data: rpc::Bytes(vec![]),
+					block_hash: Some(H256::repeat_byte(1)),
+					block_number: Some(1.into()),
+					transaction_hash: Some(H256::repeat_byte(2)),
+					transaction_index: Some(0.into()),
+					log_index: Some(0.into()),
+					transaction_log_index: Some(0.into()),
+					removed: false,
+				}],
+				logs_bloom: Default::default(),
+				outcome: rpc::ReceiptOutcome::StatusCode(U64::from(1)),
+				effective_gas_price: 1.into(),
+				transaction_type: U64::zero(),
+			}))
+		}
+
+		fn balance(&self, _: H160, number: BlockNumber) -> Result<U256> {
+			assert_eq!(number, at_hash(H256::repeat_byte(1)));
+			Ok(1000.into())
+		}
+
+		fn transaction_count(&self, _: H160, _: BlockNumber) -> Result<U256> {
+			not_mocked()
+		}
+
+		fn code_at(&self, _: H160, _: BlockNumber) -> Result<rpc::Bytes> {
+			not_mocked()
+		}
+
+		fn storage_at(&self, _: H160, _: U256, _: BlockNumber) -> Result<H256> {
+			not_mocked()
+		}
+
+		fn logs(&self, _: rpc::Filter) -> Result<Vec<rpc::Log>> {
+			not_mocked()
+		}
+
+		fn call(&self, _: rpc::CallRequest, _: BlockNumber) -> Result<rpc::Bytes> {
+			not_mocked()
+		}
+
+		fn estimate_gas(&self, _: rpc::CallRequest, _: BlockNumber) -> Result<U256> {
+			not_mocked()
+		}
+
+		fn gas_price(&self) -> Result<U256> {
+			not_mocked()
+		}
+
+		fn chain_id(&self) -> Result<Option<U64>> {
+			Ok(Some(888.into()))
+		}
+
+		fn protocol_version(&self) -> Result<u64> {
+			Ok(1)
+		}
+
+		fn syncing(&self) -> Result<rpc::SyncStatus> {
+			Ok(rpc::SyncStatus::None)
+		}
+
+		fn send_raw_transaction(&self, _: rpc::Bytes) -> Result<H256> {
+			not_mocked()
+		}
+	}
+
+	#[test]
+	fn resolves_block_with_transactions_receipts_and_accounts() {
+		let context = Context::new(Arc::new(MockSource));
+		let query = r#"{
+			chainID
+			block(number: 1) {
+				number
+				transactionCount
+				transactions { hash index status gasUsed logs { index account { address } } }
+				account(address: "0x0303030303030303030303030303030303030303") { balance }
+			}
+		}"#;
+
+		let (result, errors) = juniper::execute_sync(
+			query,
+			None,
+			&schema(),
+			&Variables::<DefaultScalarValue>::new(),
+			&context,
+		).unwrap();
+
+		assert!(errors.is_empty());
+		assert_eq!(
+			serde_json::to_value(&result).unwrap(),
+			serde_json::json!({
+				"chainID": "0x378",
+				"block": {
+					"number": "0x1",
+					"transactionCount": 1,
+					"transactions": [{
+						"hash": format!("{:?}", H256::repeat_byte(2)),
+						"index": 0,
+						"status": "0x1",
+						"gasUsed": "0x5208",
+						"logs": [{ "index": 0, "account": { "address": format!("{:?}", H160::repeat_byte(3)) } }],
+					}],
+					"account": { "balance": "0x3e8" },
+				},
+			}),
+		);
+	}
+}
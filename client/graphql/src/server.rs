@@ -0,0 +1,131 @@
+use std::{future::Future, net::SocketAddr, sync::Arc};
+use hyper::{Body, Method, Request, Response, Server, StatusCode, header};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use juniper::http::{GraphQLRequest, graphiql::graphiql_source};
+use log::{info, error};
+use crate::{Context, EthDataSource, GraphQLLimits, Schema, schema};
+
+/// Path queries are posted to.
+const GRAPHQL_PATH: &str = "/graphql";
+/// Path of the GraphiQL page for exploring the schema.
+const GRAPHIQL_PATH: &str = "/graphql/ui";
+
+fn response(status: StatusCode, content_type: &str, body: impl Into<Body>) -> Response<Body> {
+	Response::builder()
+		.status(status)
+		.header(header::CONTENT_TYPE, content_type)
+		.body(body.into())
+		.expect("status and headers are valid; qed")
+}
+
+/// Read a request body, `None` if it is larger than `limit` bytes.
+async fn read_body(mut body: Body, limit: usize) -> Result<Option<Vec<u8>>, hyper::Error> {
+	let mut bytes = Vec::new();
+	while let Some(chunk) = body.data().await {
+		let chunk = chunk?;
+		if bytes.len() + chunk.len() > limit {
+			return Ok(None)
+		}
+		bytes.extend_from_slice(&chunk);
+	}
+	Ok(Some(bytes))
+}
+
+fn bad_request(message: String) -> Response<Body> {
+	response(StatusCode::BAD_REQUEST, "text/plain; charset=utf-8", message)
+}
+
+async fn handle(
+	request: Request<Body>,
+	schema: Arc<Schema>,
+	context: Arc<Context>,
+	limits: GraphQLLimits,
+) -> Result<Response<Body>, hyper::Error> {
+	match (request.method(), request.uri().path()) {
+		(&Method::GET, GRAPHIQL_PATH) => Ok(response(
+			StatusCode::OK,
+			"text/html; charset=utf-8",
+			graphiql_source(GRAPHQL_PATH, None),
+		)),
+		(&Method::POST, GRAPHQL_PATH) => {
+			let body = match read_body(request.into_body(), limits.max_body_size).await? {
+				Some(body) => body,
+				None => return Ok(response(
+					StatusCode::PAYLOAD_TOO_LARGE,
+					"text/plain; charset=utf-8",
+					format!("request body exceeds the limit of {} bytes", limits.max_body_size),
+				)),
+			};
+			let value: serde_json::Value = match serde_json::from_slice(&body) {
+				Ok(value) => value,
+				Err(err) => return Ok(bad_request(format!("invalid GraphQL request: {}", err))),
+			};
+			let query = value.get("query").and_then(|query| query.as_str()).unwrap_or_default();
+			if let Err(err) = limits.check_query(query) {
+				return Ok(bad_request(format!("invalid GraphQL query: {}", err)))
+			}
+			let graphql_request: GraphQLRequest = match serde_json::from_value(value) {
+				Ok(graphql_request) => graphql_request,
+				Err(err) => return Ok(bad_request(format!("invalid GraphQL request: {}", err))),
+			};
+
+			// Resolvers block on the client, keep them off the server threads.
+			let executed = tokio::task::spawn_blocking(move || {
+				let graphql_response = graphql_request.execute_sync(&schema, &context);
+				(graphql_response.is_ok(), serde_json::to_string(&graphql_response))
+			}).await;
+
+			Ok(match executed {
+				Ok((is_ok, Ok(body))) => response(
+					if is_ok { StatusCode::OK } else { StatusCode::BAD_REQUEST },
+					"application/json",
+					body,
+				),
+				Ok((_, Err(err))) => response(
+					StatusCode::INTERNAL_SERVER_ERROR,
+					"text/plain; charset=utf-8",
+					format!("encode GraphQL response failed: {}", err),
+				),
+				Err(err) => response(
+					StatusCode::INTERNAL_SERVER_ERROR,
+					"text/plain; charset=utf-8",
+					format!("execute GraphQL request failed: {}", err),
+				),
+			})
+		},
+		_ => Ok(response(StatusCode::NOT_FOUND, "text/plain; charset=utf-8", "not found")),
+	}
+}
+
+/// Binds the GraphQL server to `address`, returning the future serving it, which must be
+/// spawned on the Tokio runtime. Requests exceeding `limits` are refused before being executed.
+pub fn start_server(
+	address: SocketAddr,
+	eth: Arc<dyn EthDataSource>,
+	limits: GraphQLLimits,
+) -> Result<impl Future<Output = ()>, String> {
+	let schema = Arc::new(schema());
+	let context = Arc::new(Context::new(eth));
+
+	let make_service = make_service_fn(move |_| {
+		let schema = schema.clone();
+		let context = context.clone();
+		async move {
+			Ok::<_, hyper::Error>(service_fn(move |request| {
+				handle(request, schema.clone(), context.clone(), limits)
+			}))
+		}
+	});
+
+	let server = Server::try_bind(&address)
+		.map_err(|err| format!("bind GraphQL server to {} failed: {:?}", address, err))?
+		.serve(make_service);
+	info!(target: "graphql", "GraphQL server started at http://{}{}", address, GRAPHQL_PATH);
+
+	Ok(async move {
+		if let Err(err) = server.await {
+			error!(target: "graphql", "GraphQL server failed: {:?}", err);
+		}
+	})
+}
@@ -0,0 +1,101 @@
+use ethereum_types::{H160, H256, U256, U64};
+use jsonrpc_core::{Result, futures::Future};
+use fc_rpc_core::EthApi;
+use fc_rpc_core::types::{
+	BlockNumber, Bytes, CallRequest, Filter, Log, Receipt, RichBlock, SyncStatus, Transaction,
+};
+
+/// The Ethereum RPC methods GraphQL queries are resolved with.
+///
+/// Implemented for every `EthApi`, so that queries go through the same storage overrides,
+/// runtime API calls and mapping lookups as the JSON-RPC server.
+pub trait EthDataSource: Send + Sync {
+	fn block_number(&self) -> Result<U256>;
+	fn block_by_hash(&self, hash: H256) -> Result<Option<RichBlock>>;
+	fn block_by_number(&self, number: BlockNumber) -> Result<Option<RichBlock>>;
+	fn transaction_by_hash(&self, hash: H256) -> Result<Option<Transaction>>;
+	fn transaction_receipt(&self, hash: H256) -> Result<Option<Receipt>>;
+	fn balance(&self, address: H160, number: BlockNumber) -> Result<U256>;
+	fn transaction_count(&self, address: H160, number: BlockNumber) -> Result<U256>;
+	fn code_at(&self, address: H160, number: BlockNumber) -> Result<Bytes>;
+	fn storage_at(&self, address: H160, index: U256, number: BlockNumber) -> Result<H256>;
+	fn logs(&self, filter: Filter) -> Result<Vec<Log>>;
+	fn call(&self, request: CallRequest, number: BlockNumber) -> Result<Bytes>;
+	fn estimate_gas(&self, request: CallRequest, number: BlockNumber) -> Result<U256>;
+	fn gas_price(&self) -> Result<U256>;
+	fn chain_id(&self) -> Result<Option<U64>>;
+	fn protocol_version(&self) -> Result<u64>;
+	fn syncing(&self) -> Result<SyncStatus>;
+	/// Submits the transaction and waits until the pool accepted it.
+	fn send_raw_transaction(&self, bytes: Bytes) -> Result<H256>;
+}
+
+impl<T: EthApi> EthDataSource for T {
+	fn block_number(&self) -> Result<U256> {
+		EthApi::block_number(self)
+	}
+
+	fn block_by_hash(&self, hash: H256) -> Result<Option<RichBlock>> {
+		EthApi::block_by_hash(self, hash, true)
+	}
+
+	fn block_by_number(&self, number: BlockNumber) -> Result<Option<RichBlock>> {
+		EthApi::block_by_number(self, number, true)
+	}
+
+	fn transaction_by_hash(&self, hash: H256) -> Result<Option<Transaction>> {
+		EthApi::transaction_by_hash(self, hash)
+	}
+
+	fn transaction_receipt(&self, hash: H256) -> Result<Option<Receipt>> {
+		EthApi::transaction_receipt(self, hash)
+	}
+
+	fn balance(&self, address: H160, number: BlockNumber) -> Result<U256> {
+		EthApi::balance(self, address, Some(number))
+	}
+
+	fn transaction_count(&self, address: H160, number: BlockNumber) -> Result<U256> {
+		EthApi::transaction_count(self, address, Some(number))
+	}
+
+	fn code_at(&self, address: H160, number: BlockNumber) -> Result<Bytes> {
+		EthApi::code_at(self, address, Some(number))
+	}
+
+	fn storage_at(&self, address: H160, index: U256, number: BlockNumber) -> Result<H256> {
+		EthApi::storage_at(self, address, index, Some(number))
+	}
+
+	fn logs(&self, filter: Filter) -> Result<Vec<Log>> {
+		EthApi::logs(self, filter)
+	}
+
+	fn call(&self, request: CallRequest, number: BlockNumber) -> Result<Bytes> {
+		EthApi::call(self, request, Some(number))
+	}
+
+	fn estimate_gas(&self, request: CallRequest, number: BlockNumber) -> Result<U256> {
+		EthApi::estimate_gas(self, request, Some(number))
+	}
+
+	fn gas_price(&self) -> Result<U256> {
+		EthApi::gas_price(self)
+	}
+
+	fn chain_id(&self) -> Result<Option<U64>> {
+		EthApi::chain_id(self)
+	}
+
+	fn protocol_version(&self) -> Result<u64> {
+		EthApi::protocol_version(self)
+	}
+
+	fn syncing(&self) -> Result<SyncStatus> {
+		EthApi::syncing(self)
+	}
+
+	fn send_raw_transaction(&self, bytes: Bytes) -> Result<H256> {
+		EthApi::send_raw_transaction(self, bytes).wait()
+	}
+}
@@ -0,0 +1,197 @@
+//! Consistency checks of the canonical index against the best chain.
+
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto}};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use fp_rpc::EthereumRuntimeRPCApi;
+use log::info;
+
+/// Number of blocks added to the canonical index per database commit.
+const BATCH_SIZE: usize = 1000;
+/// Number of blocks between two progress reports.
+const PROGRESS_INTERVAL: u64 = 10_000;
+
+/// What `walk_canonical_chain` does with the best chain blocks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChainWalk {
+	/// Report inconsistent mappings.
+	Check,
+	/// Report inconsistent mappings and rewrite them.
+	Repair,
+	/// Rewrite the mappings of every block from the given height.
+	Reindex { from: u64 },
+}
+
+/// Inconsistencies between the mappings of a best chain block and its header digest, and the
+/// other blocks wrongly marked as canonical for its transactions, as `(number, hash)` pairs.
+fn check_block<Block: BlockT, C: HeaderBackend<Block>>(
+	client: &C,
+	backend: &fc_db::Backend<Block>,
+	number: u64,
+	header: &Block::Header,
+) -> Result<(Vec<String>, Vec<(u64, Block::Hash)>), String> {
+	let hash = header.hash();
+	let mapping = backend.mapping();
+	let mut problems = Vec::new();
+	let mut stale = Vec::new();
+
+	if !mapping.is_synced(&hash)? {
+		problems.push("block is not marked as synced".to_string());
+	}
+	match mapping.canonical_hash(number)? {
+		Some(canonical) if canonical == hash => (),
+		Some(canonical) => {
+			problems.push(format!("block {:?} is in the canonical index instead", canonical));
+			stale.push((number, canonical));
+		},
+		None => problems.push("block is not in the canonical index".to_string()),
+	}
+
+	// The genesis block has no Ethereum digest.
+	if number == 0 {
+		return Ok((problems, stale))
+	}
+
+	let hashes = match fp_consensus::find_log(header.digest()) {
+		Ok(log) => log.into_hashes(),
+		Err(e) => {
+			problems.push(format!("no Ethereum digest: {:?}", e));
+			return Ok((problems, stale))
+		},
+	};
+
+	if !mapping.block_hashes(&hashes.block_hash)?.contains(&hash) {
+		problems.push(format!("Ethereum block {:?} is not mapped", hashes.block_hash));
+	}
+	if mapping.canonical_block_hash(&hashes.block_hash)? != Some(hash) {
+		problems.push(format!("Ethereum block {:?} is not canonical", hashes.block_hash));
+	}
+
+	for (index, transaction_hash) in hashes.transaction_hashes.iter().enumerate() {
+		let metadata = mapping.transaction_metadata(transaction_hash)?;
+		match metadata.iter().find(|entry| entry.block_hash == hash) {
+			None => problems.push(format!("transaction {:?} is not mapped", transaction_hash)),
+			Some(entry) if entry.ethereum_block_hash != hashes.block_hash ||
+				entry.ethereum_index != index as u32 =>
+			{
+				problems.push(format!(
+					"transaction {:?} is mapped to index {} of {:?}, expected {} of {:?}",
+					transaction_hash, entry.ethereum_index, entry.ethereum_block_hash,
+					index, hashes.block_hash,
+				));
+			},
+			Some(entry) if !entry.canonical => {
+				problems.push(format!("transaction {:?} is not canonical", transaction_hash));
+			},
+			Some(_) => (),
+		}
+
+		for entry in metadata.iter().filter(|entry| entry.canonical && entry.block_hash != hash) {
+			problems.push(format!(
+				"transaction {:?} is also canonical in {:?}",
+				transaction_hash, entry.block_hash,
+			));
+			// A block unknown to the client has no index entry to remove, any height will do.
+			let stale_number = client.number(entry.block_hash).map_err(|e| format!("{:?}", e))?
+				.map(UniqueSaturatedInto::unique_saturated_into)
+				.unwrap_or(number);
+			if !stale.iter().any(|(_, stale_hash)| *stale_hash == entry.block_hash) {
+				stale.push((stale_number, entry.block_hash));
+			}
+		}
+	}
+
+	Ok((problems, stale))
+}
+
+/// Walk the best chain, reporting every problem found with `report(number, hash, problem)`.
+/// Blocks left in the canonical index above the best block are reported, and removed unless
+/// checking. Returns the number of inconsistent blocks.
+pub fn walk_canonical_chain<Block, C, F>(
+	client: &C,
+	backend: &fc_db::Backend<Block>,
+	walk: ChainWalk,
+	mut report: F,
+) -> Result<u64, String> where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+	F: FnMut(u64, Block::Hash, &str),
+{
+	let info = client.info();
+	let best_number: u64 = info.best_number.unique_saturated_into();
+	let from = match walk {
+		ChainWalk::Reindex { from } => from,
+		_ => 0,
+	};
+
+	let mut inconsistent = 0;
+	let mut retracted = Vec::new();
+	let mut enacted = Vec::new();
+
+	for number in from..=best_number {
+		let header = client.header(BlockId::Number(number.unique_saturated_into()))
+			.map_err(|e| format!("{:?}", e))?
+			.ok_or_else(|| format!("Header #{} not found", number))?;
+		let hash = header.hash();
+
+		let (problems, stale) = check_block(client, backend, number, &header)?;
+		let rewrite = match walk {
+			ChainWalk::Check => false,
+			ChainWalk::Repair => !problems.is_empty(),
+			ChainWalk::Reindex { .. } => true,
+		};
+		if !matches!(walk, ChainWalk::Reindex { .. }) && !problems.is_empty() {
+			inconsistent += 1;
+			for problem in &problems {
+				report(number, hash, problem);
+			}
+		}
+
+		if rewrite {
+			if number == 0 {
+				crate::sync_genesis_block(client, backend, &header)?;
+			} else {
+				crate::sync_block(client, backend, &header)?;
+			}
+			retracted.extend(stale);
+			enacted.push((number, hash));
+		}
+
+		// The head only moves forward until the walk is done, every block below it is consistent.
+		if enacted.len() >= BATCH_SIZE {
+			let head = match backend.meta().canonical_head()? {
+				Some(head) if head.0 > number => head,
+				_ => (number, hash),
+			};
+			backend.mapping().write_canonical(fc_db::CanonicalCommitment {
+				retracted: std::mem::take(&mut retracted),
+				enacted: std::mem::take(&mut enacted),
+				head,
+			})?;
+		}
+
+		if number % PROGRESS_INTERVAL == 0 {
+			info!(target: "mapping-sync", "Walking the canonical chain: #{} of #{}", number, best_number);
+		}
+	}
+
+	// Left by a revert, or by a reorganization to a shorter chain.
+	let mut number = best_number + 1;
+	while let Some(hash) = backend.mapping().canonical_hash(number)? {
+		inconsistent += 1;
+		report(number, hash, "block above the best block is in the canonical index");
+		retracted.push((number, hash));
+		number += 1;
+	}
+
+	if walk != ChainWalk::Check {
+		backend.mapping().write_canonical(fc_db::CanonicalCommitment {
+			retracted,
+			enacted,
+			head: (best_number, info.best_hash),
+		})?;
+	}
+
+	Ok(inconsistent)
+}
@@ -1,36 +1,63 @@
+mod check;
+mod reader;
 mod worker;
 
+pub use check::{ChainWalk, walk_canonical_chain};
 pub use worker::MappingSyncWorker;
 
-use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, Zero}};
-use sp_api::ProvideRuntimeApi;
-use sc_client_api::BlockOf;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, Zero, UniqueSaturatedInto}};
+use sp_api::{ProvideRuntimeApi, ApiExt};
 use sp_blockchain::HeaderBackend;
-use fp_rpc::EthereumRuntimeRPCApi;
+use fp_rpc::{EthereumRuntimeRPCApi, EIP658Receipt};
 
-pub fn sync_block<Block: BlockT>(
-	backend: &fc_db::Backend<Block>,
+/// Read the full Ethereum block data from the block state. Returns `None` if the state is not
+/// available anymore.
+pub fn ethereum_block_data<Block: BlockT, C>(
+	client: &C,
+	id: &BlockId<Block>,
+) -> Option<fc_db::EthereumBlockData> where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
+	let api = client.runtime_api();
+
+	let block = api.current_block(id).ok()??;
+	let api_version = api.api_version::<dyn EthereumRuntimeRPCApi<Block>>(id).ok()??;
+	let receipts = if api_version < 2 {
+		#[allow(deprecated)]
+		let legacy_receipts = api.current_receipts_before_version_2(id).ok()??;
+		EIP658Receipt::from_legacy(legacy_receipts)
+	} else {
+		api.current_receipts(id).ok()??
+	};
+	let statuses = api.current_transaction_statuses(id).ok()??;
+
+	Some(fc_db::EthereumBlockData { block, receipts, statuses })
+}
+
+fn block_commitment<Block: BlockT, C>(
+	client: &C,
 	header: &Block::Header,
-) -> Result<(), String> {
+) -> Result<fc_db::MappingCommitment<Block>, String> where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
 	let log = fp_consensus::find_log(header.digest()).map_err(|e| format!("{:?}", e))?;
 	let post_hashes = log.into_hashes();
 
-	let mapping_commitment = fc_db::MappingCommitment {
+	Ok(fc_db::MappingCommitment {
 		block_hash: header.hash(),
 		ethereum_block_hash: post_hashes.block_hash,
 		ethereum_transaction_hashes: post_hashes.transaction_hashes,
-	};
-	backend.mapping().write_hashes(mapping_commitment)?;
-
-	Ok(())
+		ethereum_block_data: ethereum_block_data(client, &BlockId::Hash(header.hash())),
+	})
 }
 
-pub fn sync_genesis_block<Block: BlockT, C>(
+fn genesis_commitment<Block: BlockT, C>(
 	client: &C,
-	backend: &fc_db::Backend<Block>,
 	header: &Block::Header,
-) -> Result<(), String> where
-	C: ProvideRuntimeApi<Block> + Send + Sync + HeaderBackend<Block> + BlockOf,
+) -> Result<fc_db::MappingCommitment<Block>, String> where
+	C: ProvideRuntimeApi<Block>,
 	C::Api: EthereumRuntimeRPCApi<Block>,
 {
 	let id = BlockId::Hash(header.hash());
@@ -38,86 +65,515 @@ pub fn sync_genesis_block<Block: BlockT, C>(
 	let block = client.runtime_api().current_block(&id)
 		.map_err(|e| format!("{:?}", e))?;
 	let block_hash = block.ok_or("Ethereum genesis block not found".to_string())?.header.hash();
-	let mapping_commitment = fc_db::MappingCommitment::<Block> {
+
+	Ok(fc_db::MappingCommitment::<Block> {
 		block_hash: header.hash(),
 		ethereum_block_hash: block_hash,
 		ethereum_transaction_hashes: Vec::new(),
-	};
-	backend.mapping().write_hashes(mapping_commitment)?;
+		ethereum_block_data: ethereum_block_data(client, &id),
+	})
+}
+
+pub fn sync_block<Block: BlockT, C>(
+	client: &C,
+	backend: &fc_db::Backend<Block>,
+	header: &Block::Header,
+) -> Result<(), String> where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
+	backend.mapping().write_hashes(block_commitment(client, header)?)
+}
 
-	Ok(())
+pub fn sync_genesis_block<Block: BlockT, C>(
+	client: &C,
+	backend: &fc_db::Backend<Block>,
+	header: &Block::Header,
+) -> Result<(), String> where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
+	backend.mapping().write_hashes(genesis_commitment(client, header)?)
 }
 
-pub fn sync_one_block<Block: BlockT, C, B>(
+/// Number of best chain blocks not synced yet.
+pub fn sync_lag<Block: BlockT, C>(
 	client: &C,
-	substrate_backend: &B,
-	shadows_backend: &fc_db::Backend<Block>,
+	backend: &fc_db::Backend<Block>,
+) -> Result<u64, String> where
+	C: HeaderBackend<Block>,
+{
+	let best_number: u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(client.info().best_number);
+	let synced = backend.meta().canonical_head()?
+		.map(|(number, _)| number + 1)
+		.unwrap_or(0);
+
+	Ok(best_number.saturating_add(1).saturating_sub(synced))
+}
+
+/// Sync up to `batch_size` blocks of the best chain after the synced head, and make them
+/// canonical, in a single database commit. If the best chain no longer contains the synced head,
+/// the blocks it left are retracted first. `header_at` returns the best chain header at a height.
+/// Returns whether blocks are left to sync.
+pub fn sync_batch<Block: BlockT, C, F>(
+	client: &C,
+	backend: &fc_db::Backend<Block>,
+	batch_size: u64,
+	mut header_at: F,
 ) -> Result<bool, String> where
-	C: ProvideRuntimeApi<Block> + Send + Sync + HeaderBackend<Block> + BlockOf,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
 	C::Api: EthereumRuntimeRPCApi<Block>,
-	B: sp_blockchain::HeaderBackend<Block> + sp_blockchain::Backend<Block>,
+	F: FnMut(u64) -> Result<Block::Header, String>,
 {
-	let mut current_syncing_tips = shadows_backend.meta().current_syncing_tips()?;
+	let mapping = backend.mapping();
+	let best_number: u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(client.info().best_number);
 
-	if current_syncing_tips.is_empty() {
-		let mut leaves = substrate_backend.leaves().map_err(|e| format!("{:?}", e))?;
-		if leaves.is_empty() {
-			return Ok(false)
+	// Move back to the last synced block still in the best chain.
+	let mut retracted = Vec::new();
+	let mut head = backend.meta().canonical_head()?;
+	while let Some((number, hash)) = head {
+		let best_hash = client.hash(UniqueSaturatedInto::unique_saturated_into(number))
+			.map_err(|e| format!("{:?}", e))?;
+		if best_hash == Some(hash) {
+			break
 		}
 
-		current_syncing_tips.append(&mut leaves);
+		retracted.push((number, hash));
+		head = match number.checked_sub(1) {
+			Some(parent) => mapping.canonical_hash(parent)?.map(|hash| (parent, hash)),
+			None => None,
+		};
+	}
+
+	let from = head.map(|(number, _)| number + 1).unwrap_or(0);
+	if from > best_number && retracted.is_empty() {
+		return Ok(false)
 	}
+	let to = std::cmp::min(from.saturating_add(batch_size.saturating_sub(1)), best_number);
 
-	let mut operating_tip = None;
+	let mut commitments = Vec::new();
+	let mut enacted = Vec::new();
+	let mut parent_hash = head.map(|(_, hash)| hash);
 
-	while let Some(checking_tip) = current_syncing_tips.pop() {
-		if !shadows_backend.mapping().is_synced(&checking_tip).map_err(|e| format!("{:?}", e))? {
-			operating_tip = Some(checking_tip);
-			break
+	for number in from..=to {
+		let header = header_at(number)?;
+		let hash = header.hash();
+
+		if parent_hash.map_or(false, |parent_hash| *header.parent_hash() != parent_hash) {
+			return Err(format!("Header #{} is not a child of the previous block", number))
 		}
-	}
 
-	let operating_tip = match operating_tip {
-		Some(operating_tip) => operating_tip,
-		None => {
-			shadows_backend.meta().write_current_syncing_tips(current_syncing_tips)?;
-			return Ok(false)
+		// Blocks synced before their Ethereum data was stored get it once their state is read.
+		if !mapping.is_synced(&hash)? || !mapping.has_ethereum_block_data(&hash) {
+			commitments.push(if number.is_zero() {
+				genesis_commitment(client, &header)?
+			} else {
+				block_commitment(client, &header)?
+			});
 		}
+		enacted.push((number, hash));
+		parent_hash = Some(hash);
+	}
+
+	let head = match enacted.last().cloned().or(head) {
+		Some(head) => head,
+		None => return Err("Genesis block left the best chain".to_string()),
 	};
+	mapping.write_sync_batch(commitments, fc_db::CanonicalCommitment { retracted, enacted, head })?;
 
-	let operating_header = substrate_backend.header(BlockId::Hash(operating_tip))
-		.map_err(|e| format!("{:?}", e))?
-		.ok_or("Header not found".to_string())?;
+	Ok(to < best_number)
+}
 
-	if operating_header.number() == &Zero::zero() {
-		sync_genesis_block(client, shadows_backend, &operating_header)?;
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+	use codec::Encode;
+	use ethereum_types::{H64, H160, H256, U256, Bloom};
+	use fp_consensus::{Hashes, PostLog, SHADOWS_ENGINE_ID};
+	use fp_rpc::TransactionStatus;
+	use sp_api::{ApiError, ApiRef};
+	use sp_blockchain::{BlockStatus, Info};
+	use sp_runtime::{
+		generic::{Digest, DigestItem},
+		testing::{Block as RawBlock, ExtrinsicWrapper, Header},
+	};
 
-		shadows_backend.meta().write_current_syncing_tips(current_syncing_tips)?;
-		Ok(true)
-	} else {
-		sync_block(shadows_backend, &operating_header)?;
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
 
-		current_syncing_tips.push(*operating_header.parent_hash());
-		shadows_backend.meta().write_current_syncing_tips(current_syncing_tips)?;
-		Ok(true)
+	/// Substrate headers carrying the post-log of their Ethereum block, and the best chain.
+	#[derive(Clone, Default)]
+	struct TestClient {
+		headers: HashMap<H256, Header>,
+		ethereum_blocks: HashMap<H256, ethereum::Block>,
+		best_chain: Vec<H256>,
 	}
-}
 
-pub fn sync_blocks<Block: BlockT, C, B>(
-	client: &C,
-	substrate_backend: &B,
-	shadows_backend: &fc_db::Backend<Block>,
-	limit: usize,
-) -> Result<bool, String> where
-	C: ProvideRuntimeApi<Block> + Send + Sync + HeaderBackend<Block> + BlockOf,
-	C::Api: EthereumRuntimeRPCApi<Block>,
-	B: sp_blockchain::HeaderBackend<Block> + sp_blockchain::Backend<Block>,
-{
-	let mut synced_any = false;
+	impl TestClient {
+		fn new() -> Self {
+			let mut client = Self::default();
+			let genesis = client.push(None, 0);
+			client.best_chain.push(genesis);
+			client
+		}
+
+		/// Add a block on top of `parent`, `seed` telling apart blocks of different forks.
+		fn push(&mut self, parent: Option<H256>, seed: u8) -> H256 {
+			let number = parent.map_or(0, |parent| self.headers[&parent].number + 1);
+			let ethereum_block = ethereum::Block::new(ethereum::PartialHeader {
+				parent_hash: H256::default(),
+				beneficiary: H160::default(),
+				state_root: H256::default(),
+				receipts_root: H256::default(),
+				logs_bloom: Bloom::default(),
+				difficulty: U256::zero(),
+				number: U256::from(number),
+				gas_limit: U256::zero(),
+				gas_used: U256::zero(),
+				timestamp: 0,
+				extra_data: vec![seed],
+				mix_hash: H256::default(),
+				nonce: H64::default(),
+			}, Vec::new(), Vec::new());
+
+			let logs = match parent {
+				Some(_) => vec![DigestItem::Consensus(SHADOWS_ENGINE_ID, PostLog::Hashes(Hashes {
+					block_hash: ethereum_block.header.hash(),
+					transaction_hashes: vec![transaction_hash(number, seed)],
+				}).encode())],
+				None => Vec::new(),
+			};
+			let header = Header::new(
+				number,
+				H256::default(),
+				H256::repeat_byte(seed),
+				parent.unwrap_or_default(),
+				Digest { logs },
+			);
+
+			let hash = header.hash();
+			self.headers.insert(hash, header);
+			self.ethereum_blocks.insert(hash, ethereum_block);
+			hash
+		}
+
+		/// Extend the best chain by `count` blocks from the block at height `from`.
+		fn fork(&mut self, from: u64, count: u64, seed: u8) -> Vec<H256> {
+			self.best_chain.truncate(from as usize + 1);
+			for _ in 0..count {
+				let parent = *self.best_chain.last().unwrap();
+				let hash = self.push(Some(parent), seed);
+				self.best_chain.push(hash);
+			}
+			self.best_chain.clone()
+		}
+
+		fn block_hash(&self, id: &BlockId<Block>) -> Option<H256> {
+			match id {
+				BlockId::Hash(hash) => self.headers.get(hash).map(|_| *hash),
+				BlockId::Number(number) => self.best_chain.get(*number as usize).cloned(),
+			}
+		}
+
+		fn header_at(&self, number: u64) -> Result<Header, String> {
+			self.header(BlockId::Number(number)).map_err(|e| format!("{:?}", e))?
+				.ok_or("Header not found".to_string())
+		}
+	}
 
-	for _ in 0..limit {
-		synced_any = synced_any || sync_one_block(client, substrate_backend, shadows_backend)?;
+	fn transaction_hash(number: u64, seed: u8) -> H256 {
+		H256::from_low_u64_be(((seed as u64) << 32) + number)
 	}
 
-	Ok(synced_any)
+	impl HeaderBackend<Block> for TestClient {
+		fn header(&self, id: BlockId<Block>) -> sp_blockchain::Result<Option<Header>> {
+			Ok(self.block_hash(&id).and_then(|hash| self.headers.get(&hash).cloned()))
+		}
+
+		fn info(&self) -> Info<Block> {
+			Info {
+				best_hash: *self.best_chain.last().unwrap(),
+				best_number: self.best_chain.len() as u64 - 1,
+				genesis_hash: self.best_chain[0],
+				finalized_hash: self.best_chain[0],
+				finalized_number: 0,
+				number_leaves: 1,
+			}
+		}
+
+		fn status(&self, id: BlockId<Block>) -> sp_blockchain::Result<BlockStatus> {
+			Ok(match self.block_hash(&id) {
+				Some(_) => BlockStatus::InChain,
+				None => BlockStatus::Unknown,
+			})
+		}
+
+		fn number(&self, hash: H256) -> sp_blockchain::Result<Option<u64>> {
+			Ok(self.headers.get(&hash).map(|header| header.number))
+		}
+
+		fn hash(&self, number: u64) -> sp_blockchain::Result<Option<H256>> {
+			Ok(self.best_chain.get(number as usize).cloned())
+		}
+	}
+
+	struct RuntimeApi {
+		client: TestClient,
+	}
+
+	impl ProvideRuntimeApi<Block> for TestClient {
+		type Api = RuntimeApi;
+
+		fn runtime_api<'a>(&'a self) -> ApiRef<'a, Self::Api> {
+			RuntimeApi { client: self.clone() }.into()
+		}
+	}
+
+	sp_api::mock_impl_runtime_apis! {
+		impl EthereumRuntimeRPCApi<Block> for RuntimeApi {
+			fn chain_id() -> u64 { 42 }
+
+			fn account_basic(_address: H160) -> fp_evm::Account {
+				fp_evm::Account { balance: U256::zero(), nonce: U256::zero() }
+			}
+
+			fn gas_price() -> U256 { U256::zero() }
+
+			fn account_code_at(_address: H160) -> Vec<u8> { Vec::new() }
+
+			fn author() -> H160 { H160::default() }
+
+			fn storage_at(_address: H160, _index: U256) -> H256 { H256::default() }
+
+			fn call(
+				_from: H160,
+				_to: H160,
+				_data: Vec<u8>,
+				_value: U256,
+				_gas_limit: U256,
+				_gas_price: Option<U256>,
+				_nonce: Option<U256>,
+				_estimate: bool,
+			) -> Result<fp_evm::CallInfo, sp_runtime::DispatchError> {
+				Err(sp_runtime::DispatchError::Other("unsupported"))
+			}
+
+			fn create(
+				_from: H160,
+				_data: Vec<u8>,
+				_value: U256,
+				_gas_limit: U256,
+				_gas_price: Option<U256>,
+				_nonce: Option<U256>,
+				_estimate: bool,
+			) -> Result<fp_evm::CreateInfo, sp_runtime::DispatchError> {
+				Err(sp_runtime::DispatchError::Other("unsupported"))
+			}
+
+			#[advanced]
+			fn current_block(&self, at: &BlockId<Block>) -> Result<Option<ethereum::Block>, ApiError> {
+				Ok(self.client.block_hash(at).and_then(|hash| self.client.ethereum_blocks.get(&hash).cloned()))
+			}
+
+			fn current_receipts() -> Option<Vec<EIP658Receipt>> { Some(Vec::new()) }
+
+			fn current_transaction_statuses() -> Option<Vec<TransactionStatus>> { Some(Vec::new()) }
+
+			fn current_all() -> (
+				Option<ethereum::Block>,
+				Option<Vec<EIP658Receipt>>,
+				Option<Vec<TransactionStatus>>
+			) {
+				(None, None, None)
+			}
+
+			fn pending_block(
+				_xts: Vec<ExtrinsicWrapper<u64>>,
+			) -> (Option<ethereum::Block>, Option<Vec<TransactionStatus>>) {
+				(None, None)
+			}
+		}
+	}
+
+	/// Run `scenario` against a fresh database of every kind.
+	fn with_each_backend(scenario: impl Fn(&fc_db::Backend<Block>)) {
+		let tmp = tempfile::tempdir().unwrap();
+		let sources = vec![
+			fc_db::DatabaseSettingsSrc::InMemory,
+			fc_db::DatabaseSettingsSrc::RocksDb { path: tmp.path().join("rocksdb"), cache_size: 0 },
+			fc_db::DatabaseSettingsSrc::ParityDb { path: tmp.path().join("paritydb") },
+		];
+
+		for source in sources {
+			let backend = fc_db::Backend::<Block>::new(&fc_db::DatabaseSettings { source }).unwrap();
+			scenario(&backend);
+		}
+	}
+
+	fn sync_all(client: &TestClient, backend: &fc_db::Backend<Block>, batch_size: u64) -> usize {
+		let mut batches = 1;
+		while sync_batch(client, backend, batch_size, |number| client.header_at(number)).unwrap() {
+			batches += 1;
+		}
+		batches
+	}
+
+	fn assert_canonical(client: &TestClient, backend: &fc_db::Backend<Block>, chain: &[H256]) {
+		let mapping = backend.mapping();
+		for (number, hash) in chain.iter().enumerate() {
+			assert_eq!(mapping.canonical_hash(number as u64).unwrap(), Some(*hash));
+
+			let ethereum_hash = client.ethereum_blocks[hash].header.hash();
+			assert_eq!(mapping.canonical_block_hash(&ethereum_hash).unwrap(), Some(*hash));
+		}
+		assert_eq!(mapping.canonical_hash(chain.len() as u64).unwrap(), None);
+		assert_eq!(
+			backend.meta().canonical_head().unwrap(),
+			Some((chain.len() as u64 - 1, *chain.last().unwrap())),
+		);
+		assert_eq!(sync_lag(client, backend).unwrap(), 0);
+	}
+
+	fn canonical_blocks(backend: &fc_db::Backend<Block>, transaction_hash: &H256) -> Vec<H256> {
+		backend.mapping().transaction_metadata(transaction_hash).unwrap()
+			.into_iter()
+			.filter(|metadata| metadata.canonical)
+			.map(|metadata| metadata.block_hash)
+			.collect()
+	}
+
+	#[test]
+	fn sync_batch_follows_the_best_chain() {
+		with_each_backend(|backend| {
+			let mut client = TestClient::new();
+			let chain = client.fork(0, 5, 1);
+			assert_eq!(sync_lag(&client, backend).unwrap(), 6);
+
+			assert_eq!(sync_all(&client, backend, 4), 2);
+			assert_canonical(&client, backend, &chain);
+			assert_eq!(canonical_blocks(backend, &transaction_hash(3, 1)), vec![chain[3]]);
+			assert_eq!(
+				backend.mapping().ethereum_block(&chain[3]).unwrap().map(|block| block.header.hash()),
+				Some(client.ethereum_blocks[&chain[3]].header.hash()),
+			);
+
+			// Nothing left to sync.
+			assert!(!sync_batch(&client, backend, 4, |number| client.header_at(number)).unwrap());
+
+			let chain = client.fork(5, 2, 1);
+			assert_eq!(sync_lag(&client, backend).unwrap(), 2);
+			assert_eq!(sync_all(&client, backend, 4), 1);
+			assert_canonical(&client, backend, &chain);
+		});
+	}
+
+	#[test]
+	fn sync_batch_stores_missing_block_data() {
+		with_each_backend(|backend| {
+			let mut client = TestClient::new();
+			let chain = client.fork(0, 2, 1);
+
+			// Synced before the Ethereum data of blocks was stored.
+			let mut commitment = block_commitment(&client, &client.headers[&chain[1]]).unwrap();
+			commitment.ethereum_block_data = None;
+			backend.mapping().write_hashes(commitment).unwrap();
+			assert!(!backend.mapping().has_ethereum_block_data(&chain[1]));
+
+			sync_all(&client, backend, 16);
+			assert_canonical(&client, backend, &chain);
+			assert!(backend.mapping().has_ethereum_block_data(&chain[1]));
+		});
+	}
+
+	#[test]
+	fn sync_batch_switches_to_a_longer_fork() {
+		with_each_backend(|backend| {
+			let mut client = TestClient::new();
+			let first = client.fork(0, 5, 1);
+			sync_all(&client, backend, 16);
+
+			let second = client.fork(2, 4, 2);
+			assert_eq!(sync_all(&client, backend, 2), 2);
+			assert_canonical(&client, backend, &second);
+			assert_eq!(&first[..3], &second[..3]);
+
+			assert!(canonical_blocks(backend, &transaction_hash(3, 1)).is_empty());
+			assert_eq!(canonical_blocks(backend, &transaction_hash(3, 2)), vec![second[3]]);
+			assert_eq!(canonical_blocks(backend, &transaction_hash(2, 1)), vec![first[2]]);
+			assert!(backend.mapping().is_synced(&first[4]).unwrap());
+		});
+	}
+
+	fn walk(client: &TestClient, backend: &fc_db::Backend<Block>, walk: ChainWalk) -> u64 {
+		walk_canonical_chain(client, backend, walk, |_, _, _| ()).unwrap()
+	}
+
+	#[test]
+	fn repair_retracts_stale_canonical_flags() {
+		with_each_backend(|backend| {
+			let mut client = TestClient::new();
+			let chain = client.fork(0, 3, 1);
+			sync_all(&client, backend, 16);
+			assert_eq!(walk(&client, backend, ChainWalk::Check), 0);
+
+			// A fork block holding the same transaction, left canonical.
+			let fork = H256::repeat_byte(0xf0);
+			backend.mapping().write_hashes(fc_db::MappingCommitment {
+				block_hash: fork,
+				ethereum_block_hash: H256::repeat_byte(0xf1),
+				ethereum_transaction_hashes: vec![transaction_hash(2, 1)],
+				ethereum_block_data: None,
+			}).unwrap();
+			backend.mapping().write_canonical(fc_db::CanonicalCommitment {
+				retracted: vec![],
+				enacted: vec![(2, fork)],
+				head: (3, chain[3]),
+			}).unwrap();
+			assert_eq!(canonical_blocks(backend, &transaction_hash(2, 1)), vec![chain[2], fork]);
+
+			let mut reported = Vec::new();
+			walk_canonical_chain(&client, backend, ChainWalk::Check, |number, hash, _| {
+				reported.push((number, hash));
+			}).unwrap();
+			reported.dedup();
+			assert_eq!(reported, vec![(2, chain[2])]);
+
+			assert_eq!(walk(&client, backend, ChainWalk::Repair), 1);
+			assert_eq!(canonical_blocks(backend, &transaction_hash(2, 1)), vec![chain[2]]);
+			assert_canonical(&client, backend, &chain);
+			assert_eq!(walk(&client, backend, ChainWalk::Check), 0);
+		});
+	}
+
+	#[test]
+	fn reindex_removes_blocks_above_the_best_block() {
+		with_each_backend(|backend| {
+			let mut client = TestClient::new();
+			let first = client.fork(0, 5, 1);
+			sync_all(&client, backend, 16);
+
+			let shorter = client.fork(1, 1, 2);
+			assert_eq!(walk(&client, backend, ChainWalk::Check), 4);
+
+			assert_eq!(walk(&client, backend, ChainWalk::Reindex { from: 0 }), 3);
+			assert_canonical(&client, backend, &shorter);
+			assert!(canonical_blocks(backend, &transaction_hash(2, 1)).is_empty());
+			assert_eq!(canonical_blocks(backend, &transaction_hash(1, 1)), vec![first[1]]);
+			assert_eq!(walk(&client, backend, ChainWalk::Check), 0);
+		});
+	}
+
+	#[test]
+	fn sync_batch_switches_to_a_shorter_fork() {
+		with_each_backend(|backend| {
+			let mut client = TestClient::new();
+			client.fork(0, 5, 1);
+			sync_all(&client, backend, 16);
+
+			let shorter = client.fork(1, 1, 2);
+			assert_eq!(sync_all(&client, backend, 16), 1);
+			assert_canonical(&client, backend, &shorter);
+			assert!(canonical_blocks(backend, &transaction_hash(4, 1)).is_empty());
+		});
+	}
 }
@@ -0,0 +1,60 @@
+use std::sync::{Arc, mpsc::{sync_channel, Receiver}};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, UniqueSaturatedInto}};
+use sp_blockchain::HeaderBackend;
+
+/// Read the best chain header at `number`.
+pub fn read_header<Block: BlockT, C: HeaderBackend<Block>>(
+	client: &C,
+	number: u64,
+) -> Result<Block::Header, String> {
+	client.header(BlockId::Number(number.unique_saturated_into()))
+		.map_err(|e| format!("{:?}", e))?
+		.ok_or_else(|| format!("Header #{} not found", number))
+}
+
+/// Reads best chain headers on a separate thread, ahead of the blocks being synced.
+pub struct HeaderReader<Block: BlockT> {
+	receiver: Receiver<Result<Block::Header, String>>,
+	/// Height of the next header to receive.
+	next: u64,
+	/// Height of the last header to read.
+	last: u64,
+}
+
+impl<Block: BlockT> HeaderReader<Block> {
+	/// Read headers `from..=last`, keeping at most `capacity` of them in memory.
+	pub fn spawn<C>(client: Arc<C>, from: u64, last: u64, capacity: usize) -> Result<Self, String> where
+		C: HeaderBackend<Block> + Send + Sync + 'static,
+	{
+		let (sender, receiver) = sync_channel(capacity);
+
+		std::thread::Builder::new()
+			.name("mapping-sync-headers".into())
+			.spawn(move || {
+				for number in from..=last {
+					let header = read_header(client.as_ref(), number);
+					let failed = header.is_err();
+
+					// The reader has been dropped.
+					if sender.send(header).is_err() || failed {
+						break
+					}
+				}
+			})
+			.map_err(|e| format!("{:?}", e))?;
+
+		Ok(Self { receiver, next: from, last })
+	}
+
+	/// Whether the header at `number` is the next one read.
+	pub fn reads(&self, number: u64) -> bool {
+		self.next == number && number <= self.last
+	}
+
+	/// Wait for the next header.
+	pub fn next_header(&mut self) -> Result<Block::Header, String> {
+		let header = self.receiver.recv().map_err(|_| "Header reader stopped".to_string())??;
+		self.next += 1;
+		Ok(header)
+	}
+}
@@ -1,107 +1,205 @@
 use std::time::Duration;
 use std::pin::Pin;
-use std::sync::Arc;
-use futures::{prelude::*, task::{Context, Poll}};
-use sp_runtime::traits::Block as BlockT;
-use sc_client_api::ImportNotifications;
+use std::sync::{Arc, mpsc};
+use futures::{prelude::*, channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender}, task::{Context, Poll}};
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+use sc_client_api::{ImportNotifications, FinalityNotifications};
 use sp_api::ProvideRuntimeApi;
-use sc_client_api::BlockOf;
 use sp_blockchain::HeaderBackend;
 use fp_rpc::EthereumRuntimeRPCApi;
 use futures_timer::Delay;
-use log::warn;
+use log::{info, warn};
+use crate::reader::{HeaderReader, read_header};
 
-const LIMIT: usize = 8;
+/// Number of blocks written per database commit.
+const BATCH_SIZE: u64 = 256;
+/// Number of headers read ahead of the blocks being synced. Headers are read as blocks are synced
+/// once fewer blocks are left.
+const READ_AHEAD: usize = 1024;
+/// Lag, in blocks, from which progress is reported.
+const REPORT_LAG: u64 = 1024;
 
-pub struct MappingSyncWorker<Block: BlockT, C, B> {
+pub struct MappingSyncWorker<Block: BlockT> {
 	import_notifications: ImportNotifications<Block>,
+	finality_notifications: FinalityNotifications<Block>,
 	timeout: Duration,
 	inner_delay: Option<Delay>,
 
-	client: Arc<C>,
-	substrate_backend: Arc<B>,
-	shadows_backend: Arc<fc_db::Backend<Block>>,
-
+	/// Requests a batch from the sync thread.
+	requests: mpsc::Sender<()>,
+	/// Outcome of the requested batches, whether blocks are left to sync.
+	results: UnboundedReceiver<Result<bool, String>>,
+	syncing: bool,
+	notified: bool,
 	have_next: bool,
 }
 
-impl<Block: BlockT, C, B> MappingSyncWorker<Block, C, B> {
-	pub fn new(
+impl<Block: BlockT> MappingSyncWorker<Block> {
+	pub fn new<C>(
 		import_notifications: ImportNotifications<Block>,
+		finality_notifications: FinalityNotifications<Block>,
 		timeout: Duration,
 		client: Arc<C>,
-		substrate_backend: Arc<B>,
 		shadows_backend: Arc<fc_db::Backend<Block>>,
-	) -> Self {
+	) -> Self where
+		C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+		C::Api: EthereumRuntimeRPCApi<Block>,
+	{
+		let (requests, request_receiver) = mpsc::channel();
+		let (result_sender, results) = unbounded();
+
+		// Batches read the database and call the runtime, so they run off the async executor.
+		std::thread::Builder::new()
+			.name("mapping-sync".into())
+			.spawn(move || SyncThread {
+				client,
+				shadows_backend,
+				reader: None,
+			}.run(request_receiver, result_sender))
+			.expect("Spawning the mapping sync thread failed");
+
 		Self {
 			import_notifications,
+			finality_notifications,
 			timeout,
 			inner_delay: None,
 
-			client,
-			substrate_backend,
-			shadows_backend,
-
+			requests,
+			results,
+			syncing: false,
+			notified: false,
 			have_next: true,
 		}
 	}
 }
 
-impl<Block: BlockT, C, B> Stream for MappingSyncWorker<Block, C, B> where
-	C: ProvideRuntimeApi<Block> + Send + Sync + HeaderBackend<Block> + BlockOf,
+struct SyncThread<Block: BlockT, C> {
+	client: Arc<C>,
+	shadows_backend: Arc<fc_db::Backend<Block>>,
+	reader: Option<HeaderReader<Block>>,
+}
+
+impl<Block: BlockT, C> SyncThread<Block, C> where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
 	C::Api: EthereumRuntimeRPCApi<Block>,
-	B: sc_client_api::Backend<Block>,
 {
+	fn run(mut self, requests: mpsc::Receiver<()>, results: UnboundedSender<Result<bool, String>>) {
+		// Stops once the worker is dropped.
+		while requests.recv().is_ok() {
+			let result = self.sync_batch();
+			if result.is_err() {
+				// Headers read ahead may belong to a retracted chain.
+				self.reader = None;
+			}
+
+			if results.unbounded_send(result).is_err() {
+				break
+			}
+		}
+	}
+
+	fn sync_batch(&mut self) -> Result<bool, String> {
+		let client = self.client.clone();
+		let best_number: u64 = client.info().best_number.unique_saturated_into();
+		// Close to the best block, a reader would only live for a batch or two.
+		let read_ahead = crate::sync_lag(client.as_ref(), self.shadows_backend.as_ref())? >= READ_AHEAD as u64;
+		if !read_ahead {
+			self.reader = None;
+		}
+		let reader = &mut self.reader;
+
+		let have_next = crate::sync_batch(
+			client.as_ref(),
+			self.shadows_backend.as_ref(),
+			BATCH_SIZE,
+			|number| {
+				if !read_ahead {
+					return read_header(client.as_ref(), number)
+				}
+				if !reader.as_ref().map_or(false, |reader| reader.reads(number)) {
+					*reader = Some(HeaderReader::spawn(client.clone(), number, best_number, READ_AHEAD)?);
+				}
+				match reader {
+					Some(reader) => reader.next_header(),
+					None => Err("Header reader not started".to_string()),
+				}
+			},
+		)?;
+
+		let lag = crate::sync_lag(client.as_ref(), self.shadows_backend.as_ref())?;
+		if lag >= REPORT_LAG {
+			info!(target: "mapping-sync", "Syncing Ethereum mappings, {} blocks behind #{}", lag, best_number);
+		}
+
+		Ok(have_next)
+	}
+}
+
+impl<Block: BlockT> Stream for MappingSyncWorker<Block> {
 	type Item = ();
 
 	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<()>> {
-		let mut fire = false;
-
 		loop {
 			match Stream::poll_next(Pin::new(&mut self.import_notifications), cx) {
 				Poll::Pending => break,
 				Poll::Ready(Some(_)) => {
-					fire = true;
+					self.notified = true;
 				},
 				Poll::Ready(None) => return Poll::Ready(None),
 			}
 		}
 
+		loop {
+			match Stream::poll_next(Pin::new(&mut self.finality_notifications), cx) {
+				Poll::Pending => break,
+				Poll::Ready(Some(_)) => {
+					self.notified = true;
+				},
+				Poll::Ready(None) => return Poll::Ready(None),
+			}
+		}
+
+		if self.syncing {
+			return match Stream::poll_next(Pin::new(&mut self.results), cx) {
+				Poll::Pending => Poll::Pending,
+				Poll::Ready(Some(Ok(have_next))) => {
+					self.syncing = false;
+					self.have_next = have_next;
+					Poll::Ready(Some(()))
+				},
+				Poll::Ready(Some(Err(e))) => {
+					self.syncing = false;
+					self.have_next = false;
+					warn!(target: "mapping-sync", "Syncing failed with error {:?}, retrying.", e);
+					Poll::Ready(Some(()))
+				},
+				Poll::Ready(None) => Poll::Ready(None),
+			}
+		}
+
 		let timeout = self.timeout.clone();
 		let inner_delay = self.inner_delay.get_or_insert_with(|| Delay::new(timeout));
 
 		match Future::poll(Pin::new(inner_delay), cx) {
 			Poll::Pending => (),
 			Poll::Ready(()) => {
-				fire = true;
+				self.notified = true;
 			},
 		}
 
-		if self.have_next {
-			fire = true;
-		}
-
-		if fire {
+		if self.have_next || self.notified {
 			self.inner_delay = None;
+			self.notified = false;
 
-			match crate::sync_blocks(
-				self.client.as_ref(),
-				self.substrate_backend.blockchain(),
-				self.shadows_backend.as_ref(),
-				LIMIT,
-			) {
-				Ok(have_next) => {
-					self.have_next = have_next;
-					Poll::Ready(Some(()))
-				},
-				Err(e) => {
-					self.have_next = false;
-					warn!(target: "mapping-sync", "Syncing failed with error {:?}, retrying.", e);
-					Poll::Ready(Some(()))
-				},
+			if self.requests.send(()).is_err() {
+				return Poll::Ready(None)
 			}
-		} else {
-			Poll::Pending
+			self.syncing = true;
+
+			// Wait for the batch, so that the task is woken up once it is done.
+			return Stream::poll_next(self, cx)
 		}
+
+		Poll::Pending
 	}
 }
@@ -6,11 +6,12 @@ use jsonrpc_core::{BoxFuture, Result};
 use jsonrpc_derive::rpc;
 
 use crate::types::{
-	BlockNumber, Bytes, CallRequest, Filter, FilterChanges, Index, Log, Receipt,
-	RichBlock, SyncStatus, Transaction, Work, TransactionRequest,
+	BlockNumber, Bytes, CallRequest, EthAccount, FeeHistory, Filter, FilterChanges, Index, Log,
+	Receipt, RichBlock, SyncStatus, Transaction, Work, TransactionRequest, TypedData,
 };
 pub use rpc_impl_EthApi::gen_server::EthApi as EthApiServer;
 pub use rpc_impl_EthFilterApi::gen_server::EthFilterApi as EthFilterApiServer;
+pub use rpc_impl_LightEthApi::gen_server::LightEthApi as LightEthApiServer;
 
 /// Eth rpc interface.
 #[rpc(server)]
@@ -45,6 +46,15 @@ pub trait EthApi {
 	#[rpc(name = "eth_gasPrice")]
 	fn gas_price(&self) -> Result<U256>;
 
+	/// Returns the suggested tip on top of the base fee.
+	#[rpc(name = "eth_maxPriorityFeePerGas")]
+	fn max_priority_fee_per_gas(&self) -> Result<U256>;
+
+	/// Returns base fees, gas used ratios and reward percentiles of a range of blocks ending
+	/// at the given block.
+	#[rpc(name = "eth_feeHistory")]
+	fn fee_history(&self, _: U256, _: BlockNumber, _: Option<Vec<f64>>) -> Result<FeeHistory>;
+
 	/// Returns accounts list.
 	#[rpc(name = "eth_accounts")]
 	fn accounts(&self) -> Result<Vec<H160>>;
@@ -61,6 +71,10 @@ pub trait EthApi {
 	#[rpc(name = "eth_getStorageAt")]
 	fn storage_at(&self, _: H160, _: U256, _: Option<BlockNumber>) -> Result<H256>;
 
+	/// Returns the account and storage values of the given account, with Merkle proofs.
+	#[rpc(name = "eth_getProof")]
+	fn proof(&self, _: H160, _: Vec<U256>, _: Option<BlockNumber>) -> Result<EthAccount>;
+
 	/// Returns block with given hash.
 	#[rpc(name = "eth_getBlockByHash")]
 	fn block_by_hash(&self, _: H256, _: bool) -> Result<Option<RichBlock>>;
@@ -102,6 +116,20 @@ pub trait EthApi {
 	#[rpc(name = "eth_sendRawTransaction")]
 	fn send_raw_transaction(&self, _: Bytes) -> BoxFuture<H256>;
 
+	/// Signs data prefixed with "\x19Ethereum Signed Message:\n" and its length (EIP-191)
+	/// with an account of the node.
+	#[rpc(name = "eth_sign")]
+	fn sign(&self, _: H160, _: Bytes) -> Result<Bytes>;
+
+	/// Signs typed structured data (EIP-712) with an account of the node.
+	#[rpc(name = "eth_signTypedData_v4")]
+	fn sign_typed_data(&self, _: H160, _: TypedData) -> Result<Bytes>;
+
+	/// Signs a transaction with an account of the node, returning it RLP encoded without
+	/// submitting it.
+	#[rpc(name = "eth_signTransaction")]
+	fn sign_transaction(&self, _: TransactionRequest) -> Result<Bytes>;
+
 	/// Call contract, returning the output data.
 	#[rpc(name = "eth_call")]
 	fn call(&self, _: CallRequest, _: Option<BlockNumber>) -> Result<Bytes>;
@@ -134,6 +162,10 @@ pub trait EthApi {
 	#[rpc(name = "eth_getTransactionReceipt")]
 	fn transaction_receipt(&self, _: H256) -> Result<Option<Receipt>>;
 
+	/// Returns the receipts of every transaction of the given block.
+	#[rpc(name = "eth_getBlockReceipts")]
+	fn block_receipts(&self, _: BlockNumber) -> Result<Option<Vec<Receipt>>>;
+
 	/// Returns an uncles at given block and index.
 	#[rpc(name = "eth_getUncleByBlockHashAndIndex")]
 	fn uncle_by_block_hash_and_index(&self, _: H256, _: Index) -> Result<Option<RichBlock>>;
@@ -190,3 +222,53 @@ pub trait EthFilterApi {
 	#[rpc(name = "eth_uninstallFilter")]
 	fn uninstall_filter(&self, _: Index) -> Result<bool>;
 }
+
+/// Eth rpc interface served by light clients, which fetch state from full nodes.
+#[rpc(server)]
+pub trait LightEthApi {
+	/// Returns protocol version encoded as a string (quotes are necessary).
+	#[rpc(name = "eth_protocolVersion")]
+	fn protocol_version(&self) -> Result<u64>;
+
+	/// Returns an object with data about the sync status or false.
+	#[rpc(name = "eth_syncing")]
+	fn syncing(&self) -> Result<SyncStatus>;
+
+	/// Returns the chain ID used for transaction signing at the
+	/// current best block. None is returned if not
+	/// available.
+	#[rpc(name = "eth_chainId")]
+	fn chain_id(&self) -> BoxFuture<Option<U64>>;
+
+	/// Returns current gas_price.
+	#[rpc(name = "eth_gasPrice")]
+	fn gas_price(&self) -> BoxFuture<U256>;
+
+	/// Returns accounts list.
+	#[rpc(name = "eth_accounts")]
+	fn accounts(&self) -> Result<Vec<H160>>;
+
+	/// Returns highest block number.
+	#[rpc(name = "eth_blockNumber")]
+	fn block_number(&self) -> Result<U256>;
+
+	/// Returns balance of the given account.
+	#[rpc(name = "eth_getBalance")]
+	fn balance(&self, _: H160, _: Option<BlockNumber>) -> BoxFuture<U256>;
+
+	/// Returns content of the storage at given address.
+	#[rpc(name = "eth_getStorageAt")]
+	fn storage_at(&self, _: H160, _: U256, _: Option<BlockNumber>) -> BoxFuture<H256>;
+
+	/// Returns the number of transactions sent from given address at given time (block number).
+	#[rpc(name = "eth_getTransactionCount")]
+	fn transaction_count(&self, _: H160, _: Option<BlockNumber>) -> BoxFuture<U256>;
+
+	/// Returns the code at given address at given time (block number).
+	#[rpc(name = "eth_getCode")]
+	fn code_at(&self, _: H160, _: Option<BlockNumber>) -> BoxFuture<Bytes>;
+
+	/// Sends signed transaction, returning its hash.
+	#[rpc(name = "eth_sendRawTransaction")]
+	fn send_raw_transaction(&self, _: Bytes) -> BoxFuture<H256>;
+}
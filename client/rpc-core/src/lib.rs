@@ -4,9 +4,11 @@ pub mod types;
 mod eth;
 mod eth_pubsub;
 mod net;
+mod personal;
 mod web3;
 
-pub use eth::{EthApi, EthApiServer, EthFilterApi, EthFilterApiServer};
+pub use eth::{EthApi, EthApiServer, EthFilterApi, EthFilterApiServer, LightEthApi, LightEthApiServer};
 pub use eth_pubsub::{EthPubSubApi, EthPubSubApiServer};
 pub use net::{NetApi, NetApiServer};
+pub use personal::{PersonalApi, PersonalApiServer};
 pub use web3::{Web3Api, Web3ApiServer};
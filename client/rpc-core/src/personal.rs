@@ -0,0 +1,56 @@
+//! Personal rpc interface.
+use std::collections::BTreeMap;
+use ethereum_types::{H160, H256};
+use jsonrpc_core::{BoxFuture, Result};
+use jsonrpc_derive::rpc;
+
+use crate::types::{Bytes, ExtAccountInfo, RecoveredAccount, TransactionRequest};
+
+pub use rpc_impl_PersonalApi::gen_server::PersonalApi as PersonalApiServer;
+
+/// Personal rpc interface, managing the local Ethereum keys. All its methods are unsafe.
+#[rpc(server)]
+pub trait PersonalApi {
+	/// Returns the accounts of the local keystore.
+	#[rpc(name = "personal_listAccounts")]
+	fn list_accounts(&self) -> Result<Vec<H160>>;
+
+	/// Returns the name, metadata and key file identifier of the local accounts.
+	#[rpc(name = "personal_accountsInfo")]
+	fn accounts_info(&self) -> Result<BTreeMap<H160, ExtAccountInfo>>;
+
+	/// Creates an account whose key is encrypted with the given password.
+	#[rpc(name = "personal_newAccount")]
+	fn new_account(&self, _: String) -> Result<H160>;
+
+	/// Imports a hex encoded secret key, encrypted with the given password.
+	#[rpc(name = "personal_importRawKey")]
+	fn import_raw_key(&self, _: String, _: String) -> Result<H160>;
+
+	/// Unlocks an account for the given number of seconds, 300 by default and until it is
+	/// locked again with 0.
+	#[rpc(name = "personal_unlockAccount")]
+	fn unlock_account(&self, _: H160, _: String, _: Option<u64>) -> Result<bool>;
+
+	/// Locks an unlocked account.
+	#[rpc(name = "personal_lockAccount")]
+	fn lock_account(&self, _: H160) -> Result<bool>;
+
+	/// Signs data prefixed with "\x19Ethereum Signed Message:\n" and its length.
+	#[rpc(name = "personal_sign")]
+	fn sign(&self, _: Bytes, _: H160, _: String) -> Result<Bytes>;
+
+	/// Returns the account which signed data with `personal_sign`.
+	#[rpc(name = "personal_ecRecover")]
+	fn ec_recover(&self, _: Bytes, _: Bytes) -> Result<H160>;
+
+	/// Returns the account which signed data with `personal_sign`, with its public key and
+	/// whether the signature is replay protected for the current chain.
+	#[rpc(name = "personal_verifySignature")]
+	fn verify_signature(&self, _: Bytes, _: Bytes) -> Result<RecoveredAccount>;
+
+	/// Signs a transaction with the key of its sender decrypted by the given password, and
+	/// submits it.
+	#[rpc(name = "personal_sendTransaction")]
+	fn send_transaction(&self, _: TransactionRequest, _: String) -> BoxFuture<H256>;
+}
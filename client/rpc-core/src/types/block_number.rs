@@ -21,6 +21,10 @@ pub enum BlockNumber {
 	Earliest,
 	/// Pending block (being mined)
 	Pending,
+	/// Latest finalized block
+	Finalized,
+	/// Latest block considered safe from reorgs, which is the finalized block under GRANDPA
+	Safe,
 }
 
 impl Default for BlockNumber {
@@ -55,6 +59,8 @@ impl Serialize for BlockNumber {
 			BlockNumber::Latest => serializer.serialize_str("latest"),
 			BlockNumber::Earliest => serializer.serialize_str("earliest"),
 			BlockNumber::Pending => serializer.serialize_str("pending"),
+			BlockNumber::Finalized => serializer.serialize_str("finalized"),
+			BlockNumber::Safe => serializer.serialize_str("safe"),
 		}
 	}
 }
@@ -65,7 +71,7 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 	type Value = BlockNumber;
 
 	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-		write!(formatter, "a block number or 'latest', 'earliest' or 'pending'")
+		write!(formatter, "a block number or 'latest', 'earliest', 'pending', 'finalized' or 'safe'")
 	}
 
 	fn visit_map<V>(self, mut visitor: V) -> Result<Self::Value, V::Error> where V: MapAccess<'a> {
@@ -121,6 +127,8 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 			"latest" => Ok(BlockNumber::Latest),
 			"earliest" => Ok(BlockNumber::Earliest),
 			"pending" => Ok(BlockNumber::Pending),
+			"finalized" => Ok(BlockNumber::Finalized),
+			"safe" => Ok(BlockNumber::Safe),
 			_ if value.starts_with("0x") => u64::from_str_radix(&value[2..], 16).map(BlockNumber::Num).map_err(|e| {
 				Error::custom(format!("Invalid block number: {}", e))
 			}),
@@ -161,4 +169,14 @@ mod tests {
 		assert_eq!(match_block_number(bn_hex).unwrap(), 69 as u64);
 		assert_eq!(match_block_number(bn_u64).unwrap(), 420 as u64);
 	}
+
+	#[test]
+	fn block_tag_deserialize() {
+		let finalized: BlockNumber = serde_json::from_str(r#""finalized""#).unwrap();
+		let safe: BlockNumber = serde_json::from_str(r#""safe""#).unwrap();
+
+		assert_eq!(finalized, BlockNumber::Finalized);
+		assert_eq!(safe, BlockNumber::Safe);
+		assert_eq!(serde_json::to_string(&safe).unwrap(), r#""safe""#);
+	}
 }
@@ -0,0 +1,102 @@
+use std::{sync::{Arc, Mutex}, collections::BTreeMap};
+use ethereum_types::{H256, U256};
+use serde::Serialize;
+
+/// Fee history of a range of blocks, as returned by `eth_feeHistory`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+	/// Lowest block number of the range.
+	pub oldest_block: U256,
+	/// Base fee of each block of the range, followed by the base fee of the next block.
+	pub base_fee_per_gas: Vec<U256>,
+	/// Gas used divided by gas limit, for each block of the range.
+	pub gas_used_ratio: Vec<f64>,
+	/// Rewards at the requested percentiles, for each block of the range.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub reward: Option<Vec<Vec<U256>>>,
+}
+
+/// Fee data of a single block.
+#[derive(Debug, Clone)]
+pub struct FeeHistoryCacheItem {
+	/// Hash of the Substrate block the data was read from.
+	pub hash: H256,
+	/// Base fee of the block.
+	pub base_fee: U256,
+	/// Gas used divided by gas limit.
+	pub gas_used_ratio: f64,
+	/// `(reward, gas used)` of each transaction, sorted by reward.
+	pub rewards: Vec<(U256, u64)>,
+}
+
+impl FeeHistoryCacheItem {
+	/// Reward paid at each of the given percentiles of the gas used in the block. Percentiles
+	/// are expected in `[0, 100]` and in ascending order.
+	pub fn rewards_at(&self, percentiles: &[f64]) -> Vec<U256> {
+		let last = match self.rewards.last() {
+			Some((reward, _)) => *reward,
+			None => return vec![U256::zero(); percentiles.len()],
+		};
+		let total: u64 = self.rewards.iter().map(|(_, gas_used)| gas_used).sum();
+
+		percentiles.iter().map(|percentile| {
+			let threshold = (total as f64 * percentile / 100.0) as u64;
+			let mut cumulative = 0u64;
+			for (reward, gas_used) in &self.rewards {
+				cumulative += gas_used;
+				if cumulative >= threshold {
+					return *reward
+				}
+			}
+			last
+		}).collect()
+	}
+}
+
+/// Fee data of recent blocks, by block number.
+pub type FeeHistoryCache = Arc<Mutex<BTreeMap<u64, FeeHistoryCacheItem>>>;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn item(rewards: Vec<(u64, u64)>) -> FeeHistoryCacheItem {
+		FeeHistoryCacheItem {
+			hash: H256::zero(),
+			base_fee: U256::one(),
+			gas_used_ratio: 0.5,
+			rewards: rewards.into_iter().map(|(reward, gas_used)| (reward.into(), gas_used)).collect(),
+		}
+	}
+
+	#[test]
+	fn rewards_are_weighted_by_gas_used() {
+		let item = item(vec![(1, 21_000), (5, 21_000), (10, 42_000)]);
+
+		assert_eq!(
+			item.rewards_at(&[0.0, 25.0, 26.0, 50.0, 51.0, 100.0]),
+			vec![1, 1, 5, 5, 10, 10].into_iter().map(U256::from).collect::<Vec<_>>(),
+		);
+	}
+
+	#[test]
+	fn empty_block_has_zero_rewards() {
+		assert_eq!(item(vec![]).rewards_at(&[10.0, 90.0]), vec![U256::zero(); 2]);
+	}
+
+	#[test]
+	fn fee_history_serialization() {
+		let history = FeeHistory {
+			oldest_block: 3.into(),
+			base_fee_per_gas: vec![1.into(), 1.into()],
+			gas_used_ratio: vec![0.5],
+			reward: None,
+		};
+
+		assert_eq!(
+			serde_json::to_string(&history).unwrap(),
+			r#"{"oldestBlock":"0x3","baseFeePerGas":["0x1","0x1"],"gasUsedRatio":[0.5]}"#,
+		);
+	}
+}
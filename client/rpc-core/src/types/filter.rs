@@ -33,6 +33,16 @@ impl<'a, T> Deserialize<'a> for VariadicValue<T> where T: DeserializeOwned {
 	}
 }
 
+impl<T> Serialize for VariadicValue<T> where T: DeserializeOwned + Serialize {
+	fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		match self {
+			VariadicValue::Single(value) => value.serialize(s),
+			VariadicValue::Multiple(values) => values.serialize(s),
+			VariadicValue::Null => s.serialize_none(),
+		}
+	}
+}
+
 /// Filter Address
 pub type FilterAddress = VariadicValue<H160>;
 /// Topic, supports `A` | `null` | `[A,B,C]` | `[A,[B,C]]` | [null,[B,C]] | [null,[null,C]]
@@ -43,7 +53,7 @@ pub type Topic = VariadicValue<Option<
 pub type FlatTopic = VariadicValue<Option<H256>>;
 
 /// Filter
-#[derive(Debug, PartialEq, Clone, Deserialize, Eq, Hash)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Eq, Hash)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct Filter {
@@ -310,20 +320,55 @@ impl Serialize for FilterChanges {
 
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FilterType {
 	Block,
 	PendingTransaction,
 	Log(Filter)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilterPoolItem {
-	pub last_poll: BlockNumber,
+	/// Substrate block up to which changes were reported. Until the first poll, the best block
+	/// when the filter was created, which is reported too.
+	pub last_poll: H256,
+	/// Whether the changes of the filter were polled yet.
+	pub polled: bool,
 	pub filter_type: FilterType,
 	pub at_block: u64
 }
 
-/// On-memory stored filters created through the `eth_newFilter` RPC.
+/// On-memory stored filters created through the `eth_newFilter` RPC, optionally persisted in the
+/// Frontier database.
 pub type FilterPool = Arc<Mutex<BTreeMap<U256, FilterPoolItem>>>;
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn filter_pool_item_roundtrip() {
+		let filter: Filter = serde_json::from_str(r#"{
+			"fromBlock": "0x1",
+			"address": ["0xc2bf5f29a4384b1ab0c063e1c666f02121b6084a"],
+			"topics": [null, ["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"]]
+		}"#).unwrap();
+		let item = FilterPoolItem {
+			last_poll: H256::repeat_byte(1),
+			polled: true,
+			filter_type: FilterType::Log(filter.clone()),
+			at_block: 7,
+		};
+
+		let decoded: FilterPoolItem = serde_json::from_str(&serde_json::to_string(&item).unwrap()).unwrap();
+
+		assert_eq!(decoded.last_poll, item.last_poll);
+		assert!(decoded.polled);
+		assert_eq!(decoded.at_block, 7);
+		match decoded.filter_type {
+			FilterType::Log(decoded) => assert_eq!(decoded, filter),
+			other => panic!("unexpected filter type {:?}", other),
+		}
+	}
+}
@@ -6,6 +6,7 @@ mod block;
 mod block_number;
 mod bytes;
 mod call_request;
+mod fee;
 mod filter;
 mod index;
 mod log;
@@ -13,6 +14,7 @@ mod receipt;
 mod sync;
 mod transaction;
 mod transaction_request;
+mod typed_data;
 mod work;
 
 pub mod pubsub;
@@ -22,13 +24,14 @@ pub use self::bytes::Bytes;
 pub use self::block::{RichBlock, Block, BlockTransactions, Header, RichHeader, Rich};
 pub use self::block_number::BlockNumber;
 pub use self::call_request::CallRequest;
+pub use self::fee::{FeeHistory, FeeHistoryCache, FeeHistoryCacheItem};
 pub use self::filter::{
 	Filter, FilterChanges, FilterPool, FilterPoolItem, FilterType, VariadicValue, FilterAddress,
 	Topic, FilteredParams,
 };
 pub use self::index::Index;
 pub use self::log::Log;
-pub use self::receipt::Receipt;
+pub use self::receipt::{Receipt, ReceiptOutcome};
 pub use self::sync::{
 	SyncStatus, SyncInfo, Peers, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo,
 	TransactionStats, ChainStatus, EthProtocolInfo, PipProtocolInfo,
@@ -37,4 +40,5 @@ pub use self::transaction::{
 	Transaction, RichRawTransaction, LocalTransactionStatus, PendingTransactions, PendingTransaction,
 };
 pub use self::transaction_request::TransactionRequest;
+pub use self::typed_data::{TypedData, TypedDataField};
 pub use self::work::Work;
@@ -47,6 +47,8 @@ impl Serialize for Result {
 pub enum Kind {
 	/// New block headers subscription.
 	NewHeads,
+	/// Newly finalized block headers subscription.
+	NewFinalizedHeads,
 	/// Logs subscription.
 	Logs,
 	/// New Pending Transactions subscription.
@@ -1,4 +1,3 @@
-
 use serde::Serialize;
 use ethereum_types::{H160, H256, U64, U256, Bloom as H2048};
 use crate::types::Log;
@@ -19,7 +18,7 @@ pub struct Receipt {
 	pub to: Option<H160>,
 	/// Block number
 	pub block_number: Option<U256>,
-	/// Cumulative gas used
+	/// Gas used by this transaction and the ones before it in the block
 	pub cumulative_gas_used: U256,
 	/// Gas used
 	pub gas_used: Option<U256>,
@@ -27,14 +26,63 @@ pub struct Receipt {
 	pub contract_address: Option<H160>,
 	/// Logs
 	pub logs: Vec<Log>,
-	/// State Root
-	// NOTE(niklasad1): EIP98 makes this optional field, if it's missing then skip serializing it
-	#[serde(skip_serializing_if = "Option::is_none", rename = "root")]
-	pub state_root: Option<H256>,
 	/// Logs bloom
 	pub logs_bloom: H2048,
-	/// Status code
-	// NOTE(niklasad1): Unknown after EIP98 rules, if it's missing then skip serializing it
-	#[serde(skip_serializing_if = "Option::is_none", rename = "status")]
-	pub status_code: Option<U64>,
+	/// Post-transaction state root before EIP-658, status code after it
+	#[serde(flatten)]
+	pub outcome: ReceiptOutcome,
+	/// Gas price paid per unit of gas
+	pub effective_gas_price: U256,
+	/// Transaction type, `0x0` for legacy transactions
+	#[serde(rename = "type")]
+	pub transaction_type: U64,
+}
+
+/// Outcome of a transaction, serialized as either `root` or `status`.
+#[derive(Debug, Serialize)]
+pub enum ReceiptOutcome {
+	/// State root after the transaction, in pre-Byzantium receipts.
+	#[serde(rename = "root")]
+	StateRoot(H256),
+	/// `1` if the transaction succeeded, `0` otherwise (EIP-658).
+	#[serde(rename = "status")]
+	StatusCode(U64),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn receipt(outcome: ReceiptOutcome) -> serde_json::Value {
+		serde_json::to_value(Receipt {
+			transaction_hash: None,
+			transaction_index: None,
+			block_hash: None,
+			from: None,
+			to: None,
+			block_number: None,
+			cumulative_gas_used: 42.into(),
+			gas_used: None,
+			contract_address: None,
+			logs: Vec::new(),
+			logs_bloom: H2048::default(),
+			outcome,
+			effective_gas_price: 1.into(),
+			transaction_type: U64::zero(),
+		}).unwrap()
+	}
+
+	#[test]
+	fn receipt_has_either_root_or_status() {
+		let with_status = receipt(ReceiptOutcome::StatusCode(U64::one()));
+		assert_eq!(with_status["status"], "0x1");
+		assert!(with_status.get("root").is_none());
+		assert_eq!(with_status["effectiveGasPrice"], "0x1");
+		assert_eq!(with_status["type"], "0x0");
+		assert_eq!(with_status["cumulativeGasUsed"], "0x2a");
+
+		let with_root = receipt(ReceiptOutcome::StateRoot(H256::zero()));
+		assert_eq!(with_root["root"], format!("{:?}", H256::zero()));
+		assert!(with_root.get("status").is_none());
+	}
 }
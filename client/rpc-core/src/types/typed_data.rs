@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize, Deserializer, de::Error};
+use serde_json::Value;
+
+/// Member of an EIP-712 struct type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypedDataField {
+	/// Name of the member
+	pub name: String,
+	/// Type of the member, a struct, atomic, dynamic or array type
+	#[serde(rename = "type")]
+	pub type_: String,
+}
+
+/// `TypedData` given as a JSON object.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TypedDataObject {
+	types: BTreeMap<String, Vec<TypedDataField>>,
+	primary_type: String,
+	domain: Value,
+	message: Value,
+}
+
+/// Structured data signed with `eth_signTypedData_v4`, as defined by EIP-712. It is accepted
+/// both as a JSON object and as a string encoding it, as sent by wallets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+	/// Struct types, including `EIP712Domain`
+	pub types: BTreeMap<String, Vec<TypedDataField>>,
+	/// Type of the message
+	pub primary_type: String,
+	/// Domain separator values
+	pub domain: Value,
+	/// Message values
+	pub message: Value,
+}
+
+impl<'a> Deserialize<'a> for TypedData {
+	fn deserialize<D>(deserializer: D) -> Result<TypedData, D::Error>
+	where D: Deserializer<'a> {
+		let object = match Value::deserialize(deserializer)? {
+			Value::String(json) => serde_json::from_str::<TypedDataObject>(&json),
+			value => serde_json::from_value::<TypedDataObject>(value),
+		}.map_err(|err| D::Error::custom(format!("invalid typed data: {}", err)))?;
+
+		Ok(TypedData {
+			types: object.types,
+			primary_type: object.primary_type,
+			domain: object.domain,
+			message: object.message,
+		})
+	}
+}
@@ -0,0 +1,281 @@
+use std::future::Future;
+use ethereum::Block as EthereumBlock;
+use futures::{StreamExt, channel::{mpsc, oneshot}};
+use log::{debug, warn};
+use lru::LruCache;
+use prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
+use sp_runtime::traits::Block as BlockT;
+use fp_rpc::{EIP658Receipt, TransactionStatus};
+
+/// Number of lookups between two reports of the hit and miss counts.
+const REPORT_INTERVAL: u64 = 1_000;
+
+/// Hit and miss counts of the cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+	pub hits: u64,
+	pub misses: u64,
+}
+
+#[derive(Clone)]
+struct Metrics {
+	hits: Counter<U64>,
+	misses: Counter<U64>,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			hits: register(
+				Counter::new(
+					"eth_block_data_cache_hits",
+					"Number of Ethereum block data lookups served from the cache",
+				)?,
+				registry,
+			)?,
+			misses: register(
+				Counter::new(
+					"eth_block_data_cache_misses",
+					"Number of Ethereum block data lookups missing from the cache",
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+enum Message<B: BlockT> {
+	Block(B::Hash, oneshot::Sender<Option<EthereumBlock>>),
+	Receipts(B::Hash, oneshot::Sender<Option<Vec<EIP658Receipt>>>),
+	Statuses(B::Hash, oneshot::Sender<Option<Vec<TransactionStatus>>>),
+	InsertBlock(B::Hash, EthereumBlock),
+	InsertReceipts(B::Hash, Vec<EIP658Receipt>),
+	InsertStatuses(B::Hash, Vec<TransactionStatus>),
+	Stats(oneshot::Sender<CacheStats>),
+}
+
+/// Ethereum blocks, receipts and transaction statuses of recently requested blocks, by Substrate
+/// block hash.
+///
+/// The data is owned by a background task answering requests over a channel, so that RPC threads
+/// never contend on a lock. Lookups fall back to storage, with a warning, when the task is not
+/// running.
+pub struct EthBlockDataCache<B: BlockT> {
+	sender: mpsc::UnboundedSender<Message<B>>,
+}
+
+impl<B: BlockT> Clone for EthBlockDataCache<B> {
+	fn clone(&self) -> Self {
+		Self { sender: self.sender.clone() }
+	}
+}
+
+impl<B: BlockT> EthBlockDataCache<B> {
+	/// Cache keeping up to `capacity` blocks of each kind of data, along with the task serving
+	/// it, which must be spawned. Its hit and miss counts are exported to `registry`, if any.
+	pub fn new(capacity: usize, registry: Option<&Registry>) -> (Self, impl Future<Output = ()>) {
+		let metrics = registry.and_then(|registry| match Metrics::register(registry) {
+			Ok(metrics) => Some(metrics),
+			Err(e) => {
+				warn!(target: "rpc", "Failed to register Ethereum block data cache metrics: {:?}", e);
+				None
+			},
+		});
+		let (sender, receiver) = mpsc::unbounded();
+
+		(Self { sender }, cache_task::<B>(receiver, capacity.max(1), metrics))
+	}
+
+	fn request<T>(&self, message: impl FnOnce(oneshot::Sender<T>) -> Message<B>) -> Option<T> {
+		let (response_tx, response_rx) = oneshot::channel();
+		if self.sender.unbounded_send(message(response_tx)).is_err() {
+			warn!(target: "rpc", "Ethereum block data cache task is not running");
+			return None
+		}
+		// The task only looks up its in-memory caches, so the response comes right away.
+		match futures::executor::block_on(response_rx) {
+			Ok(response) => Some(response),
+			Err(_) => {
+				warn!(target: "rpc", "Ethereum block data cache task stopped");
+				None
+			},
+		}
+	}
+
+	fn send(&self, message: Message<B>) {
+		if self.sender.unbounded_send(message).is_err() {
+			warn!(target: "rpc", "Ethereum block data cache task is not running");
+		}
+	}
+
+	pub fn current_block(&self, hash: B::Hash) -> Option<EthereumBlock> {
+		self.request(|tx| Message::Block(hash, tx))?
+	}
+
+	pub fn current_receipts(&self, hash: B::Hash) -> Option<Vec<EIP658Receipt>> {
+		self.request(|tx| Message::Receipts(hash, tx))?
+	}
+
+	pub fn current_transaction_statuses(&self, hash: B::Hash) -> Option<Vec<TransactionStatus>> {
+		self.request(|tx| Message::Statuses(hash, tx))?
+	}
+
+	pub fn insert_block(&self, hash: B::Hash, block: EthereumBlock) {
+		self.send(Message::InsertBlock(hash, block))
+	}
+
+	pub fn insert_receipts(&self, hash: B::Hash, receipts: Vec<EIP658Receipt>) {
+		self.send(Message::InsertReceipts(hash, receipts))
+	}
+
+	pub fn insert_transaction_statuses(&self, hash: B::Hash, statuses: Vec<TransactionStatus>) {
+		self.send(Message::InsertStatuses(hash, statuses))
+	}
+
+	/// Hit and miss counts since the cache was created, `None` if the task is not running.
+	pub fn stats(&self) -> Option<CacheStats> {
+		self.request(Message::Stats)
+	}
+}
+
+struct Caches<B: BlockT> {
+	blocks: LruCache<B::Hash, EthereumBlock>,
+	receipts: LruCache<B::Hash, Vec<EIP658Receipt>>,
+	statuses: LruCache<B::Hash, Vec<TransactionStatus>>,
+	stats: CacheStats,
+	metrics: Option<Metrics>,
+}
+
+impl<B: BlockT> Caches<B> {
+	fn lookup<T: Clone>(
+		stats: &mut CacheStats,
+		metrics: &Option<Metrics>,
+		cache: &mut LruCache<B::Hash, T>,
+		hash: &B::Hash,
+		response: oneshot::Sender<Option<T>>,
+	) {
+		let value = cache.get(hash).cloned();
+		if value.is_some() {
+			stats.hits += 1;
+		} else {
+			stats.misses += 1;
+		}
+		if let Some(metrics) = metrics {
+			if value.is_some() {
+				metrics.hits.inc();
+			} else {
+				metrics.misses.inc();
+			}
+		}
+		if (stats.hits + stats.misses) % REPORT_INTERVAL == 0 {
+			debug!(
+				target: "rpc",
+				"Ethereum block data cache: {} hits, {} misses",
+				stats.hits,
+				stats.misses,
+			);
+		}
+		let _ = response.send(value);
+	}
+
+	fn handle(&mut self, message: Message<B>) {
+		match message {
+			Message::Block(hash, response) =>
+				Self::lookup(&mut self.stats, &self.metrics, &mut self.blocks, &hash, response),
+			Message::Receipts(hash, response) =>
+				Self::lookup(&mut self.stats, &self.metrics, &mut self.receipts, &hash, response),
+			Message::Statuses(hash, response) =>
+				Self::lookup(&mut self.stats, &self.metrics, &mut self.statuses, &hash, response),
+			Message::InsertBlock(hash, block) => {
+				self.blocks.put(hash, block);
+			},
+			Message::InsertReceipts(hash, receipts) => {
+				self.receipts.put(hash, receipts);
+			},
+			Message::InsertStatuses(hash, statuses) => {
+				self.statuses.put(hash, statuses);
+			},
+			Message::Stats(response) => {
+				let _ = response.send(self.stats);
+			},
+		}
+	}
+}
+
+async fn cache_task<B: BlockT>(
+	mut receiver: mpsc::UnboundedReceiver<Message<B>>,
+	capacity: usize,
+	metrics: Option<Metrics>,
+) {
+	let mut caches = Caches::<B> {
+		blocks: LruCache::new(capacity),
+		receipts: LruCache::new(capacity),
+		statuses: LruCache::new(capacity),
+		stats: CacheStats::default(),
+		metrics,
+	};
+
+	while let Some(message) = receiver.next().await {
+		caches.handle(message);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ethereum_types::H256;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	fn status(index: u32) -> TransactionStatus {
+		TransactionStatus { transaction_index: index, ..Default::default() }
+	}
+
+	#[test]
+	fn counts_hits_and_evicts_least_recently_used() {
+		let (cache, task) = EthBlockDataCache::<Block>::new(2, None);
+		std::thread::spawn(move || futures::executor::block_on(task));
+
+		let (a, b, c) = (H256::repeat_byte(1), H256::repeat_byte(2), H256::repeat_byte(3));
+		assert_eq!(cache.current_transaction_statuses(a), None);
+		cache.insert_transaction_statuses(a, vec![status(1)]);
+		cache.insert_transaction_statuses(b, vec![status(2)]);
+		assert_eq!(cache.current_transaction_statuses(a), Some(vec![status(1)]));
+
+		// `b` is the least recently used entry.
+		cache.insert_transaction_statuses(c, vec![status(3)]);
+		assert_eq!(cache.current_transaction_statuses(b), None);
+		assert_eq!(cache.current_transaction_statuses(c), Some(vec![status(3)]));
+
+		assert_eq!(cache.stats(), Some(CacheStats { hits: 2, misses: 2 }));
+	}
+
+	#[test]
+	fn exports_stats_to_prometheus() {
+		let registry = Registry::new();
+		let metrics = Metrics::register(&registry).expect("Metrics are registered");
+		let (sender, receiver) = mpsc::unbounded();
+		let cache = EthBlockDataCache::<Block> { sender };
+		let task = cache_task::<Block>(receiver, 2, Some(metrics.clone()));
+		std::thread::spawn(move || futures::executor::block_on(task));
+
+		cache.insert_transaction_statuses(H256::zero(), vec![status(1)]);
+		assert!(cache.current_transaction_statuses(H256::zero()).is_some());
+		assert!(cache.current_transaction_statuses(H256::repeat_byte(1)).is_none());
+		assert!(cache.current_receipts(H256::zero()).is_none());
+
+		assert_eq!(cache.stats(), Some(CacheStats { hits: 1, misses: 2 }));
+		assert_eq!(metrics.hits.get(), 1);
+		assert_eq!(metrics.misses.get(), 2);
+	}
+
+	#[test]
+	fn lookups_fail_without_task() {
+		let (cache, task) = EthBlockDataCache::<Block>::new(2, None);
+		drop(task);
+
+		assert_eq!(cache.current_block(H256::zero()), None);
+		assert_eq!(cache.stats(), None);
+	}
+}
@@ -0,0 +1,300 @@
+//! Hashing of typed structured data, as defined by EIP-712 and signed by `eth_signTypedData_v4`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use ethereum_types::{H256, U256};
+use rustc_hex::FromHex;
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+use fc_rpc_core::types::{TypedData, TypedDataField};
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+fn keccak_256(data: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+/// Hash signed for the typed data: `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`,
+/// the message being left out when the primary type is the domain.
+pub fn typed_data_hash(data: &TypedData) -> Result<H256, String> {
+	let encoder = Encoder { types: &data.types };
+	let mut encoded = vec![0x19, 0x01];
+	encoded.extend_from_slice(encoder.hash_struct(DOMAIN_TYPE, &data.domain)?.as_bytes());
+	if data.primary_type != DOMAIN_TYPE {
+		encoded.extend_from_slice(encoder.hash_struct(&data.primary_type, &data.message)?.as_bytes());
+	}
+	Ok(keccak_256(&encoded))
+}
+
+struct Encoder<'a> {
+	types: &'a BTreeMap<String, Vec<TypedDataField>>,
+}
+
+impl<'a> Encoder<'a> {
+	fn fields(&self, name: &str) -> Result<&'a [TypedDataField], String> {
+		self.types.get(name)
+			.map(|fields| &fields[..])
+			.ok_or_else(|| format!("unknown type {}", name))
+	}
+
+	/// Collects the struct types referenced by the type, including itself.
+	fn dependencies(&self, type_: &str, found: &mut BTreeSet<&'a str>) -> Result<(), String> {
+		let base = type_.split('[').next().unwrap_or(type_);
+		let (name, fields) = match self.types.get_key_value(base) {
+			Some(entry) => entry,
+			None => return Ok(()),
+		};
+		if found.insert(name.as_str()) {
+			for field in fields {
+				self.dependencies(&field.type_, found)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// `encodeType`: the type, then the struct types it references sorted by name, as
+	/// `Name(type1 name1,type2 name2)`.
+	fn encode_type(&self, name: &str) -> Result<String, String> {
+		let mut dependencies = BTreeSet::new();
+		self.dependencies(name, &mut dependencies)?;
+		dependencies.remove(name);
+
+		let mut encoded = String::new();
+		for name in std::iter::once(name).chain(dependencies.into_iter()) {
+			let members = self.fields(name)?.iter()
+				.map(|field| format!("{} {}", field.type_, field.name))
+				.collect::<Vec<_>>();
+			encoded.push_str(&format!("{}({})", name, members.join(",")));
+		}
+		Ok(encoded)
+	}
+
+	/// `hashStruct`: `keccak256(typeHash ‖ encodeData(value))`.
+	fn hash_struct(&self, name: &str, value: &Value) -> Result<H256, String> {
+		let fields = self.fields(name)?;
+		let values = value.as_object()
+			.ok_or_else(|| format!("value of {} is not an object", name))?;
+
+		let mut encoded = keccak_256(self.encode_type(name)?.as_bytes()).as_bytes().to_vec();
+		for field in fields {
+			let value = values.get(&field.name).unwrap_or(&Value::Null);
+			let member = self.encode_value(&field.type_, value)
+				.map_err(|err| format!("{}.{}: {}", name, field.name, err))?;
+			encoded.extend_from_slice(member.as_bytes());
+		}
+		Ok(keccak_256(&encoded))
+	}
+
+	/// Encodes a member as 32 bytes: atomic values are padded, dynamic values, arrays and
+	/// structs are hashed.
+	fn encode_value(&self, type_: &str, value: &Value) -> Result<H256, String> {
+		if self.types.contains_key(type_) {
+			return match value {
+				Value::Null => Ok(H256::zero()),
+				value => self.hash_struct(type_, value),
+			};
+		}
+		if let Some(item_type) = array_item_type(type_) {
+			let items = value.as_array().ok_or("value is not an array")?;
+			if let Some(length) = fixed_array_length(type_)? {
+				if items.len() != length {
+					return Err(format!("array has {} items instead of {}", items.len(), length));
+				}
+			}
+			let mut encoded = Vec::with_capacity(32 * items.len());
+			for item in items {
+				encoded.extend_from_slice(self.encode_value(item_type, item)?.as_bytes());
+			}
+			return Ok(keccak_256(&encoded));
+		}
+
+		match type_ {
+			"string" => Ok(keccak_256(value.as_str().ok_or("value is not a string")?.as_bytes())),
+			"bytes" => Ok(keccak_256(&hex_bytes(value)?)),
+			"bool" => match value {
+				Value::Bool(value) => Ok(H256::from_low_u64_be(*value as u64)),
+				_ => Err("value is not a boolean".into()),
+			},
+			"address" => {
+				let address = hex_bytes(value)?;
+				if address.len() != 20 {
+					return Err(format!("invalid address length: {}", address.len()));
+				}
+				let mut encoded = H256::zero();
+				encoded.as_bytes_mut()[12..].copy_from_slice(&address);
+				Ok(encoded)
+			},
+			_ if type_.starts_with("bytes") => {
+				let size = type_size(&type_[5..], 1, 32, 1)?;
+				let bytes = hex_bytes(value)?;
+				if bytes.len() > size {
+					return Err(format!("{} bytes do not fit {}", bytes.len(), type_));
+				}
+				let mut encoded = H256::zero();
+				encoded.as_bytes_mut()[..bytes.len()].copy_from_slice(&bytes);
+				Ok(encoded)
+			},
+			_ if type_.starts_with("uint") => {
+				type_size(&type_[4..], 8, 256, 8)?;
+				let (negative, value) = integer(value)?;
+				if negative {
+					return Err("negative value of unsigned type".into());
+				}
+				Ok(word(value))
+			},
+			_ if type_.starts_with("int") => {
+				type_size(&type_[3..], 8, 256, 8)?;
+				let (negative, value) = integer(value)?;
+				let value = if negative { (!value).overflowing_add(U256::one()).0 } else { value };
+				Ok(word(value))
+			},
+			_ => Err(format!("unsupported type {}", type_)),
+		}
+	}
+}
+
+fn word(value: U256) -> H256 {
+	let mut word = H256::zero();
+	value.to_big_endian(word.as_bytes_mut());
+	word
+}
+
+/// Type of the items of an array type, `Person[2]` for `Person[2][]`.
+fn array_item_type(type_: &str) -> Option<&str> {
+	if type_.ends_with(']') {
+		type_.rfind('[').map(|index| &type_[..index])
+	} else {
+		None
+	}
+}
+
+fn fixed_array_length(type_: &str) -> Result<Option<usize>, String> {
+	let index = type_.rfind('[').ok_or_else(|| format!("invalid array type {}", type_))?;
+	match &type_[index + 1..type_.len() - 1] {
+		"" => Ok(None),
+		length => length.parse().map(Some).map_err(|_| format!("invalid array type {}", type_)),
+	}
+}
+
+/// Size of an atomic type from its suffix, `256` for `uint256`. No suffix is not allowed.
+fn type_size(suffix: &str, min: usize, max: usize, step: usize) -> Result<usize, String> {
+	match suffix.parse::<usize>() {
+		Ok(size) if size >= min && size <= max && size % step == 0 => Ok(size),
+		_ => Err(format!("invalid type size {}", suffix)),
+	}
+}
+
+fn hex_bytes(value: &Value) -> Result<Vec<u8>, String> {
+	let value = value.as_str().ok_or("value is not a hex string")?;
+	value.strip_prefix("0x")
+		.ok_or_else(|| format!("{} is not 0x prefixed", value))?
+		.from_hex()
+		.map_err(|_| format!("{} is not hex", value))
+}
+
+/// Sign and magnitude of an integer, given as a JSON number or a decimal or 0x prefixed hex
+/// string.
+fn integer(value: &Value) -> Result<(bool, U256), String> {
+	match value {
+		Value::Number(number) => match (number.as_u64(), number.as_i64()) {
+			(Some(value), _) => Ok((false, U256::from(value))),
+			(None, Some(value)) => Ok((true, U256::from(value.wrapping_neg() as u64))),
+			_ => Err(format!("{} is not an integer", number)),
+		},
+		Value::String(value) => {
+			let (negative, magnitude) = match value.strip_prefix('-') {
+				Some(magnitude) => (true, magnitude),
+				None => (false, &value[..]),
+			};
+			let magnitude = match magnitude.strip_prefix("0x") {
+				Some(hex) => U256::from_str_radix(hex, 16),
+				None => U256::from_dec_str(magnitude),
+			}.map_err(|_| format!("{} is not an integer", value))?;
+			Ok((negative && !magnitude.is_zero(), magnitude))
+		},
+		_ => Err("value is not an integer".into()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+	use ethereum_types::H160;
+
+	const MAIL: &str = r#"{"types":{"EIP712Domain":[{"name":"name","type":"string"},{"name":"version","type":"string"},{"name":"chainId","type":"uint256"},{"name":"verifyingContract","type":"address"}],"Person":[{"name":"name","type":"string"},{"name":"wallet","type":"address"}],"Mail":[{"name":"from","type":"Person"},{"name":"to","type":"Person"},{"name":"contents","type":"string"}]},"primaryType":"Mail","domain":{"name":"Ether Mail","version":"1","chainId":1,"verifyingContract":"0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"},"message":{"from":{"name":"Cow","wallet":"0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},"to":{"name":"Bob","wallet":"0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},"contents":"Hello, Bob!"}}"#;
+
+	const GROUP: &str = r#"{"types":{"EIP712Domain":[{"name":"name","type":"string"},{"name":"chainId","type":"uint256"}],"Person":[{"name":"name","type":"string"},{"name":"wallets","type":"address[]"},{"name":"score","type":"int64"}],"Group":[{"name":"name","type":"string"},{"name":"members","type":"Person[]"},{"name":"owner","type":"Person"},{"name":"tag","type":"bytes4"},{"name":"active","type":"bool"},{"name":"data","type":"bytes"}]},"primaryType":"Group","domain":{"name":"Groups","chainId":"0x2a"},"message":{"name":"Cows","members":[{"name":"Cow","wallets":["0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826","0xDeaDbeefdEAdbeefdEadbEEFdeadbeEFdEaDbeeF"],"score":-5},{"name":"Bob","wallets":[],"score":"12"}],"owner":null,"tag":"0xdeadbeef","active":true,"data":"0x0102"}}"#;
+
+	fn h256(hex: &str) -> H256 {
+		H256::from_str(hex).unwrap()
+	}
+
+	#[test]
+	fn hashes_the_eip712_example() {
+		let data: TypedData = serde_json::from_str(MAIL).unwrap();
+		let encoder = Encoder { types: &data.types };
+
+		assert_eq!(
+			encoder.encode_type("Mail").unwrap(),
+			"Mail(Person from,Person to,string contents)Person(string name,address wallet)",
+		);
+		assert_eq!(
+			encoder.hash_struct(DOMAIN_TYPE, &data.domain).unwrap(),
+			h256("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"),
+		);
+		assert_eq!(
+			encoder.hash_struct("Mail", &data.message).unwrap(),
+			h256("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"),
+		);
+		assert_eq!(
+			typed_data_hash(&data).unwrap(),
+			h256("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"),
+		);
+	}
+
+	#[test]
+	fn signatures_recover_the_example_signer() {
+		// The example key is `keccak256("cow")`.
+		let secret = secp256k1::SecretKey::parse_slice(&keccak_256(b"cow")[..]).unwrap();
+		let data: TypedData = serde_json::from_str(MAIL).unwrap();
+		let hash = typed_data_hash(&data).unwrap();
+
+		let signature = crate::sign_hash(&secret, hash).unwrap();
+		assert_eq!(
+			crate::recover_signer(hash, &signature).unwrap().1,
+			H160::from_str("cd2a3d9f938e13cd947ec05abc7fe734df8dd826").unwrap(),
+		);
+	}
+
+	#[test]
+	fn hashes_nested_structs_and_arrays() {
+		let data: TypedData = serde_json::from_str(GROUP).unwrap();
+		assert_eq!(
+			Encoder { types: &data.types }.encode_type("Group").unwrap(),
+			"Group(string name,Person[] members,Person owner,bytes4 tag,bool active,bytes data)\
+			Person(string name,address[] wallets,int64 score)",
+		);
+		assert_eq!(
+			typed_data_hash(&data).unwrap(),
+			h256("adda62a6299add66570ffe104b361ca48374a72e7f63bd8827383d988fa19e56"),
+		);
+
+		// Wallets send the typed data as a JSON string.
+		let encoded: TypedData = serde_json::from_value(Value::String(GROUP.into())).unwrap();
+		assert_eq!(encoded, data);
+	}
+
+	#[test]
+	fn rejects_invalid_values() {
+		let mut data: TypedData = serde_json::from_str(MAIL).unwrap();
+		data.message["to"]["wallet"] = Value::String("0x1234".into());
+		assert!(typed_data_hash(&data).unwrap_err().contains("invalid address length"));
+
+		data.message["to"] = Value::Null;
+		data.message["contents"] = Value::Null;
+		assert!(typed_data_hash(&data).unwrap_err().contains("Mail.contents"));
+
+		data.primary_type = "Letter".into();
+		assert_eq!(typed_data_hash(&data).unwrap_err(), "unknown type Letter");
+	}
+}
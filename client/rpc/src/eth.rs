@@ -7,32 +7,43 @@ use ethereum_types::{H160, H256, H64, U256, U64, H512};
 use jsonrpc_core::{BoxFuture, Result, futures::future::{self, Future}};
 use futures::{StreamExt, future::TryFutureExt};
 use sp_runtime::{
-	traits::{Block as BlockT, UniqueSaturatedInto, Zero, One, Saturating, BlakeTwo256},
+	traits::{Block as BlockT, UniqueSaturatedInto, Zero, One, Saturating, BlakeTwo256, Hash as HashT},
 	transaction_validity::TransactionSource,
 };
-use sp_api::{ProvideRuntimeApi, BlockId, Core, HeaderT};
+use sp_api::{ProvideRuntimeApi, BlockId, Core, HeaderT, ApiExt, ApiRef, TransactionOutcome};
 use sp_transaction_pool::{TransactionPool, InPoolTransaction};
 use sc_client_api::{client::BlockchainEvents, backend::{StorageProvider, Backend, StateBackend, AuxStore}};
 use sha3::{Keccak256, Digest};
+use log::warn;
 use sp_blockchain::{Error as BlockChainError, HeaderMetadata, HeaderBackend};
 use sc_network::{NetworkService, ExHashT};
+use sc_rpc_api::DenyUnsafe;
 use fc_rpc_core::{
 	EthApi as EthApiT, NetApi as NetApiT, Web3Api as Web3ApiT, EthFilterApi as EthFilterApiT
 };
 use fc_rpc_core::types::{
 	BlockNumber, Bytes, CallRequest, Filter, FilteredParams, FilterChanges, FilterPool, FilterPoolItem,
-	FilterType, Index, Log, Receipt, RichBlock, SyncStatus, SyncInfo, Transaction, Work, Rich, Block,
-	BlockTransactions, TransactionRequest, PendingTransactions, PendingTransaction,
+	FilterType, Index, Log, Receipt, ReceiptOutcome, RichBlock, SyncStatus, SyncInfo, Transaction, Work, Rich, Block,
+	BlockTransactions, TransactionRequest, PendingTransactions, PendingTransaction, EthAccount,
+	StorageProof, FeeHistory, FeeHistoryCache, FeeHistoryCacheItem, TypedData,
 };
-use fp_rpc::{EthereumRuntimeRPCApi, ConvertTransaction, TransactionStatus};
+use fp_rpc::{EthereumRuntimeRPCApi, ConvertTransaction, TransactionStatus, EIP658Receipt};
 use fp_storage::PALLET_ETHEREUM_SCHEMA;
-use crate::{internal_err, error_on_execution_failure, EthSigner, public_key};
+use crate::{internal_err, error_on_execution_failure, fill_transaction_request, EthSigner, public_key};
 use sp_storage::StorageKey;
 
 pub use fc_rpc_core::{EthApiServer, NetApiServer, Web3ApiServer, EthFilterApiServer};
 use codec::{self, Encode, Decode};
 use pallet_ethereum::EthereumStorageSchema;
-use crate::overrides::{StorageOverride, RuntimeApiStorageOverride};
+use crate::overrides::{StorageOverride, PersistedStorageOverride};
+use crate::gas_oracle::GasPriceOracle;
+use crate::limits::RpcLimits;
+use crate::cache::EthBlockDataCache;
+
+/// Maximum number of blocks covered by a single `eth_feeHistory` call.
+const MAX_FEE_HISTORY_BLOCKS: u64 = 1024;
+/// Maximum number of blocks kept in the fee history cache.
+const FEE_HISTORY_CACHE_LIMIT: usize = 2048;
 
 pub struct EthApi<B: BlockT, C, P, CT, BE, H: ExHashT> {
 	pool: Arc<P>,
@@ -45,11 +56,18 @@ pub struct EthApi<B: BlockT, C, P, CT, BE, H: ExHashT> {
 	fallback: Box<dyn StorageOverride<B> + Send + Sync>,
 	pending_transactions: PendingTransactions,
 	backend: Arc<fc_db::Backend<B>>,
+	state_trie: Option<Arc<fc_state_trie::Backend<B>>>,
+	fee_history_cache: FeeHistoryCache,
+	gas_price_oracle: Arc<GasPriceOracle>,
+	block_data_cache: EthBlockDataCache<B>,
+	limits: RpcLimits,
+	deny_unsafe: DenyUnsafe,
+	pending_block: Mutex<Option<PendingBlock>>,
 	_marker: PhantomData<(B, BE)>,
 }
 
 impl<B: BlockT, C, P, CT, BE, H: ExHashT> EthApi<B, C, P, CT, BE, H> where
-	C: ProvideRuntimeApi<B>,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B>,
 	C::Api: EthereumRuntimeRPCApi<B>,
 	B: BlockT<Hash=H256> + Send + Sync + 'static,
 	C: Send + Sync + 'static,
@@ -63,7 +81,13 @@ impl<B: BlockT, C, P, CT, BE, H: ExHashT> EthApi<B, C, P, CT, BE, H> where
 		signers: Vec<Box<dyn EthSigner>>,
 		overrides: BTreeMap<EthereumStorageSchema, Box<dyn StorageOverride<B> + Send + Sync>>,
 		backend: Arc<fc_db::Backend<B>>,
+		state_trie: Option<Arc<fc_state_trie::Backend<B>>>,
+		fee_history_cache: FeeHistoryCache,
+		gas_price_oracle: Arc<GasPriceOracle>,
+		block_data_cache: EthBlockDataCache<B>,
+		limits: RpcLimits,
 		is_authority: bool,
+		deny_unsafe: DenyUnsafe,
 	) -> Self {
 		Self {
 			client: client.clone(),
@@ -73,14 +97,41 @@ impl<B: BlockT, C, P, CT, BE, H: ExHashT> EthApi<B, C, P, CT, BE, H> where
 			is_authority,
 			signers,
 			overrides,
-			fallback: Box::new(RuntimeApiStorageOverride::new(client)),
+			fallback: Box::new(PersistedStorageOverride::new(client, backend.clone())),
 			pending_transactions,
 			backend,
+			state_trie,
+			fee_history_cache,
+			gas_price_oracle,
+			block_data_cache,
+			limits,
+			deny_unsafe,
+			pending_block: Mutex::new(None),
 			_marker: PhantomData,
 		}
 	}
 }
 
+/// The best block with the ready transactions of the pool applied on top of it.
+struct PendingView<'a, B: BlockT, A> {
+	/// Runtime API holding the state changes of the pending transactions.
+	api: ApiRef<'a, A>,
+	/// Block to call `api` at.
+	at: BlockId<B>,
+	block: Option<EthereumBlock>,
+	statuses: Option<Vec<TransactionStatus>>,
+}
+
+/// Ethereum data of the last pending view built, valid as long as neither the best block nor the
+/// ready transactions of the pool change.
+struct PendingBlock {
+	parent_hash: H256,
+	/// Hash of the ready extrinsics applied on top of `parent_hash`.
+	extrinsics_hash: H256,
+	block: Option<EthereumBlock>,
+	statuses: Option<Vec<TransactionStatus>>,
+}
+
 fn rich_block_build(
 	block: ethereum::Block,
 	statuses: Vec<Option<TransactionStatus>>,
@@ -198,6 +249,79 @@ fn transaction_build(
 	}
 }
 
+/// Replace the `finalized` and `safe` tags of a filter range with the current finalized block
+/// number, so that the range follows finality.
+pub(crate) fn resolve_finalized_tags<B: BlockT, C: HeaderBackend<B>>(client: &C, filter: &Filter) -> Filter {
+	let finalized: u64 = client.info().finalized_number.unique_saturated_into();
+	let resolve = |number: &Option<BlockNumber>| match number {
+		Some(BlockNumber::Finalized) | Some(BlockNumber::Safe) => Some(BlockNumber::Num(finalized)),
+		number => number.clone(),
+	};
+
+	Filter {
+		from_block: resolve(&filter.from_block),
+		to_block: resolve(&filter.to_block),
+		..filter.clone()
+	}
+}
+
+/// Receipt of the transaction at `index` in `block`, as specified by the Ethereum JSON-RPC API.
+fn receipt_build(
+	block: &EthereumBlock,
+	statuses: &[TransactionStatus],
+	receipts: &[EIP658Receipt],
+	index: usize,
+) -> Option<Receipt> {
+	let (transaction, status, receipt) = match (
+		block.transactions.get(index),
+		statuses.get(index),
+		receipts.get(index),
+	) {
+		(Some(transaction), Some(status), Some(receipt)) => (transaction, status, receipt),
+		_ => return None,
+	};
+	let block_hash = H256::from_slice(
+		Keccak256::digest(&rlp::encode(&block.header)).as_slice()
+	);
+	let previous_receipts = &receipts[..index];
+	let previous_cumulative_gas_used = previous_receipts.last()
+		.map(|r| r.cumulative_gas_used)
+		.unwrap_or_default();
+	// Log indexes are counted from the first log of the block.
+	let first_log_index = previous_receipts.iter().map(|r| r.logs.len()).sum::<usize>();
+
+	Some(Receipt {
+		transaction_hash: Some(status.transaction_hash),
+		transaction_index: Some(status.transaction_index.into()),
+		block_hash: Some(block_hash),
+		from: Some(status.from),
+		to: status.to,
+		block_number: Some(block.header.number),
+		cumulative_gas_used: receipt.cumulative_gas_used,
+		gas_used: Some(receipt.cumulative_gas_used.saturating_sub(previous_cumulative_gas_used)),
+		contract_address: status.contract_address,
+		logs: receipt.logs.iter().enumerate().map(|(i, log)| {
+			Log {
+				address: log.address,
+				topics: log.topics.clone(),
+				data: Bytes(log.data.clone()),
+				block_hash: Some(block_hash),
+				block_number: Some(block.header.number),
+				transaction_hash: Some(status.transaction_hash),
+				transaction_index: Some(status.transaction_index.into()),
+				log_index: Some(U256::from(first_log_index + i)),
+				transaction_log_index: Some(U256::from(i)),
+				removed: false,
+			}
+		}).collect(),
+		logs_bloom: receipt.logs_bloom,
+		// Receipts are always built after EIP-658.
+		outcome: ReceiptOutcome::StatusCode(U64::from(receipt.status_code)),
+		effective_gas_price: transaction.gas_price,
+		transaction_type: U64::zero(),
+	})
+}
+
 fn logs_build(
 	filter: Filter,
 	blocks_and_statuses: Vec<(EthereumBlock, Vec<TransactionStatus>)>
@@ -281,7 +405,10 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApi<B, C, P, CT, BE, H> where
 				self.load_hash(hash).unwrap_or(None)
 			},
 			BlockNumber::Num(number) => {
-				Some(BlockId::Number(number.unique_saturated_into()))
+				match self.backend.mapping().canonical_hash(number) {
+					Ok(Some(hash)) => Some(BlockId::Hash(hash)),
+					_ => Some(BlockId::Number(number.unique_saturated_into())),
+				}
 			},
 			BlockNumber::Latest => {
 				Some(BlockId::Hash(
@@ -293,29 +420,36 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApi<B, C, P, CT, BE, H> where
 			},
 			BlockNumber::Pending => {
 				None
-			}
+			},
+			BlockNumber::Finalized | BlockNumber::Safe => {
+				Some(BlockId::Hash(
+					self.client.info().finalized_hash
+				))
+			},
 		})
 	}
 
-	// Asumes there is only one mapped canonical block in the AuxStore, otherwise something is wrong
 	fn load_hash(&self, hash: H256) -> Result<Option<BlockId<B>>> {
-		let hashes = self.backend.mapping().block_hashes(&hash)
+		let canonical = self.backend.mapping().canonical_block_hash(&hash)
 			.map_err(|err| internal_err(format!("fetch aux store failed: {:?}", err)))?;
-		let out: Vec<H256> = hashes.into_iter()
-			.filter_map(|h| {
-				if self.is_canon(h) {
-					Some(h)
-				} else {
-					None
-				}
-			}).collect();
 
-		if out.len() == 1 {
-			return Ok(Some(
-				BlockId::Hash(out[0])
-			));
+		if canonical.is_none() {
+			self.ensure_mapping_synced()?;
 		}
-		Ok(None)
+		Ok(canonical.map(BlockId::Hash))
+	}
+
+	/// A hash missing from the mapping may belong to a block that is not synced yet. Refuse such
+	/// lookups when the mapping lags too far behind the best block.
+	fn ensure_mapping_synced(&self) -> Result<()> {
+		let best_number: u64 = self.client.info().best_number.unique_saturated_into();
+		let synced = self.backend.meta().canonical_head()
+			.map_err(|err| internal_err(format!("fetch aux store failed: {:?}", err)))?
+			.map(|(number, _)| number + 1)
+			.unwrap_or(0);
+		let lag = best_number.saturating_add(1).saturating_sub(synced);
+
+		self.limits.check_mapping_lag(lag)
 	}
 
 	fn onchain_storage_schema(&self, at: BlockId<B>) -> EthereumStorageSchema {
@@ -325,25 +459,251 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApi<B, C, P, CT, BE, H> where
 		}
 	}
 
-	fn is_canon(&self, target_hash: H256) -> bool {
-		if let Ok(Some(number)) = self.client.number(target_hash) {
-			if let Ok(Some(header)) = self.client.header(BlockId::Number(number)) {
-				return header.hash() == target_hash;
+	/// Ethereum block stored at `id`, read from the block data cache when possible.
+	fn current_block(&self, id: &BlockId<B>) -> Option<EthereumBlock> {
+		let hash = self.client.block_hash_from_id(id).ok()??;
+		if let Some(block) = self.block_data_cache.current_block(hash) {
+			return Some(block)
+		}
+		let schema = self.onchain_storage_schema(*id);
+		let block = self.overrides.get(&schema).unwrap_or(&self.fallback).current_block(id)?;
+		self.block_data_cache.insert_block(hash, block.clone());
+		Some(block)
+	}
+
+	/// Receipts stored at `id`, read from the block data cache when possible.
+	fn current_receipts(&self, id: &BlockId<B>) -> Option<Vec<EIP658Receipt>> {
+		let hash = self.client.block_hash_from_id(id).ok()??;
+		if let Some(receipts) = self.block_data_cache.current_receipts(hash) {
+			return Some(receipts)
+		}
+		let schema = self.onchain_storage_schema(*id);
+		let receipts = self.overrides.get(&schema).unwrap_or(&self.fallback).current_receipts(id)?;
+		self.block_data_cache.insert_receipts(hash, receipts.clone());
+		Some(receipts)
+	}
+
+	/// Transaction statuses stored at `id`, read from the block data cache when possible.
+	fn current_transaction_statuses(&self, id: &BlockId<B>) -> Option<Vec<TransactionStatus>> {
+		let hash = self.client.block_hash_from_id(id).ok()??;
+		if let Some(statuses) = self.block_data_cache.current_transaction_statuses(hash) {
+			return Some(statuses)
+		}
+		let schema = self.onchain_storage_schema(*id);
+		let statuses = self.overrides.get(&schema).unwrap_or(&self.fallback)
+			.current_transaction_statuses(id)?;
+		self.block_data_cache.insert_transaction_statuses(hash, statuses.clone());
+		Some(statuses)
+	}
+
+	/// Ethereum-compatible state root of the given block, if the node maintains the state trie.
+	fn ethereum_state_root(&self, id: &BlockId<B>) -> Option<H256> {
+		let state_trie = self.state_trie.as_ref()?;
+		let hash = self.client.expect_block_hash_from_id(id).ok()?;
+		state_trie.state_root(&hash).ok()?
+	}
+
+	/// Ready extrinsics of the pool, in the order they would be included.
+	fn ready_extrinsics(&self) -> Vec<<B as BlockT>::Extrinsic> {
+		self.pool.ready()
+			.map(|in_pool_tx| in_pool_tx.data().clone())
+			.collect()
+	}
+
+	/// Build the pending view, `None` if the runtime does not support it.
+	fn pending_view(&self) -> Result<Option<PendingView<'_, B, C::Api>>> {
+		let best_hash = self.client.info().best_hash;
+		let at = BlockId::Hash(best_hash);
+		let api = self.client.runtime_api();
+
+		let api_version = api.api_version::<dyn EthereumRuntimeRPCApi<B>>(&at)
+			.map_err(|err| internal_err(format!("failed to retrieve Runtime Api version: {:?}", err)))?;
+		if api_version.map_or(true, |version| version < 3) {
+			return Ok(None)
+		}
+
+		let best_header = self.client.header(at)
+			.map_err(|err| internal_err(format!("{:?}", err)))?
+			.ok_or_else(|| internal_err("best header not found"))?;
+		let header = <B::Header as HeaderT>::new(
+			*best_header.number() + One::one(),
+			Default::default(),
+			Default::default(),
+			best_hash,
+			Default::default(),
+		);
+		api.initialize_block(&at, &header)
+			.map_err(|err| internal_err(format!("failed to initialize pending block: {:?}", err)))?;
+
+		let xts = self.ready_extrinsics();
+		let extrinsics_hash = BlakeTwo256::hash_of(&xts);
+		let (block, statuses) = api.pending_block(&at, xts)
+			.map_err(|err| internal_err(format!("failed to build pending block: {:?}", err)))?;
+
+		*self.pending_block.lock().map_err(|_| internal_err("pending block lock poisoned"))? = Some(PendingBlock {
+			parent_hash: best_hash,
+			extrinsics_hash,
+			block: block.clone(),
+			statuses: statuses.clone(),
+		});
+
+		Ok(Some(PendingView { api, at, block, statuses }))
+	}
+
+	/// Ethereum block and transaction statuses of the pending view, only built again once the
+	/// best block or the ready transactions change. `None` if the runtime does not support it.
+	fn pending_block(&self) -> Result<Option<(Option<EthereumBlock>, Option<Vec<TransactionStatus>>)>> {
+		let parent_hash = self.client.info().best_hash;
+		let extrinsics_hash = BlakeTwo256::hash_of(&self.ready_extrinsics());
+
+		if let Some(cached) = self.pending_block.lock()
+			.map_err(|_| internal_err("pending block lock poisoned"))?
+			.as_ref()
+		{
+			if cached.parent_hash == parent_hash && cached.extrinsics_hash == extrinsics_hash {
+				return Ok(Some((cached.block.clone(), cached.statuses.clone())))
+			}
+		}
+
+		Ok(self.pending_view()?.map(|view| (view.block, view.statuses)))
+	}
+
+	/// Runtime API and block to execute calls at: the pending view for `BlockNumber::Pending`,
+	/// the best block otherwise.
+	fn call_api(&self, number: Option<BlockNumber>) -> Result<(ApiRef<'_, C::Api>, BlockId<B>)> {
+		if let Some(BlockNumber::Pending) = number {
+			if let Some(view) = self.pending_view()? {
+				return Ok((view.api, view.at))
+			}
+		}
+
+		Ok((self.client.runtime_api(), BlockId::Hash(self.client.info().best_hash)))
+	}
+
+	/// The chain of at most `count` blocks ending at `hash`, as `(number, hash)` pairs, oldest
+	/// block first.
+	fn chain_back(&self, mut hash: H256, count: u64) -> Result<Vec<(u64, H256)>> {
+		let mut blocks = Vec::new();
+		while (blocks.len() as u64) < count {
+			let header = match self.client.header(BlockId::Hash(hash))
+				.map_err(|err| internal_err(format!("{:?}", err)))? {
+				Some(header) => header,
+				None => break,
+			};
+			let number: u64 = (*header.number()).unique_saturated_into();
+			blocks.push((number, hash));
+
+			if number == 0 {
+				break
+			}
+			hash = *header.parent_hash();
+		}
+		blocks.reverse();
+
+		Ok(blocks)
+	}
+
+	/// Gas price suggested by the oracle at the best block, with the runtime minimum gas price.
+	fn suggested_gas_price(&self) -> Result<(U256, U256)> {
+		let best = self.client.info().best_hash;
+		let minimum: U256 = self.client.runtime_api().gas_price(&BlockId::Hash(best))
+			.map_err(|err| internal_err(format!("fetch runtime gas price failed: {:?}", err)))?;
+
+		let price = self.gas_price_oracle.suggest(best, minimum, |count| {
+			let mut prices = Vec::new();
+			for (number, hash) in self.chain_back(best, count)? {
+				let item = self.fee_history_item(number, hash)?;
+				prices.extend(item.rewards.iter().map(|(reward, _)| reward.saturating_add(item.base_fee)));
+			}
+			Ok(prices)
+		})?;
+
+		Ok((price, minimum))
+	}
+
+	/// Fee data of the block `hash`, read from the cache when it has been computed before.
+	fn fee_history_item(&self, number: u64, hash: H256) -> Result<FeeHistoryCacheItem> {
+		if let Some(item) = self.fee_history_cache.lock().unwrap().get(&number) {
+			if item.hash == hash {
+				return Ok(item.clone())
 			}
 		}
-		false
+
+		let id = BlockId::Hash(hash);
+		let (block, receipts) = match (self.current_block(&id), self.current_receipts(&id)) {
+			(Some(block), Some(receipts)) => (block, receipts),
+			_ => return Err(internal_err(format!("failed to read fee data of block #{}", number))),
+		};
+		let base_fee: U256 = self.client.runtime_api().gas_price(&id)
+			.map_err(|err| internal_err(format!("fetch runtime gas price failed: {:?}", err)))?;
+
+		let gas_used_ratio = if block.header.gas_limit.is_zero() {
+			0.0
+		} else {
+			block.header.gas_used.low_u64() as f64 / block.header.gas_limit.low_u64() as f64
+		};
+
+		// Receipts hold the cumulative gas used.
+		let mut previous_gas_used = U256::zero();
+		let mut rewards: Vec<(U256, u64)> = block.transactions.iter()
+			.zip(receipts.iter())
+			.map(|(transaction, receipt)| {
+				let gas_used = receipt.cumulative_gas_used.saturating_sub(previous_gas_used);
+				previous_gas_used = receipt.cumulative_gas_used;
+				(transaction.gas_price.saturating_sub(base_fee), gas_used.low_u64())
+			})
+			.collect();
+		rewards.sort_by(|a, b| a.0.cmp(&b.0));
+
+		let item = FeeHistoryCacheItem { hash, base_fee, gas_used_ratio, rewards };
+
+		let mut cache = self.fee_history_cache.lock().unwrap();
+		cache.insert(number, item.clone());
+		while cache.len() > FEE_HISTORY_CACHE_LIMIT {
+			let oldest = *cache.keys().next().expect("cache is not empty; qed");
+			cache.remove(&oldest);
+		}
+
+		Ok(item)
 	}
 
 	fn load_transactions(&self, transaction_hash: H256) -> Result<Option<(H256, u32)>> {
 		let transaction_metadata = self.backend.mapping().transaction_metadata(&transaction_hash)
 			.map_err(|err| internal_err(format!("fetch aux store failed: {:?}", err)))?;
 
-		if transaction_metadata.len() == 1 {
-			Ok(Some((transaction_metadata[0].ethereum_block_hash, transaction_metadata[0].ethereum_index)))
+		let canonical: Vec<_> = transaction_metadata.into_iter()
+			.filter(|metadata| metadata.canonical)
+			.collect();
+
+		if canonical.len() == 1 {
+			Ok(Some((canonical[0].ethereum_block_hash, canonical[0].ethereum_index)))
 		} else {
+			self.ensure_mapping_synced()?;
 			Ok(None)
 		}
 	}
+
+	/// Signer holding the key of the given account, refused when unsafe RPC methods are denied.
+	fn signer(&self, address: &H160) -> Result<&dyn EthSigner> {
+		self.deny_unsafe.check_if_safe()?;
+		self.signers.iter()
+			.find(|signer| signer.accounts().contains(address))
+			.map(|signer| &**signer)
+			.ok_or(internal_err("no signer available"))
+	}
+
+	/// Sign a transaction request, filling its missing fields.
+	fn sign_request(&self, request: TransactionRequest) -> Result<ethereum::Transaction> {
+		let from = match request.from {
+			Some(from) => from,
+			None => self.accounts()?.get(0).cloned()
+				.ok_or(internal_err("no signer available"))?,
+		};
+		let signer = self.signer(&from)?;
+		let message = fill_transaction_request(self, from, request)?;
+
+		signer.sign(message, &from)
+	}
 }
 
 impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
@@ -387,13 +747,9 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 
 	fn author(&self) -> Result<H160> {
 		let block = BlockId::Hash(self.client.info().best_hash);
-		let schema = self.onchain_storage_schema(block);
 
 		Ok(
-			self.overrides
-			.get(&schema)
-			.unwrap_or(&self.fallback)
-			.current_block(&block)
+			self.current_block(&block)
 			.ok_or(internal_err("fetching author through override failed"))?
 			.header.beneficiary
 		)
@@ -410,15 +766,70 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 	}
 
 	fn gas_price(&self) -> Result<U256> {
-		let block = BlockId::Hash(self.client.info().best_hash);
+		let (price, _) = self.suggested_gas_price()?;
+		Ok(price)
+	}
 
-		Ok(
-			self.client
-				.runtime_api()
-				.gas_price(&block)
-				.map_err(|err| internal_err(format!("fetch runtime chain id failed: {:?}", err)))?
-				.into(),
-		)
+	fn max_priority_fee_per_gas(&self) -> Result<U256> {
+		// The runtime minimum gas price plays the role of the base fee.
+		let (price, minimum) = self.suggested_gas_price()?;
+		Ok(price.saturating_sub(minimum))
+	}
+
+	fn fee_history(
+		&self,
+		block_count: U256,
+		newest_block: BlockNumber,
+		reward_percentiles: Option<Vec<f64>>,
+	) -> Result<FeeHistory> {
+		let block_count = if block_count > U256::from(MAX_FEE_HISTORY_BLOCKS) {
+			MAX_FEE_HISTORY_BLOCKS
+		} else {
+			block_count.as_u64()
+		};
+
+		if let Some(percentiles) = &reward_percentiles {
+			let mut previous = 0.0;
+			for percentile in percentiles {
+				if *percentile < previous || *percentile > 100.0 {
+					return Err(internal_err(format!("invalid reward percentile {}", percentile)))
+				}
+				previous = *percentile;
+			}
+		}
+
+		// The pending block is not built yet, use the best block instead.
+		let newest = match self.native_block_id(Some(newest_block))? {
+			Some(id) => id,
+			None => BlockId::Hash(self.client.info().best_hash),
+		};
+		let hash = self.client.expect_block_hash_from_id(&newest)
+			.map_err(|err| internal_err(format!("{:?}", err)))?;
+		let blocks = self.chain_back(hash, block_count)?;
+
+		let mut history = FeeHistory {
+			oldest_block: blocks.first().map(|(number, _)| *number).unwrap_or_default().into(),
+			base_fee_per_gas: Vec::new(),
+			gas_used_ratio: Vec::new(),
+			reward: reward_percentiles.as_ref().map(|_| Vec::new()),
+		};
+
+		for (number, hash) in blocks {
+			let item = self.fee_history_item(number, hash)?;
+
+			history.base_fee_per_gas.push(item.base_fee);
+			history.gas_used_ratio.push(item.gas_used_ratio);
+			if let (Some(reward), Some(percentiles)) = (&mut history.reward, &reward_percentiles) {
+				reward.push(item.rewards_at(percentiles));
+			}
+		}
+
+		// The base fee is set by the runtime, so the next block has the same as the newest one.
+		if let Some(base_fee) = history.base_fee_per_gas.last().cloned() {
+			history.base_fee_per_gas.push(base_fee);
+		}
+
+		Ok(history)
 	}
 
 	fn accounts(&self) -> Result<Vec<H160>> {
@@ -434,6 +845,15 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 	}
 
 	fn balance(&self, address: H160, number: Option<BlockNumber>) -> Result<U256> {
+		if let Some(BlockNumber::Pending) = number {
+			let (api, at) = self.call_api(number)?;
+			return Ok(
+				api.account_basic(&at, address)
+					.map_err(|err| internal_err(format!("fetch runtime account basic failed: {:?}", err)))?
+					.balance
+			)
+		}
+
 		if let Ok(Some(id)) = self.native_block_id(number) {
 			return Ok(
 				self.client
@@ -460,6 +880,41 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 		Ok(H256::default())
 	}
 
+	fn proof(&self, address: H160, storage_keys: Vec<U256>, number: Option<BlockNumber>) -> Result<EthAccount> {
+		let state_trie = self.state_trie.as_ref()
+			.ok_or(internal_err("Ethereum state trie is not enabled, start the node with `--enable-eth-state-trie`"))?;
+		let id = self.native_block_id(number)?
+			.ok_or(internal_err("block not found"))?;
+		let hash = self.client.expect_block_hash_from_id(&id)
+			.map_err(|err| internal_err(format!("{:?}", err)))?;
+
+		let keys = storage_keys.iter().map(|key| {
+			let mut index = [0u8; 32];
+			key.to_big_endian(&mut index);
+			H256::from(index)
+		}).collect::<Vec<_>>();
+
+		let proof = state_trie.prove(&hash, address, &keys)
+			.map_err(|err| internal_err(format!("fetch state trie proof failed: {:?}", err)))?
+			.ok_or(internal_err("Ethereum state trie is not synced up to the requested block yet"))?;
+
+		Ok(EthAccount {
+			address,
+			balance: proof.account.balance,
+			nonce: proof.account.nonce,
+			code_hash: proof.account.code_hash,
+			storage_hash: proof.account.storage_root,
+			account_proof: proof.proof.into_iter().map(Bytes).collect(),
+			storage_proof: storage_keys.into_iter().zip(proof.storage_proofs).map(|(key, storage)| {
+				StorageProof {
+					key,
+					value: storage.value,
+					proof: storage.proof.into_iter().map(Bytes).collect(),
+				}
+			}).collect(),
+		})
+	}
+
 	fn block_by_hash(&self, hash: H256, full: bool) -> Result<Option<RichBlock>> {
 		let id = match self.load_hash(hash)
 			.map_err(|err| internal_err(format!("{:?}", err)))?
@@ -467,20 +922,22 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 			Some(hash) => hash,
 			_ => return Ok(None),
 		};
-		let schema = self.onchain_storage_schema(id);
-		let handler = self.overrides.get(&schema).unwrap_or(&self.fallback);
-
-		let block = handler.current_block(&id);
-		let statuses = handler.current_transaction_statuses(&id);
+		let block = self.current_block(&id);
+		let statuses = self.current_transaction_statuses(&id);
 
 		match (block, statuses) {
 			(Some(block), Some(statuses)) => {
-				Ok(Some(rich_block_build(
+				let mut rich_block = rich_block_build(
 					block,
 					statuses.into_iter().map(|s| Some(s)).collect(),
 					Some(hash),
 					full,
-				)))
+				);
+				if let Some(state_root) = self.ethereum_state_root(&id) {
+					rich_block.inner.state_root = state_root;
+				}
+
+				Ok(Some(rich_block))
 			},
 			_ => {
 				Ok(None)
@@ -489,15 +946,34 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 	}
 
 	fn block_by_number(&self, number: BlockNumber, full: bool) -> Result<Option<RichBlock>> {
+		if number == BlockNumber::Pending {
+			let (block, statuses) = match self.pending_block()? {
+				Some(pending) => pending,
+				None => return Ok(None),
+			};
+
+			return Ok(match (block, statuses) {
+				(Some(block), Some(statuses)) => {
+					let mut rich_block = rich_block_build(
+						block,
+						statuses.into_iter().map(Some).collect(),
+						None,
+						full,
+					);
+					// The pending block is not sealed, so it has no hash yet.
+					rich_block.inner.hash = None;
+					Some(rich_block)
+				},
+				_ => None,
+			})
+		}
+
 		let id = match self.native_block_id(Some(number))? {
 			Some(id) => id,
 			None => return Ok(None),
 		};
-		let schema = self.onchain_storage_schema(id);
-		let handler = self.overrides.get(&schema).unwrap_or(&self.fallback);
-
-		let block = handler.current_block(&id);
-		let statuses = handler.current_transaction_statuses(&id);
+		let block = self.current_block(&id);
+		let statuses = self.current_transaction_statuses(&id);
 
 		match (block, statuses) {
 			(Some(block), Some(statuses)) => {
@@ -505,12 +981,17 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 					Keccak256::digest(&rlp::encode(&block.header)).as_slice(),
 				);
 
-				Ok(Some(rich_block_build(
+				let mut rich_block = rich_block_build(
 					block,
 					statuses.into_iter().map(|s| Some(s)).collect(),
 					Some(hash),
 					full,
-				)))
+				);
+				if let Some(state_root) = self.ethereum_state_root(&id) {
+					rich_block.inner.state_root = state_root;
+				}
+
+				Ok(Some(rich_block))
 			},
 			_ => {
 				Ok(None)
@@ -520,6 +1001,15 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 
 	fn transaction_count(&self, address: H160, number: Option<BlockNumber>) -> Result<U256> {
 		if let Some(BlockNumber::Pending) = number {
+			if let Some(view) = self.pending_view()? {
+				return Ok(
+					view.api.account_basic(&view.at, address)
+						.map_err(|err| internal_err(format!("fetch runtime account basic failed: {:?}", err)))?
+						.nonce
+				)
+			}
+
+			// Without a pending view, count the ready transactions of the account.
 			let block = BlockId::Hash(self.client.info().best_hash);
 
 			let nonce = self.client.runtime_api()
@@ -561,8 +1051,7 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 			Some(hash) => hash,
 			_ => return Ok(None),
 		};
-		let schema = self.onchain_storage_schema(id);
-		let block = self.overrides.get(&schema).unwrap_or(&self.fallback).current_block(&id);
+		let block = self.current_block(&id);
 
 		match block {
 			Some(block) => Ok(Some(U256::from(block.transactions.len()))),
@@ -575,8 +1064,7 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 			Some(id) => id,
 			None => return Ok(None),
 		};
-		let schema = self.onchain_storage_schema(id);
-		let block = self.overrides.get(&schema).unwrap_or(&self.fallback).current_block(&id);
+		let block = self.current_block(&id);
 
 		match block {
 			Some(block) => Ok(Some(U256::from(block.transactions.len()))),
@@ -609,65 +1097,11 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 	}
 
 	fn send_transaction(&self, request: TransactionRequest) -> BoxFuture<H256> {
-		let from = match request.from {
-			Some(from) => from,
-			None => {
-				let accounts = match self.accounts() {
-					Ok(accounts) => accounts,
-					Err(e) => return Box::new(future::result(Err(e))),
-				};
-
-				match accounts.get(0) {
-					Some(account) => account.clone(),
-					None => return Box::new(future::result(Err(internal_err("no signer available")))),
-				}
-			},
-		};
-
-		let nonce = match request.nonce {
-			Some(nonce) => nonce,
-			None => {
-				match self.transaction_count(from, None) {
-					Ok(nonce) => nonce,
-					Err(e) => return Box::new(future::result(Err(e))),
-				}
-			},
-		};
-
-		let chain_id = match self.chain_id() {
-			Ok(chain_id) => chain_id,
+		let transaction = match self.sign_request(request) {
+			Ok(transaction) => transaction,
 			Err(e) => return Box::new(future::result(Err(e))),
 		};
 
-		let message = ethereum::TransactionMessage {
-			nonce,
-			gas_price: request.gas_price.unwrap_or(U256::from(1)),
-			gas_limit: request.gas.unwrap_or(U256::max_value()),
-			value: request.value.unwrap_or(U256::zero()),
-			input: request.data.map(|s| s.into_vec()).unwrap_or_default(),
-			action: match request.to {
-				Some(to) => ethereum::TransactionAction::Call(to),
-				None => ethereum::TransactionAction::Create,
-			},
-			chain_id: chain_id.map(|s| s.as_u64()),
-		};
-
-		let mut transaction = None;
-
-		for signer in &self.signers {
-			if signer.accounts().contains(&from) {
-				match signer.sign(message, &from) {
-					Ok(t) => transaction = Some(t),
-					Err(e) => return Box::new(future::result(Err(e))),
-				}
-				break
-			}
-		}
-
-		let transaction = match transaction {
-			Some(transaction) => transaction,
-			None => return Box::new(future::result(Err(internal_err("no signer available")))),
-		};
 		let transaction_hash = H256::from_slice(
 			Keccak256::digest(&rlp::encode(&transaction)).as_slice()
 		);
@@ -743,8 +1177,23 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 		)
 	}
 
-	fn call(&self, request: CallRequest, _: Option<BlockNumber>) -> Result<Bytes> {
-		let hash = self.client.info().best_hash;
+	fn sign(&self, address: H160, data: Bytes) -> Result<Bytes> {
+		let signature = self.signer(&address)?.sign_message(&data.0, &address)?;
+		Ok(Bytes::new(signature.to_vec()))
+	}
+
+	fn sign_typed_data(&self, address: H160, data: TypedData) -> Result<Bytes> {
+		let signature = self.signer(&address)?.sign_typed_data(&data, &address)?;
+		Ok(Bytes::new(signature.to_vec()))
+	}
+
+	fn sign_transaction(&self, request: TransactionRequest) -> Result<Bytes> {
+		let transaction = self.sign_request(request)?;
+		Ok(Bytes::new(rlp::encode(&transaction).to_vec()))
+	}
+
+	fn call(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<Bytes> {
+		let (api, at) = self.call_api(number)?;
 
 		let CallRequest {
 			from,
@@ -756,14 +1205,14 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 			nonce
 		} = request;
 
-		let gas_limit = gas.unwrap_or(U256::max_value()); // TODO: set a limit
+		let gas_limit = self.limits.gas_limit(gas)?;
 		let data = data.map(|d| d.0).unwrap_or_default();
 
 		match to {
 			Some(to) => {
-				let info = self.client.runtime_api()
+				let info = api
 					.call(
-						&BlockId::Hash(hash),
+						&at,
 						from.unwrap_or_default(),
 						to,
 						data,
@@ -781,9 +1230,9 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 				Ok(Bytes(info.value))
 			},
 			None => {
-				let info = self.client.runtime_api()
+				let info = api
 					.create(
-						&BlockId::Hash(hash),
+						&at,
 						from.unwrap_or_default(),
 						data,
 						value.unwrap_or_default(),
@@ -802,10 +1251,11 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 		}
 	}
 
-	fn estimate_gas(&self, request: CallRequest, _: Option<BlockNumber>) -> Result<U256> {
-		let calculate_gas_used = |request| {
-			let hash = self.client.info().best_hash;
-
+	fn estimate_gas(&self, request: CallRequest, number: Option<BlockNumber>) -> Result<U256> {
+		// The pending view is built once, every attempt being rolled back as calls modify the
+		// state of `api`.
+		let (api, at) = self.call_api(number)?;
+		let gas_used = |api: &C::Api, request: CallRequest| -> Result<U256> {
 			let CallRequest {
 				from,
 				to,
@@ -816,14 +1266,14 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 				nonce
 			} = request;
 
-			let gas_limit = gas.unwrap_or(U256::max_value()); // TODO: set a limit
+			let gas_limit = self.limits.gas_limit(gas)?;
 			let data = data.map(|d| d.0).unwrap_or_default();
 
 			let used_gas = match to {
 				Some(to) => {
-					let info = self.client.runtime_api()
+					let info = api
 						.call(
-							&BlockId::Hash(hash),
+							&at,
 							from.unwrap_or_default(),
 							to,
 							data,
@@ -841,9 +1291,9 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 					info.used_gas
 				},
 				None => {
-					let info = self.client.runtime_api()
+					let info = api
 						.create(
-							&BlockId::Hash(hash),
+							&at,
 							from.unwrap_or_default(),
 							data,
 							value.unwrap_or_default(),
@@ -863,10 +1313,12 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 
 			Ok(used_gas)
 		};
+		let calculate_gas_used = |request| {
+			api.execute_in_transaction(|api| TransactionOutcome::Rollback(gas_used(api, request)))
+		};
 		if cfg!(feature = "rpc_binary_search_estimate") {
 			let mut lower = U256::from(21_000);
-			// TODO: get a good upper limit, but below U64::max to operation overflow
-			let mut upper = U256::from(1_000_000_000);
+			let mut upper = self.limits.gas_limit(request.gas)?;
 			let mut mid = upper;
 			let mut best = mid;
 			let mut old_best: U256;
@@ -931,11 +1383,8 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 			Some(hash) => hash,
 			_ => return Ok(None),
 		};
-		let schema = self.onchain_storage_schema(id);
-		let handler = self.overrides.get(&schema).unwrap_or(&self.fallback);
-
-		let block = handler.current_block(&id);
-		let statuses = handler.current_transaction_statuses(&id);
+		let block = self.current_block(&id);
+		let statuses = self.current_transaction_statuses(&id);
 
 		match (block, statuses) {
 			(Some(block), Some(statuses)) => {
@@ -962,11 +1411,8 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 		};
 		let index = index.value();
 
-		let schema = self.onchain_storage_schema(id);
-		let handler = self.overrides.get(&schema).unwrap_or(&self.fallback);
-
-		let block = handler.current_block(&id);
-		let statuses = handler.current_transaction_statuses(&id);
+		let block = self.current_block(&id);
+		let statuses = self.current_transaction_statuses(&id);
 
 		match (block, statuses) {
 			(Some(block), Some(statuses)) => {
@@ -990,11 +1436,8 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 			None => return Ok(None),
 		};
 		let index = index.value();
-		let schema = self.onchain_storage_schema(id);
-		let handler = self.overrides.get(&schema).unwrap_or(&self.fallback);
-
-		let block = handler.current_block(&id);
-		let statuses = handler.current_transaction_statuses(&id);
+		let block = self.current_block(&id);
+		let statuses = self.current_transaction_statuses(&id);
 
 		match (block, statuses) {
 			(Some(block), Some(statuses)) => {
@@ -1021,68 +1464,32 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 			Some(hash) => hash,
 			_ => return Ok(None),
 		};
-		let schema = self.onchain_storage_schema(id);
-		let handler = self.overrides.get(&schema).unwrap_or(&self.fallback);
+		let block = self.current_block(&id);
+		let statuses = self.current_transaction_statuses(&id);
+		let receipts = self.current_receipts(&id);
 
-		let block = handler.current_block(&id);
-		let statuses = handler.current_transaction_statuses(&id);
-		let receipts = handler.current_receipts(&id);
+		match (block, statuses, receipts) {
+			(Some(block), Some(statuses), Some(receipts)) =>
+				Ok(receipt_build(&block, &statuses, &receipts, index)),
+			_ => Ok(None),
+		}
+	}
+
+	fn block_receipts(&self, number: BlockNumber) -> Result<Option<Vec<Receipt>>> {
+		let id = match self.native_block_id(Some(number))? {
+			Some(id) => id,
+			None => return Ok(None),
+		};
+		let block = self.current_block(&id);
+		let statuses = self.current_transaction_statuses(&id);
+		let receipts = self.current_receipts(&id);
 
 		match (block, statuses, receipts) {
-			(Some(block), Some(statuses), Some(receipts)) => {
-				let block_hash = H256::from_slice(
-					Keccak256::digest(&rlp::encode(&block.header)).as_slice()
-				);
-				let receipt = receipts[index].clone();
-				let status = statuses[index].clone();
-				let mut cumulative_receipts = receipts.clone();
-				cumulative_receipts.truncate((status.transaction_index + 1) as usize);
-
-				return Ok(Some(Receipt {
-					transaction_hash: Some(status.transaction_hash),
-					transaction_index: Some(status.transaction_index.into()),
-					block_hash: Some(block_hash),
-					from: Some(status.from),
-					to: status.to,
-					block_number: Some(block.header.number),
-					cumulative_gas_used: {
-						let cumulative_gas: u32 = cumulative_receipts.iter().map(|r| {
-							r.used_gas.as_u32()
-						}).sum();
-						U256::from(cumulative_gas)
-					},
-					gas_used: Some(receipt.used_gas),
-					contract_address: status.contract_address,
-					logs: {
-						let mut pre_receipts_log_index = None;
-						if cumulative_receipts.len() > 0 {
-							cumulative_receipts.truncate(cumulative_receipts.len() - 1);
-							pre_receipts_log_index = Some(cumulative_receipts.iter().map(|r| {
-								r.logs.len() as u32
-							}).sum::<u32>());
-						}
-						receipt.logs.iter().enumerate().map(|(i, log)| {
-							Log {
-								address: log.address,
-								topics: log.topics.clone(),
-								data: Bytes(log.data.clone()),
-								block_hash: Some(block_hash),
-								block_number: Some(block.header.number),
-								transaction_hash: Some(status.transaction_hash),
-								transaction_index: Some(status.transaction_index.into()),
-								log_index: Some(U256::from(
-									(pre_receipts_log_index.unwrap_or(0)) + i as u32
-								)),
-								transaction_log_index: Some(U256::from(i)),
-								removed: false,
-							}
-						}).collect()
-					},
-					status_code: Some(U64::from(receipt.state_root.to_low_u64_be())),
-					logs_bloom: receipt.logs_bloom,
-					state_root: None,
-				}))
-			}
+			(Some(block), Some(statuses), Some(receipts)) => Ok(Some(
+				(0..statuses.len())
+					.filter_map(|index| receipt_build(&block, &statuses, &receipts, index))
+					.collect()
+			)),
 			_ => Ok(None),
 		}
 	}
@@ -1100,6 +1507,7 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 	}
 
 	fn logs(&self, filter: Filter) -> Result<Vec<Log>> {
+		let filter = resolve_finalized_tags(&*self.client, &filter);
 		let mut blocks_and_statuses = Vec::new();
 		if let Some(hash) = filter.block_hash.clone() {
 			let id = match self.load_hash(hash)
@@ -1109,9 +1517,8 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 				_ => return Ok(Vec::new()),
 			};
 
-			let (block, _, statuses) = self.client.runtime_api()
-				.current_all(&id)
-				.map_err(|err| internal_err(format!("fetch runtime account basic failed: {:?}", err)))?;
+			let block = self.current_block(&id);
+			let statuses = self.current_transaction_statuses(&id);
 
 			if let (Some(block), Some(statuses)) = (block, statuses) {
 				blocks_and_statuses.push((block, statuses));
@@ -1134,12 +1541,14 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 				.unwrap_or(
 					self.client.info().best_number
 				);
+			self.limits.check_logs_range(
+				from_number.unique_saturated_into(),
+				current_number.unique_saturated_into(),
+			)?;
 			while current_number >= from_number {
 				let id = BlockId::Number(current_number);
-
-				let (block, _, statuses) = self.client.runtime_api()
-					.current_all(&id)
-					.map_err(|err| internal_err(format!("fetch runtime account basic failed: {:?}", err)))?;
+				let block = self.current_block(&id);
+				let statuses = self.current_transaction_statuses(&id);
 
 				if let (Some(block), Some(statuses)) = (block, statuses) {
 					blocks_and_statuses.push((block, statuses));
@@ -1153,7 +1562,7 @@ impl<B, C, P, CT, BE, H: ExHashT> EthApiT for EthApi<B, C, P, CT, BE, H> where
 			}
 		}
 
-		Ok(logs_build(filter,blocks_and_statuses))
+		self.limits.check_logs_count(logs_build(filter,blocks_and_statuses))
 	}
 
 	fn work(&self) -> Result<Work> {
@@ -1261,28 +1670,78 @@ impl<B, C> Web3ApiT for Web3Api<B, C> where
 	}
 }
 
-pub struct EthFilterApi<B, C> {
+pub struct EthFilterApi<B: BlockT, C> {
 	client: Arc<C>,
 	filter_pool: FilterPool,
 	max_stored_filters: usize,
+	limits: RpcLimits,
+	backend: Option<Arc<fc_db::Backend<B>>>,
+	/// Ethereum data of the polled blocks, read from the Frontier database once pruned.
+	block_data: Arc<dyn StorageOverride<B> + Send + Sync>,
 	_marker: PhantomData<B>,
 }
 
-impl<B, C> EthFilterApi<B, C> {
+impl<B: BlockT, C> EthFilterApi<B, C> {
+	/// Filters are persisted in `backend` when one is given.
 	pub fn new(
 		client: Arc<C>,
 		filter_pool: FilterPool,
 		max_stored_filters: usize,
+		limits: RpcLimits,
+		backend: Option<Arc<fc_db::Backend<B>>>,
+		block_data: Arc<dyn StorageOverride<B> + Send + Sync>,
 	) -> Self {
 		Self {
 			client,
 			filter_pool,
 			max_stored_filters,
+			limits,
+			backend,
+			block_data,
 			_marker: PhantomData,
 		}
 	}
 }
 
+/// Filters persisted in the Frontier database, or an empty pool.
+pub fn load_filter_pool<B: BlockT>(backend: &fc_db::Backend<B>) -> BTreeMap<U256, FilterPoolItem> {
+	let raw = match backend.meta().filter_pool() {
+		Ok(Some(raw)) => raw,
+		Ok(None) => return BTreeMap::new(),
+		Err(e) => {
+			warn!("Failed to read the filter pool: {:?}", e);
+			return BTreeMap::new()
+		},
+	};
+	match serde_json::from_slice::<Vec<(U256, FilterPoolItem)>>(&raw) {
+		Ok(filters) => filters.into_iter().collect(),
+		Err(e) => {
+			warn!("Failed to decode the filter pool: {:?}", e);
+			BTreeMap::new()
+		},
+	}
+}
+
+fn encode_filter_pool(filter_pool: &BTreeMap<U256, FilterPoolItem>) -> std::result::Result<Vec<u8>, String> {
+	let filters: Vec<_> = filter_pool.iter().collect();
+	serde_json::to_vec(&filters).map_err(|e| format!("{:?}", e))
+}
+
+/// Persist the pool after a filter was installed or uninstalled. Polls are persisted in batches
+/// by `EthTask::filter_pool_task`.
+fn store_filter_pool<B: BlockT>(
+	backend: &Option<Arc<fc_db::Backend<B>>>,
+	filter_pool: &BTreeMap<U256, FilterPoolItem>,
+) {
+	if let Some(backend) = backend {
+		let result = encode_filter_pool(filter_pool)
+			.and_then(|raw| backend.meta().write_filter_pool(&raw));
+		if let Err(e) = result {
+			warn!("Failed to persist the filter pool: {:?}", e);
+		}
+	}
+}
+
 impl<B, C> EthFilterApi<B, C> where
 	C: ProvideRuntimeApi<B> + AuxStore,
 	C::Api: EthereumRuntimeRPCApi<B>,
@@ -1291,9 +1750,8 @@ impl<B, C> EthFilterApi<B, C> where
 	B: BlockT<Hash=H256> + Send + Sync + 'static,
 {
 	fn create_filter(&self, filter_type: FilterType) -> Result<U256> {
-		let block_number = UniqueSaturatedInto::<u64>::unique_saturated_into(
-			self.client.info().best_number
-		);
+		let info = self.client.info();
+		let block_number = UniqueSaturatedInto::<u64>::unique_saturated_into(info.best_number);
 		let pool = self.filter_pool.clone();
 		let response = if let Ok(locked) = &mut pool.lock() {
 			if locked.len() >= self.max_stored_filters {
@@ -1310,17 +1768,56 @@ impl<B, C> EthFilterApi<B, C> where
 			locked.insert(
 				key,
 				FilterPoolItem {
-					last_poll: BlockNumber::Num(block_number),
+					last_poll: info.best_hash,
+					polled: false,
 					filter_type: filter_type,
 					at_block: block_number
 				}
 			);
+			store_filter_pool(&self.backend, locked);
 			Ok(key)
 		} else {
 			Err(internal_err("Filter pool is not available."))
 		};
 		response
 	}
+
+	/// Blocks that left and joined the best chain since the last poll of `pool_item`, both oldest
+	/// first, as `(number, hash)` pairs.
+	fn poll_route(
+		&self,
+		pool_item: &FilterPoolItem,
+		best_hash: H256,
+	) -> Result<(Vec<(u64, H256)>, Vec<(u64, H256)>)> {
+		// A persisted filter may have last been polled at a block this node no longer knows,
+		// after a restart on a reverted chain: changes are then reported from the best block on.
+		let last_poll = self.client.header(BlockId::Hash(pool_item.last_poll))
+			.map_err(|err| internal_err(format!("fetch header of last poll failed: {:?}", err)))?;
+		if last_poll.is_none() {
+			return Ok((Vec::new(), Vec::new()))
+		}
+
+		let route = sp_blockchain::tree_route(&*self.client, pool_item.last_poll, best_hash)
+			.map_err(|err| internal_err(
+				format!("fetch route since last poll failed: {:?}", err)
+			))?;
+		let pair = |block: &sp_blockchain::HashAndNumber<B>| (
+			UniqueSaturatedInto::<u64>::unique_saturated_into(block.number),
+			block.hash,
+		);
+
+		let mut retracted: Vec<_> = route.retracted().iter().rev().map(pair).collect();
+		let mut enacted: Vec<_> = route.enacted().iter().map(pair).collect();
+		if !pool_item.polled {
+			// Nothing was reported yet: the block the filter was created at is reported as well,
+			// unless it left the best chain since.
+			if retracted.is_empty() {
+				enacted.insert(0, pair(route.common_block()));
+			}
+			retracted.clear();
+		}
+		Ok((retracted, enacted))
+	}
 }
 
 impl<B, C> EthFilterApiT for EthFilterApi<B, C> where
@@ -1344,111 +1841,73 @@ impl<B, C> EthFilterApiT for EthFilterApi<B, C> where
 
 	fn filter_changes(&self, index: Index) -> Result<FilterChanges> {
 		let key = U256::from(index.value());
-		let block_number = UniqueSaturatedInto::<u64>::unique_saturated_into(
-			self.client.info().best_number
-		);
+		let best_hash = self.client.info().best_hash;
 		let pool = self.filter_pool.clone();
 		// Try to lock.
 		let response = if let Ok(locked) = &mut pool.lock() {
 			// Try to get key.
 			if let Some(pool_item) = locked.clone().get(&key) {
-				match &pool_item.filter_type {
-					// For each block created since last poll, get a vector of ethereum hashes.
+				let (retracted, enacted) = self.poll_route(pool_item, best_hash)?;
+				let changes = match &pool_item.filter_type {
+					// For each block that joined the best chain since last poll, get a vector of
+					// ethereum hashes.
 					FilterType::Block => {
-						let last = pool_item.last_poll.to_min_block_num().unwrap();
-						let next = block_number + 1;
 						let mut ethereum_hashes: Vec<H256> = Vec::new();
-						for n in last..next {
-							let id = BlockId::Number(n.unique_saturated_into());
-							let block = self.client.runtime_api()
-								.current_block(&id)
-								.map_err(|err| internal_err(
-									format!("fetch runtime block failed: {:?}", err)
-								))?;
-							if let Some(block) = block {
+						for (_, hash) in enacted {
+							if let Some(block) = self.block_data.current_block(&BlockId::Hash(hash)) {
 								ethereum_hashes.push(block.header.hash())
 							}
 						}
-						// Update filter `last_poll`.
-						locked.insert(
-							key,
-							FilterPoolItem {
-								last_poll: BlockNumber::Num(next),
-								filter_type: pool_item.clone().filter_type,
-								at_block: pool_item.at_block
-							}
-						);
-						Ok(FilterChanges::Hashes(ethereum_hashes))
+						FilterChanges::Hashes(ethereum_hashes)
 					},
-					// For each event since last poll, get a vector of ethereum logs.
+					// Logs of the blocks that left the best chain since last poll are reported as
+					// removed, followed by the logs of the blocks that joined it.
 					FilterType::Log(filter) => {
-						// Either the filter-specific `to` block or best block.
-						let best_number = self.client.info().best_number;
-						let mut current_number = filter
-							.to_block.clone()
+						let filter = &resolve_finalized_tags(&*self.client, filter);
+						let from_number = filter.from_block.clone()
 							.and_then(|v| v.to_min_block_num())
-							.map(|s| s.unique_saturated_into())
-							.unwrap_or(best_number);
-
-						if current_number > best_number {
-							current_number = best_number;
-						}
-
-						// The from clause is the max(last_poll, filter_from).
-						let last_poll = pool_item
-							.last_poll
-							.to_min_block_num().unwrap()
-							.unique_saturated_into();
-
-						let filter_from = filter.from_block.clone()
+							.unwrap_or(0);
+						let to_number = filter.to_block.clone()
 							.and_then(|v| v.to_min_block_num())
-							.map(|s| s.unique_saturated_into())
-							.unwrap_or(
-								last_poll
-							);
-
-						let from_number = std::cmp::max(last_poll, filter_from);
-						// Build the response.
-						let mut blocks_and_statuses = Vec::new();
-						while current_number >= from_number {
-							let id = BlockId::Number(current_number);
-
-							let (block, _, statuses) = self.client.runtime_api()
-								.current_all(&id)
-								.map_err(|err| internal_err(
-									format!("fetch runtime account basic failed: {:?}", err)
-								))?;
-
-							if let (Some(block), Some(statuses)) = (block, statuses) {
-								blocks_and_statuses.push((block, statuses));
+							.unwrap_or(u64::max_value());
+
+						let mut logs = Vec::new();
+						let blocks = retracted.into_iter().map(|block| (block, true))
+							.chain(enacted.into_iter().map(|block| (block, false)));
+						for ((number, hash), removed) in blocks {
+							if number < from_number || number > to_number {
+								continue
 							}
+							let id = BlockId::Hash(hash);
+							let block = self.block_data.current_block(&id);
+							let statuses = self.block_data.current_transaction_statuses(&id);
 
-							if current_number == Zero::zero() {
-								break
-							} else {
-								current_number = current_number.saturating_sub(One::one());
+							if let (Some(block), Some(statuses)) = (block, statuses) {
+								logs.extend(
+									logs_build(filter.clone(), vec![(block, statuses)])
+										.into_iter()
+										.map(|log| Log { removed, ..log })
+								);
 							}
 						}
-						// Update filter `last_poll`.
-						locked.insert(
-							key,
-							FilterPoolItem {
-								last_poll: BlockNumber::Num(
-									block_number + 1
-								),
-								filter_type: pool_item.clone().filter_type,
-								at_block: pool_item.at_block
-							}
-						);
-						Ok(FilterChanges::Logs(
-							logs_build(filter.clone(), blocks_and_statuses)
-						))
+						FilterChanges::Logs(self.limits.check_logs_count(logs)?)
 					},
 					// Should never reach here.
 					_ => {
-						Err(internal_err("Method not available."))
+						return Err(internal_err("Method not available."))
 					}
-				}
+				};
+				// Update filter `last_poll`.
+				locked.insert(
+					key,
+					FilterPoolItem {
+						last_poll: best_hash,
+						polled: true,
+						filter_type: pool_item.clone().filter_type,
+						at_block: pool_item.at_block
+					}
+				);
+				Ok(changes)
 			} else {
 				Err(internal_err(format!("Filter id {:?} does not exist.", key)))
 			}
@@ -1467,6 +1926,7 @@ impl<B, C> EthFilterApiT for EthFilterApi<B, C> where
 			if let Some(pool_item) = locked.clone().get(&key) {
 				match &pool_item.filter_type {
 					FilterType::Log(filter) => {
+						let filter = &resolve_finalized_tags(&*self.client, filter);
 						let best_number = self.client.info().best_number;
 						let mut current_number = filter
 							.to_block.clone()
@@ -1488,16 +1948,17 @@ impl<B, C> EthFilterApiT for EthFilterApi<B, C> where
 							.unwrap_or(
 								self.client.info().best_number
 							);
+						self.limits.check_logs_range(
+							from_number.unique_saturated_into(),
+							current_number.unique_saturated_into(),
+						)?;
 
 						let mut blocks_and_statuses = Vec::new();
 						while current_number >= from_number {
 							let id = BlockId::Number(current_number);
 
-							let (block, _, statuses) = self.client.runtime_api()
-								.current_all(&id)
-								.map_err(|err| internal_err(
-									format!("fetch runtime account basic failed: {:?}", err)
-								))?;
+							let block = self.block_data.current_block(&id);
+							let statuses = self.block_data.current_transaction_statuses(&id);
 
 							if let (Some(block), Some(statuses)) = (block, statuses) {
 								blocks_and_statuses.push((block, statuses));
@@ -1509,7 +1970,7 @@ impl<B, C> EthFilterApiT for EthFilterApi<B, C> where
 								current_number = current_number.saturating_sub(One::one());
 							}
 						}
-						Ok(logs_build(filter.clone(), blocks_and_statuses))
+						self.limits.check_logs_count(logs_build(filter.clone(), blocks_and_statuses))
 					},
 					_ => Err(internal_err(
 						format!("Filter id {:?} is not a Log filter.", key)
@@ -1530,6 +1991,7 @@ impl<B, C> EthFilterApiT for EthFilterApi<B, C> where
 		// Try to lock.
 		let response = if let Ok(locked) = &mut pool.lock() {
 			if let Some(_) = locked.remove(&key) {
+				store_filter_pool(&self.backend, locked);
 				Ok(true)
 			} else {
 				Err(internal_err(
@@ -1586,9 +2048,11 @@ where
 	pub async fn filter_pool_task(
 		client: Arc<C>,
 		filter_pool: Arc<Mutex<BTreeMap<U256, FilterPoolItem>>>,
-		retain_threshold: u64
+		retain_threshold: u64,
+		backend: Option<Arc<fc_db::Backend<B>>>,
 	) {
 		let mut notification_st = client.import_notification_stream();
+		let mut persisted: Option<Vec<u8>> = None;
 
 		while let Some(notification) = notification_st.next().await {
 			if let Ok(filter_pool) = &mut filter_pool.lock() {
@@ -1614,6 +2078,20 @@ where
 				for key in remove_list {
 					filter_pool.remove(&key);
 				}
+
+				// Polls move the `last_poll` of filters, persist them at most once per block.
+				if let Some(backend) = &backend {
+					match encode_filter_pool(filter_pool) {
+						Ok(raw) if persisted.as_ref() != Some(&raw) => {
+							match backend.meta().write_filter_pool(&raw) {
+								Ok(()) => persisted = Some(raw),
+								Err(e) => warn!("Failed to persist the filter pool: {:?}", e),
+							}
+						},
+						Ok(_) => (),
+						Err(e) => warn!("Failed to encode the filter pool: {:?}", e),
+					}
+				}
 			}
 		}
 	}
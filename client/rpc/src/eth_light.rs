@@ -0,0 +1,449 @@
+use std::{marker::PhantomData, sync::Arc};
+use ethereum_types::{H160, H256, U256, U64};
+use jsonrpc_core::{BoxFuture, Result, futures::future::{self, Future}};
+use futures::{FutureExt, future::TryFutureExt};
+use sp_runtime::{
+	traits::{Block as BlockT, UniqueSaturatedInto},
+	transaction_validity::TransactionSource,
+};
+use sp_api::BlockId;
+use sp_blockchain::HeaderBackend;
+use sp_transaction_pool::{TransactionPool, InPoolTransaction};
+use sc_client_api::light::{
+	future_header, Fetcher, RemoteBlockchain, RemoteCallRequest, RemoteReadRequest,
+};
+use sha3::{Keccak256, Digest};
+use codec::{Encode, Decode};
+use fc_rpc_core::LightEthApi as LightEthApiT;
+use fc_rpc_core::types::{BlockNumber, Bytes, SyncStatus};
+use fp_rpc::ConvertTransaction;
+use crate::internal_err;
+use crate::overrides::{account_code_key, account_storage_key};
+
+pub use fc_rpc_core::LightEthApiServer;
+
+/// Ethereum RPC for light clients.
+///
+/// State is read from full nodes through the fetcher, which checks the returned storage and
+/// execution proofs against the state root of the locally imported header. Reads are answered
+/// as futures, so that RPC threads do not wait for the network.
+pub struct LightEthApi<B: BlockT, C, F, P, CT> {
+	client: Arc<C>,
+	remote_blockchain: Arc<dyn RemoteBlockchain<B>>,
+	fetcher: Arc<F>,
+	pool: Arc<P>,
+	convert_transaction: CT,
+	_marker: PhantomData<B>,
+}
+
+impl<B: BlockT, C, F, P, CT> LightEthApi<B, C, F, P, CT> {
+	pub fn new(
+		client: Arc<C>,
+		remote_blockchain: Arc<dyn RemoteBlockchain<B>>,
+		fetcher: Arc<F>,
+		pool: Arc<P>,
+		convert_transaction: CT,
+	) -> Self {
+		Self {
+			client,
+			remote_blockchain,
+			fetcher,
+			pool,
+			convert_transaction,
+			_marker: PhantomData,
+		}
+	}
+}
+
+/// Header of the given block, fetched and checked against the CHT roots when it has been pruned
+/// locally.
+async fn fetch_header<B: BlockT, F: Fetcher<B>>(
+	remote_blockchain: &dyn RemoteBlockchain<B>,
+	fetcher: &F,
+	hash: B::Hash,
+) -> Result<B::Header> {
+	future_header(remote_blockchain, fetcher, BlockId::Hash(hash)).await
+		.map_err(|err| internal_err(format!("fetch header failed: {:?}", err)))?
+		.ok_or(internal_err("block not found"))
+}
+
+impl<B, C, F, P, CT> LightEthApi<B, C, F, P, CT> where
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: HeaderBackend<B> + Send + Sync + 'static,
+	F: Fetcher<B> + 'static,
+{
+	/// Hash of the requested block. The pending state is not known to light clients, so it is
+	/// served from the best block.
+	fn block_hash(&self, number: Option<BlockNumber>) -> Result<B::Hash> {
+		let info = self.client.info();
+		Ok(match number.unwrap_or(BlockNumber::Latest) {
+			// Light clients keep no mapping from Ethereum to Substrate block hashes.
+			BlockNumber::Hash { .. } => {
+				return Err(internal_err("block hashes are not supported on light clients"))
+			},
+			BlockNumber::Num(number) => {
+				self.client.hash(number.unique_saturated_into())
+					.map_err(|err| internal_err(format!("{:?}", err)))?
+					.ok_or(internal_err("block not found"))?
+			},
+			BlockNumber::Latest | BlockNumber::Pending => info.best_hash,
+			BlockNumber::Earliest => info.genesis_hash,
+			BlockNumber::Finalized | BlockNumber::Safe => info.finalized_hash,
+		})
+	}
+
+	/// Value stored at `key` in the state of the given block.
+	fn remote_storage<T: Decode + Send + 'static>(
+		&self,
+		number: Option<BlockNumber>,
+		key: Vec<u8>,
+	) -> BoxFuture<Option<T>> {
+		let block = self.block_hash(number);
+		let remote_blockchain = self.remote_blockchain.clone();
+		let fetcher = self.fetcher.clone();
+
+		Box::new(async move {
+			let block = block?;
+			let header = fetch_header(&*remote_blockchain, &*fetcher, block).await?;
+			let mut values = fetcher.remote_read(RemoteReadRequest {
+				block,
+				header,
+				keys: vec![key.clone()],
+				retry_count: None,
+			}).await.map_err(|err| internal_err(format!("remote storage read failed: {:?}", err)))?;
+
+			values.remove(&key)
+				.flatten()
+				.map(|value| T::decode(&mut &value[..])
+					.map_err(|err| internal_err(format!("decode storage value failed: {:?}", err))))
+				.transpose()
+		}.boxed().compat())
+	}
+
+	/// Result of an `EthereumRuntimeRPCApi` method executed by a full node on the given block.
+	fn remote_runtime_call<T: Decode + Send + 'static>(
+		&self,
+		number: Option<BlockNumber>,
+		method: &str,
+		call_data: Vec<u8>,
+	) -> BoxFuture<T> {
+		let block = self.block_hash(number);
+		let method = format!("EthereumRuntimeRPCApi_{}", method);
+		let remote_blockchain = self.remote_blockchain.clone();
+		let fetcher = self.fetcher.clone();
+
+		Box::new(async move {
+			let block = block?;
+			let header = fetch_header(&*remote_blockchain, &*fetcher, block).await?;
+			let result = fetcher.remote_call(RemoteCallRequest {
+				block,
+				header,
+				method,
+				call_data,
+				retry_count: None,
+			}).await.map_err(|err| internal_err(format!("remote runtime call failed: {:?}", err)))?;
+
+			T::decode(&mut &result[..])
+				.map_err(|err| internal_err(format!("decode runtime call result failed: {:?}", err)))
+		}.boxed().compat())
+	}
+
+	fn account_basic(&self, address: H160, number: Option<BlockNumber>) -> BoxFuture<fp_evm::Account> {
+		self.remote_runtime_call(number, "account_basic", address.encode())
+	}
+}
+
+impl<B, C, F, P, CT> LightEthApiT for LightEthApi<B, C, F, P, CT> where
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: HeaderBackend<B> + Send + Sync + 'static,
+	F: Fetcher<B> + 'static,
+	P: TransactionPool<Block=B> + Send + Sync + 'static,
+	CT: ConvertTransaction<<B as BlockT>::Extrinsic> + Send + Sync + 'static,
+{
+	fn protocol_version(&self) -> Result<u64> {
+		Ok(1)
+	}
+
+	fn syncing(&self) -> Result<SyncStatus> {
+		Ok(SyncStatus::None)
+	}
+
+	fn chain_id(&self) -> BoxFuture<Option<U64>> {
+		Box::new(
+			self.remote_runtime_call::<u64>(None, "chain_id", vec![])
+				.map(|chain_id| Some(chain_id.into()))
+		)
+	}
+
+	fn gas_price(&self) -> BoxFuture<U256> {
+		self.remote_runtime_call(None, "gas_price", vec![])
+	}
+
+	fn accounts(&self) -> Result<Vec<H160>> {
+		Ok(vec![])
+	}
+
+	fn block_number(&self) -> Result<U256> {
+		Ok(U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(self.client.info().best_number)))
+	}
+
+	fn balance(&self, address: H160, number: Option<BlockNumber>) -> BoxFuture<U256> {
+		Box::new(self.account_basic(address, number).map(|account| account.balance))
+	}
+
+	fn storage_at(&self, address: H160, index: U256, number: Option<BlockNumber>) -> BoxFuture<H256> {
+		Box::new(
+			self.remote_storage(number, account_storage_key(address, index))
+				.map(|value| value.unwrap_or_default())
+		)
+	}
+
+	fn transaction_count(&self, address: H160, number: Option<BlockNumber>) -> BoxFuture<U256> {
+		let pool = self.pool.clone();
+		let pending = number == Some(BlockNumber::Pending);
+		Box::new(self.account_basic(address, number).map(move |account| {
+			if pending {
+				// Count the ready transactions of the account on top of the best block.
+				let mut current_nonce = account.nonce;
+				let mut current_tag = (address, current_nonce).encode();
+				for tx in pool.ready() {
+					if tx.provides().get(0) == Some(&current_tag) {
+						current_nonce = current_nonce.saturating_add(1.into());
+						current_tag = (address, current_nonce).encode();
+					}
+				}
+				return current_nonce
+			}
+			account.nonce
+		}))
+	}
+
+	fn code_at(&self, address: H160, number: Option<BlockNumber>) -> BoxFuture<Bytes> {
+		Box::new(
+			self.remote_storage::<Vec<u8>>(number, account_code_key(address))
+				.map(|code| code.unwrap_or_default().into())
+		)
+	}
+
+	fn send_raw_transaction(&self, bytes: Bytes) -> BoxFuture<H256> {
+		let transaction = match rlp::decode::<ethereum::Transaction>(&bytes.0[..]) {
+			Ok(transaction) => transaction,
+			Err(_) => return Box::new(
+				future::result(Err(internal_err("decode transaction failed")))
+			),
+		};
+		let transaction_hash = H256::from_slice(
+			Keccak256::digest(&rlp::encode(&transaction)).as_slice()
+		);
+		let hash = self.client.info().best_hash;
+		Box::new(
+			self.pool
+				.submit_one(
+					&BlockId::hash(hash),
+					TransactionSource::Local,
+					self.convert_transaction.convert_transaction(transaction),
+				)
+				.compat()
+				.map(move |_| transaction_hash)
+				.map_err(|err| internal_err(format!("submit transaction to pool failed: {:?}", err)))
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::{BTreeMap, HashMap};
+	use futures::future::{ready, Ready};
+	use sc_client_api::light::{
+		LocalOrRemote, RemoteBodyRequest, RemoteChangesRequest, RemoteHeaderRequest,
+		RemoteReadChildRequest,
+	};
+	use sp_blockchain::{BlockStatus, Error as ClientError, Info};
+	use sp_runtime::{
+		generic::Digest,
+		testing::{Block as RawBlock, ExtrinsicWrapper, Header},
+		traits::{BlakeTwo256, Header as HeaderT},
+	};
+	use sp_state_machine::{InMemoryBackend, prove_read, read_proof_check};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+	type Storage = BTreeMap<Vec<u8>, Vec<u8>>;
+
+	fn state_root(storage: &Storage) -> H256 {
+		*InMemoryBackend::<BlakeTwo256>::from(storage.clone()).root()
+	}
+
+	/// Chain of locally imported headers, the state of the best one having `storage`.
+	struct TestClient {
+		headers: Vec<Header>,
+	}
+
+	impl TestClient {
+		fn new(storage: &Storage) -> Self {
+			let genesis = Header::new(0, H256::default(), H256::default(), H256::default(), Digest::default());
+			let best = Header::new(1, H256::default(), state_root(storage), genesis.hash(), Digest::default());
+			Self { headers: vec![genesis, best] }
+		}
+
+		fn block_number(&self, id: &BlockId<Block>) -> Option<usize> {
+			match id {
+				BlockId::Hash(hash) => self.headers.iter().position(|header| header.hash() == *hash),
+				BlockId::Number(number) => Some(*number as usize).filter(|n| *n < self.headers.len()),
+			}
+		}
+	}
+
+	impl HeaderBackend<Block> for TestClient {
+		fn header(&self, id: BlockId<Block>) -> sp_blockchain::Result<Option<Header>> {
+			Ok(self.block_number(&id).map(|number| self.headers[number].clone()))
+		}
+
+		fn info(&self) -> Info<Block> {
+			let best = self.headers.last().unwrap();
+			Info {
+				best_hash: best.hash(),
+				best_number: best.number,
+				genesis_hash: self.headers[0].hash(),
+				finalized_hash: self.headers[0].hash(),
+				finalized_number: 0,
+				number_leaves: 1,
+			}
+		}
+
+		fn status(&self, id: BlockId<Block>) -> sp_blockchain::Result<BlockStatus> {
+			Ok(match self.block_number(&id) {
+				Some(_) => BlockStatus::InChain,
+				None => BlockStatus::Unknown,
+			})
+		}
+
+		fn number(&self, hash: H256) -> sp_blockchain::Result<Option<u64>> {
+			Ok(self.block_number(&BlockId::Hash(hash)).map(|number| number as u64))
+		}
+
+		fn hash(&self, number: u64) -> sp_blockchain::Result<Option<H256>> {
+			Ok(self.headers.get(number as usize).map(|header| header.hash()))
+		}
+	}
+
+	impl RemoteBlockchain<Block> for TestClient {
+		fn header(
+			&self,
+			id: BlockId<Block>,
+		) -> sp_blockchain::Result<LocalOrRemote<Header, RemoteHeaderRequest<Header>>> {
+			Ok(match HeaderBackend::header(self, id)? {
+				Some(header) => LocalOrRemote::Local(header),
+				None => LocalOrRemote::Unknown,
+			})
+		}
+	}
+
+	/// Full node serving `storage` and the encoded results of `calls`. Read proofs are checked
+	/// against the state root of the requested header, as the network's light data checker does.
+	#[derive(Default)]
+	struct TestFetcher {
+		storage: Storage,
+		calls: HashMap<String, Vec<u8>>,
+	}
+
+	fn not_served<T>() -> Ready<std::result::Result<T, ClientError>> {
+		ready(Err(ClientError::NotAvailableOnLightClient))
+	}
+
+	impl Fetcher<Block> for TestFetcher {
+		type RemoteHeaderResult = Ready<std::result::Result<Header, ClientError>>;
+		type RemoteReadResult = Ready<std::result::Result<HashMap<Vec<u8>, Option<Vec<u8>>>, ClientError>>;
+		type RemoteCallResult = Ready<std::result::Result<Vec<u8>, ClientError>>;
+		type RemoteChangesResult = Ready<std::result::Result<Vec<(u64, u32)>, ClientError>>;
+		type RemoteBodyResult = Ready<std::result::Result<Vec<ExtrinsicWrapper<u64>>, ClientError>>;
+
+		fn remote_header(&self, _: RemoteHeaderRequest<Header>) -> Self::RemoteHeaderResult {
+			not_served()
+		}
+
+		fn remote_read(&self, request: RemoteReadRequest<Header>) -> Self::RemoteReadResult {
+			let backend = InMemoryBackend::<BlakeTwo256>::from(self.storage.clone());
+			ready(
+				prove_read(backend, &request.keys)
+					.and_then(|proof| read_proof_check::<BlakeTwo256, _>(
+						*request.header.state_root(),
+						proof,
+						&request.keys,
+					))
+					.map_err(|err| ClientError::Msg(format!("{:?}", err)))
+			)
+		}
+
+		fn remote_read_child(&self, _: RemoteReadChildRequest<Header>) -> Self::RemoteReadResult {
+			not_served()
+		}
+
+		fn remote_call(&self, request: RemoteCallRequest<Header>) -> Self::RemoteCallResult {
+			ready(self.calls.get(&request.method).cloned().ok_or(ClientError::NotAvailableOnLightClient))
+		}
+
+		fn remote_changes(&self, _: RemoteChangesRequest<Header>) -> Self::RemoteChangesResult {
+			not_served()
+		}
+
+		fn remote_body(&self, _: RemoteBodyRequest<Header>) -> Self::RemoteBodyResult {
+			not_served()
+		}
+	}
+
+	fn light_api(local: &Storage, fetcher: TestFetcher) -> LightEthApi<Block, TestClient, TestFetcher, (), ()> {
+		let client = Arc::new(TestClient::new(local));
+		LightEthApi::new(client.clone(), client, Arc::new(fetcher), Arc::new(()), ())
+	}
+
+	fn storage_slot() -> (Vec<u8>, H256) {
+		(account_storage_key(H160::repeat_byte(1), U256::from(2)), H256::repeat_byte(3))
+	}
+
+	#[test]
+	fn reads_storage_checked_against_the_header() {
+		let (key, value) = storage_slot();
+		let storage: Storage = vec![(key.clone(), value.encode())].into_iter().collect();
+		let api = light_api(&storage, TestFetcher { storage: storage.clone(), ..Default::default() });
+
+		assert_eq!(api.remote_storage::<H256>(None, key).wait().unwrap(), Some(value));
+		assert_eq!(api.remote_storage::<H256>(None, vec![4]).wait().unwrap(), None);
+	}
+
+	#[test]
+	fn rejects_storage_with_a_bad_proof() {
+		let (key, value) = storage_slot();
+		let storage: Storage = vec![(key.clone(), value.encode())].into_iter().collect();
+		let mut served = storage.clone();
+		served.insert(key.clone(), H256::repeat_byte(4).encode());
+		let api = light_api(&storage, TestFetcher { storage: served, ..Default::default() });
+
+		assert!(api.remote_storage::<H256>(None, key).wait().is_err());
+	}
+
+	#[test]
+	fn reads_accounts_through_remote_calls() {
+		let account = fp_evm::Account { nonce: U256::from(1), balance: U256::from(2) };
+		let fetcher = TestFetcher {
+			calls: vec![("EthereumRuntimeRPCApi_account_basic".to_string(), account.encode())]
+				.into_iter()
+				.collect(),
+			..Default::default()
+		};
+		let api = light_api(&Storage::new(), fetcher);
+
+		assert_eq!(api.account_basic(H160::repeat_byte(1), Some(BlockNumber::Num(1))).wait(), Ok(account));
+	}
+
+	#[test]
+	fn rejects_block_hashes() {
+		let (key, value) = storage_slot();
+		let storage: Storage = vec![(key.clone(), value.encode())].into_iter().collect();
+		let api = light_api(&storage, TestFetcher { storage: storage.clone(), ..Default::default() });
+
+		let best_hash = api.client.info().best_hash;
+		let number = BlockNumber::Hash { hash: best_hash, require_canonical: false };
+		assert!(api.remote_storage::<H256>(Some(number), key).wait().is_err());
+	}
+}
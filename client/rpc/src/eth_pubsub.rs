@@ -37,7 +37,9 @@ pub use fc_rpc_core::EthPubSubApiServer;
 use futures::{StreamExt as _, TryStreamExt as _};
 
 use jsonrpc_core::{Result as JsonRpcResult, futures::{Future, Sink}};
-use fp_rpc::{EthereumRuntimeRPCApi, TransactionStatus};
+use fp_rpc::{EthereumRuntimeRPCApi, TransactionStatus, EIP658Receipt};
+use crate::eth::resolve_finalized_tags;
+use crate::overrides::StorageOverride;
 
 use sc_network::{NetworkService, ExHashT};
 
@@ -70,6 +72,8 @@ pub struct EthPubSubApi<B: BlockT, P, C, BE, H: ExHashT> {
 	client: Arc<C>,
 	network: Arc<NetworkService<B, H>>,
 	subscriptions: SubscriptionManager<HexEncodedIdProvider>,
+	/// Ethereum data of the notified blocks, read from the Frontier database once pruned.
+	block_data: Arc<dyn StorageOverride<B> + Send + Sync>,
 	_marker: PhantomData<(B, BE)>,
 }
 
@@ -79,8 +83,9 @@ impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApi<B, P, C, BE, H> {
 		client: Arc<C>,
 		network: Arc<NetworkService<B, H>>,
 		subscriptions: SubscriptionManager<HexEncodedIdProvider>,
+		block_data: Arc<dyn StorageOverride<B> + Send + Sync>,
 	) -> Self {
-		Self { _pool, client, network, subscriptions, _marker: PhantomData }
+		Self { _pool, client, network, subscriptions, block_data, _marker: PhantomData }
 	}
 }
 
@@ -127,8 +132,9 @@ impl SubscriptionResult {
 	pub fn logs(
 		&self,
 		block: ethereum::Block,
-		receipts: Vec<ethereum::Receipt>,
-		params: &FilteredParams
+		statuses: Vec<TransactionStatus>,
+		params: &FilteredParams,
+		removed: bool,
 	) -> Vec<Log> {
 		let block_hash = Some(H256::from_slice(
 			Keccak256::digest(&rlp::encode(
@@ -137,16 +143,10 @@ impl SubscriptionResult {
 		));
 		let mut logs: Vec<Log> = vec![];
 		let mut log_index: u32 = 0;
-		for (receipt_index, receipt) in receipts.into_iter().enumerate() {
+		for status in statuses.into_iter() {
 			let mut transaction_log_index: u32 = 0;
-			let transaction_hash: Option<H256> = if receipt.logs.len() > 0 {
-				Some(H256::from_slice(
-					Keccak256::digest(&rlp::encode(
-						&block.transactions[receipt_index as usize]
-					)).as_slice()
-				))
-			} else { None };
-			for log in receipt.logs {
+			let transaction_hash = Some(status.transaction_hash);
+			for log in status.logs {
 				if self.add_log(
 					block_hash.unwrap(),
 					&log,
@@ -160,12 +160,12 @@ impl SubscriptionResult {
 						block_hash: block_hash,
 						block_number: Some(block.header.number),
 						transaction_hash: transaction_hash,
-						transaction_index: Some(U256::from(log_index)),
+						transaction_index: Some(U256::from(status.transaction_index)),
 						log_index: Some(U256::from(log_index)),
 						transaction_log_index: Some(U256::from(
 							transaction_log_index
 						)),
-						removed: false,
+						removed,
 					});
 				}
 				log_index += 1;
@@ -207,6 +207,50 @@ impl SubscriptionResult {
 	}
 }
 
+/// Follows the best block, reporting the blocks that left and joined the best chain each time it
+/// changes.
+struct BestChainFollower<B: BlockT, C> {
+	client: Arc<C>,
+	best: B::Hash,
+}
+
+impl<B: BlockT, C> BestChainFollower<B, C> where
+	C: HeaderBackend<B> + HeaderMetadata<B, Error=BlockChainError>,
+{
+	fn new(client: Arc<C>) -> Self {
+		let best = client.info().best_hash;
+		Self { client, best }
+	}
+
+	/// Move to the new best block `hash`. Returns the retracted and the enacted blocks, both
+	/// oldest first.
+	fn follow(&mut self, hash: B::Hash) -> (Vec<B::Hash>, Vec<B::Hash>) {
+		let previous = std::mem::replace(&mut self.best, hash);
+
+		match sp_blockchain::tree_route(&*self.client, previous, hash) {
+			Ok(route) => (
+				route.retracted().iter().rev().map(|block| block.hash).collect(),
+				route.enacted().iter().map(|block| block.hash).collect(),
+			),
+			Err(e) => {
+				warn!("Failed to compute the route from {:?} to {:?}: {:?}", previous, hash, e);
+				(Vec::new(), vec![hash])
+			},
+		}
+	}
+}
+
+/// Ethereum block and transaction statuses stored at the given block.
+fn block_and_statuses<B: BlockT>(
+	block_data: &dyn StorageOverride<B>,
+	hash: B::Hash,
+) -> Option<(ethereum::Block, Vec<TransactionStatus>)> {
+	let id = BlockId::Hash(hash);
+	let block = block_data.current_block(&id)?;
+	let statuses = block_data.current_transaction_statuses(&id)?;
+	Some((block, statuses))
+}
+
 fn storage_prefix_build(module: &[u8], storage: &[u8]) -> Vec<u8> {
 	[twox_128(module), twox_128(storage)].concat().to_vec()
 }
@@ -231,38 +275,38 @@ impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApiT for EthPubSubApi<B, P, C, BE
 		kind: Kind,
 		params: Option<Params>,
 	) {
-		let filtered_params = match params {
-			Some(Params::Logs(filter)) => FilteredParams::new(Some(filter)),
-			_ => FilteredParams::default()
+		let filter = match params {
+			Some(Params::Logs(filter)) => Some(filter),
+			_ => None,
 		};
 
 		let client = self.client.clone();
 		let network = self.network.clone();
+		let block_data = self.block_data.clone();
 		match kind {
 			Kind::Logs => {
 				self.subscriptions.add(subscriber, |sink| {
+					let mut follower = BestChainFollower::new(client.clone());
 					let stream = client.import_notification_stream()
-					.filter_map(move |notification| {
-						if notification.is_new_best {
-							let id = BlockId::Hash(notification.hash);
-							let receipts = client.runtime_api()
-								.current_receipts(&id);
-							let block = client.runtime_api()
-								.current_block(&id);
-							match (receipts, block) {
-								(Ok(Some(receipts)), Ok(Some(block))) =>
-									futures::future::ready(Some((block, receipts))),
-								_ => futures::future::ready(None)
+					.filter(|notification| futures::future::ready(notification.is_new_best))
+					.flat_map(move |notification| {
+						// Logs of retracted blocks are sent again, marked as removed, before the
+						// logs of the new best chain.
+						let (retracted, enacted) = follower.follow(notification.hash);
+						// The `finalized` and `safe` tags follow finality, resolve them every time.
+						let filtered_params = FilteredParams::new(
+							filter.as_ref().map(|filter| resolve_finalized_tags(&*client, filter))
+						);
+						let mut logs = Vec::new();
+						for (hash, removed) in retracted.into_iter().map(|hash| (hash, true))
+							.chain(enacted.into_iter().map(|hash| (hash, false)))
+						{
+							if let Some((block, statuses)) = block_and_statuses(&*block_data, hash) {
+								logs.extend(SubscriptionResult::new()
+									.logs(block, statuses, &filtered_params, removed));
 							}
-						} else {
-							futures::future::ready(None)
 						}
-					})
-					.flat_map(move |(block, receipts)| {
-						futures::stream::iter(
-							SubscriptionResult::new()
-								.logs(block, receipts, &filtered_params)
-						)
+						futures::stream::iter(logs)
 					})
 					.map(|x| {
 						return Ok::<Result<
@@ -283,19 +327,38 @@ impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApiT for EthPubSubApi<B, P, C, BE
 			},
 			Kind::NewHeads => {
 				self.subscriptions.add(subscriber, |sink| {
+					let mut follower = BestChainFollower::new(client.clone());
 					let stream = client.import_notification_stream()
+					.filter(|notification| futures::future::ready(notification.is_new_best))
+					.flat_map(move |notification| {
+						// On a reorg, every head of the new best chain is sent in order.
+						let (_, enacted) = follower.follow(notification.hash);
+						let blocks: Vec<_> = enacted.into_iter()
+							.filter_map(|hash| block_data.current_block(&BlockId::Hash(hash)))
+							.collect();
+						futures::stream::iter(blocks)
+					})
+					.map(|block| {
+						return Ok::<_, ()>(Ok(
+							SubscriptionResult::new()
+								.new_heads(block)
+						));
+					})
+					.compat();
+					sink
+						.sink_map_err(|e| warn!(
+							"Error sending notifications: {:?}", e
+						))
+						.send_all(stream)
+						.map(|_| ())
+				});
+			},
+			Kind::NewFinalizedHeads => {
+				self.subscriptions.add(subscriber, |sink| {
+					let stream = client.finality_notification_stream()
 					.filter_map(move |notification| {
-						if notification.is_new_best {
-							let id = BlockId::Hash(notification.hash);
-							let block = client.runtime_api()
-								.current_block(&id);
-							match block {
-								Ok(Some(block)) => futures::future::ready(Some(block)),
-								_ => futures::future::ready(None)
-							}
-						} else {
-							futures::future::ready(None)
-						}
+						let id = BlockId::Hash(notification.hash);
+						futures::future::ready(block_data.current_block(&id))
 					})
 					.map(|block| {
 						return Ok::<_, ()>(Ok(
@@ -331,14 +394,16 @@ impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApiT for EthPubSubApi<B, P, C, BE
 								}).collect();
 							for change in storage {
 								if let Some(data) = change {
-									let storage: Vec<(
+									let storage: Result<Vec<(
 										ethereum::Transaction,
 										TransactionStatus,
-										ethereum::Receipt
-									)> = Decode::decode(&mut &data.0[..]).unwrap();
-									let tmp: Vec<ethereum::Transaction> =
-										storage.iter().map(|x| x.0.clone()).collect();
-									transactions.extend(tmp);
+										EIP658Receipt
+									)>, _> = Decode::decode(&mut &data.0[..]);
+									if let Ok(storage) = storage {
+										let tmp: Vec<ethereum::Transaction> =
+											storage.iter().map(|x| x.0.clone()).collect();
+										transactions.extend(tmp);
+									}
 								}
 							}
 							futures::stream::iter(transactions)
@@ -0,0 +1,84 @@
+use std::sync::Mutex;
+use ethereum_types::{H256, U256};
+use jsonrpc_core::Result;
+
+/// Number of blocks sampled by default.
+pub const DEFAULT_SAMPLE_BLOCKS: u64 = 20;
+/// Percentile of the sampled gas prices suggested by default.
+pub const DEFAULT_PERCENTILE: u64 = 60;
+
+/// Suggests a gas price from the gas prices paid in recent blocks.
+///
+/// The suggestion is computed once per best block and never goes below the minimum gas price of
+/// the runtime.
+pub struct GasPriceOracle {
+	blocks: u64,
+	percentile: u64,
+	last: Mutex<Option<(H256, U256)>>,
+}
+
+impl GasPriceOracle {
+	/// Oracle suggesting the given `percentile` of the gas prices of the last `blocks` blocks.
+	pub fn new(blocks: u64, percentile: u64) -> Self {
+		Self {
+			blocks,
+			percentile: percentile.min(100),
+			last: Mutex::new(None),
+		}
+	}
+
+	/// Gas price suggested at the best block `best`. `samples` returns the effective gas prices
+	/// of the transactions of the given number of blocks ending at `best`.
+	pub fn suggest<F>(&self, best: H256, minimum: U256, samples: F) -> Result<U256> where
+		F: FnOnce(u64) -> Result<Vec<U256>>,
+	{
+		if let Some((hash, price)) = *self.last.lock().unwrap() {
+			if hash == best {
+				return Ok(price)
+			}
+		}
+
+		let mut prices = samples(self.blocks)?;
+		prices.sort();
+		let price = match prices.len() {
+			0 => minimum,
+			len => prices[(len - 1) * self.percentile as usize / 100].max(minimum),
+		};
+
+		*self.last.lock().unwrap() = Some((best, price));
+		Ok(price)
+	}
+}
+
+impl Default for GasPriceOracle {
+	fn default() -> Self {
+		Self::new(DEFAULT_SAMPLE_BLOCKS, DEFAULT_PERCENTILE)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn prices(prices: &[u64]) -> Result<Vec<U256>> {
+		Ok(prices.iter().cloned().map(U256::from).collect())
+	}
+
+	#[test]
+	fn suggests_percentile_above_minimum() {
+		let oracle = GasPriceOracle::new(2, 50);
+
+		assert_eq!(oracle.suggest(H256::repeat_byte(1), 1.into(), |_| prices(&[9, 3, 5, 7, 1])), Ok(5.into()));
+		assert_eq!(oracle.suggest(H256::repeat_byte(2), 6.into(), |_| prices(&[9, 3, 5, 7, 1])), Ok(6.into()));
+		assert_eq!(oracle.suggest(H256::repeat_byte(3), 2.into(), |_| prices(&[])), Ok(2.into()));
+	}
+
+	#[test]
+	fn suggestion_is_cached_per_best_block() {
+		let oracle = GasPriceOracle::default();
+
+		assert_eq!(oracle.suggest(H256::repeat_byte(1), 1.into(), |_| prices(&[4])), Ok(4.into()));
+		assert_eq!(oracle.suggest(H256::repeat_byte(1), 1.into(), |_| prices(&[8])), Ok(4.into()));
+		assert_eq!(oracle.suggest(H256::repeat_byte(2), 1.into(), |_| prices(&[8])), Ok(8.into()));
+	}
+}
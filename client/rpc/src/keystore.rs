@@ -0,0 +1,516 @@
+use std::{collections::BTreeMap, fs, io::Write, path::{Path, PathBuf}, sync::{Arc, RwLock}, time::{Duration, Instant}};
+use aes::Aes128;
+use ctr::Ctr128;
+use ctr::stream_cipher::{NewStreamCipher, SyncStreamCipher, generic_array::GenericArray};
+use ethereum_types::{H160, H256};
+use hmac::Hmac;
+use jsonrpc_core::Error;
+use log::warn;
+use rand::{Rng, rngs::OsRng};
+use rustc_hex::{FromHex, ToHex};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+use fc_rpc_core::types::TypedData;
+use crate::{
+	EthSigner, internal_err, personal_message_hash, secret_address, sign_hash, sign_transaction_message,
+	typed_data_hash,
+};
+
+/// Scrypt parameters of newly written key files, the "standard" ones of geth.
+const SCRYPT_LOG_N: u8 = 18;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const DERIVED_KEY_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CipherParams {
+	iv: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum KdfParams {
+	Scrypt { dklen: u32, n: u32, p: u32, r: u32, salt: String },
+	Pbkdf2 { c: u32, dklen: u32, prf: String, salt: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Crypto {
+	cipher: String,
+	cipherparams: CipherParams,
+	ciphertext: String,
+	kdf: String,
+	kdfparams: KdfParams,
+	mac: String,
+}
+
+/// An encrypted secret key, in the Web3 Secret Storage (version 3) format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyFile {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	address: Option<String>,
+	#[serde(alias = "Crypto")]
+	crypto: Crypto,
+	id: String,
+	version: u32,
+	/// Name of the account, as written by Parity.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	name: Option<String>,
+	/// JSON metadata of the account, as written by Parity.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	meta: Option<String>,
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, String> {
+	value.trim_start_matches("0x").from_hex()
+		.map_err(|err| format!("invalid {} in key file: {:?}", field, err))
+}
+
+fn derive_key(password: &str, params: &KdfParams) -> Result<Vec<u8>, String> {
+	let mut key = match params {
+		KdfParams::Scrypt { dklen, n, p, r, salt } => {
+			if !n.is_power_of_two() || *n < 2 {
+				return Err(format!("invalid scrypt parameter n: {}", n));
+			}
+			let params = scrypt::ScryptParams::new(n.trailing_zeros() as u8, *r, *p)
+				.map_err(|err| format!("invalid scrypt parameters: {:?}", err))?;
+			let mut key = vec![0u8; *dklen as usize];
+			scrypt::scrypt(password.as_bytes(), &decode_hex("salt", salt)?, &params, &mut key)
+				.map_err(|err| format!("scrypt failed: {:?}", err))?;
+			key
+		},
+		KdfParams::Pbkdf2 { c, dklen, prf, salt } => {
+			if prf != "hmac-sha256" {
+				return Err(format!("unsupported pbkdf2 function: {}", prf));
+			}
+			let mut key = vec![0u8; *dklen as usize];
+			pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), &decode_hex("salt", salt)?, *c, &mut key);
+			key
+		},
+	};
+	if key.len() < DERIVED_KEY_LEN {
+		return Err(format!("derived key is too short: {} bytes", key.len()));
+	}
+	key.truncate(DERIVED_KEY_LEN);
+	Ok(key)
+}
+
+fn mac(derived_key: &[u8], ciphertext: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(&[&derived_key[16..32], ciphertext].concat()).as_slice())
+}
+
+/// Applies AES-128-CTR, which both encrypts and decrypts.
+fn aes_128_ctr(key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<(), String> {
+	if iv.len() != 16 {
+		return Err(format!("invalid iv length: {}", iv.len()));
+	}
+	let mut cipher = Ctr128::<Aes128>::new(GenericArray::from_slice(&key[..16]), GenericArray::from_slice(iv));
+	cipher.apply_keystream(data);
+	Ok(())
+}
+
+/// A random version 4 UUID.
+fn random_uuid() -> String {
+	let mut bytes: [u8; 16] = OsRng.gen();
+	bytes[6] = (bytes[6] & 0x0f) | 0x40;
+	bytes[8] = (bytes[8] & 0x3f) | 0x80;
+	let hex: String = bytes.to_hex();
+	format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+impl KeyFile {
+	/// Encrypts the secret key with the given password.
+	pub fn encrypt(secret: &secp256k1::SecretKey, password: &str) -> Result<Self, String> {
+		Self::encrypt_with_scrypt(secret, password, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)
+	}
+
+	fn encrypt_with_scrypt(
+		secret: &secp256k1::SecretKey,
+		password: &str,
+		log_n: u8,
+		r: u32,
+		p: u32,
+	) -> Result<Self, String> {
+		let salt: [u8; 32] = OsRng.gen();
+		let iv: [u8; 16] = OsRng.gen();
+		let kdfparams = KdfParams::Scrypt {
+			dklen: DERIVED_KEY_LEN as u32,
+			n: 1 << log_n,
+			p,
+			r,
+			salt: salt.to_hex(),
+		};
+		let derived_key = derive_key(password, &kdfparams)?;
+		let mut ciphertext = secret.serialize().to_vec();
+		aes_128_ctr(&derived_key, &iv, &mut ciphertext)?;
+
+		Ok(Self {
+			address: Some(secret_address(secret).as_bytes().to_hex()),
+			crypto: Crypto {
+				cipher: "aes-128-ctr".into(),
+				cipherparams: CipherParams { iv: iv.to_hex() },
+				ciphertext: ciphertext.to_hex(),
+				kdf: "scrypt".into(),
+				kdfparams,
+				mac: mac(&derived_key, &ciphertext).as_bytes().to_hex(),
+			},
+			id: random_uuid(),
+			version: 3,
+			name: None,
+			meta: None,
+		})
+	}
+
+	/// Decrypts the secret key, failing when the password is wrong.
+	pub fn decrypt(&self, password: &str) -> Result<secp256k1::SecretKey, String> {
+		if self.version != 3 {
+			return Err(format!("unsupported key file version: {}", self.version));
+		}
+		if self.crypto.cipher != "aes-128-ctr" {
+			return Err(format!("unsupported cipher: {}", self.crypto.cipher));
+		}
+		let derived_key = derive_key(password, &self.crypto.kdfparams)?;
+		let mut data = decode_hex("ciphertext", &self.crypto.ciphertext)?;
+		if mac(&derived_key, &data).as_bytes() != &decode_hex("mac", &self.crypto.mac)?[..] {
+			return Err("invalid password".into());
+		}
+		aes_128_ctr(&derived_key, &decode_hex("iv", &self.crypto.cipherparams.iv)?, &mut data)?;
+
+		let secret = secp256k1::SecretKey::parse_slice(&data)
+			.map_err(|err| format!("invalid secret key: {:?}", err))?;
+		if let Some(address) = self.address()? {
+			if address != secret_address(&secret) {
+				return Err("secret key does not match the key file address".into());
+			}
+		}
+		Ok(secret)
+	}
+
+	/// Identifier of the key file.
+	pub fn id(&self) -> &str {
+		&self.id
+	}
+
+	/// Name of the account, if the file gives one.
+	pub fn name(&self) -> Option<&str> {
+		self.name.as_deref()
+	}
+
+	/// JSON metadata of the account, if the file gives some.
+	pub fn meta(&self) -> Option<&str> {
+		self.meta.as_deref()
+	}
+
+	/// Address of the key, if the file gives it.
+	pub fn address(&self) -> Result<Option<H160>, String> {
+		match &self.address {
+			Some(address) => {
+				let address = decode_hex("address", address)?;
+				if address.len() != 20 {
+					return Err(format!("invalid address length in key file: {}", address.len()));
+				}
+				Ok(Some(H160::from_slice(&address)))
+			},
+			None => Ok(None),
+		}
+	}
+}
+
+/// Name of a new key file of the account, as geth names them:
+/// `UTC--2016-03-22T12-57-55.920751759Z--<address>`.
+fn key_file_name(address: &H160) -> String {
+	format!(
+		"UTC--{}--{}",
+		chrono::Utc::now().format("%Y-%m-%dT%H-%M-%S%.9fZ"),
+		address.as_bytes().to_hex::<String>(),
+	)
+}
+
+/// Creates the file, readable and writable by its owner only.
+fn write_private(file: &Path, content: &[u8]) -> std::io::Result<()> {
+	let mut options = fs::OpenOptions::new();
+	options.write(true).create_new(true);
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::OpenOptionsExt;
+		options.mode(0o600);
+	}
+	options.open(file)?.write_all(content)
+}
+
+/// Ethereum keys kept as Web3 Secret Storage files in a directory.
+///
+/// Keys must be unlocked with their password before they can sign.
+pub struct EthKeystore {
+	path: PathBuf,
+	files: RwLock<BTreeMap<H160, KeyFile>>,
+	unlocked: RwLock<BTreeMap<H160, (secp256k1::SecretKey, Option<Instant>)>>,
+}
+
+impl EthKeystore {
+	/// Loads the key files of the directory, which is created if it does not exist. Files that
+	/// are not key files are skipped.
+	pub fn open(path: PathBuf) -> Result<Self, String> {
+		fs::create_dir_all(&path)
+			.map_err(|err| format!("create keystore directory {} failed: {:?}", path.display(), err))?;
+
+		let mut files = BTreeMap::new();
+		let entries = fs::read_dir(&path)
+			.map_err(|err| format!("read keystore directory {} failed: {:?}", path.display(), err))?;
+		for entry in entries {
+			let file = entry.map_err(|err| format!("{:?}", err))?.path();
+			if !file.is_file() {
+				continue
+			}
+			let key_file = fs::read(&file).map_err(|err| format!("{:?}", err))
+				.and_then(|content| serde_json::from_slice::<KeyFile>(&content).map_err(|err| format!("{:?}", err)))
+				.and_then(|key_file| Ok((key_file.address()?, key_file)));
+			match key_file {
+				Ok((Some(address), key_file)) => {
+					files.insert(address, key_file);
+				},
+				Ok((None, _)) => warn!("Skipping key file {} without address", file.display()),
+				Err(err) => warn!("Skipping invalid key file {}: {}", file.display(), err),
+			}
+		}
+
+		Ok(Self {
+			path,
+			files: RwLock::new(files),
+			unlocked: RwLock::new(BTreeMap::new()),
+		})
+	}
+
+	/// Directory of the key files.
+	pub fn path(&self) -> &PathBuf {
+		&self.path
+	}
+
+	/// Accounts of all the keys, locked or not.
+	pub fn accounts(&self) -> Vec<H160> {
+		self.files.read().expect("keystore lock is poisoned").keys().cloned().collect()
+	}
+
+	/// Key files of all the accounts.
+	pub fn key_files(&self) -> BTreeMap<H160, KeyFile> {
+		self.files.read().expect("keystore lock is poisoned").clone()
+	}
+
+	/// Generates a new key, stored encrypted with the password.
+	pub fn new_account(&self, password: &str) -> Result<H160, String> {
+		let secret = loop {
+			let bytes: [u8; 32] = OsRng.gen();
+			if let Ok(secret) = secp256k1::SecretKey::parse(&bytes) {
+				break secret
+			}
+		};
+		self.import(&secret, password)
+	}
+
+	/// Stores the secret key encrypted with the password, unless the account already has a key.
+	pub fn import(&self, secret: &secp256k1::SecretKey, password: &str) -> Result<H160, String> {
+		self.store(secret_address(secret), KeyFile::encrypt(secret, password)?)
+	}
+
+	fn store(&self, address: H160, key_file: KeyFile) -> Result<H160, String> {
+		let mut files = self.files.write().expect("keystore lock is poisoned");
+		if files.contains_key(&address) {
+			return Err(format!("account {:?} already exists", address));
+		}
+		let content = serde_json::to_vec(&key_file).map_err(|err| format!("{:?}", err))?;
+		let file = self.path.join(key_file_name(&address));
+		write_private(&file, &content)
+			.map_err(|err| format!("write key file {} failed: {:?}", file.display(), err))?;
+		files.insert(address, key_file);
+		Ok(address)
+	}
+
+	/// Decrypts the key of the account with its password.
+	pub fn decrypt(&self, address: &H160, password: &str) -> Result<secp256k1::SecretKey, String> {
+		self.files.read().expect("keystore lock is poisoned")
+			.get(address)
+			.ok_or_else(|| format!("no key for account {:?}", address))?
+			.decrypt(password)
+	}
+
+	/// Decrypts the key of the account, so that it can sign until the duration elapses, or until
+	/// it is locked again when no duration is given.
+	pub fn unlock(&self, address: &H160, password: &str, duration: Option<Duration>) -> Result<(), String> {
+		let secret = self.decrypt(address, password)?;
+		let expires = duration.map(|duration| Instant::now() + duration);
+		self.unlocked.write().expect("keystore lock is poisoned").insert(*address, (secret, expires));
+		Ok(())
+	}
+
+	/// Forgets the decrypted key of the account, returning whether it was unlocked.
+	pub fn lock(&self, address: &H160) -> bool {
+		self.unlocked.write().expect("keystore lock is poisoned").remove(address).is_some()
+	}
+
+	/// Secret key of the account, if it is unlocked.
+	pub fn secret(&self, address: &H160) -> Option<secp256k1::SecretKey> {
+		let mut unlocked = self.unlocked.write().expect("keystore lock is poisoned");
+		match unlocked.get(address) {
+			Some((_, Some(expires))) if *expires <= Instant::now() => {
+				unlocked.remove(address);
+				None
+			},
+			Some((secret, _)) => Some(secret.clone()),
+			None => None,
+		}
+	}
+}
+
+/// Signer using the unlocked keys of an `EthKeystore`.
+pub struct EthKeystoreSigner {
+	keystore: Arc<EthKeystore>,
+}
+
+impl EthKeystoreSigner {
+	pub fn new(keystore: Arc<EthKeystore>) -> Self {
+		Self { keystore }
+	}
+
+	fn secret(&self, address: &H160) -> Result<secp256k1::SecretKey, Error> {
+		self.keystore.secret(address)
+			.ok_or_else(|| internal_err(format!("account {:?} is locked", address)))
+	}
+}
+
+impl EthSigner for EthKeystoreSigner {
+	fn accounts(&self) -> Vec<H160> {
+		self.keystore.accounts()
+	}
+
+	fn sign(
+		&self,
+		message: ethereum::TransactionMessage,
+		address: &H160,
+	) -> Result<ethereum::Transaction, Error> {
+		sign_transaction_message(&self.secret(address)?, message)
+	}
+
+	fn sign_message(&self, message: &[u8], address: &H160) -> Result<[u8; 65], Error> {
+		sign_hash(&self.secret(address)?, personal_message_hash(message))
+	}
+
+	fn sign_typed_data(&self, data: &TypedData, address: &H160) -> Result<[u8; 65], Error> {
+		sign_hash(&self.secret(address)?, typed_data_hash(data).map_err(internal_err)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+
+	/// Written by geth with the "very light" scrypt parameters, the password is empty.
+	const GETH_SCRYPT_KEY_FILE: &str = r#"{"address":"45dea0fb0bba44f4fcf290bba71fd57d7117cbb8","crypto":{"cipher":"aes-128-ctr","ciphertext":"b87781948a1befd247bff51ef4063f716cf6c2d3481163e9a8f42e1f9bb74145","cipherparams":{"iv":"dc4926b48a105133d2f16b96833abf1e"},"kdf":"scrypt","kdfparams":{"dklen":32,"n":2,"p":1,"r":8,"salt":"004244bbdc51cadda545b1cfa43cff9ed2ae88e08c61f1479dbb45410722f8f0"},"mac":"39990c1684557447940d4c69e06b1b82b2aceacb43f284df65c956daf3046b85"},"id":"ce541d8d-c79b-40f8-9f8c-20f59616faba","version":3}"#;
+
+	/// The PBKDF2 test vector of the geth keystore, the password is "testpassword".
+	const GETH_PBKDF2_KEY_FILE: &str = r#"{"crypto":{"cipher":"aes-128-ctr","cipherparams":{"iv":"6087dab2f9fdbbfaddc31a909735c1e6"},"ciphertext":"5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46","kdf":"pbkdf2","kdfparams":{"c":262144,"dklen":32,"prf":"hmac-sha256","salt":"ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"},"mac":"517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"},"id":"3198bc9c-6672-5ab3-d995-4942343ae5b6","version":3}"#;
+
+	fn secret(hex: &str) -> secp256k1::SecretKey {
+		secp256k1::SecretKey::parse_slice(&hex.from_hex::<Vec<u8>>().unwrap()).unwrap()
+	}
+
+	#[test]
+	fn decrypts_geth_key_files() {
+		let scrypt: KeyFile = serde_json::from_str(GETH_SCRYPT_KEY_FILE).unwrap();
+		let key = scrypt.decrypt("").unwrap();
+		assert_eq!(key, secret("33ff86a4a29a842eedd42a84f569d945c771857d4ddb639de730a547f08030c3"));
+		assert_eq!(
+			secret_address(&key),
+			H160::from_str("45dea0fb0bba44f4fcf290bba71fd57d7117cbb8").unwrap(),
+		);
+		assert_eq!(scrypt.decrypt("wrong").unwrap_err(), "invalid password");
+
+		let pbkdf2: KeyFile = serde_json::from_str(GETH_PBKDF2_KEY_FILE).unwrap();
+		assert_eq!(
+			pbkdf2.decrypt("testpassword").unwrap(),
+			secret("7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d"),
+		);
+	}
+
+	#[test]
+	fn encrypted_keys_round_trip() {
+		let key = secret("33ff86a4a29a842eedd42a84f569d945c771857d4ddb639de730a547f08030c3");
+		let key_file = KeyFile::encrypt_with_scrypt(&key, "password", 2, 8, 1).unwrap();
+
+		let encoded = serde_json::to_string(&key_file).unwrap();
+		let decoded: KeyFile = serde_json::from_str(&encoded).unwrap();
+		assert_eq!(decoded, key_file);
+		assert_eq!(decoded.address().unwrap(), Some(secret_address(&key)));
+		assert_eq!(decoded.decrypt("password").unwrap(), key);
+		assert!(decoded.decrypt("").is_err());
+	}
+
+	#[test]
+	fn keystore_signs_with_unlocked_keys_only() {
+		let path = std::env::temp_dir().join(format!("fc-rpc-keystore-{}", random_uuid()));
+		fs::create_dir_all(&path).unwrap();
+		fs::write(path.join("geth-key.json"), GETH_SCRYPT_KEY_FILE).unwrap();
+		fs::write(path.join("README"), "not a key file").unwrap();
+
+		let keystore = Arc::new(EthKeystore::open(path.clone()).unwrap());
+		let address = H160::from_str("45dea0fb0bba44f4fcf290bba71fd57d7117cbb8").unwrap();
+		let signer = EthKeystoreSigner::new(keystore.clone());
+		assert_eq!(signer.accounts(), vec![address]);
+
+		let message = ethereum::TransactionMessage {
+			nonce: 0.into(),
+			gas_price: 1.into(),
+			gas_limit: 21000.into(),
+			action: ethereum::TransactionAction::Call(H160::zero()),
+			value: 0.into(),
+			input: vec![],
+			chain_id: Some(888),
+		};
+		assert!(signer.sign(message.clone(), &address).is_err());
+
+		keystore.unlock(&address, "", None).unwrap();
+		let transaction = signer.sign(message.clone(), &address).unwrap();
+		let public = crate::public_key(&transaction).unwrap();
+		assert_eq!(H160::from(H256::from_slice(Keccak256::digest(&public).as_slice())), address);
+
+		assert!(keystore.lock(&address));
+		assert!(!keystore.lock(&address));
+		assert!(signer.sign(message.clone(), &address).is_err());
+
+		keystore.unlock(&address, "", Some(Duration::from_secs(0))).unwrap();
+		assert!(signer.sign(message, &address).is_err());
+
+		fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn keystore_writes_private_geth_named_key_files() {
+		let path = std::env::temp_dir().join(format!("fc-rpc-keystore-{}", random_uuid()));
+		let keystore = EthKeystore::open(path.clone()).unwrap();
+		let key = secret("33ff86a4a29a842eedd42a84f569d945c771857d4ddb639de730a547f08030c3");
+		let address = secret_address(&key);
+		let key_file = KeyFile::encrypt_with_scrypt(&key, "password", 2, 8, 1).unwrap();
+		keystore.store(address, key_file.clone()).unwrap();
+		assert!(keystore.store(address, key_file).is_err());
+
+		let files = fs::read_dir(&path).unwrap().map(|entry| entry.unwrap().path()).collect::<Vec<_>>();
+		assert_eq!(files.len(), 1);
+		let name = files[0].file_name().unwrap().to_str().unwrap().to_string();
+		assert!(name.starts_with("UTC--"), "{}", name);
+		assert!(name.ends_with("Z--45dea0fb0bba44f4fcf290bba71fd57d7117cbb8"), "{}", name);
+		// The timestamp is `YYYY-MM-DDTHH-MM-SS.nnnnnnnnnZ`.
+		assert_eq!(name.len(), "UTC--".len() + 30 + "--".len() + 40);
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			assert_eq!(fs::metadata(&files[0]).unwrap().permissions().mode() & 0o777, 0o600);
+		}
+
+		let reopened = EthKeystore::open(path.clone()).unwrap();
+		assert_eq!(reopened.accounts(), vec![address]);
+		assert_eq!(reopened.decrypt(&address, "password").unwrap(), key);
+
+		fs::remove_dir_all(path).unwrap();
+	}
+}
@@ -1,20 +1,36 @@
 
+mod cache;
+mod eip712;
 mod eth;
+mod eth_light;
 mod eth_pubsub;
+mod gas_oracle;
+mod keystore;
+mod limits;
 mod overrides;
+mod personal;
 
+pub use eip712::typed_data_hash;
 pub use eth::{
 	EthApi, EthApiServer, EthFilterApi, EthFilterApiServer, NetApi, NetApiServer, Web3Api, Web3ApiServer,
-	EthTask,
+	EthTask, load_filter_pool,
 };
+pub use eth_light::{LightEthApi, LightEthApiServer};
 pub use eth_pubsub::{EthPubSubApi, EthPubSubApiServer, HexEncodedIdProvider};
-pub use overrides::{StorageOverride, SchemaV1Override};
+pub use cache::{EthBlockDataCache, CacheStats};
+pub use gas_oracle::GasPriceOracle;
+pub use keystore::{EthKeystore, EthKeystoreSigner, KeyFile};
+pub use limits::RpcLimits;
+pub use personal::{PersonalApi, PersonalApiServer};
+pub use overrides::{StorageOverride, SchemaV1Override, SchemaV2Override, PersistedStorageOverride};
 
-use ethereum_types::{H160, H256};
+use ethereum_types::{H160, H256, H512, U256};
 use ethereum::{
 	Transaction as EthereumTransaction, TransactionMessage as EthereumTransactionMessage,
 };
 use jsonrpc_core::{ErrorCode, Error, Value};
+use fc_rpc_core::EthApi as EthApiT;
+use fc_rpc_core::types::{BlockNumber, CallRequest, TransactionRequest, TypedData};
 use rustc_hex::ToHex;
 use pallet_evm::ExitReason;
 use sha3::{Digest, Keccak256};
@@ -87,6 +103,140 @@ pub trait EthSigner: Send + Sync {
 		message: ethereum::TransactionMessage,
 		address: &H160,
 	) -> Result<ethereum::Transaction, Error>;
+	/// Sign a message prefixed as defined by EIP-191 using the given account, returning the
+	/// signature as `r`, `s` and `v`.
+	fn sign_message(&self, message: &[u8], address: &H160) -> Result<[u8; 65], Error>;
+	/// Sign typed structured data as defined by EIP-712 using the given account, returning the
+	/// signature as `r`, `s` and `v`.
+	fn sign_typed_data(&self, data: &TypedData, address: &H160) -> Result<[u8; 65], Error>;
+}
+
+/// Address of the account controlled by the given secret key.
+pub(crate) fn secret_address(secret: &secp256k1::SecretKey) -> H160 {
+	let public = secp256k1::PublicKey::from_secret_key(secret);
+	let mut res = [0u8; 64];
+	res.copy_from_slice(&public.serialize()[1..65]);
+
+	H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
+}
+
+/// Sign a transaction message with the given secret key.
+pub(crate) fn sign_transaction_message(
+	secret: &secp256k1::SecretKey,
+	message: ethereum::TransactionMessage,
+) -> Result<ethereum::Transaction, Error> {
+	let signing_message = secp256k1::Message::parse_slice(&message.hash()[..])
+		.map_err(|_| internal_err("invalid signing message"))?;
+	let (signature, recid) = secp256k1::sign(&signing_message, secret);
+
+	let v = match message.chain_id {
+		None => 27 + recid.serialize() as u64,
+		Some(chain_id) => 2 * chain_id + 35 + recid.serialize() as u64,
+	};
+	let rs = signature.serialize();
+	let r = H256::from_slice(&rs[0..32]);
+	let s = H256::from_slice(&rs[32..64]);
+
+	Ok(ethereum::Transaction {
+		nonce: message.nonce,
+		gas_price: message.gas_price,
+		gas_limit: message.gas_limit,
+		action: message.action,
+		value: message.value,
+		input: message.input,
+		signature: ethereum::TransactionSignature::new(v, r, s)
+			.ok_or(internal_err("signer generated invalid signature"))?,
+	})
+}
+
+/// Transaction message of a request sent by `from`, filling its missing fields: the nonce from
+/// the pending state, the suggested gas price and the estimated gas.
+pub(crate) fn fill_transaction_request<E: EthApiT + ?Sized>(
+	eth: &E,
+	from: H160,
+	request: TransactionRequest,
+) -> Result<ethereum::TransactionMessage, Error> {
+	let nonce = match request.nonce {
+		Some(nonce) => nonce,
+		None => eth.transaction_count(from, Some(BlockNumber::Pending))?,
+	};
+	let gas_price = match request.gas_price {
+		Some(gas_price) => gas_price,
+		None => eth.gas_price()?,
+	};
+	let gas_limit = match request.gas {
+		Some(gas) => gas,
+		None => eth.estimate_gas(CallRequest {
+			from: Some(from),
+			to: request.to,
+			gas_price: Some(gas_price),
+			gas: None,
+			value: request.value,
+			data: request.data.clone(),
+			nonce: Some(nonce),
+		}, None)?,
+	};
+	let chain_id = eth.chain_id()?;
+
+	Ok(ethereum::TransactionMessage {
+		nonce,
+		gas_price,
+		gas_limit,
+		value: request.value.unwrap_or(U256::zero()),
+		input: request.data.map(|s| s.into_vec()).unwrap_or_default(),
+		action: match request.to {
+			Some(to) => ethereum::TransactionAction::Call(to),
+			None => ethereum::TransactionAction::Create,
+		},
+		chain_id: chain_id.map(|s| s.as_u64()),
+	})
+}
+
+/// Hash of a message signed with `personal_sign`, as defined by EIP-191 (version 0x45).
+pub(crate) fn personal_message_hash(message: &[u8]) -> H256 {
+	let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+	data.extend_from_slice(message);
+
+	H256::from_slice(Keccak256::digest(&data).as_slice())
+}
+
+/// Sign a hash with the given secret key, returning the signature as `r`, `s` and `v`, with
+/// `v` being 27 or 28.
+pub(crate) fn sign_hash(secret: &secp256k1::SecretKey, hash: H256) -> Result<[u8; 65], Error> {
+	let signing_message = secp256k1::Message::parse_slice(&hash[..])
+		.map_err(|_| internal_err("invalid signing message"))?;
+	let (signature, recid) = secp256k1::sign(&signing_message, secret);
+
+	let mut res = [0u8; 65];
+	res[0..64].copy_from_slice(&signature.serialize()[..]);
+	res[64] = 27 + recid.serialize();
+	Ok(res)
+}
+
+/// Recover the public key and address which signed a hash from a signature given as `r`, `s`
+/// and `v`.
+pub(crate) fn recover_signer(hash: H256, signature: &[u8]) -> Result<(H512, H160), Error> {
+	if signature.len() != 65 {
+		return Err(internal_err(format!("invalid signature length: {}", signature.len())));
+	}
+	let mut sig = [0u8; 65];
+	sig.copy_from_slice(signature);
+	// Accept replay protected `v` values as well.
+	sig[64] = match signature[64] {
+		v @ 0..=1 => v,
+		v @ 27..=28 => v - 27,
+		v if v >= 35 => (v - 35) % 2,
+		v => return Err(internal_err(format!("invalid signature v: {}", v))),
+	};
+	let mut msg = [0u8; 32];
+	msg.copy_from_slice(&hash[..]);
+
+	let public = sp_io::crypto::secp256k1_ecdsa_recover(&sig, &msg)
+		.map_err(|err| internal_err(format!("recover signer failed: {:?}", err)))?;
+	Ok((
+		H512::from_slice(&public[..]),
+		H160::from(H256::from_slice(Keccak256::digest(&public).as_slice())),
+	))
 }
 
 pub struct EthDevSigner {
@@ -106,17 +256,17 @@ impl EthDevSigner {
 			],
 		}
 	}
+
+	fn secret(&self, address: &H160) -> Result<&secp256k1::SecretKey, Error> {
+		self.keys.iter()
+			.find(|secret| &secret_address(secret) == address)
+			.ok_or(internal_err("signer not available"))
+	}
 }
 
 impl EthSigner for EthDevSigner {
 	fn accounts(&self) -> Vec<H160> {
-		self.keys.iter().map(|secret| {
-			let public = secp256k1::PublicKey::from_secret_key(secret);
-			let mut res = [0u8; 64];
-			res.copy_from_slice(&public.serialize()[1..65]);
-
-			H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
-		}).collect()
+		self.keys.iter().map(secret_address).collect()
 	}
 
 	fn sign(
@@ -124,44 +274,14 @@ impl EthSigner for EthDevSigner {
 		message: ethereum::TransactionMessage,
 		address: &H160,
 	) -> Result<ethereum::Transaction, Error> {
-		let mut transaction = None;
-
-		for secret in &self.keys {
-			let key_address = {
-				let public = secp256k1::PublicKey::from_secret_key(secret);
-				let mut res = [0u8; 64];
-				res.copy_from_slice(&public.serialize()[1..65]);
-				H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
-			};
-
-			if &key_address == address {
-				let signing_message = secp256k1::Message::parse_slice(&message.hash()[..])
-					.map_err(|_| internal_err("invalid signing message"))?;
-				let (signature, recid) = secp256k1::sign(&signing_message, secret);
-
-				let v = match message.chain_id {
-					None => 27 + recid.serialize() as u64,
-					Some(chain_id) => 2 * chain_id + 35 + recid.serialize() as u64,
-				};
-				let rs = signature.serialize();
-				let r = H256::from_slice(&rs[0..32]);
-				let s = H256::from_slice(&rs[32..64]);
-
-				transaction = Some(ethereum::Transaction {
-					nonce: message.nonce,
-					gas_price: message.gas_price,
-					gas_limit: message.gas_limit,
-					action: message.action,
-					value: message.value,
-					input: message.input.clone(),
-					signature: ethereum::TransactionSignature::new(v, r, s)
-						.ok_or(internal_err("signer generated invalid signature"))?,
-				});
-
-				break
-			}
-		}
+		sign_transaction_message(self.secret(address)?, message)
+	}
+
+	fn sign_message(&self, message: &[u8], address: &H160) -> Result<[u8; 65], Error> {
+		sign_hash(self.secret(address)?, personal_message_hash(message))
+	}
 
-		transaction.ok_or(internal_err("signer not available"))
+	fn sign_typed_data(&self, data: &TypedData, address: &H160) -> Result<[u8; 65], Error> {
+		sign_hash(self.secret(address)?, typed_data_hash(data).map_err(internal_err)?)
 	}
 }
@@ -0,0 +1,111 @@
+use ethereum_types::U256;
+use jsonrpc_core::Result;
+use fc_rpc_core::types::Log;
+use crate::internal_err;
+
+/// Gas cap used by default, as in geth.
+pub const DEFAULT_GAS_CAP: u64 = 50_000_000;
+/// Number of blocks a single logs query may search by default.
+pub const DEFAULT_MAX_LOGS_RANGE: u64 = 2_000;
+/// Number of logs a single logs query may return by default.
+pub const DEFAULT_MAX_LOGS: usize = 10_000;
+/// Number of blocks the Ethereum mapping may lag behind the best block by default.
+pub const DEFAULT_MAX_MAPPING_LAG: u64 = 16;
+
+/// Resources a single Ethereum RPC call may use.
+#[derive(Debug, Clone, Copy)]
+pub struct RpcLimits {
+	/// Gas limit of `eth_call` and `eth_estimateGas`, also used when the request gives none.
+	pub gas_cap: u64,
+	/// Number of blocks a single logs query may search.
+	pub max_logs_range: u64,
+	/// Number of logs a single logs query may return.
+	pub max_logs: usize,
+	/// Number of blocks the Ethereum mapping may lag behind the best block before lookups of
+	/// unknown hashes are refused.
+	pub max_mapping_lag: u64,
+}
+
+impl RpcLimits {
+	/// Gas limit of a call requesting `gas`.
+	pub fn gas_limit(&self, gas: Option<U256>) -> Result<U256> {
+		match gas {
+			Some(gas) if gas > U256::from(self.gas_cap) => Err(internal_err(
+				format!("gas limit {} exceeds the RPC gas cap of {}", gas, self.gas_cap)
+			)),
+			Some(gas) => Ok(gas),
+			None => Ok(U256::from(self.gas_cap)),
+		}
+	}
+
+	/// Check the number of blocks from `from` to `to`, both included.
+	pub fn check_logs_range(&self, from: u64, to: u64) -> Result<()> {
+		let range = to.saturating_sub(from).saturating_add(1);
+		if to >= from && range > self.max_logs_range {
+			return Err(internal_err(
+				format!("block range of {} blocks exceeds the limit of {}", range, self.max_logs_range)
+			))
+		}
+		Ok(())
+	}
+
+	pub fn check_logs_count(&self, logs: Vec<Log>) -> Result<Vec<Log>> {
+		if logs.len() > self.max_logs {
+			return Err(internal_err(
+				format!("query returned more than {} logs", self.max_logs)
+			))
+		}
+		Ok(logs)
+	}
+
+	/// Check the number of best chain blocks the Ethereum mapping has not synced yet.
+	pub fn check_mapping_lag(&self, lag: u64) -> Result<()> {
+		if lag > self.max_mapping_lag {
+			return Err(internal_err(format!("Ethereum mapping is {} blocks behind the best block", lag)))
+		}
+		Ok(())
+	}
+}
+
+impl Default for RpcLimits {
+	fn default() -> Self {
+		Self {
+			gas_cap: DEFAULT_GAS_CAP,
+			max_logs_range: DEFAULT_MAX_LOGS_RANGE,
+			max_logs: DEFAULT_MAX_LOGS,
+			max_mapping_lag: DEFAULT_MAX_MAPPING_LAG,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn gas_limit_is_capped() {
+		let limits = RpcLimits { gas_cap: 100, ..Default::default() };
+
+		assert_eq!(limits.gas_limit(None), Ok(100.into()));
+		assert_eq!(limits.gas_limit(Some(60.into())), Ok(60.into()));
+		assert!(limits.gas_limit(Some(101.into())).is_err());
+	}
+
+	#[test]
+	fn logs_range_is_limited() {
+		let limits = RpcLimits { max_logs_range: 10, ..Default::default() };
+
+		assert!(limits.check_logs_range(1, 10).is_ok());
+		assert!(limits.check_logs_range(5, 4).is_ok());
+		assert!(limits.check_logs_range(0, 10).is_err());
+	}
+
+	#[test]
+	fn mapping_lag_is_limited() {
+		let limits = RpcLimits { max_mapping_lag: 2, ..Default::default() };
+
+		assert!(limits.check_mapping_lag(0).is_ok());
+		assert!(limits.check_mapping_lag(2).is_ok());
+		assert!(limits.check_mapping_lag(3).is_err());
+	}
+}
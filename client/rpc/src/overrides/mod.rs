@@ -1,16 +1,20 @@
 use ethereum::Block as EthereumBlock;
 use ethereum_types::{H160, H256, U256};
 use sp_runtime::traits::Block as BlockT;
-use sp_api::{BlockId, ProvideRuntimeApi};
+use sp_api::{BlockId, ProvideRuntimeApi, ApiExt};
 use sp_io::hashing::{twox_128, blake2_128};
-use fp_rpc::TransactionStatus;
+use fp_rpc::{TransactionStatus, EIP658Receipt};
 use std::{marker::PhantomData, sync::Arc};
 use fp_rpc::EthereumRuntimeRPCApi;
 
 mod schema_v1_override;
+mod schema_v2_override;
+mod persisted_override;
 
 pub use fc_rpc_core::{EthApiServer, NetApiServer};
 pub use schema_v1_override::SchemaV1Override;
+pub use schema_v2_override::SchemaV2Override;
+pub use persisted_override::PersistedStorageOverride;
 
 /// Something that can fetch Ethereum-related data. This trait is quite similar to the runtime API,
 /// and indeed oe implementation of it uses the runtime API.
@@ -25,7 +29,7 @@ pub trait StorageOverride<Block: BlockT> {
 	/// Return the current block.
 	fn current_block(&self, block: &BlockId<Block>) -> Option<EthereumBlock>;
 	/// Return the current receipt.
-	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<ethereum::Receipt>>;
+	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<EIP658Receipt>>;
 	/// Return the current transaction status.
 	fn current_transaction_statuses(&self, block: &BlockId<Block>) -> Option<Vec<TransactionStatus>>;
 }
@@ -40,6 +44,24 @@ fn blake2_128_extend(bytes: &[u8]) -> Vec<u8> {
 	ext
 }
 
+/// Storage key of pallet_evm::AccountCodes for the given account address.
+pub(crate) fn account_code_key(address: H160) -> Vec<u8> {
+	let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountCodes");
+	key.extend(blake2_128_extend(address.as_bytes()));
+	key
+}
+
+/// Storage key of pallet_evm::AccountStorages for the given account address and index.
+pub(crate) fn account_storage_key(address: H160, index: U256) -> Vec<u8> {
+	let tmp: &mut [u8; 32] = &mut [0; 32];
+	index.to_little_endian(tmp);
+
+	let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountStorages");
+	key.extend(blake2_128_extend(address.as_bytes()));
+	key.extend(blake2_128_extend(tmp));
+	key
+}
+
 /// A wrapper type for the Runtime API. This type implements `StorageOverride`, so it can be used
 /// when calling the runtime API is desired but a `dyn StorageOverride` is required.
 pub struct RuntimeApiStorageOverride<B: BlockT, C> {
@@ -90,8 +112,17 @@ where
 	}
 
 	/// Return the current receipt.
-	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<ethereum::Receipt>> {
-		self.client.runtime_api().current_receipts(&block).ok()?
+	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<EIP658Receipt>> {
+		let api = self.client.runtime_api();
+		let api_version = api.api_version::<dyn EthereumRuntimeRPCApi<Block>>(&block).ok()??;
+
+		if api_version < 2 {
+			#[allow(deprecated)]
+			let legacy_receipts = api.current_receipts_before_version_2(&block).ok()??;
+			Some(EIP658Receipt::from_legacy(legacy_receipts))
+		} else {
+			api.current_receipts(&block).ok()?
+		}
 	}
 
 	/// Return the current transaction status.
@@ -0,0 +1,78 @@
+use ethereum::Block as EthereumBlock;
+use ethereum_types::{H160, H256, U256};
+use sp_runtime::traits::Block as BlockT;
+use sp_api::{BlockId, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use fp_rpc::{TransactionStatus, EIP658Receipt};
+use std::sync::Arc;
+use fp_rpc::EthereumRuntimeRPCApi;
+use super::{StorageOverride, RuntimeApiStorageOverride};
+
+/// Uses the runtime API, and falls back to the Ethereum data persisted in the Frontier database
+/// when the block state is not available anymore (i.e. it has been pruned).
+pub struct PersistedStorageOverride<B: BlockT, C> {
+	client: Arc<C>,
+	runtime_api: RuntimeApiStorageOverride<B, C>,
+	backend: Arc<fc_db::Backend<B>>,
+}
+
+impl<B, C> PersistedStorageOverride<B, C> where
+	C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: Send + Sync + 'static,
+{
+	pub fn new(client: Arc<C>, backend: Arc<fc_db::Backend<B>>) -> Self {
+		Self {
+			client: client.clone(),
+			runtime_api: RuntimeApiStorageOverride::new(client),
+			backend,
+		}
+	}
+
+	fn block_hash(&self, block: &BlockId<B>) -> Option<B::Hash> {
+		self.client.block_hash_from_id(block).ok()?
+	}
+}
+
+impl<Block, C> StorageOverride<Block> for PersistedStorageOverride<Block, C>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+	Block: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: Send + Sync + 'static,
+{
+	/// For a given account address, returns pallet_evm::AccountCodes.
+	fn account_code_at(&self, block: &BlockId<Block>, address: H160) -> Option<Vec<u8>> {
+		self.runtime_api.account_code_at(block, address)
+	}
+
+	/// For a given account address and index, returns pallet_evm::AccountStorages.
+	fn storage_at(&self, block: &BlockId<Block>, address: H160, index: U256) -> Option<H256> {
+		self.runtime_api.storage_at(block, address, index)
+	}
+
+	/// Return the current block.
+	fn current_block(&self, block: &BlockId<Block>) -> Option<EthereumBlock> {
+		self.runtime_api.current_block(block).or_else(|| {
+			let hash = self.block_hash(block)?;
+			self.backend.mapping().ethereum_block(&hash).ok()?
+		})
+	}
+
+	/// Return the current receipt.
+	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<EIP658Receipt>> {
+		self.runtime_api.current_receipts(block).or_else(|| {
+			let hash = self.block_hash(block)?;
+			self.backend.mapping().ethereum_receipts(&hash).ok()?
+		})
+	}
+
+	/// Return the current transaction status.
+	fn current_transaction_statuses(&self, block: &BlockId<Block>) -> Option<Vec<TransactionStatus>> {
+		self.runtime_api.current_transaction_statuses(block).or_else(|| {
+			let hash = self.block_hash(block)?;
+			self.backend.mapping().ethereum_transaction_statuses(&hash).ok()?
+		})
+	}
+}
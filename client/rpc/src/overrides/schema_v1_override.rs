@@ -7,9 +7,9 @@ use sp_storage::StorageKey;
 use codec::Decode;
 use sp_runtime::traits::{Block as BlockT, BlakeTwo256};
 use sp_api::BlockId;
-use fp_rpc::TransactionStatus;
+use fp_rpc::{TransactionStatus, EIP658Receipt};
 
-use super::{StorageOverride, storage_prefix_build, blake2_128_extend};
+use super::{StorageOverride, storage_prefix_build, account_code_key, account_storage_key};
 
 /// An override for runtimes that use Schema V1
 pub struct SchemaV1Override<B: BlockT, C, BE> {
@@ -39,7 +39,7 @@ impl<B, C, BE> SchemaV1Override<B, C, BE> where
 	// 	Decode::decode(&mut &raw_data.0[..]).map_err(|_| "Could not decode data".into())
 	// }
 
-	fn query_storage<T: Decode>(&self, id: &BlockId<B>, key: &StorageKey) -> Option<T> {
+	pub(crate) fn query_storage<T: Decode>(&self, id: &BlockId<B>, key: &StorageKey) -> Option<T> {
 		if let Ok(Some(data)) = self.client.storage(
 			id,
 			key
@@ -65,26 +65,17 @@ where
 {
 	/// For a given account address, returns pallet_evm::AccountCodes.
 	fn account_code_at(&self, block: &BlockId<Block>, address: H160) -> Option<Vec<u8>> {
-		let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountCodes");
-		key.extend(blake2_128_extend(address.as_bytes()));
 		self.query_storage::<Vec<u8>>(
 			block,
-			&StorageKey(key)
+			&StorageKey(account_code_key(address))
 		)
 	}
 
 	/// For a given account address and index, returns pallet_evm::AccountStorages.
 	fn storage_at(&self, block: &BlockId<Block>, address: H160, index: U256) -> Option<H256> {
-		let tmp: &mut [u8; 32] = &mut [0; 32];
-		index.to_little_endian(tmp);
-
-		let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountStorages");
-		key.extend(blake2_128_extend(address.as_bytes()));
-		key.extend(blake2_128_extend(tmp));
-
 		self.query_storage::<H256>(
 			block,
-			&StorageKey(key)
+			&StorageKey(account_storage_key(address, index))
 		)
 	}
 
@@ -99,13 +90,13 @@ where
 	}
 
 	/// Return the current receipt.
-	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<ethereum::Receipt>> {
+	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<EIP658Receipt>> {
 		self.query_storage::<Vec<ethereum::Receipt>>(
 			block,
 			&StorageKey(
 				storage_prefix_build(b"Ethereum", b"CurrentReceipts")
 			)
-		)
+		).map(EIP658Receipt::from_legacy)
 	}
 
 	/// Return the current transaction status.
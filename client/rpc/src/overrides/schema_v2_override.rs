@@ -0,0 +1,66 @@
+use ethereum::Block as EthereumBlock;
+use ethereum_types::{H160, H256, U256};
+use std::sync::Arc;
+use sc_client_api::backend::{StorageProvider, Backend, StateBackend, AuxStore};
+use sp_blockchain::{Error as BlockChainError, HeaderMetadata, HeaderBackend};
+use sp_storage::StorageKey;
+use sp_runtime::traits::{Block as BlockT, BlakeTwo256};
+use sp_api::BlockId;
+use fp_rpc::{TransactionStatus, EIP658Receipt};
+
+use super::{StorageOverride, SchemaV1Override, storage_prefix_build};
+
+/// An override for runtimes that use Schema V2.
+///
+/// Schema V2 only changes the format of `CurrentReceipts`, everything else is read as in V1.
+pub struct SchemaV2Override<B: BlockT, C, BE> {
+	v1: SchemaV1Override<B, C, BE>,
+}
+
+impl<B: BlockT, C, BE> SchemaV2Override<B, C, BE> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self { v1: SchemaV1Override::new(client) }
+	}
+}
+
+impl<Block, C, BE> StorageOverride<Block> for SchemaV2Override<Block, C, BE>
+where
+	C: StorageProvider<Block, BE>,
+	C: AuxStore,
+	C: HeaderBackend<Block>,
+	C: HeaderMetadata<Block, Error=BlockChainError> + 'static,
+	BE: Backend<Block> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+	Block: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: Send + Sync + 'static,
+{
+	/// For a given account address, returns pallet_evm::AccountCodes.
+	fn account_code_at(&self, block: &BlockId<Block>, address: H160) -> Option<Vec<u8>> {
+		self.v1.account_code_at(block, address)
+	}
+
+	/// For a given account address and index, returns pallet_evm::AccountStorages.
+	fn storage_at(&self, block: &BlockId<Block>, address: H160, index: U256) -> Option<H256> {
+		self.v1.storage_at(block, address, index)
+	}
+
+	/// Return the current block.
+	fn current_block(&self, block: &BlockId<Block>) -> Option<EthereumBlock> {
+		self.v1.current_block(block)
+	}
+
+	/// Return the current receipt.
+	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<EIP658Receipt>> {
+		self.v1.query_storage::<Vec<EIP658Receipt>>(
+			block,
+			&StorageKey(
+				storage_prefix_build(b"Ethereum", b"CurrentReceipts")
+			)
+		)
+	}
+
+	/// Return the current transaction status.
+	fn current_transaction_statuses(&self, block: &BlockId<Block>) -> Option<Vec<TransactionStatus>> {
+		self.v1.current_transaction_statuses(block)
+	}
+}
@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use ethereum_types::{H160, H256};
+use jsonrpc_core::{BoxFuture, Result, futures::future};
+use rustc_hex::FromHex;
+use sc_rpc_api::DenyUnsafe;
+use fc_rpc_core::{EthApi as EthApiT, PersonalApi as PersonalApiT};
+use fc_rpc_core::types::{Bytes, ExtAccountInfo, RecoveredAccount, TransactionRequest};
+use crate::{
+	EthKeystore, fill_transaction_request, internal_err, personal_message_hash, recover_signer,
+	sign_hash, sign_transaction_message,
+};
+
+pub use fc_rpc_core::PersonalApiServer;
+
+/// Seconds an account stays unlocked when `personal_unlockAccount` is given no duration.
+const DEFAULT_UNLOCK_DURATION: u64 = 300;
+
+/// Implements the `personal` namespace on a keystore, submitting transactions through an
+/// Ethereum RPC.
+pub struct PersonalApi<E> {
+	eth: E,
+	keystore: Arc<EthKeystore>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<E> PersonalApi<E> {
+	pub fn new(eth: E, keystore: Arc<EthKeystore>, deny_unsafe: DenyUnsafe) -> Self {
+		Self { eth, keystore, deny_unsafe }
+	}
+}
+
+impl<E> PersonalApiT for PersonalApi<E> where E: EthApiT + Send + Sync + 'static {
+	fn list_accounts(&self) -> Result<Vec<H160>> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.keystore.accounts())
+	}
+
+	fn accounts_info(&self) -> Result<BTreeMap<H160, ExtAccountInfo>> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.keystore.key_files().into_iter().map(|(address, key_file)| {
+			(address, ExtAccountInfo {
+				name: key_file.name().unwrap_or_default().to_string(),
+				meta: key_file.meta().unwrap_or("{}").to_string(),
+				uuid: Some(key_file.id().to_string()),
+			})
+		}).collect())
+	}
+
+	fn new_account(&self, password: String) -> Result<H160> {
+		self.deny_unsafe.check_if_safe()?;
+		self.keystore.new_account(&password).map_err(internal_err)
+	}
+
+	fn import_raw_key(&self, key: String, password: String) -> Result<H160> {
+		self.deny_unsafe.check_if_safe()?;
+		let key: Vec<u8> = key.trim_start_matches("0x").from_hex()
+			.map_err(|_| internal_err("invalid hex key"))?;
+		let secret = secp256k1::SecretKey::parse_slice(&key)
+			.map_err(|_| internal_err("invalid secret key"))?;
+		self.keystore.import(&secret, &password).map_err(internal_err)
+	}
+
+	fn unlock_account(&self, address: H160, password: String, duration: Option<u64>) -> Result<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let duration = match duration.unwrap_or(DEFAULT_UNLOCK_DURATION) {
+			0 => None,
+			seconds => Some(Duration::from_secs(seconds)),
+		};
+		self.keystore.unlock(&address, &password, duration).map_err(internal_err)?;
+		Ok(true)
+	}
+
+	fn lock_account(&self, address: H160) -> Result<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.keystore.lock(&address))
+	}
+
+	fn sign(&self, data: Bytes, address: H160, password: String) -> Result<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		let secret = self.keystore.decrypt(&address, &password).map_err(internal_err)?;
+		Ok(Bytes::new(sign_hash(&secret, personal_message_hash(&data.0))?.to_vec()))
+	}
+
+	fn ec_recover(&self, data: Bytes, signature: Bytes) -> Result<H160> {
+		self.deny_unsafe.check_if_safe()?;
+		let (_, address) = recover_signer(personal_message_hash(&data.0), &signature.0)?;
+		Ok(address)
+	}
+
+	fn verify_signature(&self, data: Bytes, signature: Bytes) -> Result<RecoveredAccount> {
+		self.deny_unsafe.check_if_safe()?;
+		let (public_key, address) = recover_signer(personal_message_hash(&data.0), &signature.0)?;
+		let chain_id = self.eth.chain_id()?;
+		let is_valid_for_current_chain = match (signature.0.last(), chain_id) {
+			(Some(v), Some(chain_id)) if *v >= 35 => u64::from((v - 35) / 2) == chain_id.as_u64(),
+			_ => false,
+		};
+
+		Ok(RecoveredAccount { address, public_key, is_valid_for_current_chain })
+	}
+
+	fn send_transaction(&self, request: TransactionRequest, password: String) -> BoxFuture<H256> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(future::result(Err(err.into())));
+		}
+		let from = match request.from {
+			Some(from) => from,
+			None => return Box::new(future::result(Err(internal_err("missing transaction sender")))),
+		};
+		let transaction = self.keystore.decrypt(&from, &password)
+			.map_err(internal_err)
+			.and_then(|secret| sign_transaction_message(&secret, fill_transaction_request(&self.eth, from, request)?));
+
+		match transaction {
+			Ok(transaction) => self.eth.send_raw_transaction(Bytes::new(rlp::encode(&transaction).to_vec())),
+			Err(err) => Box::new(future::result(Err(err))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+	use crate::secret_address;
+
+	#[test]
+	fn signs_and_recovers_personal_messages() {
+		let hash = personal_message_hash(b"hello");
+		assert_eq!(
+			hash,
+			H256::from_str("50b2c43fd39106bafbba0da34fc430e1f91e3c96ea2acee2bc34119f92b37750").unwrap(),
+		);
+
+		let secret = secp256k1::SecretKey::parse(&[0x11; 32]).unwrap();
+		let signature = sign_hash(&secret, hash).unwrap();
+		assert!(signature[64] == 27 || signature[64] == 28);
+
+		let (_, address) = recover_signer(hash, &signature).unwrap();
+		assert_eq!(address, secret_address(&secret));
+
+		let mut raw_v = signature;
+		raw_v[64] -= 27;
+		assert_eq!(recover_signer(hash, &raw_v).unwrap().1, address);
+
+		assert!(recover_signer(hash, &signature[..64]).is_err());
+		assert_ne!(recover_signer(personal_message_hash(b"hellO"), &signature).map(|(_, a)| a).ok(), Some(address));
+	}
+}
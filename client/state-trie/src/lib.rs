@@ -0,0 +1,611 @@
+//! Ethereum-compatible state trie of EVM accounts.
+//!
+//! The Ethereum block `state_root` produced by the runtime is the Substrate storage root, which
+//! no Ethereum tool can verify an account against. This crate keeps, off-chain and in its own
+//! database, a secure Merkle Patricia trie of EVM accounts (nonce, balance, storage root, code
+//! hash) with one storage trie per account. The tries are updated block by block from the
+//! accounts and storage entries that `pallet-evm` records as touched, which the runtime must
+//! enable with `TrackTouchedAccounts`. Balance changes made outside of the EVM, such as
+//! transfers, fees or rewards, are recorded as long as the balances are kept in a
+//! `TrackedAccountStore` and the EVM has seen the address of the account once.
+//!
+//! The trie is built from genesis and needs the state of every block, so it is only available
+//! on archive nodes.
+
+mod node_codec;
+mod overlay;
+mod utils;
+mod worker;
+
+pub use node_codec::{EthereumLayout, RlpNodeCodec};
+pub use fc_db::{DatabaseSettings, DatabaseSettingsSrc};
+pub use sp_database::Database;
+pub use worker::StateTrieWorker;
+
+use std::{sync::Arc, marker::PhantomData, collections::BTreeMap};
+use codec::{Encode, Decode};
+use parking_lot::Mutex;
+use rlp::{Encodable, Decodable, RlpStream, Rlp, DecoderError};
+use sha3::{Keccak256, Digest};
+use sp_core::{H160, H256, U256, hashing::twox_128};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, Zero, SaturatedConversion},
+};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_storage::StorageKey;
+use sc_client_api::backend::StorageProvider;
+use trie_db::{Trie, TrieMut, TrieDB, TrieDBMut};
+use fp_rpc::EthereumRuntimeRPCApi;
+use overlay::{TrieOverlay, ProofRecorder};
+
+const DB_HASH_LEN: usize = 32;
+/// Hash type that this backend uses for the database.
+pub type DbHash = [u8; DB_HASH_LEN];
+
+pub(crate) mod columns {
+	pub const NUM_COLUMNS: u32 = 2;
+
+	/// Trie nodes, keyed by their hash.
+	pub const NODES: u32 = 0;
+	/// State root of every synced block, keyed by the Substrate block hash, and the
+	/// `static_keys`.
+	pub const ROOTS: u32 = 1;
+}
+
+pub(crate) mod static_keys {
+	pub const BEST_SYNCED_NUMBER: &[u8] = b"BEST_SYNCED_NUMBER";
+}
+
+/// Root of an empty trie, `keccak256(rlp(""))`.
+pub const EMPTY_TRIE_ROOT: H256 = H256([
+	0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+	0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+/// Hash of empty code, `keccak256("")`.
+pub const EMPTY_CODE_HASH: H256 = H256([
+	0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+	0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+]);
+
+fn keccak_256(data: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+/// An account as stored in the Ethereum state trie.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EthereumAccount {
+	pub nonce: U256,
+	pub balance: U256,
+	pub storage_root: H256,
+	pub code_hash: H256,
+}
+
+impl Default for EthereumAccount {
+	fn default() -> Self {
+		Self {
+			nonce: U256::zero(),
+			balance: U256::zero(),
+			storage_root: EMPTY_TRIE_ROOT,
+			code_hash: EMPTY_CODE_HASH,
+		}
+	}
+}
+
+impl EthereumAccount {
+	/// Whether the account is empty in the sense of EIP-161, and is not kept in the trie.
+	pub fn is_empty(&self) -> bool {
+		self.nonce.is_zero() && self.balance.is_zero() && self.code_hash == EMPTY_CODE_HASH
+	}
+}
+
+impl Encodable for EthereumAccount {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(4);
+		s.append(&self.nonce);
+		s.append(&self.balance);
+		s.append(&self.storage_root);
+		s.append(&self.code_hash);
+	}
+}
+
+impl Decodable for EthereumAccount {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		Ok(Self {
+			nonce: rlp.val_at(0)?,
+			balance: rlp.val_at(1)?,
+			storage_root: rlp.val_at(2)?,
+			code_hash: rlp.val_at(3)?,
+		})
+	}
+}
+
+/// State of one account touched by a block, read after the block was executed.
+#[derive(Clone, Debug, Default)]
+pub struct AccountChange {
+	pub address: H160,
+	pub nonce: U256,
+	pub balance: U256,
+	pub code_hash: H256,
+	/// Whether the account storage was wiped before `storage` was written.
+	pub reset_storage: bool,
+	/// Current value of the storage entries written by the block.
+	pub storage: Vec<(H256, H256)>,
+}
+
+/// Proof of one storage entry of an account.
+#[derive(Clone, Debug)]
+pub struct StorageProof {
+	pub key: H256,
+	pub value: U256,
+	/// RLP-encoded storage trie nodes from the storage root to the entry.
+	pub proof: Vec<Vec<u8>>,
+}
+
+/// Proof of an account and some of its storage entries.
+#[derive(Clone, Debug)]
+pub struct AccountProof {
+	pub account: EthereumAccount,
+	/// RLP-encoded state trie nodes from the state root to the account.
+	pub proof: Vec<Vec<u8>>,
+	pub storage_proofs: Vec<StorageProof>,
+}
+
+pub struct Backend<Block: BlockT> {
+	db: Arc<dyn Database<DbHash>>,
+	write_lock: Arc<Mutex<()>>,
+	_marker: PhantomData<Block>,
+}
+
+impl<Block: BlockT> Backend<Block> {
+	pub fn new(config: &DatabaseSettings) -> Result<Self, String> {
+		Ok(Self {
+			db: utils::open_database(config)?,
+			write_lock: Arc::new(Mutex::new(())),
+			_marker: PhantomData,
+		})
+	}
+
+	/// Ethereum state root after the given block, if the block was synced.
+	pub fn state_root(&self, block_hash: &Block::Hash) -> Result<Option<H256>, String> {
+		match self.db.get(crate::columns::ROOTS, &block_hash.encode()) {
+			Some(raw) => Ok(Some(H256::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+			None => Ok(None),
+		}
+	}
+
+	/// Number of the highest block synced so far.
+	pub fn best_synced_number(&self) -> Result<Option<u64>, String> {
+		match self.db.get(crate::columns::ROOTS, crate::static_keys::BEST_SYNCED_NUMBER) {
+			Some(raw) => Ok(Some(u64::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e))?)),
+			None => Ok(None),
+		}
+	}
+
+	fn note_synced_number(&self, number: u64) -> Result<(), String> {
+		if self.best_synced_number()?.map_or(false, |best| best >= number) {
+			return Ok(())
+		}
+
+		let mut transaction = sp_database::Transaction::new();
+		transaction.set(crate::columns::ROOTS, crate::static_keys::BEST_SYNCED_NUMBER, &number.encode());
+		self.db.commit(transaction).map_err(|e| format!("{:?}", e))
+	}
+
+	/// Account stored under the given state root.
+	pub fn account(&self, state_root: &H256, address: H160) -> Result<Option<EthereumAccount>, String> {
+		let overlay = TrieOverlay::new(&*self.db);
+		let trie = TrieDB::<EthereumLayout>::new(&overlay, state_root)
+			.map_err(|e| format!("{:?}", e))?;
+
+		decode_account(trie.get(keccak_256(address.as_bytes()).as_bytes()))
+	}
+
+	/// Build the proof of an account and the given storage entries after the given block.
+	pub fn prove(
+		&self,
+		block_hash: &Block::Hash,
+		address: H160,
+		storage_keys: &[H256],
+	) -> Result<Option<AccountProof>, String> {
+		let state_root = match self.state_root(block_hash)? {
+			Some(state_root) => state_root,
+			None => return Ok(None),
+		};
+
+		let recorder = ProofRecorder::new(&*self.db);
+		let account = {
+			let trie = TrieDB::<EthereumLayout>::new(&recorder, &state_root)
+				.map_err(|e| format!("{:?}", e))?;
+			decode_account(trie.get(keccak_256(address.as_bytes()).as_bytes()))?
+				.unwrap_or_default()
+		};
+		let proof = recorder.drain();
+
+		let mut storage_proofs = Vec::with_capacity(storage_keys.len());
+		for key in storage_keys {
+			let trie = TrieDB::<EthereumLayout>::new(&recorder, &account.storage_root)
+				.map_err(|e| format!("{:?}", e))?;
+			let value = match trie.get(keccak_256(key.as_bytes()).as_bytes())
+				.map_err(|e| format!("{:?}", e))?
+			{
+				Some(raw) => rlp::decode::<U256>(&raw).map_err(|e| format!("{:?}", e))?,
+				None => U256::zero(),
+			};
+
+			storage_proofs.push(StorageProof {
+				key: *key,
+				value,
+				proof: recorder.drain(),
+			});
+		}
+
+		Ok(Some(AccountProof { account, proof, storage_proofs }))
+	}
+
+	/// Apply the accounts touched by a block on top of its parent state root, and store the
+	/// resulting state root for the block.
+	pub fn apply_block(
+		&self,
+		block_hash: &Block::Hash,
+		parent_root: H256,
+		changes: Vec<AccountChange>,
+	) -> Result<H256, String> {
+		let _lock = self.write_lock.lock();
+
+		let mut overlay = TrieOverlay::new(&*self.db);
+		let mut root = parent_root;
+
+		let mut accounts = Vec::with_capacity(changes.len());
+		for change in changes {
+			let key = keccak_256(change.address.as_bytes());
+
+			let existing = {
+				let trie = TrieDB::<EthereumLayout>::new(&overlay, &root)
+					.map_err(|e| format!("{:?}", e))?;
+				decode_account(trie.get(key.as_bytes()))?
+			};
+
+			let mut storage_root = match existing {
+				Some(account) if !change.reset_storage => account.storage_root,
+				_ => EMPTY_TRIE_ROOT,
+			};
+
+			if !change.storage.is_empty() {
+				let mut trie = TrieDBMut::<EthereumLayout>::from_existing(&mut overlay, &mut storage_root)
+					.map_err(|e| format!("{:?}", e))?;
+
+				for (index, value) in change.storage {
+					let index = keccak_256(index.as_bytes());
+					if value.is_zero() {
+						trie.remove(index.as_bytes()).map_err(|e| format!("{:?}", e))?;
+					} else {
+						let value = rlp::encode(&U256::from_big_endian(value.as_bytes()));
+						trie.insert(index.as_bytes(), &value).map_err(|e| format!("{:?}", e))?;
+					}
+				}
+			}
+
+			accounts.push((key, EthereumAccount {
+				nonce: change.nonce,
+				balance: change.balance,
+				storage_root,
+				code_hash: change.code_hash,
+			}));
+		}
+
+		{
+			let mut trie = TrieDBMut::<EthereumLayout>::from_existing(&mut overlay, &mut root)
+				.map_err(|e| format!("{:?}", e))?;
+
+			for (key, account) in accounts {
+				if account.is_empty() {
+					trie.remove(key.as_bytes()).map_err(|e| format!("{:?}", e))?;
+				} else {
+					trie.insert(key.as_bytes(), &rlp::encode(&account))
+						.map_err(|e| format!("{:?}", e))?;
+				}
+			}
+		}
+
+		let mut transaction = sp_database::Transaction::new();
+		overlay.drain_into(&mut transaction);
+		transaction.set(crate::columns::ROOTS, &block_hash.encode(), &root.encode());
+
+		self.db.commit(transaction).map_err(|e| format!("{:?}", e))?;
+
+		Ok(root)
+	}
+}
+
+fn decode_account<E: std::fmt::Debug>(
+	raw: Result<Option<trie_db::DBValue>, E>,
+) -> Result<Option<EthereumAccount>, String> {
+	match raw.map_err(|e| format!("{:?}", e))? {
+		Some(raw) => Ok(Some(rlp::decode::<EthereumAccount>(&raw).map_err(|e| format!("{:?}", e))?)),
+		None => Ok(None),
+	}
+}
+
+/// Entries of an `EVM` storage map at the given block, by key without the map prefix.
+fn evm_map<Block: BlockT, C, BE>(
+	client: &C,
+	id: &BlockId<Block>,
+	map: &[u8],
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>, String> where
+	C: StorageProvider<Block, BE>,
+	BE: sc_client_api::Backend<Block>,
+{
+	let prefix = [twox_128(b"EVM"), twox_128(map)].concat();
+
+	Ok(client.storage_pairs(id, &StorageKey(prefix.clone()))
+		.map_err(|e| format!("{:?}", e))?
+		.into_iter()
+		.map(|(key, value)| (key.0[prefix.len()..].to_vec(), value.0))
+		.collect())
+}
+
+/// Key of a `blake2_128_concat` map entry, decoded from the end of `key`.
+fn concat_key<T: Decode>(key: &[u8]) -> Result<T, String> {
+	key.get(16..)
+		.ok_or_else(|| "Storage key too short".to_string())
+		.and_then(|raw| T::decode(&mut &raw[..]).map_err(|e| format!("{:?}", e)))
+}
+
+/// Read the accounts touched by a block from its post state.
+pub fn block_changes<Block: BlockT, C, BE>(
+	client: &C,
+	id: &BlockId<Block>,
+) -> Result<Vec<AccountChange>, String> where
+	C: ProvideRuntimeApi<Block> + StorageProvider<Block, BE>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+	BE: sc_client_api::Backend<Block>,
+{
+	let mut changes = BTreeMap::<H160, AccountChange>::new();
+	for (key, value) in evm_map(client, id, b"TouchedAccounts")? {
+		let address = concat_key::<H160>(&key)?;
+		let reset_storage = bool::decode(&mut &value[..]).map_err(|e| format!("{:?}", e))?;
+		changes.insert(address, AccountChange { address, reset_storage, ..Default::default() });
+	}
+
+	let mut storages = Vec::new();
+	for (key, _) in evm_map(client, id, b"TouchedStorages")? {
+		let address = concat_key::<H160>(&key)?;
+		// The second key follows the 16 byte hash and the 20 byte address of the first one.
+		let index = concat_key::<H256>(key.get(36..).unwrap_or_default())?;
+		storages.push((address, index));
+	}
+
+	let api = client.runtime_api();
+	for (address, index) in storages {
+		let value = api.storage_at(id, address, U256::from_big_endian(index.as_bytes()))
+			.map_err(|e| format!("{:?}", e))?;
+		changes.entry(address)
+			.or_insert_with(|| AccountChange { address, ..Default::default() })
+			.storage.push((index, value));
+	}
+
+	for change in changes.values_mut() {
+		let basic = api.account_basic(id, change.address).map_err(|e| format!("{:?}", e))?;
+		let code = api.account_code_at(id, change.address).map_err(|e| format!("{:?}", e))?;
+
+		change.nonce = basic.nonce;
+		change.balance = basic.balance;
+		change.code_hash = if code.is_empty() { EMPTY_CODE_HASH } else { keccak_256(&code) };
+	}
+
+	Ok(changes.into_iter().map(|(_, change)| change).collect())
+}
+
+pub fn sync_block<Block: BlockT, C, BE>(
+	client: &C,
+	backend: &Backend<Block>,
+	header: &Block::Header,
+) -> Result<H256, String> where
+	C: ProvideRuntimeApi<Block> + StorageProvider<Block, BE>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+	BE: sc_client_api::Backend<Block>,
+{
+	let parent_root = if header.number().is_zero() {
+		EMPTY_TRIE_ROOT
+	} else {
+		backend.state_root(header.parent_hash())?
+			.ok_or("Parent state root not found".to_string())?
+	};
+
+	let changes = block_changes(client, &BlockId::Hash(header.hash()))?;
+
+	let root = backend.apply_block(&header.hash(), parent_root, changes)?;
+	backend.note_synced_number((*header.number()).saturated_into())?;
+
+	Ok(root)
+}
+
+/// Hashes of the blocks from `hash` back to the latest ancestor whose state root is known,
+/// newest first, or `None` if there are more than `limit` of them.
+pub fn unsynced_blocks<Block: BlockT, B>(
+	substrate_backend: &B,
+	backend: &Backend<Block>,
+	hash: Block::Hash,
+	limit: usize,
+) -> Result<Option<Vec<Block::Hash>>, String> where
+	B: HeaderBackend<Block>,
+{
+	let mut unsynced = Vec::new();
+	let mut current = hash;
+
+	while backend.state_root(&current)?.is_none() {
+		if unsynced.len() >= limit {
+			return Ok(None)
+		}
+
+		let header = substrate_backend.header(BlockId::Hash(current))
+			.map_err(|e| format!("{:?}", e))?
+			.ok_or("Header not found".to_string())?;
+
+		unsynced.push(current);
+
+		if header.number().is_zero() {
+			break
+		}
+		current = *header.parent_hash();
+	}
+
+	Ok(Some(unsynced))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hex_literal::hex;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	fn open_backend() -> (Backend<Block>, std::path::PathBuf) {
+		let path = std::env::temp_dir().join(format!(
+			"fc-state-trie-{}-{}",
+			std::process::id(),
+			unique_suffix(),
+		));
+		let backend = Backend::<Block>::new(&DatabaseSettings {
+			source: DatabaseSettingsSrc::RocksDb { path: path.clone(), cache_size: 0 },
+		}).expect("database opens");
+		(backend, path)
+	}
+
+	fn unique_suffix() -> u128 {
+		std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.expect("time after epoch")
+			.as_nanos()
+	}
+
+	#[test]
+	fn decodes_touched_storage_keys() {
+		let address = H160::repeat_byte(0x11);
+		let index = H256::repeat_byte(0x22);
+		let key = [
+			&[0xaa; 16][..], address.as_bytes(),
+			&[0xbb; 16][..], index.as_bytes(),
+		].concat();
+
+		assert_eq!(concat_key::<H160>(&key).unwrap(), address);
+		assert_eq!(concat_key::<H256>(&key[36..]).unwrap(), index);
+		assert!(concat_key::<H160>(&key[..20]).is_err());
+	}
+
+	#[test]
+	fn empty_block_keeps_empty_root() {
+		let (backend, path) = open_backend();
+
+		let root = backend.apply_block(&H256::repeat_byte(1), EMPTY_TRIE_ROOT, Vec::new()).unwrap();
+		assert_eq!(root, EMPTY_TRIE_ROOT);
+		assert_eq!(backend.state_root(&H256::repeat_byte(1)).unwrap(), Some(EMPTY_TRIE_ROOT));
+
+		std::fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn best_synced_number_only_grows() {
+		let (backend, path) = open_backend();
+
+		assert_eq!(backend.best_synced_number().unwrap(), None);
+		backend.note_synced_number(5).unwrap();
+		backend.note_synced_number(3).unwrap();
+		assert_eq!(backend.best_synced_number().unwrap(), Some(5));
+		assert_eq!(backend.state_root(&H256::repeat_byte(5)).unwrap(), None);
+
+		std::fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn state_root_matches_ethereum() {
+		let (backend, path) = open_backend();
+
+		// A single account with one wei, as in the genesis of an Ethereum test network.
+		let address = H160::from(hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b"));
+		let change = AccountChange {
+			address,
+			nonce: U256::zero(),
+			balance: U256::from(1),
+			code_hash: EMPTY_CODE_HASH,
+			reset_storage: false,
+			storage: Vec::new(),
+		};
+
+		let root = backend.apply_block(&H256::repeat_byte(1), EMPTY_TRIE_ROOT, vec![change]).unwrap();
+
+		let account = EthereumAccount { balance: U256::from(1), ..Default::default() };
+		let key = keccak_256(address.as_bytes());
+		let expected = keccak_256(&rlp::encode_list::<Vec<u8>, _>(&[
+			{
+				let mut partial = vec![0x20];
+				partial.extend_from_slice(key.as_bytes());
+				partial
+			},
+			rlp::encode(&account).to_vec(),
+		]));
+		assert_eq!(root, expected);
+
+		std::fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn proofs_follow_storage_changes() {
+		let (backend, path) = open_backend();
+
+		let address = H160::repeat_byte(0x11);
+		let index = H256::repeat_byte(0x22);
+		let mut change = AccountChange {
+			address,
+			nonce: U256::from(1),
+			balance: U256::zero(),
+			code_hash: keccak_256(&[0x00]),
+			reset_storage: false,
+			storage: vec![(index, H256::from_low_u64_be(42))],
+		};
+
+		let first = H256::repeat_byte(1);
+		let first_root = backend.apply_block(&first, EMPTY_TRIE_ROOT, vec![change.clone()]).unwrap();
+
+		let proof = backend.prove(&first, address, &[index]).unwrap().unwrap();
+		assert_eq!(proof.account.nonce, U256::from(1));
+		assert_ne!(proof.account.storage_root, EMPTY_TRIE_ROOT);
+		assert_eq!(proof.proof.len(), 1);
+		assert_eq!(keccak_256(&proof.proof[0]), first_root);
+		assert_eq!(proof.storage_proofs[0].value, U256::from(42));
+		assert_eq!(keccak_256(&proof.storage_proofs[0].proof[0]), proof.account.storage_root);
+
+		// Clearing the only storage entry empties the storage trie, while the state of the
+		// previous block stays queryable.
+		change.storage = vec![(index, H256::zero())];
+		let second = H256::repeat_byte(2);
+		backend.apply_block(&second, first_root, vec![change]).unwrap();
+
+		let proof = backend.prove(&second, address, &[index]).unwrap().unwrap();
+		assert_eq!(proof.account.storage_root, EMPTY_TRIE_ROOT);
+		assert_eq!(proof.storage_proofs[0].value, U256::zero());
+		assert!(proof.storage_proofs[0].proof.is_empty());
+		assert_eq!(
+			backend.prove(&first, address, &[index]).unwrap().unwrap().storage_proofs[0].value,
+			U256::from(42),
+		);
+
+		// Removing the account entirely.
+		let third = H256::repeat_byte(3);
+		let root = backend.apply_block(&third, first_root, vec![AccountChange {
+			address,
+			nonce: U256::zero(),
+			balance: U256::zero(),
+			code_hash: EMPTY_CODE_HASH,
+			reset_storage: true,
+			storage: Vec::new(),
+		}]).unwrap();
+		assert_eq!(root, EMPTY_TRIE_ROOT);
+
+		std::fs::remove_dir_all(path).unwrap();
+	}
+}
@@ -0,0 +1,190 @@
+//! Ethereum node encoding for `trie-db`.
+//!
+//! Nodes are RLP lists, partial keys use hex-prefix encoding and children whose encoding is
+//! shorter than 32 bytes are inlined into their parent, as in the Ethereum yellow paper.
+
+use std::{borrow::Borrow, ops::Range};
+use hash_db::Hasher;
+use rlp::{DecoderError, Prototype, Rlp, RlpStream};
+use sp_core::H256;
+use trie_db::{
+	ChildReference, NodeCodec, Partial, TrieLayout,
+	node::{NibbleSlicePlan, NodeHandlePlan, NodePlan},
+};
+use pallet_ethereum::KeccakHasher;
+
+/// Layout of the Ethereum Merkle Patricia trie.
+pub struct EthereumLayout;
+
+impl TrieLayout for EthereumLayout {
+	const USE_EXTENSION: bool = true;
+	type Hash = KeccakHasher;
+	type Codec = RlpNodeCodec;
+}
+
+/// RLP node codec of the Ethereum Merkle Patricia trie.
+pub struct RlpNodeCodec;
+
+const HASHED_CHILD_LEN: usize = 32;
+const EMPTY_NODE: [u8; 1] = [0x80];
+
+/// Hex-prefix encode a right-aligned partial key.
+fn encode_partial(mut partial: impl Iterator<Item = u8>, number_nibble: usize, is_leaf: bool) -> Vec<u8> {
+	let flag = if is_leaf { 0x20 } else { 0x00 };
+	let mut encoded = Vec::with_capacity(number_nibble / 2 + 1);
+
+	if number_nibble % 2 == 1 {
+		// The first byte of an odd partial key only holds one nibble in its low half.
+		encoded.push(flag | 0x10 | partial.next().unwrap_or_default());
+	} else {
+		encoded.push(flag);
+	}
+	encoded.extend(partial);
+
+	encoded
+}
+
+fn append_child(stream: &mut RlpStream, child: &ChildReference<H256>) {
+	match child {
+		ChildReference::Hash(hash) => {
+			stream.append(hash);
+		},
+		ChildReference::Inline(data, len) => {
+			stream.append_raw(&data.as_ref()[..*len], 1);
+		},
+	}
+}
+
+fn payload_range(item: &Rlp, offset: usize) -> Result<Range<usize>, DecoderError> {
+	let info = item.payload_info()?;
+	let start = offset + info.header_len;
+	Ok(start..(start + info.value_len))
+}
+
+fn child_plan(item: &Rlp, offset: usize) -> Result<NodeHandlePlan, DecoderError> {
+	if item.is_data() && item.size() == HASHED_CHILD_LEN {
+		Ok(NodeHandlePlan::Hash(payload_range(item, offset)?))
+	} else {
+		Ok(NodeHandlePlan::Inline(offset..(offset + item.as_raw().len())))
+	}
+}
+
+impl NodeCodec for RlpNodeCodec {
+	type Error = DecoderError;
+	type HashOut = H256;
+
+	fn hashed_null_node() -> H256 {
+		KeccakHasher::hash(&EMPTY_NODE)
+	}
+
+	fn decode_plan(data: &[u8]) -> Result<NodePlan, Self::Error> {
+		let rlp = Rlp::new(data);
+
+		match rlp.prototype()? {
+			// Leaf or extension, told apart by the hex-prefix flag of the partial key.
+			Prototype::List(2) => {
+				let (partial_rlp, partial_offset) = rlp.at_with_offset(0)?;
+				let partial_range = payload_range(&partial_rlp, partial_offset)?;
+				let flag = *data.get(partial_range.start).ok_or(DecoderError::RlpIsTooShort)?;
+				let partial = NibbleSlicePlan::new(
+					partial_range,
+					if flag & 0x10 == 0x10 { 1 } else { 2 },
+				);
+
+				let (item, offset) = rlp.at_with_offset(1)?;
+				if flag & 0x20 == 0x20 {
+					Ok(NodePlan::Leaf { partial, value: payload_range(&item, offset)? })
+				} else {
+					Ok(NodePlan::Extension { partial, child: child_plan(&item, offset)? })
+				}
+			},
+			// Branch, with sixteen children followed by an optional value.
+			Prototype::List(17) => {
+				let mut children = [
+					None, None, None, None, None, None, None, None,
+					None, None, None, None, None, None, None, None,
+				];
+				for (index, child) in children.iter_mut().enumerate() {
+					let (item, offset) = rlp.at_with_offset(index)?;
+					if !item.is_empty() {
+						*child = Some(child_plan(&item, offset)?);
+					}
+				}
+
+				let (item, offset) = rlp.at_with_offset(16)?;
+				let value = if item.is_empty() {
+					None
+				} else {
+					Some(payload_range(&item, offset)?)
+				};
+
+				Ok(NodePlan::Branch { value, children })
+			},
+			Prototype::Data(0) => Ok(NodePlan::Empty),
+			_ => Err(DecoderError::Custom("Invalid trie node")),
+		}
+	}
+
+	fn is_empty_node(data: &[u8]) -> bool {
+		data == &EMPTY_NODE[..]
+	}
+
+	fn empty_node() -> &'static [u8] {
+		&EMPTY_NODE
+	}
+
+	fn leaf_node(partial: Partial, value: &[u8]) -> Vec<u8> {
+		let ((number_encoded, first), rest) = partial;
+		let number_nibble = number_encoded as usize + rest.len() * 2;
+		let partial = (0..number_encoded).map(|_| first).chain(rest.iter().cloned());
+
+		let mut stream = RlpStream::new_list(2);
+		stream.append(&encode_partial(partial, number_nibble, true));
+		stream.append_iter(value.iter().cloned());
+		stream.out().to_vec()
+	}
+
+	fn extension_node(
+		partial: impl Iterator<Item = u8>,
+		number_nibble: usize,
+		child: ChildReference<H256>,
+	) -> Vec<u8> {
+		let mut stream = RlpStream::new_list(2);
+		stream.append(&encode_partial(partial, number_nibble, false));
+		append_child(&mut stream, &child);
+		stream.out().to_vec()
+	}
+
+	fn branch_node(
+		children: impl Iterator<Item = impl Borrow<Option<ChildReference<H256>>>>,
+		value: Option<&[u8]>,
+	) -> Vec<u8> {
+		let mut stream = RlpStream::new_list(17);
+		for child in children {
+			match child.borrow() {
+				Some(child) => append_child(&mut stream, child),
+				None => {
+					stream.append_empty_data();
+				},
+			}
+		}
+		match value {
+			Some(value) => {
+				stream.append_iter(value.iter().cloned());
+			},
+			None => {
+				stream.append_empty_data();
+			},
+		}
+		stream.out().to_vec()
+	}
+
+	fn branch_node_nibbled(
+		_partial: impl Iterator<Item = u8>,
+		_number_nibble: usize,
+		_children: impl Iterator<Item = impl Borrow<Option<ChildReference<H256>>>>,
+		_value: Option<&[u8]>,
+	) -> Vec<u8> {
+		unreachable!("Ethereum tries use extension nodes; qed")
+	}
+}
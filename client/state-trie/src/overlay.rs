@@ -0,0 +1,133 @@
+use std::collections::{HashMap, hash_map::Entry};
+use hash_db::{AsHashDB, HashDB, HashDBRef, Hasher, Prefix};
+use parking_lot::Mutex;
+use sp_core::H256;
+use trie_db::DBValue;
+use pallet_ethereum::KeccakHasher;
+use crate::{Database, DbHash, EMPTY_TRIE_ROOT};
+
+fn get_node(db: &dyn Database<DbHash>, key: &H256) -> Option<DBValue> {
+	if key == &EMPTY_TRIE_ROOT {
+		return Some(vec![0x80])
+	}
+
+	db.get(crate::columns::NODES, key.as_bytes())
+}
+
+/// Trie nodes written while applying a block, on top of the nodes already in the database.
+///
+/// Nodes are never removed from the database: they are content-addressed and the trie of every
+/// synced block stays queryable.
+pub struct TrieOverlay<'a> {
+	db: &'a dyn Database<DbHash>,
+	pending: HashMap<H256, (DBValue, i32)>,
+}
+
+impl<'a> TrieOverlay<'a> {
+	pub fn new(db: &'a dyn Database<DbHash>) -> Self {
+		Self { db, pending: HashMap::new() }
+	}
+
+	/// Move the nodes still referenced into a database transaction.
+	pub fn drain_into(self, transaction: &mut sp_database::Transaction<DbHash>) {
+		for (key, (value, rc)) in self.pending {
+			if rc > 0 {
+				transaction.set_from_vec(crate::columns::NODES, key.as_bytes(), value);
+			}
+		}
+	}
+}
+
+impl<'a> HashDB<KeccakHasher, DBValue> for TrieOverlay<'a> {
+	fn get(&self, key: &H256, _prefix: Prefix) -> Option<DBValue> {
+		match self.pending.get(key) {
+			Some((value, rc)) if *rc > 0 => Some(value.clone()),
+			_ => get_node(self.db, key),
+		}
+	}
+
+	fn contains(&self, key: &H256, prefix: Prefix) -> bool {
+		HashDB::get(self, key, prefix).is_some()
+	}
+
+	fn insert(&mut self, prefix: Prefix, value: &[u8]) -> H256 {
+		let key = KeccakHasher::hash(value);
+		self.emplace(key, prefix, value.to_vec());
+		key
+	}
+
+	fn emplace(&mut self, key: H256, _prefix: Prefix, value: DBValue) {
+		match self.pending.entry(key) {
+			Entry::Occupied(mut entry) => {
+				let (existing, rc) = entry.get_mut();
+				if *rc <= 0 {
+					*existing = value;
+				}
+				*rc += 1;
+			},
+			Entry::Vacant(entry) => {
+				entry.insert((value, 1));
+			},
+		}
+	}
+
+	fn remove(&mut self, key: &H256, _prefix: Prefix) {
+		if let Some((_, rc)) = self.pending.get_mut(key) {
+			*rc -= 1;
+		}
+	}
+}
+
+impl<'a> HashDBRef<KeccakHasher, DBValue> for TrieOverlay<'a> {
+	fn get(&self, key: &H256, prefix: Prefix) -> Option<DBValue> {
+		HashDB::get(self, key, prefix)
+	}
+
+	fn contains(&self, key: &H256, prefix: Prefix) -> bool {
+		HashDB::contains(self, key, prefix)
+	}
+}
+
+impl<'a> AsHashDB<KeccakHasher, DBValue> for TrieOverlay<'a> {
+	fn as_hash_db(&self) -> &dyn HashDB<KeccakHasher, DBValue> {
+		self
+	}
+
+	fn as_hash_db_mut<'b>(&'b mut self) -> &'b mut (dyn HashDB<KeccakHasher, DBValue> + 'b) {
+		self
+	}
+}
+
+/// Read-only view of the database recording every node fetched, in order. A single lookup
+/// fetches the nodes on the path from the root to the key, which is an Ethereum proof.
+pub struct ProofRecorder<'a> {
+	db: &'a dyn Database<DbHash>,
+	recorded: Mutex<Vec<DBValue>>,
+}
+
+impl<'a> ProofRecorder<'a> {
+	pub fn new(db: &'a dyn Database<DbHash>) -> Self {
+		Self { db, recorded: Mutex::new(Vec::new()) }
+	}
+
+	/// Take the nodes recorded so far.
+	pub fn drain(&self) -> Vec<DBValue> {
+		std::mem::take(&mut *self.recorded.lock())
+	}
+}
+
+impl<'a> HashDBRef<KeccakHasher, DBValue> for ProofRecorder<'a> {
+	fn get(&self, key: &H256, _prefix: Prefix) -> Option<DBValue> {
+		let node = get_node(self.db, key);
+		if let Some(node) = &node {
+			if key != &EMPTY_TRIE_ROOT {
+				self.recorded.lock().push(node.clone());
+			}
+		}
+		node
+	}
+
+	fn contains(&self, key: &H256, _prefix: Prefix) -> bool {
+		get_node(self.db, key).is_some()
+	}
+}
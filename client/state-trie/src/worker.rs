@@ -0,0 +1,181 @@
+use std::time::Duration;
+use std::pin::Pin;
+use std::sync::Arc;
+use futures::{prelude::*, task::{Context, Poll}};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, SaturatedConversion}};
+use sc_client_api::{ImportNotifications, backend::StorageProvider};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use fp_rpc::EthereumRuntimeRPCApi;
+use futures_timer::Delay;
+use log::{error, warn};
+
+const LIMIT: usize = 8;
+/// Most blocks walked back from the best block to find a synced ancestor. Further behind, the
+/// worker catches up along the canonical chain this many blocks at a time.
+const ROUTE_LIMIT: usize = 1024;
+
+/// Keeps the Ethereum state trie in sync with the best chain.
+pub struct StateTrieWorker<Block: BlockT, C, B> {
+	import_notifications: ImportNotifications<Block>,
+	timeout: Duration,
+	inner_delay: Option<Delay>,
+
+	client: Arc<C>,
+	substrate_backend: Arc<B>,
+	state_trie_backend: Arc<crate::Backend<Block>>,
+
+	/// Blocks left to sync, oldest last.
+	unsynced: Vec<Block::Hash>,
+	have_next: bool,
+	/// Set once a block to sync has its state pruned, after which the trie cannot progress.
+	halted: bool,
+}
+
+impl<Block: BlockT, C, B> StateTrieWorker<Block, C, B> {
+	pub fn new(
+		import_notifications: ImportNotifications<Block>,
+		timeout: Duration,
+		client: Arc<C>,
+		substrate_backend: Arc<B>,
+		state_trie_backend: Arc<crate::Backend<Block>>,
+	) -> Self {
+		Self {
+			import_notifications,
+			timeout,
+			inner_delay: None,
+
+			client,
+			substrate_backend,
+			state_trie_backend,
+
+			unsynced: Vec::new(),
+			have_next: true,
+			halted: false,
+		}
+	}
+}
+
+impl<Block: BlockT, C, B> StateTrieWorker<Block, C, B> where
+	C: ProvideRuntimeApi<Block> + StorageProvider<Block, B> + Send + Sync,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+	B: sc_client_api::Backend<Block>,
+{
+	fn unsynced_route(&self) -> Result<Vec<Block::Hash>, String> {
+		let blockchain = self.substrate_backend.blockchain();
+		let info = blockchain.info();
+
+		if let Some(route) = crate::unsynced_blocks(
+			blockchain,
+			self.state_trie_backend.as_ref(),
+			info.best_hash,
+			ROUTE_LIMIT,
+		)? {
+			return Ok(route)
+		}
+
+		let best_number: u64 = info.best_number.saturated_into();
+		let target = match self.state_trie_backend.best_synced_number()? {
+			Some(number) => number.saturating_add(ROUTE_LIMIT as u64),
+			None => ROUTE_LIMIT as u64 - 1,
+		};
+		let hash = blockchain.hash(target.min(best_number).saturated_into())
+			.map_err(|e| format!("{:?}", e))?
+			.ok_or("Canonical block not found".to_string())?;
+
+		crate::unsynced_blocks(blockchain, self.state_trie_backend.as_ref(), hash, ROUTE_LIMIT)?
+			.ok_or("No synced ancestor of the canonical chain found".to_string())
+	}
+
+	fn sync_blocks(&mut self) -> Result<bool, String> {
+		if self.unsynced.is_empty() {
+			self.unsynced = self.unsynced_route()?;
+		}
+
+		let blockchain = self.substrate_backend.blockchain();
+		for _ in 0..LIMIT {
+			let hash = match self.unsynced.pop() {
+				Some(hash) => hash,
+				None => break,
+			};
+
+			let header = blockchain.header(BlockId::Hash(hash))
+				.map_err(|e| format!("{:?}", e))?
+				.ok_or("Header not found".to_string())?;
+
+			if !self.substrate_backend.have_state_at(&hash, *header.number()) {
+				error!(
+					target: "state-trie",
+					"State of block #{} is pruned, the Ethereum state trie needs an archive node (`--pruning archive`).",
+					header.number(),
+				);
+				self.halted = true;
+				return Ok(false)
+			}
+
+			crate::sync_block(self.client.as_ref(), self.state_trie_backend.as_ref(), &header)?;
+		}
+
+		Ok(!self.unsynced.is_empty())
+	}
+}
+
+impl<Block: BlockT, C, B> Stream for StateTrieWorker<Block, C, B> where
+	C: ProvideRuntimeApi<Block> + StorageProvider<Block, B> + Send + Sync,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+	B: sc_client_api::Backend<Block>,
+{
+	type Item = ();
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<()>> {
+		let mut fire = false;
+
+		loop {
+			match Stream::poll_next(Pin::new(&mut self.import_notifications), cx) {
+				Poll::Pending => break,
+				Poll::Ready(Some(_)) => {
+					fire = true;
+				},
+				Poll::Ready(None) => return Poll::Ready(None),
+			}
+		}
+
+		if self.halted {
+			return Poll::Pending
+		}
+
+		let timeout = self.timeout.clone();
+		let inner_delay = self.inner_delay.get_or_insert_with(|| Delay::new(timeout));
+
+		match Future::poll(Pin::new(inner_delay), cx) {
+			Poll::Pending => (),
+			Poll::Ready(()) => {
+				fire = true;
+			},
+		}
+
+		if self.have_next {
+			fire = true;
+		}
+
+		if fire {
+			self.inner_delay = None;
+
+			match self.sync_blocks() {
+				Ok(have_next) => {
+					self.have_next = have_next;
+					Poll::Ready(Some(()))
+				},
+				Err(e) => {
+					// The best chain may have changed under us, rebuild the route on the next try.
+					self.unsynced.clear();
+					self.have_next = false;
+					warn!(target: "state-trie", "Syncing failed with error {:?}, retrying.", e);
+					Poll::Ready(Some(()))
+				},
+			}
+		} else {
+			Poll::Pending
+		}
+	}
+}
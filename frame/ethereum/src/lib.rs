@@ -29,7 +29,7 @@ use codec::{Encode, Decode};
 use fp_consensus::{SHADOWS_ENGINE_ID, PostLog, PreLog};
 use fp_storage::PALLET_ETHEREUM_SCHEMA;
 
-pub use fp_rpc::TransactionStatus;
+pub use fp_rpc::{TransactionStatus, EIP658Receipt};
 pub use ethereum::{Transaction, Log, Block, Receipt, TransactionAction, TransactionMessage};
 
 #[cfg(all(feature = "std", test))]
@@ -49,6 +49,8 @@ pub enum ReturnValue {
 pub enum EthereumStorageSchema {
 	Undefined,
 	V1,
+	/// Receipts are stored as `EIP658Receipt`.
+	V2,
 }
 
 impl Default for EthereumStorageSchema {
@@ -57,6 +59,28 @@ impl Default for EthereumStorageSchema {
 	}
 }
 
+/// Keccak-256 hasher, used to build Ethereum tries.
+pub struct KeccakHasher;
+
+impl hash_db::Hasher for KeccakHasher {
+	type Out = H256;
+	type StdHasher = hash256_std_hasher::Hash256StdHasher;
+	const LENGTH: usize = 32;
+
+	fn hash(x: &[u8]) -> Self::Out {
+		H256::from_slice(Keccak256::digest(x).as_slice())
+	}
+}
+
+/// Root of an Ethereum trie keyed by the RLP-encoded index of each item, as used for the
+/// transactions and receipts roots of a block header.
+pub fn ordered_trie_root<I, V>(input: I) -> H256 where
+	I: IntoIterator<Item = V>,
+	V: AsRef<[u8]>,
+{
+	triehash::ordered_trie_root::<KeccakHasher, I>(input)
+}
+
 /// A type alias for the balance type from this pallet's point of view.
 pub type BalanceOf<T> = <T as pallet_balances::Config>::Balance;
 
@@ -84,12 +108,12 @@ pub trait Config: frame_system::Config<Hash=H256> + pallet_balances::Config + pa
 decl_storage! {
 	trait Store for Module<T: Config> as Ethereum {
 		/// Current building block's transactions and receipts.
-		Pending: Vec<(ethereum::Transaction, TransactionStatus, ethereum::Receipt)>;
+		Pending: Vec<(ethereum::Transaction, TransactionStatus, EIP658Receipt)>;
 
 		/// The current Ethereum block.
 		CurrentBlock: Option<ethereum::Block>;
 		/// The current Ethereum receipts.
-		CurrentReceipts: Option<Vec<ethereum::Receipt>>;
+		CurrentReceipts: Option<Vec<EIP658Receipt>>;
 		/// The current transaction statuses.
 		CurrentTransactionStatuses: Option<Vec<TransactionStatus>>;
 	}
@@ -99,7 +123,7 @@ decl_storage! {
 			<Module<T>>::store_block(false);
 
 			// Initialize the storage schema at the well known key.
-			frame_support::storage::unhashed::put::<EthereumStorageSchema>(&PALLET_ETHEREUM_SCHEMA, &EthereumStorageSchema::V1);
+			frame_support::storage::unhashed::put::<EthereumStorageSchema>(&PALLET_ETHEREUM_SCHEMA, &EthereumStorageSchema::V2);
 		});
 	}
 }
@@ -157,6 +181,19 @@ decl_module! {
 
 			0
 		}
+
+		fn on_runtime_upgrade() -> Weight {
+			let schema = frame_support::storage::unhashed::get::<EthereumStorageSchema>(&PALLET_ETHEREUM_SCHEMA)
+				.unwrap_or_default();
+
+			if schema < EthereumStorageSchema::V2 {
+				// Receipts are rewritten on every block, so bumping the schema is enough.
+				frame_support::storage::unhashed::put::<EthereumStorageSchema>(&PALLET_ETHEREUM_SCHEMA, &EthereumStorageSchema::V2);
+				T::DbWeight::get().reads_writes(1, 1)
+			} else {
+				T::DbWeight::get().reads(1)
+			}
+		}
 	}
 }
 
@@ -248,9 +285,7 @@ impl<T: Config> Module<T> {
 			beneficiary: <Module<T>>::find_author(),
 			// TODO: figure out if there's better way to get a sort-of-valid state root.
 			state_root: H256::default(),
-			receipts_root: H256::from_slice(
-				Keccak256::digest(&rlp::encode_list(&receipts)[..]).as_slice(),
-			), // TODO: check receipts hash.
+			receipts_root: ordered_trie_root(receipts.iter().map(|r| rlp::encode(r))),
 			logs_bloom,
 			difficulty: U256::zero(),
 			number: U256::from(
@@ -259,7 +294,7 @@ impl<T: Config> Module<T> {
 				)
 			),
 			gas_limit: T::BlockGasLimit::get(),
-			gas_used: receipts.clone().into_iter().fold(U256::zero(), |acc, r| acc + r.used_gas),
+			gas_used: receipts.last().map(|r| r.cumulative_gas_used).unwrap_or_default(),
 			timestamp: UniqueSaturatedInto::<u64>::unique_saturated_into(
 				pallet_timestamp::Module::<T>::get()
 			),
@@ -267,7 +302,7 @@ impl<T: Config> Module<T> {
 			mix_hash: H256::default(),
 			nonce: H64::default(),
 		};
-		let mut block = ethereum::Block::new(partial_header, transactions.clone(), ommers);
+		let mut block = ethereum::Block::new(partial_header, transactions, ommers);
 		block.header.state_root = T::StateRoot::get();
 
 		CurrentBlock::put(block.clone());
@@ -304,7 +339,11 @@ impl<T: Config> Module<T> {
 		let transaction_hash = H256::from_slice(
 			Keccak256::digest(&rlp::encode(&transaction)).as_slice()
 		);
-		let transaction_index = Pending::get().len() as u32;
+		let pending = Pending::get();
+		let transaction_index = pending.len() as u32;
+		let previous_cumulative_gas_used = pending.last()
+			.map(|(_, _, receipt)| receipt.cumulative_gas_used)
+			.unwrap_or_default();
 
 		let (to, contract_address, info) = Self::execute(
 			source,
@@ -356,14 +395,14 @@ impl<T: Config> Module<T> {
 			},
 		};
 
-		let receipt = ethereum::Receipt {
-			state_root: match reason {
-				ExitReason::Succeed(_) => H256::from_low_u64_be(1),
-				ExitReason::Error(_) => H256::from_low_u64_le(0),
-				ExitReason::Revert(_) => H256::from_low_u64_le(0),
-				ExitReason::Fatal(_) => H256::from_low_u64_le(0),
+		let receipt = EIP658Receipt {
+			status_code: match reason {
+				ExitReason::Succeed(_) => 1,
+				ExitReason::Error(_) => 0,
+				ExitReason::Revert(_) => 0,
+				ExitReason::Fatal(_) => 0,
 			},
-			used_gas,
+			cumulative_gas_used: previous_cumulative_gas_used.saturating_add(used_gas),
 			logs_bloom: status.clone().logs_bloom,
 			logs: status.clone().logs,
 		};
@@ -398,7 +437,7 @@ impl<T: Config> Module<T> {
 	}
 
 	/// Get receipts by number.
-	pub fn current_receipts() -> Option<Vec<ethereum::Receipt>> {
+	pub fn current_receipts() -> Option<Vec<EIP658Receipt>> {
 		CurrentReceipts::get()
 	}
 
@@ -139,6 +139,7 @@ impl pallet_evm::Config for Test {
 	type Runner = pallet_evm::runner::stack::Runner<Self>;
 	type ChainId = ChainId;
 	type OnChargeTransaction = ();
+	type TrackTouchedAccounts = ();
 }
 
 parameter_types! {
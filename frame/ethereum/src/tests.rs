@@ -9,6 +9,7 @@ use ethereum::TransactionSignature;
 use frame_support::{
 	assert_noop, assert_err, assert_ok,
 	unsigned::ValidateUnsigned,
+	traits::OnFinalize,
 };
 use sp_runtime::transaction_validity::{TransactionSource, InvalidTransaction};
 
@@ -298,3 +299,107 @@ fn call_should_handle_errors() {
 		).ok().unwrap();
 	});
 }
+
+#[test]
+fn receipts_should_carry_status_and_cumulative_gas() {
+	// Same contract as in `call_should_handle_errors`: `foo()` succeeds and `bar()` reverts.
+	let contract: &str = "608060405234801561001057600080fd5b50610113806100206000396000f3fe6080604052348015600f57600080fd5b506004361060325760003560e01c8063c2985578146037578063febb0f7e146057575b600080fd5b603d605f565b604051808215151515815260200191505060405180910390f35b605d6068565b005b60006001905090565b600060db576040517f08c379a00000000000000000000000000000000000000000000000000000000081526004018080602001828103825260098152602001807f6572726f725f6d7367000000000000000000000000000000000000000000000081525060200191505060405180910390fd5b56fea2646970667358221220fde68a3968e0e99b16fabf9b2997a78218b32214031f8e07e2c502daf603a69e64736f6c63430006060033";
+
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let contract_address = contract_address(alice.address, 0);
+		let create = UnsignedTransaction {
+			nonce: U256::zero(),
+			gas_price: U256::from(1),
+			gas_limit: U256::from(0x100000),
+			action: ethereum::TransactionAction::Create,
+			value: U256::zero(),
+			input: FromHex::from_hex(contract).unwrap(),
+		}.sign(&alice.private_key);
+		let call_bar = UnsignedTransaction {
+			nonce: U256::from(1),
+			gas_price: U256::from(1),
+			gas_limit: U256::from(0x100000),
+			action: ethereum::TransactionAction::Call(contract_address),
+			value: U256::zero(),
+			input: FromHex::from_hex("febb0f7e").unwrap(),
+		}.sign(&alice.private_key);
+
+		assert_ok!(Ethereum::transact(Origin::none(), create));
+		assert_ok!(Ethereum::transact(Origin::none(), call_bar));
+
+		Ethereum::on_finalize(1);
+
+		let block = Ethereum::current_block().unwrap();
+		let receipts = Ethereum::current_receipts().unwrap();
+
+		assert_eq!(receipts.len(), 2);
+		assert_eq!(receipts[0].status_code, 1);
+		assert_eq!(receipts[1].status_code, 0);
+		assert!(receipts[1].cumulative_gas_used > receipts[0].cumulative_gas_used);
+		assert_eq!(block.header.gas_used, receipts[1].cumulative_gas_used);
+
+		// The status code is the first item of the RLP-encoded receipt (EIP-658).
+		let encoded = rlp::encode(&receipts[1]);
+		assert_eq!(rlp::Rlp::new(&encoded).val_at::<u8>(0).unwrap(), 0);
+
+		assert_eq!(
+			block.header.receipts_root,
+			ordered_trie_root(receipts.iter().map(|r| rlp::encode(r))),
+		);
+	});
+}
+
+#[test]
+fn block_should_commit_to_mainnet_transactions_root() {
+	// The only transaction of Ethereum mainnet block 46147, the first one ever to transfer value.
+	let raw: Vec<u8> = FromHex::from_hex(
+		"f86780862d79883d2000825208945df9b87991262f6ba471f09758cde1c0fc1de734827a69801ca088ff6cf0fefd94db46111149ae4bfc179e9b94721fffd821d38d16464b3f71d0a045e0aff800961cfce805daef7016b9b675c137a6a41a548f7b60a3484c06a33a"
+	).unwrap();
+	let transaction: Transaction = rlp::decode(&raw).unwrap();
+	assert_eq!(
+		H256::from_slice(Keccak256::digest(&rlp::encode(&transaction)).as_slice()),
+		H256::from_str("5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060").unwrap(),
+	);
+
+	let partial_header = ethereum::PartialHeader {
+		parent_hash: H256::default(),
+		beneficiary: H160::default(),
+		state_root: H256::default(),
+		receipts_root: H256::default(),
+		logs_bloom: Bloom::default(),
+		difficulty: U256::zero(),
+		number: U256::from(46147),
+		gas_limit: U256::zero(),
+		gas_used: U256::zero(),
+		timestamp: 0,
+		extra_data: Vec::new(),
+		mix_hash: H256::default(),
+		nonce: H64::default(),
+	};
+	let block = ethereum::Block::new(partial_header, vec![transaction], Vec::new());
+
+	assert_eq!(
+		block.header.transactions_root,
+		H256::from_str("4513310fcb9f6f616972a3b948dc5d547f280849a87ebb5af0191f98b87be598").unwrap(),
+	);
+}
+
+#[test]
+fn empty_block_should_have_empty_trie_roots() {
+	let (_, mut ext) = new_test_ext(1);
+
+	ext.execute_with(|| {
+		Ethereum::on_finalize(1);
+
+		let block = Ethereum::current_block().unwrap();
+		let empty_trie_root = H256::from_str(
+			"56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+		).unwrap();
+
+		assert_eq!(block.header.receipts_root, empty_trie_root);
+		assert_eq!(block.header.transactions_root, empty_trie_root);
+	});
+}
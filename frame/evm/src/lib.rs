@@ -46,18 +46,21 @@ pub use fp_evm::{
 };
 pub use evm::{ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal};
 
-use sp_std::vec::Vec;
+use sp_std::{vec::Vec, collections::btree_set::BTreeSet};
 #[cfg(feature = "std")]
 use codec::{Encode, Decode};
 #[cfg(feature = "std")]
 use serde::{Serialize, Deserialize};
 use frame_support::{decl_module, decl_storage, decl_event, decl_error};
-use frame_support::weights::{Weight, Pays, PostDispatchInfo};
-use frame_support::traits::{Currency, ExistenceRequirement, Get, WithdrawReasons, Imbalance, OnUnbalanced};
+use frame_support::storage::StoragePrefixedMap;
+use frame_support::weights::{Weight, Pays, PostDispatchInfo, DispatchClass};
+use frame_support::traits::{
+	Currency, ExistenceRequirement, Get, WithdrawReasons, Imbalance, OnUnbalanced, StoredMap,
+};
 use frame_support::dispatch::DispatchResultWithPostInfo;
 use frame_system::RawOrigin;
 use sp_core::{U256, H256, H160, Hasher};
-use sp_runtime::{AccountId32, traits::{UniqueSaturatedInto, BadOrigin, Saturating}};
+use sp_runtime::{AccountId32, StoredMapError, traits::{UniqueSaturatedInto, BadOrigin, Saturating}};
 use evm::Config as EvmConfig;
 
 /// Type alias for currency balance.
@@ -243,6 +246,12 @@ pub trait Config: frame_system::Config + pallet_timestamp::Config {
 	/// Similar to `OnChargeTransaction` of `pallet_transaction_payment`
 	type OnChargeTransaction: OnChargeEVMTransaction<Self>;
 
+	/// Whether the accounts and storage entries changed in each block are recorded in
+	/// `TouchedAccounts` and `TouchedStorages`, for nodes keeping an Ethereum-compatible state
+	/// trie. Balance changes made outside of the EVM are only recorded if the balances are kept
+	/// in a `TrackedAccountStore`.
+	type TrackTouchedAccounts: Get<bool>;
+
 	/// EVM config used in the module.
 	fn config() -> &'static EvmConfig {
 		&ISTANBUL_CONFIG
@@ -268,6 +277,22 @@ decl_storage! {
 		pub AccountCodes get(fn account_codes): map hasher(blake2_128_concat) H160 => Vec<u8>;
 		pub AccountStorages get(fn account_storages):
 			double_map hasher(blake2_128_concat) H160, hasher(blake2_128_concat) H256 => H256;
+
+		/// Accounts whose nonce, balance, code or storage changed in the current block, mapped to
+		/// whether their storage was wiped. Together with `TouchedStorages`, this lets a node
+		/// maintain an Ethereum-compatible state trie off-chain. Only recorded when
+		/// `TrackTouchedAccounts` is set, and cleared at the start of every block.
+		pub TouchedAccounts get(fn touched_accounts):
+			map hasher(blake2_128_concat) H160 => Option<bool>;
+		/// Storage entries written by the EVM in the current block.
+		pub TouchedStorages get(fn touched_storages):
+			double_map hasher(blake2_128_concat) H160, hasher(blake2_128_concat) H256 => bool;
+		/// Number of entries in `TouchedAccounts` and `TouchedStorages`.
+		TouchedCount: u32;
+		/// Address of every account recorded in `TouchedAccounts` so far, by account id, so that
+		/// balance changes made outside of the EVM can be recorded too.
+		pub TouchedAddresses get(fn touched_address):
+			map hasher(blake2_128_concat) T::AccountId => Option<H160>;
 	}
 
 	add_extra_genesis {
@@ -293,6 +318,14 @@ decl_storage! {
 					AccountStorages::insert(address, index, value);
 				}
 			}
+
+			Module::<T>::record_touched(
+				config.accounts.keys().cloned().collect(),
+				config.accounts.iter()
+					.flat_map(|(address, account)| account.storage.keys().map(move |index| (*address, *index)))
+					.collect(),
+				Default::default(),
+			);
 		});
 	}
 }
@@ -342,6 +375,19 @@ decl_module! {
 
 		fn deposit_event() = default;
 
+		fn on_initialize(_n: T::BlockNumber) -> Weight {
+			let count = TouchedCount::get();
+			if count == 0 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			<TouchedAccounts as StoragePrefixedMap<bool>>::remove_all();
+			<TouchedStorages as StoragePrefixedMap<bool>>::remove_all();
+			TouchedCount::kill();
+
+			T::DbWeight::get().reads_writes(1, 1 + count as Weight)
+		}
+
 		/// Withdraw balance from EVM into currency/balances module.
 		#[weight = 0]
 		fn withdraw(origin, address: H160, value: BalanceOf<T>) {
@@ -354,6 +400,12 @@ decl_module! {
 				value,
 				ExistenceRequirement::AllowDeath
 			)?;
+
+			Module::<T>::note_touched(
+				sp_std::iter::once(address).collect(),
+				Default::default(),
+				Default::default(),
+			);
 		}
 
 		/// Issue an EVM call operation. This is similar to a message call transaction in Ethereum.
@@ -522,6 +574,74 @@ impl<T: Config> Module<T> {
 		AccountStorages::remove_prefix(address);
 	}
 
+	/// Record accounts and storage entries changed by the EVM in the current block, if the
+	/// runtime tracks them. `resets` are the accounts whose storage was wiped.
+	pub fn note_touched(
+		accounts: BTreeSet<H160>,
+		storages: BTreeSet<(H160, H256)>,
+		resets: BTreeSet<H160>,
+	) {
+		let weight = Self::record_touched(accounts, storages, resets);
+		frame_system::Module::<T>::register_extra_weight_unchecked(weight, DispatchClass::Normal);
+	}
+
+	fn record_touched(
+		accounts: BTreeSet<H160>,
+		storages: BTreeSet<(H160, H256)>,
+		resets: BTreeSet<H160>,
+	) -> Weight {
+		if !T::TrackTouchedAccounts::get() {
+			return 0
+		}
+
+		let mut added = 0u32;
+		let mut writes = 0;
+		for address in accounts.union(&resets) {
+			let reset = resets.contains(address);
+			match TouchedAccounts::get(address) {
+				Some(previous) if previous || !reset => continue,
+				Some(_) => (),
+				None => {
+					TouchedAddresses::<T>::insert(T::AddressMapping::into_account_id(*address), address);
+					added += 1;
+					writes += 1;
+				},
+			}
+			TouchedAccounts::insert(address, reset);
+			writes += 1;
+		}
+		for (address, index) in &storages {
+			if !TouchedStorages::contains_key(address, index) {
+				TouchedStorages::insert(address, index, true);
+				added += 1;
+				writes += 1;
+			}
+		}
+		if added > 0 {
+			TouchedCount::mutate(|count| *count = count.saturating_add(added));
+			writes += 1;
+		}
+
+		let reads = accounts.union(&resets).count() + storages.len() + 1;
+		T::DbWeight::get().reads_writes(reads as Weight, writes)
+	}
+
+	/// Record a balance change of the account in the current block, if the runtime tracks
+	/// touched accounts and the EVM knows the address of the account.
+	pub fn note_balance_changed(who: &T::AccountId) {
+		if !T::TrackTouchedAccounts::get() {
+			return
+		}
+
+		frame_system::Module::<T>::register_extra_weight_unchecked(
+			T::DbWeight::get().reads(1),
+			DispatchClass::Normal,
+		);
+		if let Some(address) = TouchedAddresses::<T>::get(who) {
+			Self::note_touched(sp_std::iter::once(address).collect(), Default::default(), Default::default());
+		}
+	}
+
 	/// Create an account.
 	pub fn create_account(address: H160, code: Vec<u8>) {
 		if code.is_empty() {
@@ -550,6 +670,28 @@ impl<T: Config> Module<T> {
 	}
 }
 
+/// Account store recording the balance changes of EVM accounts in `TouchedAccounts`, whether
+/// they were made by the EVM or not, such as transfers, fees and rewards. Wraps the store of the
+/// balances, usually `frame_system`.
+pub struct TrackedAccountStore<T, S>(sp_std::marker::PhantomData<(T, S)>);
+
+impl<T: Config, S, D: Default> StoredMap<T::AccountId, D> for TrackedAccountStore<T, S> where
+	S: StoredMap<T::AccountId, D>,
+{
+	fn get(who: &T::AccountId) -> D {
+		S::get(who)
+	}
+
+	fn try_mutate_exists<R, E: From<StoredMapError>>(
+		who: &T::AccountId,
+		f: impl FnOnce(&mut Option<D>) -> Result<R, E>,
+	) -> Result<R, E> {
+		let result = S::try_mutate_exists(who, f)?;
+		Module::<T>::note_balance_changed(who);
+		Ok(result)
+	}
+}
+
 /// Handle withdrawing, refunding and depositing of transaction fees.
 /// Similar to `OnChargeTransaction` of `pallet_transaction_payment`
 pub trait OnChargeEVMTransaction<T: Config> {
@@ -91,15 +91,23 @@ impl<T: Config> Runner<T> {
 
 		let state = executor.into_state();
 
+		let mut touched = state.substate.touched;
+		let mut reset_storages = state.substate.reset_storages;
+		touched.insert(source);
+
 		for address in state.substate.deletes {
 			log::debug!(
 				target: "evm",
 				"Deleting account at {:?}",
 				address
 			);
-			Module::<T>::remove_account(&address)
+			Module::<T>::remove_account(&address);
+			touched.insert(address);
+			reset_storages.insert(address);
 		}
 
+		Module::<T>::note_touched(touched, state.substate.touched_storages, reset_storages);
+
 		for log in &state.substate.logs {
 			log::trace!(
 				target: "evm",
@@ -224,6 +232,9 @@ struct SubstrateStackSubstate<'config> {
 	metadata: StackSubstateMetadata<'config>,
 	deletes: BTreeSet<H160>,
 	logs: Vec<Log>,
+	touched: BTreeSet<H160>,
+	touched_storages: BTreeSet<(H160, H256)>,
+	reset_storages: BTreeSet<H160>,
 	parent: Option<Box<SubstrateStackSubstate<'config>>>,
 }
 
@@ -242,6 +253,9 @@ impl<'config> SubstrateStackSubstate<'config> {
 			parent: None,
 			deletes: BTreeSet::new(),
 			logs: Vec::new(),
+			touched: BTreeSet::new(),
+			touched_storages: BTreeSet::new(),
+			reset_storages: BTreeSet::new(),
 		};
 		mem::swap(&mut entering, self);
 
@@ -257,6 +271,9 @@ impl<'config> SubstrateStackSubstate<'config> {
 		self.metadata.swallow_commit(exited.metadata)?;
 		self.logs.append(&mut exited.logs);
 		self.deletes.append(&mut exited.deletes);
+		self.touched.append(&mut exited.touched);
+		self.touched_storages.append(&mut exited.touched_storages);
+		self.reset_storages.append(&mut exited.reset_storages);
 
 		sp_io::storage::commit_transaction();
 		Ok(())
@@ -301,6 +318,20 @@ impl<'config> SubstrateStackSubstate<'config> {
 			address, topics, data,
 		});
 	}
+
+	pub fn touch(&mut self, address: H160) {
+		self.touched.insert(address);
+	}
+
+	pub fn touch_storage(&mut self, address: H160, index: H256) {
+		self.touched.insert(address);
+		self.touched_storages.insert((address, index));
+	}
+
+	pub fn reset_storage(&mut self, address: H160) {
+		self.touched.insert(address);
+		self.reset_storages.insert(address);
+	}
 }
 
 /// Substrate backend for EVM.
@@ -317,6 +348,9 @@ impl<'vicinity, 'config, T: Config> SubstrateStackState<'vicinity, 'config, T> {
 			metadata,
 			deletes: BTreeSet::new(),
 			logs: Vec::new(),
+			touched: BTreeSet::new(),
+			touched_storages: BTreeSet::new(),
+			reset_storages: BTreeSet::new(),
 			parent: None,
 		}, _marker: PhantomData }
 	}
@@ -423,9 +457,11 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config> for SubstrateStackState
 	fn inc_nonce(&mut self, address: H160) {
 		let account_id = T::AddressMapping::into_account_id(address);
 		frame_system::Module::<T>::inc_account_nonce(&account_id);
+		self.substate.touch(address);
 	}
 
 	fn set_storage(&mut self, address: H160, index: H256, value: H256) {
+		self.substate.touch_storage(address, index);
 		if value == H256::default() {
 			log::debug!(
 				target: "evm",
@@ -448,6 +484,7 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config> for SubstrateStackState
 
 	fn reset_storage(&mut self, address: H160) {
 		AccountStorages::remove_prefix(address);
+		self.substate.reset_storage(address);
 	}
 
 	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) {
@@ -466,11 +503,14 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config> for SubstrateStackState
 			address
 		);
 		Module::<T>::create_account(address, code);
+		self.substate.touch(address);
 	}
 
 	fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError> {
 		let source = T::AddressMapping::into_account_id(transfer.source);
 		let target = T::AddressMapping::into_account_id(transfer.target);
+		self.substate.touch(transfer.source);
+		self.substate.touch(transfer.target);
 
 		T::Currency::transfer(
 			&source,
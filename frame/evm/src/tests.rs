@@ -5,6 +5,7 @@ use super::*;
 use std::{str::FromStr, collections::BTreeMap};
 use frame_support::{
 	assert_ok, impl_outer_origin, parameter_types, impl_outer_dispatch,
+	traits::OnInitialize,
 };
 use sp_core::{Blake2Hasher, H256};
 use sp_runtime::{
@@ -75,12 +76,13 @@ impl pallet_balances::Config for Test {
 	type DustRemoval = ();
 	type Event = ();
 	type ExistentialDeposit = ExistentialDeposit;
-	type AccountStore = System;
+	type AccountStore = TrackedAccountStore<Test, System>;
 	type WeightInfo = ();
 }
 
 parameter_types! {
 	pub const MinimumPeriod: u64 = 1000;
+	pub const TrackTouchedAccounts: bool = true;
 }
 impl pallet_timestamp::Config for Test {
 	type Moment = u64;
@@ -113,6 +115,7 @@ impl Config for Test {
 	type Precompiles = ();
 	type ChainId = ();
 	type OnChargeTransaction = ();
+	type TrackTouchedAccounts = TrackTouchedAccounts;
 }
 
 type System = frame_system::Module<Test>;
@@ -198,3 +201,79 @@ fn fee_deduction() {
 		assert_eq!(Balances::free_balance(&substrate_addr), 95);
 	});
 }
+
+#[test]
+fn touched_accounts_are_tracked_per_block() {
+	new_test_ext().execute_with(|| {
+		let contract = H160::from_str("1000000000000000000000000000000000000001").unwrap();
+		let touched = || TouchedAccounts::iter().collect::<BTreeMap<_, _>>();
+
+		// Genesis accounts are reported as touched in the genesis block.
+		assert_eq!(touched().len(), 2);
+
+		<EVM as OnInitialize<u64>>::on_initialize(1);
+		assert!(touched().is_empty());
+		assert_eq!(TouchedCount::get(), 0);
+
+		assert_ok!(EVM::call(
+			Origin::root(),
+			H160::default(),
+			contract,
+			Vec::new(),
+			U256::default(),
+			1000000,
+			U256::default(),
+			None,
+		));
+
+		assert_eq!(touched().get(&H160::default()), Some(&false));
+		assert_eq!(touched().get(&contract), Some(&false));
+		assert_eq!(TouchedStorages::iter().count(), 0);
+		assert_eq!(TouchedCount::get(), 2);
+	});
+}
+
+#[test]
+fn balance_changes_outside_of_the_evm_are_tracked() {
+	new_test_ext().execute_with(|| {
+		let contract = H160::from_str("1000000000000000000000000000000000000001").unwrap();
+		let unknown = H160::from_str("1000000000000000000000000000000000000003").unwrap();
+		<EVM as OnInitialize<u64>>::on_initialize(1);
+
+		// The EVM has seen the contract at genesis, but never the other address.
+		let contract_id = <Test as Config>::AddressMapping::into_account_id(contract);
+		let unknown_id = <Test as Config>::AddressMapping::into_account_id(unknown);
+		assert_eq!(EVM::touched_address(&contract_id), Some(contract));
+
+		let _ = Balances::deposit_creating(&contract_id, 10);
+		let _ = Balances::deposit_creating(&unknown_id, 10);
+
+		assert_eq!(EVM::touched_accounts(contract), Some(false));
+		assert_eq!(EVM::touched_accounts(unknown), None);
+		assert_eq!(TouchedCount::get(), 1);
+	});
+}
+
+#[test]
+fn storage_resets_are_kept_within_a_block() {
+	new_test_ext().execute_with(|| {
+		let contract = H160::from_str("1000000000000000000000000000000000000002").unwrap();
+		let index = H256::repeat_byte(1);
+		<EVM as OnInitialize<u64>>::on_initialize(1);
+
+		EVM::note_touched(
+			Default::default(),
+			std::iter::once((contract, index)).collect(),
+			std::iter::once(contract).collect(),
+		);
+		EVM::note_touched(std::iter::once(contract).collect(), Default::default(), Default::default());
+
+		assert_eq!(EVM::touched_accounts(contract), Some(true));
+		assert!(EVM::touched_storages(contract, index));
+		assert_eq!(TouchedCount::get(), 2);
+
+		<EVM as OnInitialize<u64>>::on_initialize(2);
+		assert_eq!(EVM::touched_accounts(contract), None);
+		assert!(!EVM::touched_storages(contract, index));
+	});
+}
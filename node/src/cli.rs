@@ -12,6 +12,8 @@ arg_enum! {
 		Manual,
 		// Seal when transaction is executed.
 		Instant,
+		// Seal the Ethereum blocks of `--replay-source`.
+		Replay,
 	}
 }
 
@@ -34,8 +36,116 @@ pub struct RunCmd {
 	#[structopt(long = "sealing")]
 	pub sealing: Sealing,
 
+	#[cfg(feature = "manual-seal")]
+	/// Ethereum blocks to seal with `--sealing replay`: an RLP file as written by `geth export`,
+	/// or the HTTP endpoint of a node serving `debug_getRawBlock`.
+	#[structopt(long = "replay-source")]
+	pub replay_source: Option<crate::replay::ReplaySource>,
+
+	#[cfg(feature = "manual-seal")]
+	/// First Ethereum block number to replay, defaults to the one following the best block.
+	#[structopt(long = "replay-from")]
+	pub replay_from: Option<u64>,
+
 	#[structopt(long = "enable-dev-signer")]
 	pub enable_dev_signer: bool,
+
+	/// Directory of the encrypted (Web3 Secret Storage) Ethereum key files, defaults to
+	/// `keystore-eth` in the chain directory, next to the Substrate keystore.
+	#[structopt(long = "eth-keystore-path", parse(from_os_str))]
+	pub eth_keystore_path: Option<std::path::PathBuf>,
+
+	/// Comma separated Ethereum accounts of the keystore to unlock at startup, which
+	/// `eth_sendTransaction` can then sign with.
+	#[structopt(long = "eth-unlock", use_delimiter = true)]
+	pub eth_unlock: Vec<sp_core::H160>,
+
+	/// File with the passwords of the `--eth-unlock` accounts, one per line in the same order,
+	/// the last one being used for the remaining accounts. Passwords are prompted for without it.
+	#[structopt(long = "eth-password", parse(from_os_str))]
+	pub eth_password: Option<std::path::PathBuf>,
+
+	/// Maintain an Ethereum-compatible state trie of EVM accounts, used for the block
+	/// `stateRoot` and `eth_getProof`. Requires an archive node.
+	#[structopt(long = "enable-eth-state-trie")]
+	pub enable_eth_state_trie: bool,
+
+	/// Number of recent blocks sampled to suggest a gas price.
+	#[structopt(long = "gas-price-blocks", default_value = "20")]
+	pub gas_price_blocks: u64,
+
+	/// Percentile of the gas prices paid in the sampled blocks suggested by `eth_gasPrice`.
+	#[structopt(long = "gas-price-percentile", default_value = "60")]
+	pub gas_price_percentile: u64,
+
+	/// Gas limit of `eth_call` and `eth_estimateGas`, also used when the request gives none.
+	#[structopt(long = "rpc-gas-cap", default_value = "50000000")]
+	pub rpc_gas_cap: u64,
+
+	/// Maximum number of blocks searched by a single `eth_getLogs` or `eth_getFilterLogs` call.
+	#[structopt(long = "max-logs-range", default_value = "2000")]
+	pub max_logs_range: u64,
+
+	/// Maximum number of logs returned by a single logs query.
+	#[structopt(long = "max-past-logs", default_value = "10000")]
+	pub max_past_logs: usize,
+
+	/// Number of blocks the Ethereum mapping may lag behind the best block before RPC lookups of
+	/// unknown hashes are refused.
+	#[structopt(long = "max-mapping-lag", default_value = "16")]
+	pub max_mapping_lag: u64,
+
+	/// Maximum number of filters installed at once.
+	#[structopt(long = "max-stored-filters", default_value = "500")]
+	pub max_stored_filters: usize,
+
+	/// Number of blocks after which an installed filter is removed.
+	#[structopt(long = "filter-lifetime", default_value = "100")]
+	pub filter_lifetime: u64,
+
+	/// Number of blocks a transaction stays in the pending transactions of the RPC after being
+	/// submitted.
+	#[structopt(long = "pending-transaction-lifetime", default_value = "5")]
+	pub pending_transaction_lifetime: u64,
+
+	/// Number of blocks whose Ethereum block, receipts and transaction statuses are cached by the
+	/// RPC.
+	#[structopt(long = "eth-block-cache-size", default_value = "50")]
+	pub eth_block_cache_size: usize,
+
+	/// Keep the filters created with `eth_newFilter` and `eth_newBlockFilter` in the Frontier
+	/// database, so that they survive restarts.
+	#[structopt(long = "persist-filters")]
+	pub persist_filters: bool,
+
+	/// Check that the Ethereum digest of imported blocks matches the block built by the runtime.
+	#[structopt(long = "verify-eth-digests")]
+	pub verify_eth_digests: bool,
+
+	/// Serve the EIP-1767 GraphQL schema for Ethereum data at `/graphql`, with a GraphiQL page
+	/// at `/graphql/ui`.
+	#[structopt(long = "graphql")]
+	pub graphql: bool,
+
+	/// Port of the GraphQL server.
+	#[structopt(long = "graphql-port", default_value = "8547")]
+	pub graphql_port: u16,
+
+	/// Listen for GraphQL queries on all interfaces instead of the local one only.
+	#[structopt(long = "graphql-external")]
+	pub graphql_external: bool,
+
+	/// Maximum size of a GraphQL request body, in bytes.
+	#[structopt(long = "graphql-max-body-size", default_value = "1048576")]
+	pub graphql_max_body_size: usize,
+
+	/// Maximum nesting depth of the fields of a GraphQL query.
+	#[structopt(long = "graphql-max-depth", default_value = "10")]
+	pub graphql_max_depth: usize,
+
+	/// Maximum number of fields selected by a GraphQL query, fragments being counted at every use.
+	#[structopt(long = "graphql-max-complexity", default_value = "500")]
+	pub graphql_max_complexity: usize,
 }
 
 #[derive(Debug, StructOpt)]
@@ -69,4 +179,7 @@ pub enum Subcommand {
 
 	/// Revert the chain to a previous state.
 	Revert(sc_cli::RevertCmd),
+
+	/// Check, repair or rebuild the Frontier database.
+	FrontierDb(crate::frontier_db_cmd::FrontierDbCmd),
 }
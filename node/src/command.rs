@@ -1,8 +1,11 @@
+use std::{fs, io, path::Path};
 use crate::chain_spec;
 use crate::cli::{Cli, Subcommand};
 use crate::service;
 use sc_cli::{SubstrateCli, RuntimeVersion, Role, ChainSpec};
 use sc_service::PartialComponents;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto}};
 use crate::service::new_partial;
 
 impl SubstrateCli for Cli {
@@ -88,14 +91,44 @@ pub fn run() -> sc_cli::Result<()> {
 		},
 		Some(Subcommand::PurgeChain(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
-			runner.sync_run(|config| cmd.run(config.database))
+			runner.sync_run(|config| {
+				let shadows_dirs = service::shadows_database_dirs(&config);
+				let db_path = config.database.path().map(Path::to_path_buf);
+				cmd.run(config.database)?;
+
+				// Substrate asks for confirmation, and keeps its database if it is not given.
+				if db_path.map_or(false, |path| path.exists()) {
+					return Ok(())
+				}
+				shadows_dirs.iter().try_for_each(|path| purge_shadows_dir(path))
+			})
 		},
 		Some(Subcommand::Revert(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
-				let PartialComponents { client, task_manager, backend, ..}
+				let PartialComponents { client, task_manager, backend, other: (_, _, _, shadows_backend, _), ..}
+					= new_partial(&config, &cli)?;
+				let candidates = best_chain(&*client, cmd.num.parse::<u64>()?)?;
+				let revert = cmd.run(client.clone(), backend);
+				Ok((async move {
+					revert.await?;
+
+					// Only blocks above the new best block have been reverted.
+					let best_number: u64 = client.info().best_number.unique_saturated_into();
+					let reverted: Vec<_> = candidates.into_iter()
+						.filter(|(number, _)| *number > best_number)
+						.collect();
+					shadows_backend.mapping().remove_blocks(&reverted)?;
+					Ok(())
+				}, task_manager))
+			})
+		},
+		Some(Subcommand::FrontierDb(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.async_run(|config| {
+				let PartialComponents { client, task_manager, other: (_, _, _, shadows_backend, _), ..}
 					= new_partial(&config, &cli)?;
-				Ok((cmd.run(client, backend), task_manager))
+				Ok((async move { cmd.run(client, shadows_backend) }, task_manager))
 			})
 		},
 		None => {
@@ -109,3 +142,41 @@ pub fn run() -> sc_cli::Result<()> {
 		}
 	}
 }
+
+/// The last `count` blocks of the best chain, as `(number, hash)` pairs, best block first.
+fn best_chain<Block: BlockT, C: HeaderBackend<Block>>(
+	client: &C,
+	count: u64,
+) -> sc_cli::Result<Vec<(u64, Block::Hash)>> {
+	let mut blocks = Vec::new();
+	let mut hash = client.info().best_hash;
+
+	while (blocks.len() as u64) < count {
+		let header = match client.header(BlockId::Hash(hash))? {
+			Some(header) => header,
+			None => break,
+		};
+		let number: u64 = (*header.number()).unique_saturated_into();
+		blocks.push((number, hash));
+
+		if number == 0 {
+			break
+		}
+		hash = *header.parent_hash();
+	}
+
+	Ok(blocks)
+}
+
+/// Remove a Frontier database directory. Missing directories are skipped silently, as only one
+/// of the RocksDb and ParityDb directories is expected to exist.
+fn purge_shadows_dir(path: &Path) -> sc_cli::Result<()> {
+	match fs::remove_dir_all(path) {
+		Ok(_) => {
+			println!("{:?} removed.", path);
+			Ok(())
+		},
+		Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+		Err(err) => Result::Err(err.into()),
+	}
+}
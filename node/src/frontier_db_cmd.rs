@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use structopt::{StructOpt, clap::arg_enum};
+use sc_cli::{CliConfiguration, SharedParams, PruningParams, DatabaseParams};
+use sc_client_api::BlockOf;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+use fp_rpc::EthereumRuntimeRPCApi;
+use fc_mapping_sync::{ChainWalk, walk_canonical_chain};
+use log::info;
+
+arg_enum! {
+	/// Operations on the Frontier database.
+	#[derive(Debug, Copy, Clone, PartialEq)]
+	pub enum FrontierDbAction {
+		Check,
+		Repair,
+		Reindex,
+	}
+}
+
+/// Check, repair or rebuild the Ethereum mappings of the canonical chain.
+#[derive(Debug, StructOpt)]
+pub struct FrontierDbCmd {
+	/// `check` reports inconsistent mappings, `repair` rewrites them and `reindex` rebuilds every
+	/// mapping from `--from`.
+	#[structopt(possible_values = &FrontierDbAction::variants(), case_insensitive = true)]
+	pub action: FrontierDbAction,
+
+	/// Height to reindex from.
+	#[structopt(long = "from", default_value = "0")]
+	pub from: u64,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl CliConfiguration for FrontierDbCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
+
+impl FrontierDbCmd {
+	/// Walk the canonical chain up to the best block.
+	pub fn run<Block, C>(
+		&self,
+		client: Arc<C>,
+		backend: Arc<fc_db::Backend<Block>>,
+	) -> sc_cli::Result<()> where
+		Block: BlockT,
+		C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + BlockOf + Send + Sync,
+		C::Api: EthereumRuntimeRPCApi<Block>,
+	{
+		let best_number: u64 = client.info().best_number.unique_saturated_into();
+		let walk = match self.action {
+			FrontierDbAction::Check => ChainWalk::Check,
+			FrontierDbAction::Repair => ChainWalk::Repair,
+			FrontierDbAction::Reindex => ChainWalk::Reindex { from: self.from },
+		};
+
+		let inconsistent = walk_canonical_chain(client.as_ref(), &backend, walk, |number, hash, problem| {
+			println!("#{} ({:?}): {}", number, hash, problem);
+		})?;
+
+		match self.action {
+			FrontierDbAction::Check if inconsistent > 0 => {
+				Err(format!("{} blocks have inconsistent Ethereum mappings", inconsistent).into())
+			},
+			FrontierDbAction::Check => {
+				info!("Ethereum mappings of {} blocks are consistent", best_number + 1);
+				Ok(())
+			},
+			FrontierDbAction::Repair => {
+				info!("Repaired the Ethereum mappings of {} blocks", inconsistent);
+				Ok(())
+			},
+			FrontierDbAction::Reindex => {
+				info!("Reindexed the Ethereum mappings from #{} to #{}", self.from, best_number);
+				Ok(())
+			},
+		}
+	}
+}
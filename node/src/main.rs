@@ -5,6 +5,9 @@ mod chain_spec;
 mod service;
 mod cli;
 mod command;
+mod frontier_db_cmd;
+#[cfg(feature = "manual-seal")]
+mod replay;
 mod rpc;
 
 fn main() -> sc_cli::Result<()> {
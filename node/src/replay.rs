@@ -0,0 +1,396 @@
+//! Authoring from a sequence of Ethereum blocks.
+//!
+//! Every block is wrapped in a `PreLog` digest and sealed on top of the best block, so that
+//! `pallet_ethereum` executes its transactions instead of the ones in the transaction pool.
+
+use std::{
+	collections::VecDeque, fs, io::{self, BufReader, Read}, path::{Path, PathBuf}, str::FromStr,
+	sync::{Arc, Mutex}, time::Duration,
+};
+use codec::Encode;
+use futures::{SinkExt, channel::{mpsc, oneshot}};
+use jsonrpc_core_client::{RawClient, transports::http};
+use log::{info, warn, debug};
+use sc_consensus_manual_seal::{Error, ConsensusDataProvider, rpc::EngineCommand};
+use sp_api::{ProvideRuntimeApi, TransactionFor};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::BlockImportParams;
+use sp_inherents::InherentData;
+use sp_core::U256;
+use sp_runtime::{DigestItem, generic::{BlockId, Digest}, traits::{Block as BlockT, UniqueSaturatedInto}};
+use fp_consensus::{PreLog, SHADOWS_ENGINE_ID};
+use fp_rpc::EthereumRuntimeRPCApi;
+use shadows_runtime::{Hash, opaque::Block};
+
+/// Delay before asking a JSON-RPC feed again for a block it did not have.
+const FEED_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the Ethereum blocks to replay come from.
+#[derive(Debug, Clone)]
+pub enum ReplaySource {
+	/// A file of concatenated RLP-encoded blocks, as written by `geth export`.
+	RlpFile(PathBuf),
+	/// The HTTP endpoint of an Ethereum node serving `debug_getRawBlock`.
+	Rpc(String),
+}
+
+impl FromStr for ReplaySource {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, String> {
+		if s.starts_with("http://") || s.starts_with("https://") {
+			Ok(ReplaySource::Rpc(s.to_string()))
+		} else {
+			Ok(ReplaySource::RlpFile(PathBuf::from(s)))
+		}
+	}
+}
+
+/// Queue of Ethereum blocks waiting to be sealed.
+pub type ReplayQueue = Arc<Mutex<VecDeque<ethereum::Block>>>;
+
+/// Puts the next queued Ethereum block in the digest of the block being authored.
+pub struct PreLogDataProvider<C> {
+	queue: ReplayQueue,
+	_marker: std::marker::PhantomData<C>,
+}
+
+impl<C> PreLogDataProvider<C> {
+	pub fn new(queue: ReplayQueue) -> Self {
+		Self { queue, _marker: Default::default() }
+	}
+}
+
+impl<C> ConsensusDataProvider<Block> for PreLogDataProvider<C> where
+	C: ProvideRuntimeApi<Block> + Send + Sync,
+{
+	type Transaction = TransactionFor<C, Block>;
+
+	fn create_digest(
+		&self,
+		_parent: &<Block as BlockT>::Header,
+		_inherents: &InherentData,
+	) -> Result<Digest<Hash>, Error> {
+		let block = self.queue.lock().expect("replay queue lock poisoned").pop_front()
+			.ok_or_else(|| Error::StringError("No Ethereum block queued for replay".into()))?;
+
+		Ok(Digest {
+			logs: vec![DigestItem::PreRuntime(SHADOWS_ENGINE_ID, PreLog::Block(block).encode())],
+		})
+	}
+
+	fn append_block_import(
+		&self,
+		_parent: &<Block as BlockT>::Header,
+		_params: &mut BlockImportParams<Block, Self::Transaction>,
+		_inherents: &InherentData,
+	) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+/// Blocks of an RLP file, read one at a time.
+pub struct RlpBlocks<R> {
+	reader: R,
+	path: PathBuf,
+	/// Index of the next block in the file.
+	index: usize,
+	failed: bool,
+}
+
+impl<R: Read> RlpBlocks<R> {
+	/// Read the next RLP item, `None` at the end of the file.
+	fn read_item(&mut self) -> Result<Option<Vec<u8>>, String> {
+		let mut item = vec![0u8];
+		match self.reader.read_exact(&mut item) {
+			Ok(()) => (),
+			Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+			Err(e) => return Err(format!("{:?}", e)),
+		}
+
+		// Single byte, short and long strings, then short and long lists.
+		let (len_of_len, short_len) = match item[0] {
+			0x00..=0x7f => return Ok(Some(item)),
+			prefix @ 0x80..=0xb7 => (0, (prefix - 0x80) as u64),
+			prefix @ 0xb8..=0xbf => ((prefix - 0xb7) as usize, 0),
+			prefix @ 0xc0..=0xf7 => (0, (prefix - 0xc0) as u64),
+			prefix => ((prefix - 0xf7) as usize, 0),
+		};
+
+		let value_len = if len_of_len == 0 {
+			short_len
+		} else {
+			let mut len = [0u8; 8];
+			self.read_exact(&mut len[8 - len_of_len..])?;
+			item.extend_from_slice(&len[8 - len_of_len..]);
+			u64::from_be_bytes(len)
+		};
+
+		// Grows with the data read, so a corrupted length does not allocate it all upfront.
+		let header_len = item.len();
+		(&mut self.reader).take(value_len).read_to_end(&mut item).map_err(|e| format!("{:?}", e))?;
+		if ((item.len() - header_len) as u64) < value_len {
+			return Err(self.truncated())
+		}
+
+		Ok(Some(item))
+	}
+
+	fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), String> {
+		match self.reader.read_exact(buf) {
+			Ok(()) => Ok(()),
+			Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(self.truncated()),
+			Err(e) => Err(format!("{:?}", e)),
+		}
+	}
+
+	fn truncated(&self) -> String {
+		format!("Truncated block #{} in {:?}", self.index, self.path)
+	}
+}
+
+impl<R: Read> Iterator for RlpBlocks<R> {
+	type Item = Result<ethereum::Block, String>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None
+		}
+
+		let block = self.read_item().and_then(|item| match item {
+			Some(item) => rlp::decode::<ethereum::Block>(&item).map(Some).map_err(|e| format!("{:?}", e)),
+			None => Ok(None),
+		});
+		self.index += 1;
+		self.failed = block.is_err();
+		block.transpose()
+	}
+}
+
+/// Read the blocks of an RLP file as they are needed.
+pub fn read_rlp_file(path: &Path) -> Result<RlpBlocks<BufReader<fs::File>>, String> {
+	let file = fs::File::open(path).map_err(|e| format!("{:?}", e))?;
+
+	Ok(RlpBlocks { reader: BufReader::new(file), path: path.to_path_buf(), index: 0, failed: false })
+}
+
+/// Number of an Ethereum block, which must fit in 64 bits.
+fn block_number(block: &ethereum::Block) -> Result<u64, String> {
+	if block.header.number > U256::from(u64::max_value()) {
+		return Err(format!("Ethereum block number {} does not fit in 64 bits", block.header.number))
+	}
+	Ok(block.header.number.low_u64())
+}
+
+/// Fetch a block from a JSON-RPC feed, `None` if the feed does not have it (yet).
+async fn fetch_block(client: &RawClient, number: u64) -> Result<Option<ethereum::Block>, String> {
+	let params = jsonrpc_core::Params::Array(vec![format!("0x{:x}", number).into()]);
+	let raw = match client.call_method("debug_getRawBlock", params).await {
+		Ok(serde_json::Value::String(raw)) => raw,
+		Ok(_) => return Ok(None),
+		Err(e) => {
+			debug!(target: "replay", "Block #{} not available: {:?}", number, e);
+			return Ok(None)
+		},
+	};
+
+	let bytes = sp_core::bytes::from_hex(&raw).map_err(|e| format!("{:?}", e))?;
+	rlp::decode(&bytes).map(Some).map_err(|e| format!("{:?}", e))
+}
+
+/// Seal one Ethereum block, failing if the receipts root computed by the runtime differs from the
+/// original one.
+async fn seal<C>(
+	client: &C,
+	queue: &ReplayQueue,
+	command_sink: &mut mpsc::Sender<EngineCommand<Hash>>,
+	block: ethereum::Block,
+) -> Result<(), String> where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
+	let number = block_number(&block)?;
+	let receipts_root = block.header.receipts_root;
+	queue.lock().expect("replay queue lock poisoned").push_back(block);
+
+	let (sender, receiver) = oneshot::channel();
+	command_sink.send(EngineCommand::SealNewBlock {
+		create_empty: true,
+		finalize: true,
+		parent_hash: None,
+		sender: Some(sender),
+	}).await.map_err(|e| format!("{:?}", e))?;
+	let created = receiver.await
+		.map_err(|e| format!("{:?}", e))?
+		.map_err(|e| format!("Sealing Ethereum block #{} failed: {:?}", number, e))?;
+
+	let runtime_block = client.runtime_api().current_block(&BlockId::Hash(created.hash))
+		.map_err(|e| format!("{:?}", e))?
+		.ok_or_else(|| format!("No Ethereum block stored for #{}", number))?;
+
+	// Later blocks build on the state this one left, replaying them would be meaningless.
+	if runtime_block.header.receipts_root != receipts_root {
+		return Err(format!(
+			"Replayed Ethereum block #{} as {} with receipts root {:?}, expected {:?}",
+			number, created.hash, runtime_block.header.receipts_root, receipts_root,
+		))
+	}
+
+	info!(target: "replay", "Replayed Ethereum block #{} as {}", number, created.hash);
+	Ok(())
+}
+
+/// Seal the blocks of an RLP file numbered from `from`.
+async fn replay_file<C>(
+	client: &C,
+	path: PathBuf,
+	from: u64,
+	queue: &ReplayQueue,
+	command_sink: &mut mpsc::Sender<EngineCommand<Hash>>,
+) -> Result<(), String> where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
+	for block in read_rlp_file(&path)? {
+		let block = block?;
+		if block_number(&block)? >= from {
+			seal(client, queue, command_sink, block).await?;
+		}
+	}
+
+	info!(target: "replay", "Replayed all blocks of {:?}", path);
+	Ok(())
+}
+
+/// Seal the blocks of a JSON-RPC feed from `from`, waiting for blocks the feed does not have yet.
+async fn replay_feed<C>(
+	client: &C,
+	url: String,
+	from: u64,
+	queue: &ReplayQueue,
+	command_sink: &mut mpsc::Sender<EngineCommand<Hash>>,
+) -> Result<(), String> where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
+	let feed = http::connect::<RawClient>(&url).await.map_err(|e| format!("{:?}", e))?;
+	let mut number = from;
+
+	loop {
+		match fetch_block(&feed, number).await? {
+			Some(block) => {
+				seal(client, queue, command_sink, block).await?;
+				number += 1;
+			},
+			None => futures_timer::Delay::new(FEED_POLL_INTERVAL).await,
+		}
+	}
+}
+
+/// Replay the blocks of `source` from Ethereum block number `from`, or from the block following
+/// the best block.
+pub async fn run_replay<C>(
+	client: Arc<C>,
+	source: ReplaySource,
+	from: Option<u64>,
+	queue: ReplayQueue,
+	mut command_sink: mpsc::Sender<EngineCommand<Hash>>,
+) where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: EthereumRuntimeRPCApi<Block>,
+{
+	let from = from.unwrap_or_else(|| {
+		let best_number: u64 = client.info().best_number.unique_saturated_into();
+		best_number + 1
+	});
+
+	let result = match source {
+		ReplaySource::RlpFile(path) =>
+			replay_file(&*client, path, from, &queue, &mut command_sink).await,
+		ReplaySource::Rpc(url) =>
+			replay_feed(&*client, url, from, &queue, &mut command_sink).await,
+	};
+
+	if let Err(e) = result {
+		warn!(target: "replay", "Replay stopped: {}", e);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{H64, H160, H256};
+
+	fn block(number: U256) -> ethereum::Block {
+		ethereum::Block::new(ethereum::PartialHeader {
+			parent_hash: H256::default(),
+			beneficiary: H160::default(),
+			state_root: H256::default(),
+			receipts_root: H256::default(),
+			logs_bloom: Default::default(),
+			difficulty: U256::zero(),
+			number,
+			gas_limit: U256::zero(),
+			gas_used: U256::zero(),
+			timestamp: 0,
+			extra_data: Vec::new(),
+			mix_hash: H256::default(),
+			nonce: H64::default(),
+		}, Vec::new(), Vec::new())
+	}
+
+	/// Read `data` back through a temporary RLP file.
+	fn read(data: &[u8]) -> Result<Vec<ethereum::Block>, String> {
+		let path = std::env::temp_dir().join(format!(
+			"shadows-replay-{}-{}.rlp",
+			std::process::id(),
+			std::time::SystemTime::now()
+				.duration_since(std::time::UNIX_EPOCH)
+				.expect("time after epoch")
+				.as_nanos(),
+		));
+		fs::write(&path, data).unwrap();
+		let blocks = read_rlp_file(&path).and_then(|blocks| blocks.collect());
+		fs::remove_file(&path).unwrap();
+		blocks
+	}
+
+	#[test]
+	fn reads_concatenated_blocks() {
+		let mut data = rlp::encode(&block(1.into())).to_vec();
+		data.extend_from_slice(&rlp::encode(&block(2.into())));
+
+		let blocks = read(&data).unwrap();
+		assert_eq!(blocks.len(), 2);
+		assert_eq!(blocks[1].header.hash(), block(2.into()).header.hash());
+		assert!(read(&[]).unwrap().is_empty());
+	}
+
+	#[test]
+	fn rejects_truncated_blocks() {
+		let mut data = rlp::encode(&block(1.into())).to_vec();
+		let second = rlp::encode(&block(2.into()));
+		data.extend_from_slice(&second[..second.len() - 1]);
+
+		assert!(read(&data).unwrap_err().starts_with("Truncated block #1"));
+		assert!(read(&second[..2]).is_err());
+	}
+
+	#[test]
+	fn rejects_malformed_blocks() {
+		// A valid RLP string, but not a block.
+		assert!(read(&rlp::encode(&b"not a block".to_vec())).is_err());
+		// A well-formed list without the items of a block.
+		assert!(read(&rlp::encode_list::<U256, _>(&[1.into(), 2.into()])).is_err());
+		// A block followed by a byte that is not RLP at all.
+		let mut data = rlp::encode(&block(1.into())).to_vec();
+		data.push(0xb8);
+		assert!(read(&data).is_err());
+	}
+
+	#[test]
+	fn block_numbers_must_fit_in_64_bits() {
+		assert_eq!(block_number(&block(u64::max_value().into())), Ok(u64::max_value()));
+		assert!(block_number(&block(U256::from(u64::max_value()) + 1)).is_err());
+	}
+}
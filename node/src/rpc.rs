@@ -3,7 +3,7 @@
 use std::sync::Arc;
 
 use std::collections::BTreeMap;
-use fc_rpc_core::types::{PendingTransactions, FilterPool};
+use fc_rpc_core::types::{PendingTransactions, FilterPool, FeeHistoryCache};
 use sc_consensus_manual_seal::rpc::{ManualSeal, ManualSealApi};
 use shadows_runtime::{Hash, AccountId, Index, opaque::Block, Balance};
 use sp_api::ProvideRuntimeApi;
@@ -20,7 +20,10 @@ use sp_block_builder::BlockBuilder;
 use sc_network::NetworkService;
 use jsonrpc_pubsub::manager::SubscriptionManager;
 use pallet_ethereum::EthereumStorageSchema;
-use fc_rpc::{StorageOverride, SchemaV1Override};
+use fc_rpc::{
+	StorageOverride, SchemaV1Override, SchemaV2Override, PersistedStorageOverride, GasPriceOracle, RpcLimits,
+	EthBlockDataCache,
+};
 
 /// Light client extra dependencies.
 pub struct LightDeps<C, F, P> {
@@ -46,18 +49,85 @@ pub struct FullDeps<C, P> {
 	pub is_authority: bool,
 	/// Whether to enable dev signer
 	pub enable_dev_signer: bool,
+	/// Encrypted Ethereum keys, which sign once unlocked.
+	pub eth_keystore: Arc<fc_rpc::EthKeystore>,
 	/// Network service
 	pub network: Arc<NetworkService<Block, Hash>>,
 	/// Ethereum pending transactions.
 	pub pending_transactions: PendingTransactions,
 	/// EthFilterApi pool.
 	pub filter_pool: Option<FilterPool>,
+	/// Maximum number of filters in the EthFilterApi pool.
+	pub max_stored_filters: usize,
+	/// Resources a single Ethereum RPC call may use.
+	pub rpc_limits: RpcLimits,
+	/// Ethereum data of recently requested blocks, shared by all EthApi instances.
+	pub block_data_cache: EthBlockDataCache<Block>,
+	/// Fee data of recent blocks, shared by all EthApi instances.
+	pub fee_history_cache: FeeHistoryCache,
+	/// Gas price oracle, shared by all EthApi instances.
+	pub gas_price_oracle: Arc<GasPriceOracle>,
 	/// Backend.
 	pub backend: Arc<fc_db::Backend<Block>>,
+	/// Whether to persist the EthFilterApi pool in the backend.
+	pub persist_filters: bool,
+	/// Ethereum state trie, if enabled.
+	pub state_trie: Option<Arc<fc_state_trie::Backend<Block>>>,
 	/// Manual seal command sink
 	pub command_sink: Option<futures::channel::mpsc::Sender<sc_consensus_manual_seal::rpc::EngineCommand<Hash>>>,
 }
 
+/// Instantiate the Ethereum RPC, which also resolves GraphQL queries.
+pub fn create_eth<C, P, BE>(
+	deps: &FullDeps<C, P>,
+) -> fc_rpc::EthApi<Block, C, P, shadows_runtime::TransactionConverter, BE, Hash> where
+	BE: Backend<Block> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+	C: ProvideRuntimeApi<Block> + StorageProvider<Block, BE> + AuxStore,
+	C: BlockchainEvents<Block>,
+	C: HeaderBackend<Block> + HeaderMetadata<Block, Error=BlockChainError>,
+	C: Send + Sync + 'static,
+	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
+	C::Api: BlockBuilder<Block>,
+	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: fp_rpc::EthereumRuntimeRPCApi<Block>,
+	P: TransactionPool<Block=Block> + 'static,
+{
+	use fc_rpc::{EthApi, EthDevSigner, EthKeystoreSigner, EthSigner};
+
+	let mut signers = Vec::new();
+	if deps.enable_dev_signer {
+		signers.push(Box::new(EthDevSigner::new()) as Box<dyn EthSigner>);
+	}
+	signers.push(Box::new(EthKeystoreSigner::new(deps.eth_keystore.clone())) as Box<dyn EthSigner>);
+	let mut overrides = BTreeMap::new();
+	overrides.insert(
+		EthereumStorageSchema::V1,
+		Box::new(SchemaV1Override::new(deps.client.clone())) as Box<dyn StorageOverride<_> + Send + Sync>
+	);
+	overrides.insert(
+		EthereumStorageSchema::V2,
+		Box::new(SchemaV2Override::new(deps.client.clone())) as Box<dyn StorageOverride<_> + Send + Sync>
+	);
+	EthApi::new(
+		deps.client.clone(),
+		deps.pool.clone(),
+		shadows_runtime::TransactionConverter,
+		deps.network.clone(),
+		deps.pending_transactions.clone(),
+		signers,
+		overrides,
+		deps.backend.clone(),
+		deps.state_trie.clone(),
+		deps.fee_history_cache.clone(),
+		deps.gas_price_oracle.clone(),
+		deps.block_data_cache.clone(),
+		deps.rpc_limits,
+		deps.is_authority,
+		deps.deny_unsafe,
+	)
+}
+
 /// Instantiate all Full RPC extensions.
 pub fn create_full<C, P, BE>(
 	deps: FullDeps<C, P>,
@@ -78,23 +148,26 @@ pub fn create_full<C, P, BE>(
 	use substrate_frame_rpc_system::{FullSystem, SystemApi};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
 	use fc_rpc::{
-		EthApi, EthApiServer, EthFilterApi, EthFilterApiServer, NetApi, NetApiServer,
-		EthPubSubApi, EthPubSubApiServer, Web3Api, Web3ApiServer, EthDevSigner, EthSigner,
-		HexEncodedIdProvider,
+		EthApiServer, EthFilterApi, EthFilterApiServer, NetApi, NetApiServer,
+		EthPubSubApi, EthPubSubApiServer, Web3Api, Web3ApiServer, HexEncodedIdProvider, PersonalApi,
+		PersonalApiServer,
 	};
 
 	let mut io = jsonrpc_core::IoHandler::default();
+	let eth = create_eth(&deps);
+	let personal = PersonalApi::new(create_eth(&deps), deps.eth_keystore.clone(), deps.deny_unsafe);
 	let FullDeps {
 		client,
 		pool,
 		deny_unsafe,
-		is_authority,
 		network,
-		pending_transactions,
 		filter_pool,
+		max_stored_filters,
+		rpc_limits,
 		command_sink,
 		backend,
-		enable_dev_signer,
+		persist_filters,
+		..
 	} = deps;
 
 	io.extend_with(
@@ -104,35 +177,22 @@ pub fn create_full<C, P, BE>(
 		TransactionPaymentApi::to_delegate(TransactionPayment::new(client.clone()))
 	);
 
-	let mut signers = Vec::new();
-	if enable_dev_signer {
-		signers.push(Box::new(EthDevSigner::new()) as Box<dyn EthSigner>);
-	}
-	let mut overrides = BTreeMap::new();
-	overrides.insert(
-		EthereumStorageSchema::V1,
-		Box::new(SchemaV1Override::new(client.clone())) as Box<dyn StorageOverride<_> + Send + Sync>
-	);
-	io.extend_with(
-		EthApiServer::to_delegate(EthApi::new(
-			client.clone(),
-			pool.clone(),
-			shadows_runtime::TransactionConverter,
-			network.clone(),
-			pending_transactions.clone(),
-			signers,
-			overrides,
-			backend,
-			is_authority,
-		))
-	);
+	io.extend_with(EthApiServer::to_delegate(eth));
+	io.extend_with(PersonalApiServer::to_delegate(personal));
+
+	let block_data = Arc::new(
+		PersistedStorageOverride::new(client.clone(), backend.clone())
+	) as Arc<dyn StorageOverride<_> + Send + Sync>;
 
 	if let Some(filter_pool) = filter_pool {
 		io.extend_with(
 			EthFilterApiServer::to_delegate(EthFilterApi::new(
 				client.clone(),
 				filter_pool.clone(),
-				500 as usize, // max stored filters
+				max_stored_filters,
+				rpc_limits,
+				if persist_filters { Some(backend) } else { None },
+				block_data.clone(),
 			))
 		);
 	}
@@ -159,6 +219,7 @@ pub fn create_full<C, P, BE>(
 				HexEncodedIdProvider::default(),
 				Arc::new(subscription_task_executor)
 			),
+			block_data,
 		))
 	);
 
@@ -183,10 +244,11 @@ pub fn create_light<C, P, M, F>(
 	C: sp_blockchain::HeaderBackend<Block>,
 	C: Send + Sync + 'static,
 	F: sc_client_api::light::Fetcher<Block> + 'static,
-	P: TransactionPool + 'static,
+	P: TransactionPool<Block=Block> + 'static,
 	M: jsonrpc_core::Metadata + Default,
 {
 	use substrate_frame_rpc_system::{LightSystem, SystemApi};
+	use fc_rpc::{LightEthApi, LightEthApiServer};
 
 	let LightDeps {
 		client,
@@ -197,9 +259,18 @@ pub fn create_light<C, P, M, F>(
 	let mut io = jsonrpc_core::IoHandler::default();
 	io.extend_with(
 		SystemApi::<Hash, AccountId, Index>::to_delegate(
-			LightSystem::new(client, remote_blockchain, fetcher, pool)
+			LightSystem::new(client.clone(), remote_blockchain.clone(), fetcher.clone(), pool.clone())
 		)
 	);
+	io.extend_with(
+		LightEthApiServer::to_delegate(LightEthApi::new(
+			client,
+			remote_blockchain,
+			fetcher,
+			pool,
+			shadows_runtime::TransactionConverter,
+		))
+	);
 
 	io
 }
@@ -1,16 +1,17 @@
 
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
-use std::{sync::{Arc, Mutex}, cell::RefCell, time::Duration, collections::{HashMap, BTreeMap}};
-use fc_rpc::EthTask;
-use fc_rpc_core::types::{FilterPool, PendingTransactions};
+use std::{sync::{Arc, Mutex}, cell::RefCell, time::Duration, net::SocketAddr, collections::{HashMap, BTreeMap}};
+use fc_rpc::{EthTask, EthKeystore, GasPriceOracle, RpcLimits, EthBlockDataCache};
+use fc_rpc_core::types::{FilterPool, PendingTransactions, FeeHistoryCache};
 use sc_client_api::{ExecutorProvider, RemoteBackend, BlockchainEvents};
 #[cfg(feature = "manual-seal")]
 use sc_consensus_manual_seal::{self as manual_seal};
 use fc_consensus::ShadowsBlockImport;
 use fc_mapping_sync::MappingSyncWorker;
+use fc_state_trie::StateTrieWorker;
 use shadows_runtime::{self, opaque::Block, RuntimeApi, SLOT_DURATION};
-use sc_service::{error::Error as ServiceError, Configuration, TaskManager, BasePath};
+use sc_service::{error::Error as ServiceError, Configuration, TaskManager, BasePath, config::DatabaseConfig};
 use sp_inherents::{InherentDataProviders, ProvideInherentData, InherentIdentifier, InherentData};
 use sc_executor::native_executor_instance;
 pub use sc_executor::NativeExecutor;
@@ -20,6 +21,7 @@ use sc_finality_grandpa::SharedVoterState;
 use sp_timestamp::InherentError;
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sc_cli::SubstrateCli;
+use sc_rpc_api::DenyUnsafe;
 use futures::StreamExt;
 
 use crate::cli::Cli;
@@ -45,7 +47,8 @@ pub type ConsensusResult = (
 		ShadowsBlockImport<
 			Block,
 			sc_finality_grandpa::GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>,
-			FullClient
+			FullClient,
+			FullBackend
 		>,
 		AuraPair
 	>,
@@ -53,7 +56,7 @@ pub type ConsensusResult = (
 );
 
 #[cfg(feature = "manual-seal")]
-pub type ConsensusResult = (ShadowsBlockImport<Block, Arc<FullClient>, FullClient>, Sealing);
+pub type ConsensusResult = (ShadowsBlockImport<Block, Arc<FullClient>, FullClient, FullBackend>, Sealing);
 
 /// Provide a mock duration starting at 0 in millisecond for timestamp inherent.
 /// Each call will increment timestamp by slot_duration making Aura think time has passed.
@@ -83,24 +86,87 @@ impl ProvideInherentData for MockTimestampInherentDataProvider {
 	}
 }
 
-pub fn open_shadows_backend(config: &Configuration) -> Result<Arc<fc_db::Backend<Block>>, String> {
-	let config_dir = config.base_path.as_ref()
+/// Directory of the chain under the base path, holding its Substrate database and keystore.
+fn chain_config_dir(config: &Configuration) -> std::path::PathBuf {
+	config.base_path.as_ref()
 		.map(|base_path| base_path.config_dir(config.chain_spec.id()))
 		.unwrap_or_else(|| {
 			BasePath::from_project("", "", &crate::cli::Cli::executable_name())
 				.config_dir(config.chain_spec.id())
-		});
-	let database_dir = config_dir.join("shadows").join("db");
+		})
+}
+
+pub fn shadows_config_dir(config: &Configuration) -> std::path::PathBuf {
+	chain_config_dir(config).join("shadows")
+}
+
+/// Directories of the Frontier databases, removed by `purge-chain`.
+pub fn shadows_database_dirs(config: &Configuration) -> Vec<std::path::PathBuf> {
+	let config_dir = shadows_config_dir(config);
+	vec![config_dir.join("db"), config_dir.join("paritydb"), config_dir.join("state-trie")]
+}
+
+pub fn open_shadows_backend(config: &Configuration) -> Result<Arc<fc_db::Backend<Block>>, String> {
+	let config_dir = shadows_config_dir(config);
+
+	// Follow the database chosen for the Substrate client. A custom client database has no path
+	// to follow, so the mappings are kept in RocksDb under the base path.
+	let source = match config.database {
+		DatabaseConfig::ParityDb { .. } => fc_db::DatabaseSettingsSrc::ParityDb {
+			path: config_dir.join("paritydb"),
+		},
+		DatabaseConfig::RocksDb { .. } | DatabaseConfig::Custom(_) => fc_db::DatabaseSettingsSrc::RocksDb {
+			path: config_dir.join("db"),
+			cache_size: 0,
+		},
+	};
+
+	Ok(Arc::new(fc_db::Backend::<Block>::new(&fc_db::DatabaseSettings { source })?))
+}
 
-	Ok(Arc::new(fc_db::Backend::<Block>::new(&fc_db::DatabaseSettings {
-		source: fc_db::DatabaseSettingsSrc::RocksDb {
+pub fn open_state_trie_backend(config: &Configuration) -> Result<Arc<fc_state_trie::Backend<Block>>, String> {
+	let database_dir = shadows_config_dir(config).join("state-trie");
+
+	Ok(Arc::new(fc_state_trie::Backend::<Block>::new(&fc_state_trie::DatabaseSettings {
+		source: fc_state_trie::DatabaseSettingsSrc::RocksDb {
 			path: database_dir,
 			cache_size: 0,
 		}
 	})?))
 }
 
-pub fn new_partial(config: &Configuration, #[allow(unused_variables)] cli: &Cli) -> Result<
+/// Opens the Ethereum keystore and unlocks the accounts given on the command line, reading
+/// their passwords from the password file or prompting for them.
+pub fn open_eth_keystore(config: &Configuration, cli: &Cli) -> Result<Arc<EthKeystore>, String> {
+	// Next to the Substrate keystore, out of the directories removed by `purge-chain`.
+	let path = cli.run.eth_keystore_path.clone()
+		.unwrap_or_else(|| chain_config_dir(config).join("keystore-eth"));
+	let keystore = EthKeystore::open(path)?;
+
+	let passwords = match &cli.run.eth_password {
+		Some(file) => std::fs::read_to_string(file)
+			.map_err(|err| format!("read password file {} failed: {:?}", file.display(), err))?
+			.lines()
+			.map(|line| line.to_string())
+			.collect::<Vec<_>>(),
+		None => Vec::new(),
+	};
+	for (i, address) in cli.run.eth_unlock.iter().enumerate() {
+		let password = match (&cli.run.eth_password, passwords.get(i).or(passwords.last())) {
+			(Some(_), Some(password)) => password.clone(),
+			(Some(file), None) => return Err(format!("password file {} is empty", file.display())),
+			(None, _) => rpassword::read_password_from_tty(Some(&format!("Password for {:?}: ", address)))
+				.map_err(|err| format!("read password failed: {:?}", err))?,
+		};
+		keystore.unlock(address, &password, None)
+			.map_err(|err| format!("unlock {:?} failed: {}", address, err))?;
+		log::info!("Unlocked Ethereum account {:?}", address);
+	}
+
+	Ok(Arc::new(keystore))
+}
+
+pub fn new_partial(config: &Configuration, cli: &Cli) -> Result<
 	sc_service::PartialComponents<
 		FullClient, FullBackend, FullSelectChain,
 		sp_consensus::import_queue::BasicQueue<Block, sp_api::TransactionFor<FullClient, Block>>,
@@ -144,11 +210,16 @@ pub fn new_partial(config: &Configuration, #[allow(unused_variables)] cli: &Cli)
 	let pending_transactions: PendingTransactions
 		= Some(Arc::new(Mutex::new(HashMap::new())));
 
-	let filter_pool: Option<FilterPool>
-		= Some(Arc::new(Mutex::new(BTreeMap::new())));
-
 	let shadows_backend = open_shadows_backend(config)?;
 
+	let filter_pool: Option<FilterPool> = Some(Arc::new(Mutex::new(
+		if cli.run.persist_filters {
+			fc_rpc::load_filter_pool(&shadows_backend)
+		} else {
+			BTreeMap::new()
+		}
+	)));
+
 	#[cfg(feature = "manual-seal")] {
 		let sealing = cli.run.sealing;
 
@@ -160,7 +231,9 @@ pub fn new_partial(config: &Configuration, #[allow(unused_variables)] cli: &Cli)
 		let shadows_block_import = ShadowsBlockImport::new(
 			client.clone(),
 			client.clone(),
+			backend.clone(),
 			shadows_backend.clone(),
+			cli.run.verify_eth_digests,
 		);
 
 		let import_queue = sc_consensus_manual_seal::import_queue(
@@ -193,7 +266,9 @@ pub fn new_partial(config: &Configuration, #[allow(unused_variables)] cli: &Cli)
 		let shadows_block_import = ShadowsBlockImport::new(
 			grandpa_block_import.clone(),
 			client.clone(),
+			backend.clone(),
 			shadows_backend.clone(),
+			cli.run.verify_eth_digests,
 		);
 
 		let aura_block_import = sc_consensus_aura::AuraBlockImport::<_, _, _, AuraPair>::new(
@@ -235,6 +310,12 @@ pub fn new_full(
 	cli: &Cli,
 ) -> Result<TaskManager, ServiceError> {
 	let enable_dev_signer = cli.run.enable_dev_signer;
+	let eth_keystore = open_eth_keystore(&config, cli)?;
+	let state_trie = if cli.run.enable_eth_state_trie {
+		Some(open_state_trie_backend(&config)?)
+	} else {
+		None
+	};
 
 	let sc_service::PartialComponents {
 		client, backend, mut task_manager, import_queue, keystore_container,
@@ -255,6 +336,9 @@ pub fn new_full(
 
 	// Channel for the rpc handler to communicate with the authorship task.
 	let (command_sink, commands_stream) = futures::channel::mpsc::channel(1000);
+	// Replayed blocks are sealed through their own channel, one at a time.
+	#[cfg(feature = "manual-seal")]
+	let (replay_sink, replay_commands) = futures::channel::mpsc::channel(1);
 
 	if config.offchain_worker.enabled {
 		sc_service::build_offchain_workers(
@@ -270,6 +354,19 @@ pub fn new_full(
 	let prometheus_registry = config.prometheus_registry().cloned();
 	let is_authority = role.is_authority();
 	let subscription_task_executor = sc_rpc::SubscriptionTaskExecutor::new(task_manager.spawn_handle());
+	let fee_history_cache: FeeHistoryCache = Arc::new(Mutex::new(BTreeMap::new()));
+	let gas_price_oracle = Arc::new(GasPriceOracle::new(
+		cli.run.gas_price_blocks,
+		cli.run.gas_price_percentile,
+	));
+	let (block_data_cache, block_data_cache_task) = EthBlockDataCache::new(
+		cli.run.eth_block_cache_size,
+		prometheus_registry.as_ref(),
+	);
+	task_manager.spawn_essential_handle().spawn(
+		"shadows-eth-block-data-cache",
+		block_data_cache_task,
+	);
 
 	let rpc_extensions_builder = {
 		let client = client.clone();
@@ -278,6 +375,19 @@ pub fn new_full(
 		let pending = pending_transactions.clone();
 		let filter_pool = filter_pool.clone();
 		let shadows_backend = shadows_backend.clone();
+		let state_trie = state_trie.clone();
+		let fee_history_cache = fee_history_cache.clone();
+		let gas_price_oracle = gas_price_oracle.clone();
+		let block_data_cache = block_data_cache.clone();
+		let eth_keystore = eth_keystore.clone();
+		let persist_filters = cli.run.persist_filters;
+		let max_stored_filters = cli.run.max_stored_filters;
+		let rpc_limits = RpcLimits {
+			gas_cap: cli.run.rpc_gas_cap,
+			max_logs_range: cli.run.max_logs_range,
+			max_logs: cli.run.max_past_logs,
+			max_mapping_lag: cli.run.max_mapping_lag,
+		};
 
 		Box::new(move |deny_unsafe, _| {
 			let deps = crate::rpc::FullDeps {
@@ -286,10 +396,18 @@ pub fn new_full(
 				deny_unsafe,
 				is_authority,
 				enable_dev_signer,
+				eth_keystore: eth_keystore.clone(),
 				network: network.clone(),
 				pending_transactions: pending.clone(),
 				filter_pool: filter_pool.clone(),
+				max_stored_filters,
+				rpc_limits,
+				block_data_cache: block_data_cache.clone(),
+				fee_history_cache: fee_history_cache.clone(),
+				gas_price_oracle: gas_price_oracle.clone(),
 				backend: shadows_backend.clone(),
+				persist_filters,
+				state_trie: state_trie.clone(),
 				command_sink: Some(command_sink.clone())
 			};
 			crate::rpc::create_full(
@@ -299,17 +417,77 @@ pub fn new_full(
 		})
 	};
 
+	if cli.run.graphql {
+		let interface = if cli.run.graphql_external {
+			log::warn!(
+				"The GraphQL server listens on all interfaces, consider putting it behind a proxy \
+				server that filters and rate-limits requests",
+			);
+			[0, 0, 0, 0]
+		} else {
+			[127, 0, 0, 1]
+		};
+		let deps = crate::rpc::FullDeps {
+			client: client.clone(),
+			pool: transaction_pool.clone(),
+			deny_unsafe: DenyUnsafe::Yes,
+			is_authority,
+			enable_dev_signer: false,
+			eth_keystore: eth_keystore.clone(),
+			network: network.clone(),
+			pending_transactions: pending_transactions.clone(),
+			filter_pool: None,
+			max_stored_filters: cli.run.max_stored_filters,
+			rpc_limits: RpcLimits {
+				gas_cap: cli.run.rpc_gas_cap,
+				max_logs_range: cli.run.max_logs_range,
+				max_logs: cli.run.max_past_logs,
+				max_mapping_lag: cli.run.max_mapping_lag,
+			},
+			block_data_cache: block_data_cache.clone(),
+			fee_history_cache: fee_history_cache.clone(),
+			gas_price_oracle: gas_price_oracle.clone(),
+			backend: shadows_backend.clone(),
+			persist_filters: false,
+			state_trie: state_trie.clone(),
+			command_sink: None,
+		};
+		let server = fc_graphql::start_server(
+			SocketAddr::from((interface, cli.run.graphql_port)),
+			Arc::new(crate::rpc::create_eth::<_, _, FullBackend>(&deps)),
+			fc_graphql::GraphQLLimits {
+				max_body_size: cli.run.graphql_max_body_size,
+				max_depth: cli.run.graphql_max_depth,
+				max_complexity: cli.run.graphql_max_complexity,
+			},
+		).map_err(ServiceError::Other)?;
+		task_manager.spawn_handle().spawn("shadows-graphql-server", server);
+	}
+
 	task_manager.spawn_essential_handle().spawn(
 		"shadows-mapping-sync-worker",
 		MappingSyncWorker::new(
 			client.import_notification_stream(),
+			client.finality_notification_stream(),
 			Duration::new(6, 0),
 			client.clone(),
-			backend.clone(),
 			shadows_backend.clone(),
 		).for_each(|()| futures::future::ready(()))
 	);
 
+	if let Some(state_trie) = state_trie {
+		task_manager.spawn_essential_handle().spawn(
+			"shadows-state-trie-worker",
+			StateTrieWorker::new(
+				client.import_notification_stream(),
+				Duration::new(6, 0),
+				client.clone(),
+				backend.clone(),
+				state_trie,
+			).for_each(|()| futures::future::ready(()))
+		);
+	}
+
 	let _rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
 		network: network.clone(),
 		client: client.clone(),
@@ -323,27 +501,26 @@ pub fn new_full(
 	})?;
 
 	if let Some(filter_pool) = filter_pool {
-		// Each filter is allowed to stay in the pool for 100 blocks.
-		const FILTER_RETAIN_THRESHOLD: u64 = 100;
+		// Each filter is allowed to stay in the pool for `--filter-lifetime` blocks.
 		task_manager.spawn_essential_handle().spawn(
 			"shadows-filter-pool",
 			EthTask::filter_pool_task(
 				Arc::clone(&client),
 				filter_pool,
-				FILTER_RETAIN_THRESHOLD,
+				cli.run.filter_lifetime,
+				if cli.run.persist_filters { Some(shadows_backend.clone()) } else { None },
 			)
 		);
 	}
 
 	// Spawn Shadows pending transactions maintenance task (as essential, otherwise we leak).
 	if let Some(pending_transactions) = pending_transactions {
-		const TRANSACTION_RETAIN_THRESHOLD: u64 = 5;
 		task_manager.spawn_essential_handle().spawn(
 			"shadows-pending-transactions",
 			EthTask::pending_transaction_task(
 				Arc::clone(&client),
 				pending_transactions,
-				TRANSACTION_RETAIN_THRESHOLD,
+				cli.run.pending_transaction_lifetime,
 			)
 		);
 	}
@@ -392,7 +569,43 @@ pub fn new_full(
 					);
 					// we spawn the future on a background thread managed by service.
 					task_manager.spawn_essential_handle().spawn_blocking("instant-seal", authorship_future);
-				}
+				},
+				Sealing::Replay => {
+					let source = cli.run.replay_source.clone().ok_or_else(|| {
+						ServiceError::Other("`--sealing replay` requires `--replay-source`".into())
+					})?;
+					let queue = crate::replay::ReplayQueue::default();
+					// Blocks sealed through the RPC would take the queued Ethereum blocks.
+					drop(commands_stream);
+
+					task_manager.spawn_essential_handle().spawn(
+						"shadows-replay",
+						crate::replay::run_replay(
+							client.clone(),
+							source,
+							cli.run.replay_from,
+							queue.clone(),
+							replay_sink,
+						)
+					);
+
+					let authorship_future = manual_seal::run_manual_seal(
+						manual_seal::ManualSealParams {
+							block_import,
+							env,
+							client: client.clone(),
+							pool: transaction_pool.pool().clone(),
+							commands_stream: replay_commands,
+							select_chain,
+							consensus_data_provider: Some(Box::new(
+								crate::replay::PreLogDataProvider::<FullClient>::new(queue)
+							)),
+							inherent_data_providers,
+						}
+					);
+					// we spawn the future on a background thread managed by service.
+					task_manager.spawn_essential_handle().spawn_blocking("replay-seal", authorship_future);
+				},
 			};
 
 		}
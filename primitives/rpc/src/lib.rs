@@ -5,6 +5,8 @@ use ethereum::{Log, Block as EthereumBlock};
 use ethereum_types::Bloom;
 use codec::{Encode, Decode};
 use sp_std::vec::Vec;
+use sp_runtime::traits::Block as BlockT;
+use rlp::{Encodable, Decodable, RlpStream, Rlp, DecoderError};
 
 #[derive(Eq, PartialEq, Clone, Encode, Decode, sp_runtime::RuntimeDebug)]
 pub struct TransactionStatus {
@@ -31,8 +33,63 @@ impl Default for TransactionStatus {
 	}
 }
 
+/// A post-Byzantium transaction receipt, as specified by EIP-658.
+///
+/// Unlike `ethereum::Receipt`, the first field carries the status code of the transaction
+/// instead of an intermediate state root, and the gas field is cumulative within the block.
+#[derive(Eq, PartialEq, Clone, Encode, Decode, sp_runtime::RuntimeDebug)]
+pub struct EIP658Receipt {
+	/// `1` if the transaction succeeded, `0` otherwise.
+	pub status_code: u8,
+	/// Gas used by this transaction and all preceding transactions of the block.
+	pub cumulative_gas_used: U256,
+	pub logs_bloom: Bloom,
+	pub logs: Vec<Log>,
+}
+
+impl EIP658Receipt {
+	/// Convert receipts of a whole block in the legacy format, where the status was stored in
+	/// `state_root` and `used_gas` was not cumulative.
+	pub fn from_legacy(receipts: Vec<ethereum::Receipt>) -> Vec<EIP658Receipt> {
+		let mut cumulative_gas_used = U256::zero();
+
+		receipts.into_iter().map(|receipt| {
+			cumulative_gas_used = cumulative_gas_used.saturating_add(receipt.used_gas);
+
+			EIP658Receipt {
+				status_code: if receipt.state_root.is_zero() { 0 } else { 1 },
+				cumulative_gas_used,
+				logs_bloom: receipt.logs_bloom,
+				logs: receipt.logs,
+			}
+		}).collect()
+	}
+}
+
+impl Encodable for EIP658Receipt {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(4);
+		s.append(&self.status_code);
+		s.append(&self.cumulative_gas_used);
+		s.append(&self.logs_bloom);
+		s.append_list(&self.logs);
+	}
+}
+
+impl Decodable for EIP658Receipt {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		Ok(EIP658Receipt {
+			status_code: rlp.val_at(0)?,
+			cumulative_gas_used: rlp.val_at(1)?,
+			logs_bloom: rlp.val_at(2)?,
+			logs: rlp.list_at(3)?,
+		})
+	}
+}
+
 sp_api::decl_runtime_apis! {
 	/// API necessary for Ethereum-compatibility layer.
+	#[api_version(3)]
 	pub trait EthereumRuntimeRPCApi {
 		/// Returns runtime defined pallet_evm::ChainId.
 		fn chain_id() -> u64;
@@ -70,15 +127,31 @@ sp_api::decl_runtime_apis! {
 		/// Return the current block.
 		fn current_block() -> Option<EthereumBlock>;
 		/// Return the current receipt.
+		#[changed_in(2)]
 		fn current_receipts() -> Option<Vec<ethereum::Receipt>>;
+		/// Return the current receipt.
+		fn current_receipts() -> Option<Vec<EIP658Receipt>>;
 		/// Return the current transaction status.
 		fn current_transaction_statuses() -> Option<Vec<TransactionStatus>>;
 		/// Return all the current data for a block in a single runtime call.
+		#[changed_in(2)]
 		fn current_all() -> (
 			Option<EthereumBlock>,
 			Option<Vec<ethereum::Receipt>>,
 			Option<Vec<TransactionStatus>>
 		);
+		/// Return all the current data for a block in a single runtime call.
+		fn current_all() -> (
+			Option<EthereumBlock>,
+			Option<Vec<EIP658Receipt>>,
+			Option<Vec<TransactionStatus>>
+		);
+		/// Apply the given extrinsics on top of an initialized block and return the Ethereum
+		/// block and transaction statuses they would produce. The state changes are kept for the
+		/// following calls on the same API instance.
+		fn pending_block(
+			xts: Vec<<Block as BlockT>::Extrinsic>,
+		) -> (Option<EthereumBlock>, Option<Vec<TransactionStatus>>);
 	}
 }
 
@@ -33,7 +33,7 @@ pub use pallet_balances::Call as BalancesCall;
 pub use sp_runtime::{Permill, Perbill};
 pub use frame_support::{
 	construct_runtime, parameter_types, StorageValue,
-	traits::{KeyOwnerProofSystem, Randomness, FindAuthor},
+	traits::{KeyOwnerProofSystem, Randomness, FindAuthor, OnFinalize},
 	weights::{
 		Weight, IdentityFee,
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
@@ -101,7 +101,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("node-shadows"),
 	impl_name: create_runtime_str!("node-shadows"),
 	authoring_version: 1,
-	spec_version: 1,
+	spec_version: 4,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -242,7 +242,8 @@ impl pallet_balances::Config for Runtime {
 	type Event = Event;
 	type DustRemoval = ();
 	type ExistentialDeposit = ExistentialDeposit;
-	type AccountStore = System;
+	/// Records balance changes of EVM accounts for nodes keeping an Ethereum state trie.
+	type AccountStore = pallet_evm::TrackedAccountStore<Runtime, System>;
 	type WeightInfo = ();
 }
 
@@ -274,6 +275,7 @@ impl FeeCalculator for FixedGasPrice {
 
 parameter_types! {
 	pub const ChainId: u64 = 888;
+	pub const TrackTouchedAccounts: bool = true;
 }
 
 impl pallet_evm::Config for Runtime {
@@ -296,6 +298,7 @@ impl pallet_evm::Config for Runtime {
 	);
 	type ChainId = ChainId;
 	type OnChargeTransaction = ();
+	type TrackTouchedAccounts = TrackTouchedAccounts;
 }
 
 pub struct EthereumFindAuthor<F>(PhantomData<F>);
@@ -562,13 +565,13 @@ impl_runtime_apis! {
 			Ethereum::current_block()
 		}
 
-		fn current_receipts() -> Option<Vec<pallet_ethereum::Receipt>> {
+		fn current_receipts() -> Option<Vec<pallet_ethereum::EIP658Receipt>> {
 			Ethereum::current_receipts()
 		}
 
 		fn current_all() -> (
 			Option<pallet_ethereum::Block>,
-			Option<Vec<pallet_ethereum::Receipt>>,
+			Option<Vec<pallet_ethereum::EIP658Receipt>>,
 			Option<Vec<TransactionStatus>>
 		) {
 			(
@@ -577,6 +580,29 @@ impl_runtime_apis! {
 				Ethereum::current_transaction_statuses()
 			)
 		}
+
+		fn pending_block(
+			xts: Vec<<Block as BlockT>::Extrinsic>,
+		) -> (Option<pallet_ethereum::Block>, Option<Vec<TransactionStatus>>) {
+			// Extrinsics that are no longer valid are left out of the pending block.
+			let mut skipped = 0u32;
+			for ext in xts.into_iter() {
+				if let Err(err) = Executive::apply_extrinsic(ext) {
+					log::debug!(target: "runtime", "Pending extrinsic skipped: {:?}", err);
+					skipped += 1;
+				}
+			}
+			if skipped > 0 {
+				log::warn!(target: "runtime", "{} ready extrinsics left out of the pending block", skipped);
+			}
+
+			Ethereum::on_finalize(System::block_number());
+
+			(
+				Ethereum::current_block(),
+				Ethereum::current_transaction_statuses()
+			)
+		}
 	}
 
 	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<
@@ -0,0 +1,49 @@
+import {expect} from "chai";
+import {step} from "mocha-steps";
+
+import {createAndFinalizeBlock, describeWithShadows, customRequest} from "./util";
+
+describeWithShadows("Shadows RPC (Fee History)", (context) => {
+    const GENESIS_ACCOUNT = "0xAA7358886fd6FEc1d64323D9da340FD3c0B9a9E4";
+    const GENESIS_ACCOUNT_PRIVATE_KEY = "0x665c5c10437cc1220b805b3b6d015c82f476e1d8144f08ba85840eddf4b903a5";
+    const TEST_ACCOUNT = "0x1111111111111111111111111111111111111111";
+
+    step("should return empty blocks", async function () {
+        expect((await customRequest(context.web3, "eth_gasPrice", [])).result).to.equal("0x1");
+        await createAndFinalizeBlock(context.polkadotApi);
+
+        const result = (await customRequest(context.web3, "eth_feeHistory", ["0x2", "latest", [50]])).result;
+        expect(result.oldestBlock).to.equal("0x0");
+        expect(result.baseFeePerGas).to.eql(["0x1", "0x1", "0x1"]);
+        expect(result.gasUsedRatio).to.eql([0, 0]);
+        expect(result.reward).to.eql([["0x0"], ["0x0"]]);
+    });
+
+    step("should report rewards above the base fee", async function () {
+        this.timeout(10_000);
+        const tx = await context.web3.eth.accounts.signTransaction({
+            from: GENESIS_ACCOUNT,
+            to: TEST_ACCOUNT,
+            value: "0x200",
+            gasPrice: "0x05",
+            gas: "0x100000",
+        }, GENESIS_ACCOUNT_PRIVATE_KEY);
+        await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction]);
+        await createAndFinalizeBlock(context.polkadotApi);
+
+        const result = (await customRequest(context.web3, "eth_feeHistory", ["0x1", "latest", [0, 100]])).result;
+        expect(result.oldestBlock).to.equal("0x2");
+        expect(result.gasUsedRatio[0]).to.be.above(0);
+        expect(result.reward).to.eql([["0x4", "0x4"]]);
+    });
+
+    step("should suggest the gas price paid in recent blocks", async function () {
+        expect((await customRequest(context.web3, "eth_gasPrice", [])).result).to.equal("0x5");
+        expect((await customRequest(context.web3, "eth_maxPriorityFeePerGas", [])).result).to.equal("0x4");
+    });
+
+    step("should omit rewards without percentiles", async function () {
+        const result = (await customRequest(context.web3, "eth_feeHistory", ["0x1", "latest"])).result;
+        expect(result.reward).to.be.undefined;
+    });
+});
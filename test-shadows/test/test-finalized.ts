@@ -0,0 +1,96 @@
+import {expect} from "chai";
+import {step} from "mocha-steps";
+
+import {createAndFinalizeBlock, customRequest, describeWithShadows} from "./util";
+
+describeWithShadows("Shadows RPC (Finalized)", (context) => {
+    step("should resolve finalized and safe to the finalized block", async function () {
+        await createAndFinalizeBlock(context.polkadotApi);
+        // Seal a block without finalizing it.
+        await customRequest(context.web3, "engine_createBlock", [true, false, null]);
+
+        expect((await context.web3.eth.getBlock("latest")).number).to.equal(2);
+        const finalized = (await customRequest(context.web3, "eth_getBlockByNumber", ["finalized", false])).result;
+        const safe = (await customRequest(context.web3, "eth_getBlockByNumber", ["safe", false])).result;
+        expect(finalized.number).to.equal("0x1");
+        expect(safe.hash).to.equal(finalized.hash);
+    });
+
+    step("should bound log ranges by the finalized block", async function () {
+        const logs = await customRequest(context.web3, "eth_getLogs", [{fromBlock: "earliest", toBlock: "finalized"}]);
+        expect(logs.error).to.be.undefined;
+        expect(logs.result).to.eql([]);
+    });
+});
+
+describeWithShadows("Shadows RPC (Finalized Subscription)", (context) => {
+    step("should notify newly finalized heads", async function () {
+        this.timeout(15000);
+        const provider = context.web3.currentProvider as any;
+        const subscriptionId = await new Promise<string>((resolve) => {
+            provider.send(
+                {jsonrpc: "2.0", id: 1, method: "eth_subscribe", params: ["newFinalizedHeads"]},
+                (_: Error, response: any) => resolve(response.result)
+            );
+        });
+        const header = new Promise<any>((resolve) => {
+            provider.on("data", (data: any) => {
+                if (data.params && data.params.subscription === subscriptionId) {
+                    resolve(data.params.result);
+                }
+            });
+        });
+
+        await createAndFinalizeBlock(context.polkadotApi);
+
+        expect((await header).number).to.equal("0x1");
+    });
+}, "ws");
+
+describeWithShadows("Shadows RPC (Finalized Logs Subscription)", (context) => {
+    const GENESIS_ACCOUNT = "0xAA7358886fd6FEc1d64323D9da340FD3c0B9a9E4";
+    const GENESIS_ACCOUNT_PRIVATE_KEY = "0x665c5c10437cc1220b805b3b6d015c82f476e1d8144f08ba85840eddf4b903a5";
+    // Init code emitting an empty log: PUSH1 0, PUSH1 0, LOG0, STOP.
+    const LOG_BYTECODE = "0x60006000a000";
+
+    async function subscribe(filter: any, results: any[]) {
+        const provider = context.web3.currentProvider as any;
+        const subscriptionId = await new Promise<string>((resolve) => {
+            provider.send(
+                {jsonrpc: "2.0", id: 1, method: "eth_subscribe", params: ["logs", filter]},
+                (_: Error, response: any) => resolve(response.result)
+            );
+        });
+        provider.on("data", (data: any) => {
+            if (data.params && data.params.subscription === subscriptionId) {
+                results.push(data.params.result);
+            }
+        });
+    }
+
+    step("should not notify logs after the finalized block", async function () {
+        this.timeout(15000);
+        const all: any[] = [];
+        const finalized: any[] = [];
+        await subscribe({}, all);
+        await subscribe({toBlock: "finalized"}, finalized);
+
+        const tx = await context.web3.eth.accounts.signTransaction({
+            from: GENESIS_ACCOUNT,
+            data: LOG_BYTECODE,
+            value: "0x00",
+            gasPrice: "0x01",
+            gas: "0x100000",
+        }, GENESIS_ACCOUNT_PRIVATE_KEY);
+        await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction]);
+        // Seal a block without finalizing it.
+        await customRequest(context.web3, "engine_createBlock", [true, false, null]);
+
+        while (all.length < 1) {
+            await new Promise((resolve) => setTimeout(resolve, 100));
+        }
+        await new Promise((resolve) => setTimeout(resolve, 500));
+        expect(all[0].blockNumber).to.equal("0x1");
+        expect(finalized).to.eql([]);
+    });
+}, "ws");
@@ -0,0 +1,77 @@
+import {expect} from "chai";
+import {step} from "mocha-steps";
+
+import {createAndFinalizeBlock, customRequest, describeWithShadows, graphqlRequest} from "./util";
+
+describeWithShadows("Shadows GraphQL", (context) => {
+    const GENESIS_ACCOUNT = "0xAA7358886fd6FEc1d64323D9da340FD3c0B9a9E4";
+    const GENESIS_ACCOUNT_PRIVATE_KEY = "0x665c5c10437cc1220b805b3b6d015c82f476e1d8144f08ba85840eddf4b903a5";
+    const TEST_ACCOUNT = "0x1111111111111111111111111111111111111111";
+
+    let transactionHash: string;
+
+    step("should submit raw transactions", async function () {
+        const tx = await context.web3.eth.accounts.signTransaction({
+            from: GENESIS_ACCOUNT,
+            to: TEST_ACCOUNT,
+            value: "0x200",
+            gasPrice: "0x01",
+            gas: "0x100000",
+        }, GENESIS_ACCOUNT_PRIVATE_KEY);
+
+        const response = await graphqlRequest(
+            "mutation($data: Bytes!) { sendRawTransaction(data: $data) }",
+            {data: tx.rawTransaction},
+        );
+        expect(response.errors).to.be.undefined;
+        transactionHash = response.data.sendRawTransaction;
+        expect(transactionHash).to.equal(tx.transactionHash);
+        await createAndFinalizeBlock(context.polkadotApi);
+    });
+
+    step("should resolve a block with its transactions, receipts and accounts", async function () {
+        const response = await graphqlRequest(`{
+            block(number: 1) {
+                number
+                transactionCount
+                transactions { hash index from { address } to { address } value status gasUsed }
+                account(address: "${TEST_ACCOUNT}") { balance }
+            }
+        }`);
+        expect(response.errors).to.be.undefined;
+
+        const block = response.data.block;
+        const rpcBlock = (await customRequest(context.web3, "eth_getBlockByNumber", ["0x1", false])).result;
+        expect(block.number).to.equal("0x1");
+        expect(block.transactionCount).to.equal(rpcBlock.transactions.length);
+        expect(block.transactions[0]).to.eql({
+            hash: transactionHash,
+            index: 0,
+            from: {address: GENESIS_ACCOUNT.toLowerCase()},
+            to: {address: TEST_ACCOUNT},
+            value: "0x200",
+            status: "0x1",
+            gasUsed: "0x5208",
+        });
+        expect(block.account.balance).to.equal("0x200");
+    });
+
+    step("should answer like the JSON-RPC methods", async function () {
+        const response = await graphqlRequest(`{
+            chainID
+            gasPrice
+            transaction(hash: "${transactionHash}") { block { hash } }
+        }`);
+        expect(response.errors).to.be.undefined;
+
+        const receipt = (await customRequest(context.web3, "eth_getTransactionReceipt", [transactionHash])).result;
+        expect(response.data.chainID).to.equal((await customRequest(context.web3, "eth_chainId", [])).result);
+        expect(response.data.gasPrice).to.equal((await customRequest(context.web3, "eth_gasPrice", [])).result);
+        expect(response.data.transaction.block.hash).to.equal(receipt.blockHash);
+    });
+
+    step("should reject ambiguous block queries", async function () {
+        const response = await graphqlRequest(`{ block(number: 1, hash: "${transactionHash}") { number } }`);
+        expect(response.errors[0].message).to.equal("only one of number or hash must be specified");
+    });
+});
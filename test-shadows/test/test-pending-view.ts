@@ -0,0 +1,69 @@
+import {expect} from "chai";
+import {step} from "mocha-steps";
+
+import {createAndFinalizeBlock, customRequest, describeWithShadows} from "./util";
+
+describeWithShadows("Shadows RPC (Pending View)", (context) => {
+    const GENESIS_ACCOUNT = "0xAA7358886fd6FEc1d64323D9da340FD3c0B9a9E4";
+    const GENESIS_ACCOUNT_PRIVATE_KEY = "0x665c5c10437cc1220b805b3b6d015c82f476e1d8144f08ba85840eddf4b903a5";
+    const TEST_ACCOUNT = "0x1111111111111111111111111111111111111111";
+
+    // Solidity: contract test { function multiply(uint a) public pure returns(uint d) {return a * 7;}}
+    const TEST_CONTRACT_BYTECODE =
+        "0x6080604052348015600f57600080fd5b5060ae8061001e6000396000f3fe6080604052348015600f57600080fd5b506004361060285760003560e01c8063c6888fa114602d575b600080fd5b605660048036036020811015604157600080fd5b8101908080359060200190929190505050606c565b6040518082815260200191505060405180910390f35b600060078202905091905056fea265627a7a72315820f06085b229f27f9ad48b2ff3dd9714350c1698a37853a30136fa6c5a7762af7364736f6c63430005110032";
+    const FIRST_CONTRACT_ADDRESS = "0xc2bf5f29a4384b1ab0c063e1c666f02121b6084a";
+
+    step("should apply ready transactions in the pending view", async function () {
+        this.timeout(15000);
+        const transfer = await context.web3.eth.accounts.signTransaction({
+            from: GENESIS_ACCOUNT,
+            to: TEST_ACCOUNT,
+            value: "0x200",
+            gasPrice: "0x01",
+            gas: "0x100000",
+        }, GENESIS_ACCOUNT_PRIVATE_KEY);
+        const deploy = await context.web3.eth.accounts.signTransaction({
+            from: GENESIS_ACCOUNT,
+            data: TEST_CONTRACT_BYTECODE,
+            value: "0x00",
+            gasPrice: "0x01",
+            gas: "0x100000",
+            nonce: 1,
+        }, GENESIS_ACCOUNT_PRIVATE_KEY);
+        await customRequest(context.web3, "eth_sendRawTransaction", [transfer.rawTransaction]);
+        const deployHash = (await customRequest(context.web3, "eth_sendRawTransaction", [deploy.rawTransaction])).result;
+
+        expect(await context.web3.eth.getBalance(TEST_ACCOUNT, "latest")).to.equal("0");
+        expect(await context.web3.eth.getBalance(TEST_ACCOUNT, "pending")).to.equal("512");
+        expect(await context.web3.eth.getTransactionCount(GENESIS_ACCOUNT, "pending")).to.equal(2);
+
+        const pending = (await customRequest(context.web3, "eth_getBlockByNumber", ["pending", false])).result;
+        expect(pending.hash).to.be.null;
+        expect(pending.number).to.equal("0x1");
+        expect(pending.transactions).to.have.lengthOf(2);
+        expect(pending.transactions[1]).to.equal(deployHash);
+    });
+
+    step("should call contracts deployed in the pending view", async function () {
+        const call = {
+            to: FIRST_CONTRACT_ADDRESS,
+            data: "0xc6888fa10000000000000000000000000000000000000000000000000000000000000003",
+        };
+
+        expect((await customRequest(context.web3, "eth_call", [call, "pending"])).result).to.equal(
+            "0x0000000000000000000000000000000000000000000000000000000000000015"
+        );
+        expect((await customRequest(context.web3, "eth_call", [call, "latest"])).result).to.equal("0x");
+        const estimate = (await customRequest(context.web3, "eth_estimateGas", [call, "pending"])).result;
+        expect(parseInt(estimate, 16)).to.be.above(21000);
+    });
+
+    step("should match the sealed block", async function () {
+        await createAndFinalizeBlock(context.polkadotApi);
+
+        expect(await context.web3.eth.getBalance(TEST_ACCOUNT, "latest")).to.equal("512");
+        const pending = (await customRequest(context.web3, "eth_getBlockByNumber", ["pending", false])).result;
+        expect(pending.number).to.equal("0x2");
+        expect(pending.transactions).to.be.empty;
+    });
+});
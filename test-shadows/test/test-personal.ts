@@ -0,0 +1,102 @@
+import { expect } from "chai";
+import { step } from "mocha-steps";
+
+import { createAndFinalizeBlock, describeWithShadows, customRequest } from "./util";
+
+describeWithShadows("Shadows RPC (Personal)", (context) => {
+    const GENESIS_ACCOUNT = "0xAA7358886fd6FEc1d64323D9da340FD3c0B9a9E4";
+    const GENESIS_ACCOUNT_PRIVATE_KEY = "0x665c5c10437cc1220b805b3b6d015c82f476e1d8144f08ba85840eddf4b903a5";
+    const TEST_ACCOUNT = "0x1111111111111111111111111111111111111111";
+    const PASSWORD = "personal-password";
+
+    step("imported keys are listed", async function () {
+        this.timeout(20_000);
+        const imported = await customRequest(context.web3, "personal_importRawKey", [
+            GENESIS_ACCOUNT_PRIVATE_KEY.slice(2),
+            PASSWORD,
+        ]);
+        expect(imported.result.toLowerCase()).to.equal(GENESIS_ACCOUNT.toLowerCase());
+
+        const accounts = await customRequest(context.web3, "personal_listAccounts", []);
+        expect(accounts.result.map((account: string) => account.toLowerCase()))
+            .to.include(GENESIS_ACCOUNT.toLowerCase());
+    });
+
+    step("importing a key twice fails", async function () {
+        this.timeout(20_000);
+        const imported = await customRequest(context.web3, "personal_importRawKey", [
+            GENESIS_ACCOUNT_PRIVATE_KEY,
+            PASSWORD,
+        ]);
+        expect(imported.error.message).to.contain("already exists");
+    });
+
+    step("new accounts are listed", async function () {
+        this.timeout(20_000);
+        const created = await customRequest(context.web3, "personal_newAccount", [PASSWORD]);
+        expect(created.result).to.match(/^0x[0-9a-f]{40}$/);
+
+        const info = await customRequest(context.web3, "personal_accountsInfo", []);
+        expect(info.result[created.result].uuid).to.be.a("string");
+    });
+
+    step("personal_sign signatures are recovered", async function () {
+        this.timeout(20_000);
+        const message = context.web3.utils.utf8ToHex("hello");
+        const signature = await customRequest(context.web3, "personal_sign", [message, GENESIS_ACCOUNT, PASSWORD]);
+        expect(signature.result).to.equal(
+            await context.web3.eth.accounts.sign("hello", GENESIS_ACCOUNT_PRIVATE_KEY).signature
+        );
+
+        const recovered = await customRequest(context.web3, "personal_ecRecover", [message, signature.result]);
+        expect(recovered.result.toLowerCase()).to.equal(GENESIS_ACCOUNT.toLowerCase());
+
+        const wrong = await customRequest(context.web3, "personal_sign", [message, GENESIS_ACCOUNT, "wrong"]);
+        expect(wrong.error.message).to.equal("invalid password");
+    });
+
+    step("locked accounts do not sign eth_sendTransaction", async function () {
+        this.timeout(20_000);
+        const sent = await customRequest(context.web3, "eth_sendTransaction", [{
+            from: GENESIS_ACCOUNT,
+            to: TEST_ACCOUNT,
+            value: "0x200",
+            gasPrice: "0x01",
+            gas: "0x100000",
+        }]);
+        expect(sent.error.message).to.contain("is locked");
+    });
+
+    step("unlocked accounts sign eth_sendTransaction until locked", async function () {
+        this.timeout(20_000);
+        const unlocked = await customRequest(context.web3, "personal_unlockAccount", [GENESIS_ACCOUNT, PASSWORD, 60]);
+        expect(unlocked.result).to.be.true;
+
+        const sent = await customRequest(context.web3, "eth_sendTransaction", [{
+            from: GENESIS_ACCOUNT,
+            to: TEST_ACCOUNT,
+            value: "0x200",
+            gasPrice: "0x01",
+            gas: "0x100000",
+        }]);
+        expect(sent.result).to.match(/^0x[0-9a-f]{64}$/);
+        await createAndFinalizeBlock(context.polkadotApi);
+        expect(await context.web3.eth.getBalance(TEST_ACCOUNT)).to.equal("512");
+
+        const locked = await customRequest(context.web3, "personal_lockAccount", [GENESIS_ACCOUNT]);
+        expect(locked.result).to.be.true;
+    });
+
+    step("personal_sendTransaction signs with the password", async function () {
+        this.timeout(20_000);
+        const sent = await customRequest(context.web3, "personal_sendTransaction", [{
+            from: GENESIS_ACCOUNT,
+            to: TEST_ACCOUNT,
+            value: "0x200",
+            gasPrice: "0x01",
+        }, PASSWORD]);
+        expect(sent.result).to.match(/^0x[0-9a-f]{64}$/);
+        await createAndFinalizeBlock(context.polkadotApi);
+        expect(await context.web3.eth.getBalance(TEST_ACCOUNT)).to.equal("1024");
+    });
+});
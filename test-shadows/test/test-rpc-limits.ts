@@ -0,0 +1,24 @@
+import {expect} from "chai";
+import {step} from "mocha-steps";
+
+import {customRequest, describeWithShadows} from "./util";
+
+describeWithShadows("Shadows RPC (Limits)", (context) => {
+    const GENESIS_ACCOUNT = "0xAA7358886fd6FEc1d64323D9da340FD3c0B9a9E4";
+    const TEST_ACCOUNT = "0x1111111111111111111111111111111111111111";
+
+    step("should refuse calls above the gas cap", async function () {
+        const call = {from: GENESIS_ACCOUNT, to: TEST_ACCOUNT, gas: "0x2faf081"};
+
+        const result = await customRequest(context.web3, "eth_call", [call, "latest"]);
+        expect(result.error.message).to.equal("gas limit 50000001 exceeds the RPC gas cap of 50000000");
+        const estimate = await customRequest(context.web3, "eth_estimateGas", [call]);
+        expect(estimate.error.message).to.equal("gas limit 50000001 exceeds the RPC gas cap of 50000000");
+    });
+
+    step("should use the gas cap when no gas is given", async function () {
+        const result = await customRequest(context.web3, "eth_call", [{from: GENESIS_ACCOUNT, to: TEST_ACCOUNT}, "latest"]);
+        expect(result.error).to.be.undefined;
+        expect(result.result).to.equal("0x");
+    });
+});
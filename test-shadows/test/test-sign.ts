@@ -0,0 +1,100 @@
+import { expect } from "chai";
+import { step } from "mocha-steps";
+import { ethers } from "ethers";
+
+import { createAndFinalizeBlock, describeWithShadows, customRequest } from "./util";
+
+describeWithShadows("Shadows RPC (Sign)", (context) => {
+    const GENESIS_ACCOUNT = "0xAA7358886fd6FEc1d64323D9da340FD3c0B9a9E4";
+    const GENESIS_ACCOUNT_PRIVATE_KEY = "0x665c5c10437cc1220b805b3b6d015c82f476e1d8144f08ba85840eddf4b903a5";
+    const TEST_ACCOUNT = "0x1111111111111111111111111111111111111111";
+    const PASSWORD = "sign-password";
+
+    const TYPED_DATA = {
+        types: {
+            EIP712Domain: [
+                { name: "name", type: "string" },
+                { name: "version", type: "string" },
+                { name: "chainId", type: "uint256" },
+                { name: "verifyingContract", type: "address" },
+            ],
+            Person: [
+                { name: "name", type: "string" },
+                { name: "wallets", type: "address[]" },
+            ],
+            Mail: [
+                { name: "from", type: "Person" },
+                { name: "to", type: "Person[]" },
+                { name: "contents", type: "string" },
+            ],
+        },
+        primaryType: "Mail",
+        domain: {
+            name: "Ether Mail",
+            version: "1",
+            chainId: 1,
+            verifyingContract: "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC",
+        },
+        message: {
+            from: { name: "Cow", wallets: ["0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"] },
+            to: [{ name: "Bob", wallets: ["0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"] }],
+            contents: "Hello, Bob!",
+        },
+    };
+
+    step("signing needs an unlocked account", async function () {
+        this.timeout(20_000);
+        await customRequest(context.web3, "personal_importRawKey", [GENESIS_ACCOUNT_PRIVATE_KEY, PASSWORD]);
+
+        const signed = await customRequest(context.web3, "eth_sign", [GENESIS_ACCOUNT, "0x1234"]);
+        expect(signed.error.message).to.contain("is locked");
+
+        await customRequest(context.web3, "personal_unlockAccount", [GENESIS_ACCOUNT, PASSWORD, 0]);
+    });
+
+    step("eth_sign signs EIP-191 messages", async function () {
+        const message = context.web3.utils.utf8ToHex("hello");
+        const signed = await customRequest(context.web3, "eth_sign", [GENESIS_ACCOUNT, message]);
+        expect(signed.result).to.equal(
+            context.web3.eth.accounts.sign("hello", GENESIS_ACCOUNT_PRIVATE_KEY).signature
+        );
+    });
+
+    step("eth_signTypedData_v4 signs EIP-712 data", async function () {
+        const { EIP712Domain, ...types } = TYPED_DATA.types;
+        const expected = await new ethers.Wallet(GENESIS_ACCOUNT_PRIVATE_KEY)
+            ._signTypedData(TYPED_DATA.domain, types, TYPED_DATA.message);
+
+        // Wallets send the typed data encoded as a string.
+        const signed = await customRequest(context.web3, "eth_signTypedData_v4", [
+            GENESIS_ACCOUNT,
+            JSON.stringify(TYPED_DATA),
+        ]);
+        expect(signed.result).to.equal(expected);
+
+        const signedObject = await customRequest(context.web3, "eth_signTypedData_v4", [GENESIS_ACCOUNT, TYPED_DATA]);
+        expect(signedObject.result).to.equal(expected);
+    });
+
+    step("eth_signTransaction returns the raw transaction without sending it", async function () {
+        this.timeout(20_000);
+        const transaction = {
+            from: GENESIS_ACCOUNT,
+            to: TEST_ACCOUNT,
+            value: "0x200",
+            gasPrice: "0x01",
+            gas: "0x100000",
+            nonce: "0x0",
+        };
+        const signed = await customRequest(context.web3, "eth_signTransaction", [transaction]);
+        const expected = await context.web3.eth.accounts.signTransaction(transaction, GENESIS_ACCOUNT_PRIVATE_KEY);
+        expect(signed.result).to.equal(expected.rawTransaction);
+
+        await createAndFinalizeBlock(context.polkadotApi);
+        expect(await context.web3.eth.getBalance(TEST_ACCOUNT)).to.equal("0");
+
+        await customRequest(context.web3, "eth_sendRawTransaction", [signed.result]);
+        await createAndFinalizeBlock(context.polkadotApi);
+        expect(await context.web3.eth.getBalance(TEST_ACCOUNT)).to.equal("512");
+    });
+});
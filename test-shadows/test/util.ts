@@ -1,11 +1,13 @@
 import Web3 from "web3";
 import {JsonRpcResponse} from "web3-core-helpers";
 import {spawn, ChildProcess} from "child_process";
+import * as http from "http";
 import {ApiPromise, WsProvider} from "@polkadot/api";
 
 export const PORT = 19931;
 export const RPC_PORT = 19932;
 export const WS_PORT = 19933;
+export const GRAPHQL_PORT = 19934;
 export const SPECS_PATH = `./Shadows-test-specs`;
 
 export const DISPLAY_LOG = process.env.Shadows_LOG || true;
@@ -41,6 +43,33 @@ export async function customRequest(web3: Web3, method: string, params: any[]) {
     });
 }
 
+export async function graphqlRequest(query: string, variables?: object): Promise<any> {
+    console.error(` <== graphql request ${query}`);
+    const body = JSON.stringify({query, variables});
+    return new Promise<any>((resolve, reject) => {
+        const request = http.request(
+            {
+                host: "localhost",
+                port: GRAPHQL_PORT,
+                path: "/graphql",
+                method: "POST",
+                headers: {"Content-Type": "application/json", "Content-Length": Buffer.byteLength(body)},
+            },
+            (response) => {
+                let data = "";
+                response.on("data", (chunk) => (data += chunk));
+                response.on("end", () => {
+                    console.error(` ==> graphql response ${data}`);
+                    resolve(JSON.parse(data));
+                });
+            }
+        );
+        request.on("error", (error) => reject(`Failed to send graphql request: ${error.message}`));
+        request.write(body);
+        request.end();
+    });
+}
+
 // Create a block and finalize it.
 // It will include all previously executed transactions since the last finalized block.
 export async function createAndFinalizeBlock(api: ApiPromise): Promise<number> {
@@ -97,6 +126,8 @@ export async function startShadowsNode(provider?: string): Promise<{ context: Co
         `--port=${PORT}`,
         `--rpc-port=${RPC_PORT}`,
         `--ws-port=${WS_PORT}`,
+        `--graphql`,
+        `--graphql-port=${GRAPHQL_PORT}`,
         `--tmp`,
     ];
     const binary = spawn(cmd, args);